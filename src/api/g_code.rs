@@ -1,11 +1,39 @@
 use crate::api::{
-    zmc_converter_run, zmc_converter_set_freq, zmc_converter_stop, zmc_move_abs, zmc_set_speed,
+    zmc_converter_run, zmc_converter_set_freq, zmc_converter_stop, zmc_get_axis_position,
+    zmc_get_parameters, zmc_move_abs, zmc_set_speed,
 };
 #[cfg(feature = "ssr")]
+use crate::model::round_coordinate;
+use crate::model::ExecutionOptions;
+use crate::model::GCodeWindow;
+#[cfg(feature = "ssr")]
+use crate::model::IgnoredCode;
+use crate::model::JobStats;
+#[cfg(feature = "ssr")]
+use crate::model::JobSubmission;
+#[cfg(feature = "ssr")]
+use crate::model::MotionKind;
+#[cfg(feature = "ssr")]
+use crate::model::Parameters;
+use crate::model::PathBounds;
+use crate::model::PathImageStyle;
+use crate::model::RecentGCodeFile;
+#[cfg(feature = "ssr")]
+use crate::model::RunOutcome;
+use crate::model::RunRecord;
+#[cfg(feature = "ssr")]
+use crate::model::RunState;
+#[cfg(feature = "ssr")]
+use crate::model::SpeedParameters;
+#[cfg(feature = "ssr")]
+use crate::model::SpindleParameters;
+#[cfg(feature = "ssr")]
 use crate::utils::Bitmap;
 use leptos::prelude::*;
 use leptos_ws::ServerSignal;
 #[cfg(feature = "ssr")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "ssr")]
 use std::sync::Arc;
 #[cfg(feature = "ssr")]
 use std::sync::LazyLock;
@@ -21,27 +49,308 @@ enum GCodeError {
     ExecutionError(String),
 }
 
+// 上传的G代码保存位置，上传过程中先写入.part文件，完成后替换
+#[cfg(feature = "ssr")]
+const GCODE_UPLOAD_FILE: &str = "gcode_upload.nc";
+#[cfg(feature = "ssr")]
+const GCODE_UPLOAD_PART_FILE: &str = "gcode_upload.nc.part";
+// 最近上传文件的副本目录和列表，最多保留RECENT_GCODE_CAPACITY个
+#[cfg(feature = "ssr")]
+const RECENT_GCODE_DIR: &str = "recent_gcode";
+#[cfg(feature = "ssr")]
+const RECENT_GCODE_LIST_FILE: &str = "recent_gcode/recent.json";
+#[cfg(feature = "ssr")]
+const RECENT_GCODE_CAPACITY: usize = 10;
+// 行号索引每隔多少行记录一次文件偏移
+#[cfg(feature = "ssr")]
+const LINE_INDEX_INTERVAL: usize = 1000;
+// 单次读取的最大行数
+#[cfg(feature = "ssr")]
+const MAX_WINDOW_LINES: usize = 1000;
+// 通过HTTP接口提交的任务所属的会话标识
+#[cfg(feature = "ssr")]
+const JOB_API_CLIENT: &str = "job_api";
+// 运行记录每行一条追加保存，超过RUN_HISTORY_MAX_BYTES时轮转为旧文件，只保留一份旧文件
+#[cfg(feature = "ssr")]
+const RUN_HISTORY_FILE: &str = "run_history.jsonl";
+#[cfg(feature = "ssr")]
+const RUN_HISTORY_OLD_FILE: &str = "run_history.jsonl.1";
+#[cfg(feature = "ssr")]
+const RUN_HISTORY_MAX_BYTES: u64 = 1024 * 1024;
+
+// 保存在磁盘上的G代码文件，按行号读取时先跳到最近的索引点再往后数行
+#[cfg(feature = "ssr")]
+struct GCodeFile {
+    path: std::path::PathBuf,
+    total: usize,
+    // checkpoints[i]为第i * LINE_INDEX_INTERVAL行的起始字节偏移
+    checkpoints: Vec<u64>,
+}
+
+#[cfg(feature = "ssr")]
+impl GCodeFile {
+    fn open(path: std::path::PathBuf) -> std::io::Result<Self> {
+        use std::io::BufRead;
+        let mut reader = std::io::BufReader::new(std::fs::File::open(&path)?);
+        let mut checkpoints = Vec::new();
+        let (mut total, mut offset) = (0, 0u64);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            if total % LINE_INDEX_INTERVAL == 0 {
+                checkpoints.push(offset);
+            }
+            offset += read as u64;
+            total += 1;
+        }
+        Ok(Self {
+            path,
+            total,
+            checkpoints,
+        })
+    }
+
+    fn window(&self, start: usize, count: usize) -> std::io::Result<Vec<String>> {
+        use std::io::{BufRead, Seek};
+        let end = start.saturating_add(count).min(self.total);
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        let checkpoint = start / LINE_INDEX_INTERVAL;
+        let mut reader = std::io::BufReader::new(std::fs::File::open(&self.path)?);
+        reader.seek(std::io::SeekFrom::Start(self.checkpoints[checkpoint]))?;
+        let mut lines = Vec::with_capacity(end - start);
+        let mut buf = Vec::new();
+        for index in checkpoint * LINE_INDEX_INTERVAL..end {
+            buf.clear();
+            reader.read_until(b'\n', &mut buf)?;
+            if index >= start {
+                let line = String::from_utf8_lossy(&buf);
+                lines.push(line.trim_end_matches(['\r', '\n']).to_string());
+            }
+        }
+        Ok(lines)
+    }
+}
+
+// G代码的行：接口提交的程序保存在内存中，上传的文件保存在磁盘上按需读取
+#[cfg(feature = "ssr")]
+enum GCodeLines {
+    Memory(Vec<String>),
+    File(GCodeFile),
+}
+
+#[cfg(feature = "ssr")]
+impl GCodeLines {
+    fn len(&self) -> usize {
+        match self {
+            GCodeLines::Memory(lines) => lines.len(),
+            GCodeLines::File(file) => file.total,
+        }
+    }
+
+    // 读取[start, start + count)范围内的行，超出末尾的部分被截掉
+    fn window(&self, start: usize, count: usize) -> Vec<String> {
+        match self {
+            GCodeLines::Memory(lines) => lines.iter().skip(start).take(count).cloned().collect(),
+            GCodeLines::File(file) => file.window(start, count).unwrap_or_else(|e| {
+                eprintln!("Failed to read G-code lines {}..+{}: {}", start, count, e);
+                Vec::new()
+            }),
+        }
+    }
+
+    // 按块顺序读取全部行，避免一次把大文件读进内存
+    fn iter(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.len())
+            .step_by(LINE_INDEX_INTERVAL)
+            .flat_map(|start| self.window(start, LINE_INDEX_INTERVAL))
+    }
+}
+
 #[cfg(feature = "ssr")]
 struct GCodeManager {
     // G-code file content lines
-    lines: Arc<Mutex<Vec<String>>>,
+    lines: Arc<Mutex<GCodeLines>>,
     // Current line being processed
     current_line: ServerSignal<usize>,
+    run_state: ServerSignal<RunState>,
+    options: Arc<Mutex<ExecutionOptions>>,
     thread_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    // 安全停止请求：当前行走完后停主轴、抬刀再结束
+    safe_stop_requested: Arc<Mutex<bool>>,
     bitmap: Arc<Mutex<Bitmap>>,
+    last_job_stats: Arc<Mutex<Option<JobStats>>>,
     path_img_preview: ServerSignal<String>,
     preview_processed_line: ServerSignal<usize>,
+    // 当前运行中跳过的未实现代码，作为非致命警告推送给界面
+    ignored_codes: ServerSignal<Vec<IgnoredCode>>,
+    // 当前运行中超过最大速度被限制的F值及所在行
+    clamped_feeds: ServerSignal<Vec<IgnoredCode>>,
+    // MDI保留的模态状态(进给、单位)，位置每次从控制器读取
+    mdi_modal: Arc<Mutex<ModalState>>,
+    // 断点行索引(从0开始，同current_line)，执行到这些行之前暂停
+    breakpoints: Arc<Mutex<HashSet<usize>>>,
+    // 排序后的断点，推送给所有客户端显示
+    breakpoint_lines: ServerSignal<Vec<usize>>,
+    // 通过HTTP接口提交的任务编号
+    job_counter: Arc<Mutex<u64>>,
+    // 串行化对最近文件列表的读写
+    recent_lock: Arc<Mutex<()>>,
+    // 启动当前运行的客户端会话标识，运行或暂停期间只有它能修改执行状态
+    owner: Arc<Mutex<Option<String>>>,
+    // 当前程序的文件名和本次运行的开始时间(毫秒时间戳)，用于运行记录
+    program_name: Arc<Mutex<String>>,
+    run_started_ms: Arc<Mutex<i64>>,
+    // 当前运行是否为空运行及其执行选项，从暂停处继续时沿用，不受之后修改的选项影响
+    run_dry_run: Arc<Mutex<bool>>,
+    run_options: Arc<Mutex<ExecutionOptions>>,
+    // 加载程序时计算的路径包围盒，开始运行前据此检查软限位
+    path_bounds: Arc<Mutex<Option<PathBounds>>>,
+    // 当前运行到目前为止的统计，每行执行完更新，暂停后继续时接着累计
+    run_progress: Arc<Mutex<JobStats>>,
+    // 服务端发出过的会话标识及最近一次使用的时间，cookie中的标识不在其中时重新发放
+    sessions: Arc<Mutex<HashMap<String, std::time::Instant>>>,
 }
 
 #[cfg(feature = "ssr")]
 impl GCodeManager {
-    pub async fn load_gcode(&self, content: String) {
+    // 只有空闲、完成或出错时才能换程序，暂停的运行继续时还要用原来的程序
+    fn ensure_can_load(&self) -> Result<(), String> {
+        if self.run_state.get_untracked().can_start() {
+            Ok(())
+        } else {
+            Err("G-code execution already in progress".to_string())
+        }
+    }
+
+    pub async fn load_gcode(&self, content: String) -> Result<(), String> {
+        self.ensure_can_load()?;
+        let bounds = compute_path_bounds(content.lines().map(|line| line.to_string()));
         let mut lines = self.lines.lock().await;
-        *lines = content.lines().map(|line| line.to_string()).collect();
+        *lines = GCodeLines::Memory(content.lines().map(|line| line.to_string()).collect());
+        *self.path_bounds.lock().await = bounds;
+        self.program_name.lock().await.clear();
+        self.current_line.update(|v| *v = 0);
+        self.set_breakpoints(Vec::new()).await;
+        Ok(())
+    }
+
+    // 换了程序后原来的断点没有意义，加载新程序时清空
+    pub async fn set_breakpoints(&self, lines: Vec<usize>) {
+        let breakpoints: HashSet<usize> = lines.into_iter().collect();
+        let mut sorted: Vec<usize> = breakpoints.iter().copied().collect();
+        sorted.sort_unstable();
+        *self.breakpoints.lock().await = breakpoints;
+        self.breakpoint_lines.update(|v| *v = sorted);
+    }
+
+    // 开始一次分块上传，清空上次未完成的临时文件
+    pub async fn begin_upload(&self) -> Result<(), String> {
+        self.ensure_can_load()?;
+        std::fs::write(GCODE_UPLOAD_PART_FILE, []).map_err(|e| e.to_string())
+    }
+
+    pub async fn append_upload(&self, chunk: &[u8]) -> Result<(), String> {
+        use std::io::Write;
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(GCODE_UPLOAD_PART_FILE)
+            .and_then(|mut file| file.write_all(chunk))
+            .map_err(|e| e.to_string())
+    }
+
+    // 上传完成后建立行索引并替换当前程序，同时记入最近文件，返回总行数
+    pub async fn finish_upload(&self, name: &str) -> Result<usize, String> {
+        self.ensure_can_load()?;
+        std::fs::rename(GCODE_UPLOAD_PART_FILE, GCODE_UPLOAD_FILE).map_err(|e| e.to_string())?;
+        let total = self.open_upload_file().await?;
+        *self.program_name.lock().await = name.to_string();
+        self.remember_recent(name, total).await;
+        Ok(total)
+    }
+
+    // 建立索引和计算包围盒都要读整个文件，在阻塞线程中完成，不占用lines的锁
+    async fn open_upload_file(&self) -> Result<usize, String> {
+        let (lines, bounds) = tokio::task::spawn_blocking(|| {
+            let lines = GCodeLines::File(GCodeFile::open(GCODE_UPLOAD_FILE.into())?);
+            let bounds = compute_path_bounds(lines.iter());
+            Ok::<_, std::io::Error>((lines, bounds))
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+        let total = lines.len();
+        *self.lines.lock().await = lines;
+        *self.path_bounds.lock().await = bounds;
         self.current_line.update(|v| *v = 0);
+        self.set_breakpoints(Vec::new()).await;
+        Ok(total)
+    }
+
+    // 保存一份副本，失败只打印错误，不影响本次上传
+    async fn remember_recent(&self, name: &str, lines: usize) {
+        let _recent = self.recent_lock.lock().await;
+        let Some(path) = recent_gcode_path(name) else {
+            return;
+        };
+        let copied = std::fs::create_dir_all(RECENT_GCODE_DIR)
+            .and_then(|_| std::fs::copy(GCODE_UPLOAD_FILE, &path));
+        if let Err(e) = copied {
+            eprintln!("Failed to keep a copy of {}: {}", name, e);
+            return;
+        }
+        let mut list = load_recent_gcode_list();
+        let entry = RecentGCodeFile {
+            name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            lines,
+            uploaded_ms: chrono::Utc::now().timestamp_millis(),
+        };
+        for removed in push_recent_gcode(&mut list, entry, RECENT_GCODE_CAPACITY) {
+            if let Some(path) = recent_gcode_path(&removed.name) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+        save_recent_gcode_list(&list);
+    }
+
+    pub async fn recent_files(&self) -> Vec<RecentGCodeFile> {
+        let _recent = self.recent_lock.lock().await;
+        load_recent_gcode_list()
+    }
+
+    // 从最近文件的副本重新加载程序，返回总行数
+    pub async fn load_recent(&self, name: &str) -> Result<usize, String> {
+        self.ensure_can_load()?;
+        let path = recent_gcode_path(name).ok_or_else(|| format!("Invalid file name: {}", name))?;
+        std::fs::copy(&path, GCODE_UPLOAD_FILE)
+            .map_err(|e| format!("Failed to load {}: {}", name, e))?;
+        let total = self.open_upload_file().await?;
+        *self.program_name.lock().await = name.to_string();
+        self.remember_recent(name, total).await;
+        Ok(total)
+    }
+
+    pub async fn window(&self, start: usize, count: usize) -> GCodeWindow {
+        let lines = self.lines.lock().await;
+        GCodeWindow {
+            start,
+            total: lines.len(),
+            lines: lines.window(start, count.min(MAX_WINDOW_LINES)),
+        }
     }
 
-    pub async fn generate_path_preview(&self) -> Result<(), String> {
+    // 在后台生成预览图，同时直接返回路径的包围盒用于检查软限位
+    pub async fn generate_path_preview(&self) -> Result<Option<PathBounds>, String> {
+        let bounds = *self.path_bounds.lock().await;
         let lines = self.lines.clone();
         let bitmap = self.bitmap.clone();
         let preview_processed_line = self.preview_processed_line.clone();
@@ -49,20 +358,33 @@ impl GCodeManager {
 
         // Start async task for coordinating the work
         tokio::spawn(async move {
-            // Step 1: First acquire the locks and prepare data
-            let lines_data = {
-                let locked_lines = lines.lock().await;
-                locked_lines.clone() // Clone the data while lock is held
-            }; // Lock is released here
+            // Step 1: Only the line count is needed up front, chunks are read as they are processed
+            let total_lines = lines.lock().await.len();
 
             // Clear the bitmap (quick operation)
-            {
+            let (background, line_width, orientation, origin, format, distinguish_rapids) = {
                 let mut locked_bitmap = bitmap.lock().await;
                 locked_bitmap.clear();
-            } // Lock is released immediately after clearing
+                (
+                    locked_bitmap.background(),
+                    locked_bitmap.line_width(),
+                    locked_bitmap.orientation(),
+                    locked_bitmap.origin_fraction(),
+                    locked_bitmap.encoding(),
+                    locked_bitmap.distinguish_rapids(),
+                )
+            }; // Lock is released immediately after clearing
 
             // Step 2: Process data in batches with yield points
-            let mut processed_bitmap = Bitmap::new(800, 800, 4.0); // Create a new bitmap for processing
+            // Chunk bitmaps keep the transparent default so merging only copies the path
+            // Create a new bitmap for processing
+            let mut processed_bitmap = Bitmap::with_background(800, 800, 4.0, background);
+            // 处理后的位图会替换原位图，保留样式供下次生成预览
+            processed_bitmap.set_line_width(line_width);
+            processed_bitmap.set_orientation(orientation);
+            processed_bitmap.set_origin(origin.0, origin.1);
+            processed_bitmap.set_format(format.0, format.1);
+            processed_bitmap.set_distinguish_rapids(distinguish_rapids);
             let mut current_x: f32 = 0.0;
             let mut current_y: f32 = 0.0;
             let mut current_z: f32 = 0.0;
@@ -70,19 +392,23 @@ impl GCodeManager {
             println!("Generating path preview...");
 
             // Process in chunks with yield points
-            for (i, chunk) in lines_data.chunks(1000).enumerate() {
+            for (i, start) in (0..total_lines).step_by(1000).enumerate() {
                 // Process this chunk in a blocking task
-                let chunk_data = chunk.to_vec(); // 克隆chunk数据
+                let chunk_data = lines.lock().await.window(start, 1000);
                 let chunk_result = tokio::task::spawn_blocking(move || {
                     println!("Processing chunk {}...", i + 1);
                     let mut temp_bitmap = Bitmap::new(800, 800, 4.0);
+                    temp_bitmap.set_line_width(line_width);
+                    temp_bitmap.set_orientation(orientation);
+                    temp_bitmap.set_origin(origin.0, origin.1);
+                    temp_bitmap.set_distinguish_rapids(distinguish_rapids);
                     let mut temp_x = current_x;
                     let mut temp_y = current_y;
                     let mut temp_z = current_z;
 
                     // Process each line in this chunk
                     for line in &chunk_data {
-                        if let Some(command) = parse_gcode_line(line) {
+                        for command in parse_gcode_line(line) {
                             preview_gcode_movement(
                                 &command,
                                 &mut temp_bitmap,
@@ -121,153 +447,1098 @@ impl GCodeManager {
 
             // Update path image signal
             path_img_preview.update(|v| *v = data_url);
+            super::zmc::refresh_path_image().await;
             println!("Path preview generated successfully.");
         });
 
         println!("Thread spawned to generate path preview");
+        Ok(bounds)
+    }
+
+    // 线宽、显示方向、零点位置和快移画法在下次生成预览时生效
+    pub async fn set_preview_style(&self, style: PathImageStyle) {
+        let data_url = {
+            let mut bitmap = self.bitmap.lock().await;
+            bitmap.set_background(style.background);
+            bitmap.set_line_width(style.line_width);
+            bitmap.set_orientation(style.orientation);
+            let (origin_x, origin_y) = style.origin.fraction();
+            bitmap.set_origin(origin_x, origin_y);
+            bitmap.set_format(style.format, style.jpeg_quality);
+            bitmap.set_distinguish_rapids(style.distinguish_rapids);
+            bitmap.to_data_url()
+        };
+        if !self.path_img_preview.get_untracked().is_empty() {
+            self.path_img_preview.update(|v| *v = data_url);
+            super::zmc::refresh_path_image().await;
+        }
+    }
+
+    // 已生成的预览图，没有预览时返回None
+    pub async fn preview_bitmap(&self) -> Option<Bitmap> {
+        if self.path_img_preview.get_untracked().is_empty() {
+            return None;
+        }
+        Some(self.bitmap.lock().await.clone())
+    }
+
+    // 加载并开始一个外部提交的任务，返回任务编号
+    pub async fn submit_job(&self, content: String, validate: bool) -> Result<u64, String> {
+        if self.is_running().await {
+            return Err("G-code execution already in progress".to_string());
+        }
+        self.check_owner(JOB_API_CLIENT).await?;
+        if validate {
+            validate_gcode(&content)?;
+        }
+        self.load_gcode(content).await?;
+        *self.program_name.lock().await = format!("job #{}", *self.job_counter.lock().await + 1);
+        self.run_state.update(|v| *v = RunState::Idle);
+        self.start(false, false, JOB_API_CLIENT).await?;
+        let mut job_counter = self.job_counter.lock().await;
+        *job_counter += 1;
+        Ok(*job_counter)
+    }
+
+    pub async fn last_job_stats(&self) -> Option<JobStats> {
+        let mut last_job_stats = self.last_job_stats.lock().await;
+        if last_job_stats.is_none() {
+            *last_job_stats = load_job_stats();
+        }
+        last_job_stats.clone()
+    }
+
+    // Check if a finished task can be replaced by a new one
+    async fn is_running(&self) -> bool {
+        self.thread_handle
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    // 运行或暂停期间，其他客户端不能加载程序、重置或重新开始，
+    // 暂停、停止和中止不检查，任何客户端都能让机床停下
+    pub async fn check_owner(&self, client: &str) -> Result<(), String> {
+        if self.run_state.get_untracked().can_start() {
+            return Ok(());
+        }
+        match self.owner.lock().await.as_deref() {
+            Some(owner) if owner != client => Err("Job in progress by another client".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    // 当前程序路径超出软限位的轴和方向，没有移动时为空
+    pub async fn limit_violations(&self) -> Result<Vec<String>, String> {
+        let Some(bounds) = *self.path_bounds.lock().await else {
+            return Ok(Vec::new());
+        };
+        let params = zmc_get_parameters().await.map_err(|e| e.to_string())?;
+        Ok(bounds.violations(&params.work_area()))
+    }
+
+    // 路径超出软限位时拒绝开始，除非操作者已经确认(allow_out_of_limits)
+    pub async fn start(
+        &self,
+        dry_run: bool,
+        allow_out_of_limits: bool,
+        client: &str,
+    ) -> Result<(), String> {
+        self.check_owner(client).await?;
+        if !allow_out_of_limits {
+            let violations = self.limit_violations().await?;
+            if !violations.is_empty() {
+                return Err(format!(
+                    "Path exceeds soft limits: {}",
+                    violations.join(", ")
+                ));
+            }
+        }
+        self.run(ExecutionContext {
+            dry_run,
+            options: self.options.lock().await.clone(),
+            disabled_axes: disabled_axes().await,
+            started_ms: chrono::Utc::now().timestamp_millis(),
+            ..Default::default()
+        })
+        .await?;
+        *self.owner.lock().await = Some(client.to_string());
+        Ok(())
+    }
+
+    // 从暂停处继续执行：先根据已执行的行恢复主轴、进给等模态状态
+    pub async fn resume(&self, client: &str) -> Result<(), String> {
+        if !self.run_state.get_untracked().can_resume() || self.is_running().await {
+            return Err("No paused G-code execution to resume".to_string());
+        }
+        self.check_owner(client).await?;
+        let modal = {
+            let lines = self.lines.lock().await;
+            let executed = self.current_line.get_untracked().min(lines.len());
+            let block_delete = self.run_options.lock().await.block_delete;
+            ModalState::replay(lines.iter().take(executed), block_delete)
+        };
+        println!("Restoring modal state before resuming: {:?}", modal);
+        let progress = self.run_progress.lock().await.clone();
+        let context = ExecutionContext {
+            lines_executed: progress.lines_executed,
+            distance: progress.distance,
+            runtime_secs: progress.runtime_secs,
+            dry_run: *self.run_dry_run.lock().await,
+            options: self.run_options.lock().await.clone(),
+            modal,
+            disabled_axes: disabled_axes().await,
+            started_ms: *self.run_started_ms.lock().await,
+            resumed_from: Some(self.current_line.get_untracked()),
+            ..Default::default()
+        };
+        self.run(context).await?;
+        *self.owner.lock().await = Some(client.to_string());
         Ok(())
     }
 
-    pub async fn start(&self) -> Result<(), String> {
+    // MDI：立即执行手动输入的一行，和程序行一样解析、解释，另外检查软件限位。
+    // 暂停的程序继续时不会知道MDI改变了位置和模态状态，所以暂停期间也不可用
+    pub async fn execute_mdi(&self, line: &str) -> Result<(), String> {
+        if self.run_state.get_untracked().can_stop() || self.is_running().await {
+            return Err("A G-code program is running or paused".to_string());
+        }
+        if super::zmc::door_interlock_engaged().await {
+            return Err("Door is open, close it before moving".to_string());
+        }
+        check_gcode_line(line)?;
+        let commands = parse_gcode_line(line);
+        if commands.is_empty() {
+            return Err("No G or M command in the line".to_string());
+        }
+        let params = zmc_get_parameters().await.map_err(|e| e.to_string())?;
+        let disabled_axes = params.enabled_axes().map(|enabled| !enabled);
+        // 读取位置同时确认控制器已连接
+        let mut modal = self.mdi_modal.lock().await.clone();
+        for axis in 0..3u8 {
+            modal.position[axis as usize] = zmc_get_axis_position(axis)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        let mut target = modal.clone();
+        for command in &commands {
+            target.apply(command);
+        }
+        for axis in (0..3u8).filter(|axis| !disabled_axes[*axis as usize]) {
+            let pos = target.position[axis as usize];
+            if pos != modal.position[axis as usize] && !params.within_soft_limits(axis, pos) {
+                return Err(format!(
+                    "Axis {} target {:.3} is outside the software limits",
+                    axis, pos
+                ));
+            }
+        }
+        let mut context = ExecutionContext {
+            options: self.options.lock().await.clone(),
+            modal,
+            disabled_axes,
+            safe_z_height: params.safe_z_height,
+            spindle: params.spindle,
+            speed: params.speed,
+            line_number: 1,
+            reject_unsupported: true,
+            ..Default::default()
+        };
+        // 先空运行一遍，不支持的代码在发出任何指令之前就报错
+        let mut check = ExecutionContext {
+            dry_run: true,
+            ..context.clone()
+        };
+        for command in &commands {
+            interpret_gcode_movement(command, &mut check).await?;
+        }
+        let idle_axes: Vec<u8> = (0..3)
+            .filter(|axis| !disabled_axes[*axis as usize])
+            .collect();
+        let result = async {
+            for command in &commands {
+                interpret_gcode_movement(command, &mut context).await?;
+            }
+            zmc_wait_idle(&idle_axes).await
+        }
+        .await;
+        super::zmc::set_motion_kind(MotionKind::Feed).await;
+        result?;
+        *self.mdi_modal.lock().await = context.modal;
+        Ok(())
+    }
+
+    async fn run(&self, mut context: ExecutionContext) -> Result<(), String> {
         let lines = self.lines.clone();
         let current_line = self.current_line.clone();
-        // Check if already running
-        if self.thread_handle.lock().await.is_some() {
+        let run_state = self.run_state.clone();
+        let last_job_stats = self.last_job_stats.clone();
+        let ignored_codes = self.ignored_codes.clone();
+        let clamped_feeds = self.clamped_feeds.clone();
+        let breakpoints = self.breakpoints.clone();
+        let safe_stop_requested = self.safe_stop_requested.clone();
+        let run_progress = self.run_progress.clone();
+        if self.is_running().await {
             return Err("G-code execution already in progress".to_string());
         }
+        // 空运行不控制机床，不受门禁联锁限制
+        if !context.dry_run && super::zmc::door_interlock_engaged().await {
+            return Err("Door is open, close it before running G-code".to_string());
+        }
+        *safe_stop_requested.lock().await = false;
+        context.safe_z_height = safe_z_height().await;
+        context.spindle = spindle_parameters().await;
+        context.speed = speed_parameters().await;
+        // 门禁检查之后才恢复主轴和进给；新开始的运行也会设置一次进给速度
+        restore_modal_state(&context)
+            .await
+            .map_err(|e| format!("Failed to restore modal state: {}", e))?;
+        let idle_axes: Vec<u8> = (0..3)
+            .filter(|axis| !context.disabled_axes[*axis as usize])
+            .collect();
+        *self.run_started_ms.lock().await = context.started_ms;
+        *self.run_dry_run.lock().await = context.dry_run;
+        *self.run_options.lock().await = context.options.clone();
+        let program_name = self.program_name.lock().await.clone();
+        let total_lines = lines.lock().await.len();
+        let mut thread_handle = self.thread_handle.lock().await;
+        run_state.update(|s| *s = RunState::Running);
+        ignored_codes.update(|codes| codes.clear());
+        clamped_feeds.update(|feeds| feeds.clear());
+        // 新开始的运行从零统计，继续时从暂停前的统计接着累计
+        *run_progress.lock().await = JobStats {
+            lines_executed: context.lines_executed,
+            runtime_secs: context.runtime_secs,
+            distance: context.distance,
+            ..Default::default()
+        };
         // Spawn a new task to execute G-code lines
         let handle = tokio::spawn(async move {
-            loop {
-                let lines = lines.lock().await;
+            let started = std::time::Instant::now();
+            // Lines are read in windows so large files on disk are not loaded at once
+            let (mut buffer_start, mut buffer) = (0, Vec::new());
+            let final_state = loop {
+                // 上一行的运动已经完成，此时安全停止不会中断运动
+                // 安全停止只停主轴、抬刀，不执行程序结束的回原点等移动
+                if *safe_stop_requested.lock().await {
+                    println!("Safe stop requested, retracting before ending");
+                    run_safe_stop(&context).await;
+                    current_line.update(|v| *v = 0);
+                    break RunState::Idle;
+                }
                 let current_line_index = current_line.get_untracked();
-                if current_line_index >= lines.len() {
+                if !(buffer_start..buffer_start + buffer.len()).contains(&current_line_index) {
+                    buffer_start = current_line_index;
+                    buffer = lines
+                        .lock()
+                        .await
+                        .window(current_line_index, MAX_WINDOW_LINES);
+                }
+                let Some(line) = buffer.get(current_line_index - buffer_start) else {
                     // All lines executed, exit the loop
                     println!("All G-code lines executed.");
-                    break;
+                    run_end_of_program(&context, context.modal.position[2]).await;
+                    break RunState::Completed;
+                };
+                // 断点：执行该行前暂停，从断点继续时继续的那一行不再检查
+                if context.resumed_from != Some(current_line_index)
+                    && breakpoints.lock().await.contains(&current_line_index)
+                {
+                    println!("Breakpoint reached at line {}", current_line_index + 1);
+                    break RunState::Paused;
                 }
+                let line_started = std::time::Instant::now();
+                context.line_number = current_line_index + 1;
                 // Execute one line of G-code
-                if let Err(e) = execute_one_line(&lines[current_line_index as usize]).await {
+                let result = execute_one_line(line, &mut context).await;
+                if std::mem::take(&mut context.ignored_codes_changed) {
+                    let codes = context.ignored_codes.clone();
+                    ignored_codes.update(|v| *v = codes);
+                }
+                if std::mem::take(&mut context.clamped_feeds_changed) {
+                    let feeds = context.clamped_feeds.clone();
+                    clamped_feeds.update(|v| *v = feeds);
+                }
+                if let Err(e) = result {
                     eprintln!("Error executing G-code line: {}", e);
-                    break;
+                    break RunState::Error;
+                }
+                context.lines_executed += 1;
+                if !context.dry_run {
+                    // Wait for enabled axes to be idle
+                    if let Err(e) = zmc_wait_idle(&idle_axes).await {
+                        eprintln!("Error waiting for the axes: {}", e);
+                        break RunState::Error;
+                    }
+                }
+                // 限速调试：运动完成后补足每行的最小间隔
+                let interval_ms = context.options.min_line_interval_ms;
+                let interval = tokio::time::Duration::from_millis(interval_ms);
+                let remaining = interval.saturating_sub(line_started.elapsed());
+                if !remaining.is_zero() {
+                    tokio::time::sleep(remaining).await;
                 }
-                zmc_wait_idle(&[0, 1, 2]).await; // Wait for axis to be idle
-                                                 // Update the current line index
+                // Update the current line index
                 current_line.update(|v| *v += 1);
+                // 暂停会直接中止任务，统计每行都保存一次
+                *run_progress.lock().await = JobStats {
+                    lines_executed: context.lines_executed,
+                    runtime_secs: context.runtime_secs + started.elapsed().as_secs_f64(),
+                    distance: context.distance,
+                    ..Default::default()
+                };
+            };
+            // 之后的手动移动算作进给
+            super::zmc::set_motion_kind(MotionKind::Feed).await;
+            // 断点暂停时运行还没有结束，不保存统计
+            if final_state == RunState::Paused {
+                run_state.update(|s| *s = final_state);
+                return;
             }
+            // Save the stats before publishing the state so clients fetching them on
+            // completion see this run
+            let stats = JobStats {
+                lines_executed: context.lines_executed,
+                runtime_secs: context.runtime_secs + started.elapsed().as_secs_f64(),
+                distance: context.distance,
+                status: final_state,
+                ignored_codes: context.ignored_codes,
+            };
+            save_job_stats(&stats);
+            *last_job_stats.lock().await = Some(stats);
+            // 运行任务中只有安全停止会以Idle结束
+            let outcome = match final_state {
+                RunState::Completed => RunOutcome::Completed,
+                RunState::Error => RunOutcome::Error,
+                _ => RunOutcome::Stopped,
+            };
+            append_run_record(&RunRecord {
+                file_name: program_name,
+                started_ms: context.started_ms,
+                ended_ms: chrono::Utc::now().timestamp_millis(),
+                status: outcome,
+                lines: total_lines,
+            });
+            run_state.update(|s| *s = final_state);
         });
-        self.thread_handle.lock().await.replace(handle);
+        thread_handle.replace(handle);
         Ok(())
     }
 
+    // Abort the execution thread, returns whether a run was in progress
+    async fn abort(&self) -> bool {
+        match self.thread_handle.lock().await.take() {
+            Some(handle) => {
+                let running = !handle.is_finished();
+                handle.abort();
+                running
+            }
+            None => false,
+        }
+    }
+
+    // 暂停：中止执行但保留当前行，之后可以继续
+    pub async fn pause(&self) {
+        if self.abort().await {
+            self.run_state.update(|s| *s = RunState::Paused);
+        }
+    }
+
+    // 安全停止：不中断当前运动，走完当前行后停主轴、抬Z到安全高度再结束。
+    // 暂停时直接停主轴、抬刀再结束，没有在运行时与stop相同
+    pub async fn safe_stop(&self) {
+        if self.is_running().await {
+            *self.safe_stop_requested.lock().await = true;
+            return;
+        }
+        if self.run_state.get_untracked() == RunState::Paused && !*self.run_dry_run.lock().await {
+            println!("Safe stop while paused, retracting before ending");
+            let z_disabled = disabled_axes().await[2];
+            // 读不到Z的位置时不知道该往哪里抬，只停主轴
+            let steps = match zmc_get_axis_position(2).await {
+                Ok(z) => safe_stop_steps(z, safe_z_height().await, z_disabled),
+                Err(e) => {
+                    eprintln!("Failed to read Z position for safe stop: {}", e);
+                    vec![SafeStopStep::SpindleStop]
+                }
+            };
+            run_safe_stop_steps(steps).await;
+            self.end_run(RunOutcome::Stopped).await;
+        } else {
+            self.stop().await;
+        }
+    }
+
+    // 紧急中止：立即结束本次运行并回到第一行，可能停在运动中途
     pub async fn stop(&self) {
-        // Stop the G-code execution thread if it exists
-        if let Some(handle) = self.thread_handle.lock().await.take() {
-            handle.abort();
+        self.end_run(RunOutcome::Aborted).await;
+    }
+
+    // 结束运行或暂停中的运行并记录结果，然后回到第一行
+    async fn end_run(&self, outcome: RunOutcome) {
+        let paused = self.run_state.get_untracked() == RunState::Paused;
+        if self.abort().await || paused {
+            append_run_record(&RunRecord {
+                file_name: self.program_name.lock().await.clone(),
+                started_ms: *self.run_started_ms.lock().await,
+                ended_ms: chrono::Utc::now().timestamp_millis(),
+                status: outcome,
+                lines: self.lines.lock().await.len(),
+            });
         }
+        self.current_line.update(|v| *v = 0);
+        self.run_state.update(|s| *s = RunState::Idle);
+    }
+
+    pub async fn set_options(&self, options: ExecutionOptions) {
+        *self.options.lock().await = options;
     }
 
     pub async fn reset(&self) {
+        if self.is_running().await {
+            return;
+        }
         self.current_line.update(|v| *v = 0);
+        self.run_state.update(|s| *s = RunState::Idle);
     }
 }
 
+// 单次G代码运行的执行上下文
 #[cfg(feature = "ssr")]
-async fn execute_one_line(line: &str) -> Result<(), String> {
-    let g_code_command = parse_gcode_line(line);
-    if let Some(command) = g_code_command {
-        interpret_gcode_movement(&command).await;
-    } else {
-        eprintln!("Failed to parse G-code line: {}", line);
+#[derive(Debug, Clone, Default)]
+struct ExecutionContext {
+    // 空运行：只解析并推进行号，不向控制器发送任何指令
+    dry_run: bool,
+    options: ExecutionOptions,
+    modal: ModalState,
+    // 停用的X、Y、Z轴，对应的坐标字被忽略
+    disabled_axes: [bool; 3],
+    // 安全快移抬刀的高度，来自参数设置
+    safe_z_height: f32,
+    // 主轴启停的等待时间，来自参数设置
+    spindle: SpindleParameters,
+    // 本次运行的开始时间(毫秒时间戳)，从暂停处继续时沿用第一次开始的时间
+    started_ms: i64,
+    // 最大速度，F值换算的速度超过它时被限制，来自参数设置
+    speed: SpeedParameters,
+    // 本次运行执行的行数、移动距离和暂停之前各段的运行时间(秒)，用于统计
+    lines_executed: usize,
+    distance: f32,
+    runtime_secs: f64,
+    // 正在执行的行号(从1开始)
+    line_number: usize,
+    // 遇到的未实现G/M代码，运行中有新记录时置位changed以便推送
+    ignored_codes: Vec<IgnoredCode>,
+    ignored_codes_changed: bool,
+    // 超过最大速度被限制的F值，记录方式同忽略的代码
+    clamped_feeds: Vec<IgnoredCode>,
+    clamped_feeds_changed: bool,
+    // MDI不跳过不支持的代码，直接报错
+    reject_unsupported: bool,
+    // 从暂停处继续时的行索引，该行上的断点不再暂停
+    resumed_from: Option<usize>,
+}
+
+#[cfg(feature = "ssr")]
+impl ExecutionContext {
+    // 程序中不支持的代码跳过并记录，MDI中则作为错误返回
+    fn ignore(&mut self, command: &GCodeCommand) -> Result<(), String> {
+        let code = format!("{}{}", command.command_type, command.command_number);
+        if self.reject_unsupported {
+            return Err(format!("Unsupported code {}", code));
+        }
+        println!("Ignoring unsupported {} at line {}", code, self.line_number);
+        self.ignored_codes_changed |=
+            IgnoredCode::record(&mut self.ignored_codes, code, self.line_number);
+        Ok(())
     }
 
-    Ok(())
+    // F值换算为控制器速度，超过最大速度时限制为最大速度并记录，提醒操作员程序要求的更快
+    fn feed_speed(&mut self, feed: f32) -> f32 {
+        let speed = feed_to_speed(feed, self.modal.inch, &self.options);
+        let (clamped, limited) = self.speed.clamp_feed_speed(speed);
+        if limited {
+            println!(
+                "Clamping F{} at line {} to max speed {}",
+                feed, self.line_number, clamped
+            );
+            let code = format!("F{}", feed);
+            self.clamped_feeds_changed |=
+                IgnoredCode::record(&mut self.clamped_feeds, code, self.line_number);
+        }
+        clamped
+    }
 }
 
-/// Represents a parsed G-code command
+// 最近一次运行统计的保存位置
 #[cfg(feature = "ssr")]
-#[derive(Debug, Clone)]
-pub struct GCodeCommand {
-    pub command_type: String,         // G, M, T, etc
-    pub command_number: i32,          // The number after the command type (G1, M104, etc)
-    pub parameters: Vec<(char, f64)>, // Parameters like X10.5, Y20, etc.
-    pub comment: Option<String>,
+const JOB_STATS_FILE: &str = "job_stats.json";
+
+#[cfg(feature = "ssr")]
+fn save_job_stats(stats: &JobStats) {
+    let result = serde_json::to_string_pretty(stats)
+        .map_err(|e| e.to_string())
+        .and_then(|json| std::fs::write(JOB_STATS_FILE, json).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("Failed to save job stats: {}", e);
+    }
 }
 
-/// Parse a single line of G-code
 #[cfg(feature = "ssr")]
-pub fn parse_gcode_line(line: &str) -> Option<GCodeCommand> {
-    // Skip empty lines and pure comment lines
-    let line = line.trim();
-    if line.is_empty() || line.starts_with(';') {
-        return None;
+fn load_job_stats() -> Option<JobStats> {
+    let json = std::fs::read_to_string(JOB_STATS_FILE).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+// 追加一条运行记录，文件过大时先轮转，失败只打印错误
+#[cfg(feature = "ssr")]
+fn append_run_record(record: &RunRecord) {
+    use std::io::Write;
+    let oversized = std::fs::metadata(RUN_HISTORY_FILE)
+        .is_ok_and(|metadata| metadata.len() >= RUN_HISTORY_MAX_BYTES);
+    if oversized {
+        if let Err(e) = std::fs::rename(RUN_HISTORY_FILE, RUN_HISTORY_OLD_FILE) {
+            eprintln!("Failed to rotate run history: {}", e);
+        }
+    }
+    let result = serde_json::to_string(record)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(RUN_HISTORY_FILE)
+                .and_then(|mut file| writeln!(file, "{}", json))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        eprintln!("Failed to save run record: {}", e);
+    }
+}
+
+// 读取旧文件和当前文件中的运行记录，最新的在前，无法解析的行跳过
+#[cfg(feature = "ssr")]
+fn load_run_history() -> Vec<RunRecord> {
+    let mut records: Vec<RunRecord> = [RUN_HISTORY_OLD_FILE, RUN_HISTORY_FILE]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect::<Vec<RunRecord>>()
+        })
+        .collect();
+    records.reverse();
+    records
+}
+
+#[cfg(feature = "ssr")]
+fn load_recent_gcode_list() -> Vec<RecentGCodeFile> {
+    std::fs::read_to_string(RECENT_GCODE_LIST_FILE)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "ssr")]
+fn save_recent_gcode_list(list: &[RecentGCodeFile]) {
+    let result = serde_json::to_string_pretty(list)
+        .map_err(|e| e.to_string())
+        .and_then(|json| std::fs::write(RECENT_GCODE_LIST_FILE, json).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("Failed to save recent G-code list: {}", e);
     }
+}
 
-    // Extract comment if present
-    let (code_part, comment) = match line.split_once(';') {
-        Some((code, comment)) => (code.trim(), Some(comment.trim().to_string())),
-        None => (line, None),
-    };
+// 把文件放到最近列表最前面，同名的旧记录被替换，超出容量的从末尾移除并返回
+#[cfg(feature = "ssr")]
+fn push_recent_gcode(
+    list: &mut Vec<RecentGCodeFile>,
+    entry: RecentGCodeFile,
+    capacity: usize,
+) -> Vec<RecentGCodeFile> {
+    list.retain(|recent| recent.name != entry.name);
+    list.insert(0, entry);
+    list.split_off(capacity.min(list.len()))
+}
 
-    // Find the command (G, M, T, etc)
-    let re_command = regex::Regex::new(r"^([A-Za-z])(\d+)").unwrap();
-    let command_cap = if let Some(caps) = re_command.captures(code_part) {
-        (
-            caps.get(1).unwrap().as_str().to_uppercase(),
-            caps.get(2).unwrap().as_str().parse::<i32>().unwrap_or(0),
-        )
-    } else {
-        return None; // No valid command found
-    };
+// 只保留文件名部分，避免上传的名字指向副本目录之外
+#[cfg(feature = "ssr")]
+fn recent_gcode_path(name: &str) -> Option<std::path::PathBuf> {
+    let file_name = std::path::Path::new(name).file_name()?;
+    Some(std::path::Path::new(RECENT_GCODE_DIR).join(file_name))
+}
+
+// 读取参数中停用的轴
+#[cfg(feature = "ssr")]
+async fn disabled_axes() -> [bool; 3] {
+    zmc_get_parameters()
+        .await
+        .map(|params| params.enabled_axes().map(|enabled| !enabled))
+        .unwrap_or_default()
+}
 
-    // Extract parameters (X, Y, Z, E, F, etc)
-    let re_params = regex::Regex::new(r"([A-Za-z])(-?\d*\.?\d+)").unwrap();
-    let mut parameters = Vec::new();
+#[cfg(feature = "ssr")]
+async fn safe_z_height() -> f32 {
+    zmc_get_parameters()
+        .await
+        .map(|params| params.safe_z_height)
+        .unwrap_or_else(|_| Parameters::default().safe_z_height)
+}
 
-    for cap in re_params.captures_iter(code_part) {
-        if cap.get(0).unwrap().start() == 0 {
-            // Skip the initial command we already processed
-            continue;
-        }
+#[cfg(feature = "ssr")]
+async fn spindle_parameters() -> SpindleParameters {
+    zmc_get_parameters()
+        .await
+        .map(|params| params.spindle)
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "ssr")]
+async fn speed_parameters() -> SpeedParameters {
+    zmc_get_parameters()
+        .await
+        .map(|params| params.speed)
+        .unwrap_or_default()
+}
 
-        let param_letter = cap.get(1).unwrap().as_str().chars().next().unwrap();
-        let param_value = cap.get(2).unwrap().as_str().parse::<f64>().unwrap_or(0.0);
-        parameters.push((param_letter, param_value));
+// 等待主轴加速或停转，之后的移动才开始
+#[cfg(feature = "ssr")]
+async fn spindle_dwell(secs: f32) {
+    if secs > 0.0 {
+        println!("Waiting {:.1}s for the spindle", secs);
+        tokio::time::sleep(tokio::time::Duration::from_secs_f32(secs)).await;
     }
+}
 
-    Some(GCodeCommand {
-        command_type: command_cap.0,
-        command_number: command_cap.1,
-        parameters,
-        comment,
-    })
+// 安全快移拆分的各段移动：低于安全高度时先抬Z，再XY快移，最后移动到目标Z
+#[cfg(feature = "ssr")]
+fn safe_rapid_moves(
+    axis_list: Vec<u8>,
+    pos_list: Vec<f32>,
+    from_z: f32,
+    target_z: f32,
+    safe_z: f32,
+) -> Vec<(Vec<u8>, Vec<f32>)> {
+    let xy: (Vec<u8>, Vec<f32>) = axis_list
+        .into_iter()
+        .zip(pos_list)
+        .filter(|(axis, _)| *axis != 2)
+        .unzip();
+    let mut moves = Vec::new();
+    if from_z < safe_z {
+        moves.push((vec![2], vec![safe_z]));
+    }
+    moves.push(xy);
+    // 目标Z高于抬刀高度时同样要移动过去
+    if target_z != from_z.max(safe_z) {
+        moves.push((vec![2], vec![target_z]));
+    }
+    moves
 }
 
+// 安全停止时依次执行的动作
 #[cfg(feature = "ssr")]
-async fn zmc_wait_idle(axis_list: &[u8]) {
-    // Wait for the ZMC to be idle before executing the next command
-    let mut idle_axis_num;
-    use super::zmc_get_idle;
-    loop {
-        idle_axis_num = 0; // Reset idle count for each iteration
-        for axis in axis_list {
-            // Try to get idle status up to 10 times
-            if zmc_get_idle(*axis)
-                .await
-                .expect("Failed to get idle status")
-            {
-                idle_axis_num += 1;
-                tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-            }
-        }
-        if idle_axis_num == axis_list.len() {
-            // All axes are idle
-            return;
-        }
+#[derive(Debug, Clone, PartialEq)]
+enum SafeStopStep {
+    SpindleStop,
+    RetractZ(f32),
+}
+
+// 先停主轴，Z低于安全高度时再抬刀，Z轴停用时不抬
+#[cfg(feature = "ssr")]
+fn safe_stop_steps(current_z: f32, safe_z: f32, z_disabled: bool) -> Vec<SafeStopStep> {
+    let mut steps = vec![SafeStopStep::SpindleStop];
+    if !z_disabled && current_z < safe_z {
+        steps.push(SafeStopStep::RetractZ(safe_z));
     }
+    steps
 }
 
 #[cfg(feature = "ssr")]
-async fn interpret_gcode_movement(command: &GCodeCommand) {
+async fn run_safe_stop(context: &ExecutionContext) {
+    if context.dry_run {
+        return;
+    }
+    run_safe_stop_steps(safe_stop_steps(
+        context.modal.position[2],
+        context.safe_z_height,
+        context.disabled_axes[2],
+    ))
+    .await;
+}
+
+#[cfg(feature = "ssr")]
+async fn run_safe_stop_steps(steps: Vec<SafeStopStep>) {
+    for step in steps {
+        let result = match step {
+            SafeStopStep::SpindleStop => zmc_converter_stop().await,
+            SafeStopStep::RetractZ(z) => match zmc_move_abs(vec![2], vec![z]).await {
+                Ok(()) => zmc_wait_idle(&[2]).await.map_err(ServerFnError::new),
+                Err(e) => Err(e),
+            },
+        };
+        if let Err(e) = result {
+            eprintln!("Safe stop step {:?} failed: {}", step, e);
+        }
+    }
+}
+
+// 程序结束后的移动：开启结束抬刀或回XY原点时先抬Z到安全高度，再回X0/Y0，停用的轴不动
+#[cfg(feature = "ssr")]
+fn end_of_program_moves(
+    options: &ExecutionOptions,
+    current_z: f32,
+    safe_z: f32,
+    disabled_axes: [bool; 3],
+) -> Vec<(Vec<u8>, Vec<f32>)> {
+    let mut moves = Vec::new();
+    if !(options.end_retract || options.end_return_xy) {
+        return moves;
+    }
+    if !disabled_axes[2] && current_z < safe_z {
+        moves.push((vec![2], vec![safe_z]));
+    }
+    if options.end_return_xy {
+        let xy: Vec<u8> = (0..2)
+            .filter(|axis| !disabled_axes[*axis as usize])
+            .collect();
+        if !xy.is_empty() {
+            moves.push((xy.clone(), vec![0.0; xy.len()]));
+        }
+    }
+    moves
+}
+
+#[cfg(feature = "ssr")]
+async fn run_end_of_program(context: &ExecutionContext, current_z: f32) {
+    if context.dry_run {
+        return;
+    }
+    let moves = end_of_program_moves(
+        &context.options,
+        current_z,
+        context.safe_z_height,
+        context.disabled_axes,
+    );
+    for (axis_list, pos_list) in moves {
+        println!(
+            "End of program move: axes {:?} to {:?}",
+            axis_list, pos_list
+        );
+        if let Err(e) = zmc_move_abs(axis_list.clone(), pos_list).await {
+            eprintln!("End of program move failed: {}", e);
+            return;
+        }
+        if let Err(e) = zmc_wait_idle(&axis_list).await {
+            eprintln!("End of program move failed: {}", e);
+            return;
+        }
+    }
+}
+
+// 已执行的G代码所隐含的模态状态，用于停止后继续执行时恢复
+#[cfg(feature = "ssr")]
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ModalState {
+    // 主轴状态：None为停止，Some(inverted)为正转/反转
+    spindle: Option<bool>,
+    // 最近一次M3/M4设置的主轴频率
+    spindle_freq: Option<u32>,
+    // 最近一次G0/G1设置的进给速度(G代码原始F值)
+    feed: Option<f32>,
+    // G20英制/G21公制
+    inch: bool,
+    // G0~G3移动后的X、Y、Z坐标
+    position: [f32; 3],
+}
+
+#[cfg(feature = "ssr")]
+impl ModalState {
+    fn apply(&mut self, command: &GCodeCommand) {
+        match (command.command_type.as_str(), command.command_number) {
+            ("G", 0..=3) => {
+                for (param, value) in &command.parameters {
+                    match param {
+                        'F' if command.command_number <= 1 => self.feed = Some(*value as f32),
+                        'X' => self.position[0] = *value as f32,
+                        'Y' => self.position[1] = *value as f32,
+                        'Z' => self.position[2] = *value as f32,
+                        _ => {}
+                    }
+                }
+            }
+            ("M", 3 | 4) => {
+                self.spindle = Some(command.command_number == 4);
+                if let Some((_, value)) = command.parameters.iter().find(|(p, _)| *p == 'S') {
+                    self.spindle_freq = Some(*value as u32);
+                }
+            }
+            ("M", 0 | 1 | 5) => self.spindle = None,
+            ("G", 20) => self.inch = true,
+            ("G", 21) => self.inch = false,
+            _ => {}
+        }
+    }
+
+    // 和执行时一样，开启跳段时跳过以/开头的行
+    fn replay(lines: impl Iterator<Item = String>, block_delete: bool) -> Self {
+        let mut state = Self::default();
+        let commands = lines.flat_map(|line| {
+            block_deleted_line(&line, block_delete)
+                .map(parse_gcode_line)
+                .unwrap_or_default()
+        });
+        for command in commands {
+            state.apply(&command);
+        }
+        state
+    }
+}
+
+// 将G代码的F值(mm/min或G20下的in/min)换算为控制器速度(units/s)
+#[cfg(feature = "ssr")]
+fn feed_to_speed(feed: f32, inch: bool, options: &ExecutionOptions) -> f32 {
+    let feed = if inch { feed * 25.4 } else { feed };
+    if options.speed_per_minute {
+        feed
+    } else {
+        feed / 60.0
+    }
+}
+
+// 重新下发模态状态对应的指令
+#[cfg(feature = "ssr")]
+async fn restore_modal_state(context: &ExecutionContext) -> Result<(), ServerFnError> {
+    if context.dry_run {
+        return Ok(());
+    }
+    // 程序还没有给出F值时使用加工速度，不沿用点动等留下的速度
+    let speed = match context.modal.feed {
+        Some(feed) => {
+            let speed = feed_to_speed(feed, context.modal.inch, &context.options);
+            context.speed.clamp_feed_speed(speed).0
+        }
+        None => context.speed.processing_speed,
+    };
+    for i in (0..3).filter(|i| !context.disabled_axes[*i as usize]) {
+        zmc_set_speed(i, speed).await?;
+    }
+    if let Some(inverted) = context.modal.spindle {
+        zmc_converter_run(inverted).await?;
+        if let Some(freq) = context.modal.spindle_freq {
+            zmc_converter_set_freq(freq).await?;
+        }
+        spindle_dwell(context.spindle.spin_up_secs).await;
+    }
+    Ok(())
+}
+
+// 以/开头的可选程序段：开启跳段时跳过(返回None)，否则去掉/后正常执行
+#[cfg(feature = "ssr")]
+fn block_deleted_line(line: &str, block_delete: bool) -> Option<&str> {
+    match line.trim_start().strip_prefix('/') {
+        Some(_) if block_delete => None,
+        Some(rest) => Some(rest),
+        None => Some(line),
+    }
+}
+
+#[cfg(feature = "ssr")]
+async fn execute_one_line(line: &str, context: &mut ExecutionContext) -> Result<(), String> {
+    let Some(line) = block_deleted_line(line, context.options.block_delete) else {
+        return Ok(());
+    };
+    let g_code_commands = parse_gcode_line(line);
+    if g_code_commands.is_empty() {
+        eprintln!("Failed to parse G-code line: {}", line);
+    }
+    for command in &g_code_commands {
+        interpret_gcode_movement(command, context).await?;
+    }
+
+    Ok(())
+}
+
+/// Represents a parsed G-code command
+#[cfg(feature = "ssr")]
+#[derive(Debug, Clone)]
+pub struct GCodeCommand {
+    pub command_type: String,         // G, M, T, etc
+    pub command_number: i32,          // The number after the command type (G1, M104, etc)
+    pub parameters: Vec<(char, f64)>, // Parameters like X10.5, Y20, etc.
+    pub comment: Option<String>,
+}
+
+// 根据G0~G3的X/Y终点计算路径包围盒，圆弧只计端点；没有任何移动时返回None
+#[cfg(feature = "ssr")]
+fn compute_path_bounds(lines: impl Iterator<Item = String>) -> Option<PathBounds> {
+    let (mut x, mut y) = (0.0f32, 0.0f32);
+    let mut bounds: Option<PathBounds> = None;
+    for command in lines.flat_map(|line| parse_gcode_line(&line)) {
+        if command.command_type != "G" || !(0..=3).contains(&command.command_number) {
+            continue;
+        }
+        let mut moved = false;
+        for (param, value) in &command.parameters {
+            match param {
+                'X' => x = *value as f32,
+                'Y' => y = *value as f32,
+                _ => continue,
+            }
+            moved = true;
+        }
+        if moved {
+            match bounds.as_mut() {
+                Some(bounds) => bounds.include(x, y),
+                None => bounds = Some(PathBounds::from_point(x, y)),
+            }
+        }
+    }
+    bounds
+}
+
+// 检查每一行有代码的内容都能解析出指令，返回第一个无法解析的行
+#[cfg(feature = "ssr")]
+fn validate_gcode(content: &str) -> Result<(), String> {
+    for (i, line) in content.lines().enumerate() {
+        let code = line.split(';').next().unwrap_or("").trim();
+        let code = code.strip_prefix('/').unwrap_or(code).trim();
+        if code.is_empty() || code == "%" {
+            continue;
+        }
+        if parse_gcode_line(code).is_empty() {
+            return Err(format!("Invalid G-code at line {}: {}", i + 1, line));
+        }
+    }
+    Ok(())
+}
+
+/// Parse a single line of G-code into its commands
+///
+/// A line may hold several G/M words (e.g. `G0 X10 Y10 M3 S1000`), each word
+/// starts a new command and the following parameters are attached to it.
+/// The commands are returned in execution order: non-motion commands first,
+/// then the motion (G0-G3) commands.
+#[cfg(feature = "ssr")]
+pub fn parse_gcode_line(line: &str) -> Vec<GCodeCommand> {
+    // Skip empty lines and pure comment lines
+    let line = line.trim();
+    if line.is_empty() || line.starts_with(';') {
+        return Vec::new();
+    }
+
+    // Extract comment if present
+    let (code_part, comment) = match line.split_once(';') {
+        Some((code, comment)) => (code.trim(), Some(comment.trim().to_string())),
+        None => (line, None),
+    };
+
+    // Split the line into words (G1, X10.5, Y-20, F500, etc)
+    let re_words = regex::Regex::new(r"([A-Za-z])(-?\d*\.?\d+)").unwrap();
+    let mut commands: Vec<GCodeCommand> = Vec::new();
+    // Parameters written before any command word
+    let mut leading_parameters = Vec::new();
+
+    for cap in re_words.captures_iter(code_part) {
+        let letter = cap
+            .get(1)
+            .unwrap()
+            .as_str()
+            .chars()
+            .next()
+            .unwrap()
+            .to_ascii_uppercase();
+        let value = cap.get(2).unwrap().as_str().parse::<f64>().unwrap_or(0.0);
+        match letter {
+            'G' | 'M' | 'T' => commands.push(GCodeCommand {
+                command_type: letter.to_string(),
+                command_number: value as i32,
+                parameters: Vec::new(),
+                comment: comment.clone(),
+            }),
+            _ => match commands.last_mut() {
+                Some(command) => command.parameters.push((letter, value)),
+                None => leading_parameters.push((letter, value)),
+            },
+        }
+    }
+
+    // No valid command found
+    let Some(first) = commands.first_mut() else {
+        return Vec::new();
+    };
+    first.parameters.splice(0..0, leading_parameters);
+
+    // Stable sort keeps the written order inside each group
+    commands.sort_by_key(is_motion_command);
+    commands
+}
+
+// 检查一行G代码只由字母加数字的字组成，parse_gcode_line会忽略无法识别的内容
+#[cfg(feature = "ssr")]
+fn check_gcode_line(line: &str) -> Result<(), String> {
+    let code_part = line.split_once(';').map_or(line, |(code, _)| code);
+    let re_words = regex::Regex::new(r"[A-Za-z]\s*-?\d*\.?\d+").unwrap();
+    let rest = re_words.replace_all(code_part, " ");
+    let rest = rest.split_whitespace().collect::<Vec<_>>().join(" ");
+    if rest.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Unrecognized text: {}", rest))
+    }
+}
+
+#[cfg(feature = "ssr")]
+fn is_motion_command(command: &GCodeCommand) -> bool {
+    command.command_type == "G" && (0..=3).contains(&command.command_number)
+}
+
+#[cfg(feature = "ssr")]
+async fn zmc_wait_idle(axis_list: &[u8]) -> Result<(), String> {
+    // Wait for the ZMC to be idle before executing the next command
+    let mut idle_axis_num;
+    use super::zmc_get_idle;
+    loop {
+        idle_axis_num = 0; // Reset idle count for each iteration
+        for axis in axis_list {
+            // Try to get idle status up to 10 times
+            if zmc_get_idle(*axis)
+                .await
+                .map_err(|e| format!("Failed to get idle status: {}", e))?
+            {
+                idle_axis_num += 1;
+                tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+            }
+        }
+        if idle_axis_num == axis_list.len() {
+            // All axes are idle
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+async fn interpret_gcode_movement(
+    command: &GCodeCommand,
+    context: &mut ExecutionContext,
+) -> Result<(), String> {
+    let from = context.modal.position;
+    // 路径图据此区分快移和进给
+    if command.command_type == "G" && !context.dry_run {
+        if let Some(motion) = MotionKind::from_g_number(command.command_number) {
+            super::zmc::set_motion_kind(motion).await;
+        }
+    }
+    context.modal.apply(command);
+    context.distance += from
+        .iter()
+        .zip(context.modal.position)
+        .map(|(a, b)| (b - a).powi(2))
+        .sum::<f32>()
+        .sqrt();
     let mut movement = String::new();
     // Handle G commands (movement related)
     if command.command_type == "G" {
@@ -280,33 +1551,39 @@ async fn interpret_gcode_movement(command: &GCodeCommand) {
                     movement = String::from("Linear move to");
                 }
 
-                // Extract coordinates
+                // Extract coordinates, all axis words are sent in one call so the
+                // controller interpolates them together
+                let mut axis_list = Vec::new();
+                let mut pos_list = Vec::new();
                 for (param, value) in &command.parameters {
                     let value = value.clone() as f32;
                     match param {
-                        'X' => {
-                            zmc_move_abs(vec![0], vec![value])
-                                .await
-                                .expect("Failed to move in X direction");
-                            movement.push_str(format!(" {} in X direction,", value).as_str())
-                        }
-                        'Y' => {
-                            zmc_move_abs(vec![1], vec![value])
-                                .await
-                                .expect("Failed to move in Y direction");
-                            movement.push_str(format!(" {} in Y direction,", value).as_str())
-                        }
-                        'Z' => {
-                            zmc_move_abs(vec![2], vec![value])
-                                .await
-                                .expect("Failed to move in Z direction");
-                            movement.push_str(format!(" {} in Z direction,", value).as_str())
+                        'X' | 'Y' | 'Z' => {
+                            let axis = match param {
+                                'X' => 0,
+                                'Y' => 1,
+                                _ => 2,
+                            };
+                            if context.disabled_axes[axis as usize] {
+                                movement.push_str(
+                                    format!(" {} ignored (axis disabled),", param).as_str(),
+                                );
+                                continue;
+                            }
+                            axis_list.push(axis);
+                            pos_list
+                                .push(round_coordinate(value, context.options.coordinate_decimals));
+                            movement
+                                .push_str(format!(" {} in {} direction,", value, param).as_str())
                         }
                         'F' => {
-                            for i in 0..3 {
-                                zmc_set_speed(i, value as f32)
-                                    .await
-                                    .expect("Failed to set speed");
+                            let speed = context.feed_speed(value);
+                            for i in (0..3).filter(|i| !context.disabled_axes[*i as usize]) {
+                                if !context.dry_run {
+                                    zmc_set_speed(i, speed)
+                                        .await
+                                        .map_err(|e| format!("Failed to set speed: {}", e))?;
+                                }
                             }
                             movement.push_str(&format!(" at speed {:.0}", value));
                         }
@@ -316,6 +1593,36 @@ async fn interpret_gcode_movement(command: &GCodeCommand) {
                         }
                     }
                 }
+                let target = context.modal.position;
+                let safe_rapid = command.command_number == 0
+                    && context.options.safe_rapids
+                    && !context.disabled_axes[2]
+                    && (target[0] != from[0] || target[1] != from[1]);
+                let moves = if safe_rapid {
+                    movement.push_str(&format!(" via safe Z {:.3}", context.safe_z_height));
+                    safe_rapid_moves(
+                        axis_list,
+                        pos_list,
+                        from[2],
+                        target[2],
+                        context.safe_z_height,
+                    )
+                } else {
+                    vec![(axis_list, pos_list)]
+                };
+                for (axis_list, pos_list) in moves {
+                    if axis_list.is_empty() || context.dry_run {
+                        continue;
+                    }
+                    let wait_axes = axis_list.clone();
+                    zmc_move_abs(axis_list, pos_list)
+                        .await
+                        .map_err(|e| format!("Failed to move axes: {}", e))?;
+                    // 分段移动时每段走完再走下一段
+                    if safe_rapid {
+                        zmc_wait_idle(&wait_axes).await?;
+                    }
+                }
             }
             2 | 3 => {
                 // G2/G3: Arc movement (clockwise/counterclockwise)
@@ -350,6 +1657,8 @@ async fn interpret_gcode_movement(command: &GCodeCommand) {
                 if has_ij {
                     movement.push_str(" using IJK arc definition");
                 }
+                // 圆弧不发给控制器，模态位置却移到了终点，要报告给操作员
+                context.ignore(command)?;
             }
             4 => {
                 // G4: Dwell/pause
@@ -361,6 +1670,7 @@ async fn interpret_gcode_movement(command: &GCodeCommand) {
                     }
                 }
                 movement.push_str(format!("Pause/dwell for {:.3} milliseconds", time).as_str());
+                context.ignore(command)?;
             }
             28 => {
                 // G28: Home axes
@@ -383,38 +1693,63 @@ async fn interpret_gcode_movement(command: &GCodeCommand) {
                 } else {
                     movement.push_str(format!("Home {}", axes.join(", ")).as_str());
                 }
+                context.ignore(command)?;
+            }
+            // 只实现了公制绝对坐标：G21、G90就是当前的行为，
+            // G20只换算F值而不换算坐标，G91、G92没有实现，都作为不支持的代码报告
+            20 => {
+                movement.push_str("Set units to inches");
+                context.ignore(command)?;
             }
+            21 => movement.push_str("Set units to millimeters"),
             90 => movement.push_str("Set absolute positioning mode"),
-            91 => movement.push_str("Set relative positioning mode"),
-            92 => movement.push_str("Set position (reset origin point)"),
-            _ => movement.push_str(format!("Unknown G{} command", command.command_number).as_str()),
+            91 => {
+                movement.push_str("Set relative positioning mode");
+                context.ignore(command)?;
+            }
+            92 => {
+                movement.push_str("Set position (reset origin point)");
+                context.ignore(command)?;
+            }
+            _ => {
+                movement.push_str(format!("Unknown G{} command", command.command_number).as_str());
+                context.ignore(command)?;
+            }
         }
     }
     // Handle M commands (machine state related)
     else if command.command_type == "M" {
         match command.command_number {
             0 => {
-                zmc_converter_stop()
-                    .await
-                    .expect("Failed to stop converter");
+                if !context.dry_run {
+                    zmc_converter_stop()
+                        .await
+                        .map_err(|e| format!("Failed to stop converter: {}", e))?;
+                }
                 movement.push_str("Emergency stop");
             }
             1 => {
-                zmc_converter_stop()
-                    .await
-                    .expect("Failed to stop converter");
+                if !context.dry_run {
+                    zmc_converter_stop()
+                        .await
+                        .map_err(|e| format!("Failed to stop converter: {}", e))?;
+                }
                 movement.push_str("Sleep/pause operation");
             }
             3 | 4 => {
                 let direction = if command.command_number == 3 {
-                    zmc_converter_run(false)
-                        .await
-                        .expect("Failed to stop converter");
+                    if !context.dry_run {
+                        zmc_converter_run(false)
+                            .await
+                            .map_err(|e| format!("Failed to start converter: {}", e))?;
+                    }
                     "clockwise"
                 } else {
-                    zmc_converter_run(true)
-                        .await
-                        .expect("Failed to stop converter");
+                    if !context.dry_run {
+                        zmc_converter_run(true)
+                            .await
+                            .map_err(|e| format!("Failed to start converter: {}", e))?;
+                    }
                     "counterclockwise"
                 };
                 let mut speed = String::new();
@@ -423,18 +1758,26 @@ async fn interpret_gcode_movement(command: &GCodeCommand) {
                     if *param == 'S' {
                         speed = format!(" at speed {:.0}", value);
                         let value = value.clone();
-                        zmc_converter_set_freq(value as u32)
-                            .await
-                            .expect("Failed to set converter frequency");
+                        if !context.dry_run {
+                            zmc_converter_set_freq(value as u32)
+                                .await
+                                .map_err(|e| format!("Failed to set converter frequency: {}", e))?;
+                        }
                         break;
                     }
                 }
+                if !context.dry_run {
+                    spindle_dwell(context.spindle.spin_up_secs).await;
+                }
                 movement.push_str(format!("Spindle on {}{}", direction, speed).as_str());
             }
             5 => {
-                zmc_converter_stop()
-                    .await
-                    .expect("Failed to stop converter");
+                if !context.dry_run {
+                    zmc_converter_stop()
+                        .await
+                        .map_err(|e| format!("Failed to stop converter: {}", e))?;
+                    spindle_dwell(context.spindle.spin_down_secs).await;
+                }
                 movement.push_str("Spindle stop");
             }
             84 => movement.push_str("Stop idle hold"),
@@ -474,8 +1817,11 @@ async fn interpret_gcode_movement(command: &GCodeCommand) {
 
                 movement.push_str(format!("Set bed temperature to {:.0}°C{}", temp, wait).as_str());
             }
-            _ => movement
-                .push_str(format!("Other state change: M{}", command.command_number).as_str()),
+            _ => {
+                movement
+                    .push_str(format!("Other state change: M{}", command.command_number).as_str());
+                context.ignore(command)?;
+            }
         }
     }
     // Handle other command types
@@ -484,17 +1830,26 @@ async fn interpret_gcode_movement(command: &GCodeCommand) {
             "Non-movement command: {}{}",
             command.command_type, command.command_number
         );
+        context.ignore(command)?;
     }
     println!("command >>> {:?}", movement);
+    Ok(())
 }
 
 // Helper function to draw a line on the bitmap
 #[cfg(feature = "ssr")]
-fn draw_line(bitmap: &mut Bitmap, x1: f32, y1: f32, z1: f32, x2: f32, y2: f32, z2: f32) {
+fn draw_line(
+    bitmap: &mut Bitmap,
+    (x1, y1, z1): (f32, f32, f32),
+    (x2, y2, z2): (f32, f32, f32),
+    motion: MotionKind,
+) {
     // Use Bresenham's line algorithm for drawing
     let dx = (x2 - x1).abs();
     let dy = (y2 - y1).abs();
-    let steps = dx.max(dy).max(1.0) * 4.0; // Increase resolution for smoother lines
+    // One sample per pixel along the longer axis in bitmap space
+    let (scale_x, scale_y) = bitmap.scale();
+    let steps = (dx * scale_x).max(dy * scale_y).max(1.0);
 
     // Interpolate points along the line
     for i in 0..=steps as usize {
@@ -503,8 +1858,8 @@ fn draw_line(bitmap: &mut Bitmap, x1: f32, y1: f32, z1: f32, x2: f32, y2: f32, z
         let y = y1 + (y2 - y1) * t;
         let z = z1 + (z2 - z1) * t;
 
-        // Set the pixel in the bitmap - z value determines color
-        bitmap.set_pixel(x, y, z);
+        // Set the pixel in the bitmap - z value determines color, rapids may be dashed
+        bitmap.set_motion_pixel(x, y, z, motion);
     }
 }
 
@@ -615,7 +1970,10 @@ fn preview_gcode_movement(
                 if has_movement {
                     // Draw line from current position to target position
                     draw_line(
-                        bitmap, *current_x, *current_y, *current_z, target_x, target_y, target_z,
+                        bitmap,
+                        (*current_x, *current_y, *current_z),
+                        (target_x, target_y, target_z),
+                        MotionKind::from_g_number(command.command_number).unwrap_or_default(),
                     );
 
                     // Update current position
@@ -696,44 +2054,1417 @@ pub async fn debug_update_line() -> Result<(), ServerFnError> {
     Ok(())
 }
 
+// 供实时路径叠加显示的预览图
+#[cfg(feature = "ssr")]
+pub(crate) async fn preview_bitmap() -> Option<Bitmap> {
+    G_CODE_MANAGER.preview_bitmap().await
+}
+
+// 程序正在运行或暂停时，原始指令等操作会打乱执行状态
+#[cfg(feature = "ssr")]
+pub(crate) fn program_active() -> bool {
+    G_CODE_MANAGER.run_state.get_untracked().can_stop()
+}
+
+// 门禁联锁触发时暂停正在执行的G代码，保留当前行以便关门后继续；空运行不控制机床，不暂停
+#[cfg(feature = "ssr")]
+pub(crate) async fn hold_for_interlock() {
+    if !*G_CODE_MANAGER.run_dry_run.lock().await {
+        G_CODE_MANAGER.pause().await;
+    }
+}
+
 #[cfg(feature = "ssr")]
 static G_CODE_MANAGER: LazyLock<GCodeManager> = LazyLock::new(|| GCodeManager {
-    lines: Arc::new(Mutex::new(Vec::new())),
+    lines: Arc::new(Mutex::new(GCodeLines::Memory(Vec::new()))),
     current_line: ServerSignal::new("current_line".to_string(), 0).unwrap(),
+    run_state: ServerSignal::new("run_state".to_string(), RunState::Idle).unwrap(),
+    options: Arc::new(Mutex::new(ExecutionOptions::default())),
     thread_handle: Arc::new(Mutex::new(None)),
+    safe_stop_requested: Arc::new(Mutex::new(false)),
     bitmap: Arc::new(Mutex::new(Bitmap::new(800, 800, 4.0))),
+    last_job_stats: Arc::new(Mutex::new(None)),
     path_img_preview: ServerSignal::new("path_img_preview".to_string(), String::new()).unwrap(),
     preview_processed_line: ServerSignal::new("preview_processed_line".to_string(), 0).unwrap(),
+    ignored_codes: ServerSignal::new("ignored_gcodes".to_string(), Vec::new()).unwrap(),
+    clamped_feeds: ServerSignal::new("clamped_feeds".to_string(), Vec::new()).unwrap(),
+    mdi_modal: Arc::new(Mutex::new(ModalState::default())),
+    breakpoints: Arc::new(Mutex::new(HashSet::new())),
+    breakpoint_lines: ServerSignal::new("breakpoints".to_string(), Vec::new()).unwrap(),
+    job_counter: Arc::new(Mutex::new(0)),
+    recent_lock: Arc::new(Mutex::new(())),
+    owner: Arc::new(Mutex::new(None)),
+    program_name: Arc::new(Mutex::new(String::new())),
+    run_started_ms: Arc::new(Mutex::new(0)),
+    run_dry_run: Arc::new(Mutex::new(false)),
+    run_options: Arc::new(Mutex::new(ExecutionOptions::default())),
+    path_bounds: Arc::new(Mutex::new(None)),
+    run_progress: Arc::new(Mutex::new(JobStats::default())),
+    sessions: Arc::new(Mutex::new(HashMap::new())),
 });
 
+// 会话cookie的名称，标识由服务端生成，页面脚本读不到也不能指定
+#[cfg(feature = "ssr")]
+const SESSION_COOKIE: &str = "zmc_session";
+
+// 会话超过这段时间没有使用就作废，会话数超过上限时先丢弃最久没有使用的
+#[cfg(feature = "ssr")]
+const SESSION_EXPIRY: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+#[cfg(feature = "ssr")]
+const MAX_SESSIONS: usize = 1024;
+
+// 清理过期的会话并限制会话数，正在运行的程序的所有者(keep)不清理
+#[cfg(feature = "ssr")]
+fn prune_sessions(
+    sessions: &mut HashMap<String, std::time::Instant>,
+    keep: Option<&str>,
+    now: std::time::Instant,
+) {
+    sessions.retain(|id, used| Some(id.as_str()) == keep || now - *used < SESSION_EXPIRY);
+    while sessions.len() > MAX_SESSIONS {
+        let oldest = sessions
+            .iter()
+            .filter(|(id, _)| Some(id.as_str()) != keep)
+            .min_by_key(|(_, used)| **used)
+            .map(|(id, _)| id.clone());
+        match oldest {
+            Some(id) => sessions.remove(&id),
+            None => break,
+        };
+    }
+}
+
+// 发出请求的客户端会话：cookie中带有服务端发过的标识时沿用，否则生成新的标识通过cookie下发
+#[cfg(feature = "ssr")]
+async fn client_session() -> Result<String, ServerFnError> {
+    let parts = use_context::<axum::http::request::Parts>()
+        .ok_or_else(|| ServerFnError::new("Missing request context"))?;
+    let presented = parts
+        .headers
+        .get_all(axum::http::header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .find_map(|cookie| {
+            cookie
+                .trim()
+                .strip_prefix(SESSION_COOKIE)?
+                .strip_prefix('=')
+        })
+        .map(|id| id.to_string());
+    let owner = G_CODE_MANAGER.owner.lock().await.clone();
+    let now = std::time::Instant::now();
+    let mut sessions = G_CODE_MANAGER.sessions.lock().await;
+    prune_sessions(&mut sessions, owner.as_deref(), now);
+    if let Some(id) = presented.filter(|id| sessions.contains_key(id)) {
+        sessions.insert(id.clone(), now);
+        return Ok(id);
+    }
+    let id = new_session_id()?;
+    let cookie = format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Strict",
+        SESSION_COOKIE, id
+    );
+    expect_context::<leptos_axum::ResponseOptions>().append_header(
+        axum::http::header::SET_COOKIE,
+        cookie.parse().map_err(ServerFnError::new)?,
+    );
+    sessions.insert(id.clone(), now);
+    Ok(id)
+}
+
+// 128位的随机标识，取自操作系统的安全随机数
+#[cfg(feature = "ssr")]
+fn new_session_id() -> Result<String, ServerFnError> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| ServerFnError::new(e.to_string()))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+// 修改执行状态的接口都检查客户端会话，运行或暂停期间只接受启动它的客户端
+#[cfg(feature = "ssr")]
+async fn check_owner() -> Result<String, ServerFnError> {
+    let client = client_session().await?;
+    G_CODE_MANAGER
+        .check_owner(&client)
+        .await
+        .map_err(ServerFnError::new)?;
+    Ok(client)
+}
+
+// 运行依赖的运动、输出和变频器接口：运行或暂停期间只接受启动它的客户端。
+// 运行任务自己发出的调用没有请求上下文，不检查
+#[cfg(feature = "ssr")]
+pub(crate) async fn check_controlling_client() -> Result<(), ServerFnError> {
+    if !program_active() || use_context::<axum::http::request::Parts>().is_none() {
+        return Ok(());
+    }
+    check_owner().await.map(|_| ())
+}
+
 #[server]
 pub async fn load_gcode(content: String) -> Result<(), ServerFnError> {
+    check_owner().await?;
     println!("start loading gcode");
-    G_CODE_MANAGER.load_gcode(content).await;
-    Ok(())
+    G_CODE_MANAGER
+        .load_gcode(content)
+        .await
+        .map_err(ServerFnError::new)
 }
+// 分块上传G代码文件：begin清空临时文件，append追加一块，finish建立索引并返回总行数
 #[server]
-pub async fn start_gcode_execution() -> Result<(), ServerFnError> {
-    Ok(G_CODE_MANAGER
-        .start()
+pub async fn begin_gcode_upload() -> Result<(), ServerFnError> {
+    check_owner().await?;
+    G_CODE_MANAGER
+        .begin_upload()
         .await
-        .map_err(|e| ServerFnError::new(e))?)
+        .map_err(ServerFnError::new)
 }
 #[server]
-pub async fn stop_gcode_execution() -> Result<(), ServerFnError> {
-    G_CODE_MANAGER.stop().await;
-    Ok(())
+pub async fn append_gcode_upload(chunk: Vec<u8>) -> Result<(), ServerFnError> {
+    check_owner().await?;
+    G_CODE_MANAGER
+        .append_upload(&chunk)
+        .await
+        .map_err(ServerFnError::new)
 }
 #[server]
-pub async fn reset_gcode_execution() -> Result<(), ServerFnError> {
-    G_CODE_MANAGER.reset().await;
-    Ok(())
+pub async fn finish_gcode_upload(name: String) -> Result<usize, ServerFnError> {
+    check_owner().await?;
+    G_CODE_MANAGER
+        .finish_upload(&name)
+        .await
+        .map_err(ServerFnError::new)
+}
+// 最近上传的文件，最新的在前
+#[server]
+pub async fn get_recent_gcode_files() -> Result<Vec<RecentGCodeFile>, ServerFnError> {
+    Ok(G_CODE_MANAGER.recent_files().await)
 }
 #[server]
-pub async fn generate_path_preview() -> Result<(), ServerFnError> {
+pub async fn load_recent_gcode(name: String) -> Result<usize, ServerFnError> {
+    check_owner().await?;
     G_CODE_MANAGER
+        .load_recent(&name)
+        .await
+        .map_err(ServerFnError::new)
+}
+// 读取从start开始的count行用于显示，同时返回总行数
+#[server]
+pub async fn get_gcode_window(start: usize, count: usize) -> Result<GCodeWindow, ServerFnError> {
+    Ok(G_CODE_MANAGER.window(start, count).await)
+}
+// 当前程序路径超出软限位的情况，开始前提示操作者确认
+#[server]
+pub async fn get_path_limit_violations() -> Result<Vec<String>, ServerFnError> {
+    G_CODE_MANAGER
+        .limit_violations()
+        .await
+        .map_err(ServerFnError::new)
+}
+// allow_out_of_limits为false时，路径超出软限位会被拒绝
+#[server]
+pub async fn start_gcode_execution(allow_out_of_limits: bool) -> Result<(), ServerFnError> {
+    let client = client_session().await?;
+    Ok(G_CODE_MANAGER
+        .start(false, allow_out_of_limits, &client)
+        .await
+        .map_err(|e| ServerFnError::new(e))?)
+}
+// 空运行，只推进进度不控制机床
+#[server]
+pub async fn start_gcode_execution_dry_run(allow_out_of_limits: bool) -> Result<(), ServerFnError> {
+    let client = client_session().await?;
+    Ok(G_CODE_MANAGER
+        .start(true, allow_out_of_limits, &client)
+        .await
+        .map_err(|e| ServerFnError::new(e))?)
+}
+// 从停止处继续执行，会先恢复主轴等状态
+#[server]
+pub async fn resume_gcode_execution() -> Result<(), ServerFnError> {
+    let client = client_session().await?;
+    Ok(G_CODE_MANAGER
+        .resume(&client)
+        .await
+        .map_err(|e| ServerFnError::new(e))?)
+}
+// 暂停执行，保留当前行以便继续
+#[server]
+pub async fn pause_gcode_execution() -> Result<(), ServerFnError> {
+    G_CODE_MANAGER.pause().await;
+    Ok(())
+}
+// 安全停止：走完当前行后停主轴并抬刀
+#[server]
+pub async fn stop_gcode_execution() -> Result<(), ServerFnError> {
+    G_CODE_MANAGER.safe_stop().await;
+    Ok(())
+}
+// 紧急中止：立即中止执行，不等待当前运动完成
+#[server]
+pub async fn abort_gcode_execution() -> Result<(), ServerFnError> {
+    G_CODE_MANAGER.stop().await;
+    Ok(())
+}
+#[server]
+pub async fn set_execution_options(options: ExecutionOptions) -> Result<(), ServerFnError> {
+    check_owner().await?;
+    G_CODE_MANAGER.set_options(options).await;
+    Ok(())
+}
+// 设置断点行索引(从0开始)，覆盖原有的断点
+#[server]
+pub async fn set_breakpoints(lines: Vec<usize>) -> Result<(), ServerFnError> {
+    check_owner().await?;
+    G_CODE_MANAGER.set_breakpoints(lines).await;
+    Ok(())
+}
+// MDI：立即执行一行G/M代码，程序运行或暂停时不可用
+#[server]
+pub async fn execute_mdi_line(line: String) -> Result<(), ServerFnError> {
+    check_owner().await?;
+    G_CODE_MANAGER
+        .execute_mdi(&line)
+        .await
+        .map_err(ServerFnError::new)
+}
+// 保存的运行记录，最新的在前
+#[server]
+pub async fn get_run_history() -> Result<Vec<RunRecord>, ServerFnError> {
+    Ok(load_run_history())
+}
+// 最近一次运行的统计，没有运行过时返回None
+#[server]
+pub async fn get_last_job_stats() -> Result<Option<JobStats>, ServerFnError> {
+    Ok(G_CODE_MANAGER.last_job_stats().await)
+}
+#[server]
+pub async fn reset_gcode_execution() -> Result<(), ServerFnError> {
+    check_owner().await?;
+    G_CODE_MANAGER.reset().await;
+    Ok(())
+}
+// 设置预览图像背景色和线宽
+#[server]
+pub async fn set_preview_style(style: PathImageStyle) -> Result<(), ServerFnError> {
+    check_owner().await?;
+    G_CODE_MANAGER.set_preview_style(style).await;
+    Ok(())
+}
+#[server]
+pub async fn generate_path_preview() -> Result<Option<PathBounds>, ServerFnError> {
+    let bounds = G_CODE_MANAGER
         .generate_path_preview()
         .await
         .expect("Failed to generate path preview");
-    Ok(())
+    Ok(bounds)
+}
+
+#[cfg(feature = "ssr")]
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct JobQuery {
+    #[serde(default)]
+    validate: bool,
+}
+
+// 供任务调度系统提交G代码：POST /api/job，请求体为G代码文本，?validate=true时先检查语法
+#[cfg(feature = "ssr")]
+pub async fn job_handler(
+    axum::extract::Query(query): axum::extract::Query<JobQuery>,
+    body: String,
+) -> Result<axum::Json<JobSubmission>, (axum::http::StatusCode, String)> {
+    if body.trim().is_empty() {
+        return Err((
+            axum::http::StatusCode::BAD_REQUEST,
+            "Empty G-code program".to_string(),
+        ));
+    }
+    // 其他客户端的程序暂停时也算作占用
+    let in_progress = G_CODE_MANAGER.is_running().await
+        || G_CODE_MANAGER.check_owner(JOB_API_CLIENT).await.is_err();
+    G_CODE_MANAGER
+        .submit_job(body, query.validate)
+        .await
+        .map(|job_id| axum::Json(JobSubmission { job_id }))
+        .map_err(|e| {
+            let status = if in_progress {
+                axum::http::StatusCode::CONFLICT
+            } else {
+                axum::http::StatusCode::BAD_REQUEST
+            };
+            (status, e)
+        })
+}
+
+#[cfg(all(test, feature = "ssr"))]
+pub(crate) mod tests {
+    use super::super::zmc::tests::take_command_log;
+    use super::*;
+    use tokio::time::Duration;
+
+    #[test]
+    fn safe_rapid_moves_descends_to_a_target_below_the_safe_height() {
+        let moves = safe_rapid_moves(vec![0, 1, 2], vec![10.0, 20.0, -1.0], -2.0, -1.0, 5.0);
+        assert_eq!(
+            moves,
+            vec![
+                (vec![2], vec![5.0]),
+                (vec![0, 1], vec![10.0, 20.0]),
+                (vec![2], vec![-1.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn safe_rapid_moves_stays_at_a_target_equal_to_the_safe_height() {
+        let moves = safe_rapid_moves(vec![0, 1, 2], vec![10.0, 20.0, 5.0], -2.0, 5.0, 5.0);
+        assert_eq!(
+            moves,
+            vec![(vec![2], vec![5.0]), (vec![0, 1], vec![10.0, 20.0])]
+        );
+    }
+
+    #[test]
+    fn mdi_rejects_unsupported_codes_by_name() {
+        let command = parse_gcode_line("M7").remove(0);
+        let mut context = ExecutionContext {
+            reject_unsupported: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            context.ignore(&command),
+            Err("Unsupported code M7".to_string())
+        );
+        assert!(context.ignored_codes.is_empty());
+    }
+
+    #[test]
+    fn program_runs_skip_and_record_unsupported_codes() {
+        let command = parse_gcode_line("T1").remove(0);
+        let mut context = ExecutionContext {
+            line_number: 3,
+            ..Default::default()
+        };
+        assert_eq!(context.ignore(&command), Ok(()));
+        assert_eq!(context.ignored_codes.len(), 1);
+        assert_eq!(context.ignored_codes[0].code, "T1");
+    }
+
+    #[test]
+    fn codes_that_are_not_executed_are_reported_as_ignored() {
+        let program = [
+            "G2 X1 Y1 I1 J0",
+            "G3 X0 Y0 I-1 J0",
+            "G4 P100",
+            "G28",
+            "G20",
+            "G91",
+            "G92 X0",
+            "G21",
+            "G90",
+        ];
+        let mut context = ExecutionContext {
+            dry_run: true,
+            ..Default::default()
+        };
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            for (i, line) in program.into_iter().enumerate() {
+                context.line_number = i + 1;
+                for command in parse_gcode_line(line) {
+                    interpret_gcode_movement(&command, &mut context)
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+        let ignored: Vec<&str> = context
+            .ignored_codes
+            .iter()
+            .map(|code| code.code.as_str())
+            .collect();
+        assert_eq!(ignored, ["G2", "G3", "G4", "G28", "G20", "G91", "G92"]);
+    }
+
+    #[test]
+    fn safe_rapid_moves_rises_to_a_target_above_the_safe_height() {
+        let moves = safe_rapid_moves(vec![0, 1, 2], vec![10.0, 20.0, 8.0], 6.0, 8.0, 5.0);
+        assert_eq!(
+            moves,
+            vec![(vec![0, 1], vec![10.0, 20.0]), (vec![2], vec![8.0])]
+        );
+    }
+
+    fn replay(lines: &[&str]) -> ModalState {
+        ModalState::replay(lines.iter().map(|line| line.to_string()), false)
+    }
+
+    #[test]
+    fn modal_replay_keeps_the_last_spindle_feed_units_and_position() {
+        let modal = replay(&["G20", "M4 S8000", "G1 X1 Y2 F30", "G0 Z5", "G1 Z-1"]);
+        assert_eq!(
+            modal,
+            ModalState {
+                spindle: Some(true),
+                spindle_freq: Some(8000),
+                feed: Some(30.0),
+                inch: true,
+                position: [1.0, 2.0, -1.0],
+            }
+        );
+    }
+
+    #[test]
+    fn modal_replay_forgets_the_spindle_after_a_stop_but_keeps_its_frequency() {
+        let modal = replay(&["M3 S12000", "G1 X5 F600", "M5"]);
+        assert_eq!(modal.spindle, None);
+        assert_eq!(modal.spindle_freq, Some(12000));
+        assert_eq!(modal.feed, Some(600.0));
+    }
+
+    #[test]
+    fn modal_replay_ignores_feeds_on_arcs_and_comments() {
+        let modal = replay(&["; M3 S1000", "G1 F100", "G2 X1 Y1 I1 J0 F900", "G21"]);
+        assert_eq!(modal.spindle, None);
+        assert_eq!(modal.feed, Some(100.0));
+        assert!(!modal.inch);
+        assert_eq!(modal.position, [1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn modal_replay_skips_slashed_lines_only_with_block_delete_on() {
+        let lines = || {
+            ["/M3 S12000", "/G20", "G1 X1 F600"]
+                .map(String::from)
+                .into_iter()
+        };
+        let skipped = ModalState::replay(lines(), true);
+        assert_eq!((skipped.spindle, skipped.inch), (None, false));
+        let kept = ModalState::replay(lines(), false);
+        assert_eq!((kept.spindle, kept.inch), (Some(false), true));
+    }
+
+    #[test]
+    fn modal_replay_of_no_lines_is_the_default_state() {
+        assert_eq!(replay(&[]), ModalState::default());
+    }
+
+    #[test]
+    fn feed_to_speed_converts_per_minute_feeds_to_per_second() {
+        let options = ExecutionOptions::default();
+        assert_eq!(feed_to_speed(600.0, false, &options), 10.0);
+    }
+
+    #[test]
+    fn feed_to_speed_converts_inches_to_millimetres() {
+        let options = ExecutionOptions::default();
+        assert!((feed_to_speed(60.0, true, &options) - 25.4).abs() < 1e-4);
+    }
+
+    #[test]
+    fn feed_to_speed_keeps_the_feed_when_the_controller_uses_units_per_minute() {
+        let options = ExecutionOptions {
+            speed_per_minute: true,
+            ..Default::default()
+        };
+        assert_eq!(feed_to_speed(600.0, false, &options), 600.0);
+        assert!((feed_to_speed(10.0, true, &options) - 254.0).abs() < 1e-3);
+    }
+
+    fn path_bounds(lines: &[&str]) -> Option<PathBounds> {
+        compute_path_bounds(lines.iter().map(|line| line.to_string()))
+    }
+
+    #[test]
+    fn path_bounds_cover_the_xy_end_points_of_every_move() {
+        let bounds = path_bounds(&["G0 X10 Y5", "G1 X-3", "G2 X20 Y-4 I5 J0", "G1 Z-2"]);
+        assert_eq!(
+            bounds,
+            Some(PathBounds {
+                min_x: -3.0,
+                max_x: 20.0,
+                min_y: -4.0,
+                max_y: 5.0,
+            })
+        );
+    }
+
+    #[test]
+    fn path_bounds_are_none_without_xy_moves() {
+        assert_eq!(path_bounds(&["M3 S1000", "G1 Z-1 F100", "M5"]), None);
+    }
+
+    #[test]
+    fn a_program_leaving_the_work_area_reports_each_violated_limit() {
+        let bounds = path_bounds(&["G0 X0 Y0", "G1 X120 Y-5"]).unwrap();
+        let work_area = PathBounds {
+            min_x: 0.0,
+            max_x: 100.0,
+            min_y: 0.0,
+            max_y: 100.0,
+        };
+        assert_eq!(
+            bounds.violations(&work_area),
+            vec![
+                "X+ 120.000 > 100.000".to_string(),
+                "Y- -5.000 < 0.000".to_string()
+            ]
+        );
+    }
+
+    fn recent(name: &str, uploaded_ms: i64) -> RecentGCodeFile {
+        RecentGCodeFile {
+            name: name.to_string(),
+            lines: 1,
+            uploaded_ms,
+        }
+    }
+
+    fn names(list: &[RecentGCodeFile]) -> Vec<&str> {
+        list.iter().map(|recent| recent.name.as_str()).collect()
+    }
+
+    #[test]
+    fn recent_files_put_the_newest_upload_first() {
+        let mut list = vec![recent("a.nc", 1), recent("b.nc", 2)];
+        let removed = push_recent_gcode(&mut list, recent("c.nc", 3), 5);
+        assert_eq!(names(&list), vec!["c.nc", "a.nc", "b.nc"]);
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn recent_files_replace_an_earlier_upload_of_the_same_name() {
+        let mut list = vec![recent("a.nc", 1), recent("b.nc", 2)];
+        let removed = push_recent_gcode(&mut list, recent("b.nc", 3), 5);
+        assert_eq!(names(&list), vec!["b.nc", "a.nc"]);
+        assert_eq!(list[0].uploaded_ms, 3);
+        // 同名文件的副本被覆盖，不能当作移除的文件删掉
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn recent_files_return_the_oldest_entries_beyond_the_capacity() {
+        let mut list = vec![recent("a.nc", 3), recent("b.nc", 2), recent("c.nc", 1)];
+        let removed = push_recent_gcode(&mut list, recent("d.nc", 4), 2);
+        assert_eq!(names(&list), vec!["d.nc", "a.nc"]);
+        assert_eq!(names(&removed), vec!["b.nc", "c.nc"]);
+    }
+
+    fn end_options(end_retract: bool, end_return_xy: bool) -> ExecutionOptions {
+        ExecutionOptions {
+            end_retract,
+            end_return_xy,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn end_of_program_does_not_move_unless_enabled() {
+        let moves = end_of_program_moves(&end_options(false, false), -2.0, 5.0, [false; 3]);
+        assert!(moves.is_empty());
+    }
+
+    #[test]
+    fn end_of_program_retracts_a_low_z() {
+        let moves = end_of_program_moves(&end_options(true, false), -2.0, 5.0, [false; 3]);
+        assert_eq!(moves, vec![(vec![2], vec![5.0])]);
+        let moves = end_of_program_moves(&end_options(true, false), 8.0, 5.0, [false; 3]);
+        assert!(moves.is_empty());
+    }
+
+    #[test]
+    fn end_of_program_retracts_before_returning_to_xy_zero() {
+        let moves = end_of_program_moves(&end_options(false, true), -2.0, 5.0, [false; 3]);
+        assert_eq!(
+            moves,
+            vec![(vec![2], vec![5.0]), (vec![0, 1], vec![0.0, 0.0])]
+        );
+    }
+
+    #[test]
+    fn end_of_program_skips_disabled_axes() {
+        let moves = end_of_program_moves(&end_options(true, true), -2.0, 5.0, [true, false, true]);
+        assert_eq!(moves, vec![(vec![1], vec![0.0])]);
+    }
+
+    #[test]
+    fn safe_stop_stops_the_spindle_then_retracts_a_low_z() {
+        assert_eq!(
+            safe_stop_steps(-2.0, 5.0, false),
+            vec![SafeStopStep::SpindleStop, SafeStopStep::RetractZ(5.0)]
+        );
+    }
+
+    #[test]
+    fn safe_stop_does_not_move_z_at_or_above_the_safe_height() {
+        assert_eq!(
+            safe_stop_steps(5.0, 5.0, false),
+            vec![SafeStopStep::SpindleStop]
+        );
+        assert_eq!(
+            safe_stop_steps(9.0, 5.0, false),
+            vec![SafeStopStep::SpindleStop]
+        );
+    }
+
+    #[test]
+    fn safe_stop_does_not_retract_a_disabled_z() {
+        assert_eq!(
+            safe_stop_steps(-2.0, 5.0, true),
+            vec![SafeStopStep::SpindleStop]
+        );
+    }
+
+    // 用到全局控制器和G代码管理器的测试不能同时运行
+    static FAKE_CONTROLLER_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    // X、Y、Z使用不同轴号的参数，默认参数的轴号都是0
+    pub(crate) fn fake_parameters() -> Parameters {
+        let mut params = Parameters::default();
+        (params.x.axis_num, params.y.axis_num, params.z.axis_num) = (0, 1, 2);
+        params
+    }
+
+    // 连接模拟控制器后执行测试。全局管理器中的ServerSignal要在提供了ServerSignals的上下文中创建，
+    // 运行记录和统计写到临时目录，并允许测试开启开发者模式
+    pub(crate) fn with_fake_controller<F: std::future::Future<Output = ()>>(
+        test: impl FnOnce() -> F,
+    ) {
+        let _guard = FAKE_CONTROLLER_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = std::env::temp_dir().join("zmc_controller_upper_tests");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::env::set_current_dir(&dir).unwrap();
+        std::env::set_var("ZMC_ALLOW_DEVELOPER_MODE", "1");
+        Owner::new().with(|| {
+            provide_context(leptos_ws::server_signals::ServerSignals::new());
+            LazyLock::force(&G_CODE_MANAGER);
+            tokio::runtime::Runtime::new().unwrap().block_on(async {
+                crate::api::zmc_init_fake().await.unwrap();
+                crate::api::zmc_set_parameters(fake_parameters())
+                    .await
+                    .unwrap();
+                G_CODE_MANAGER.stop().await;
+                G_CODE_MANAGER
+                    .set_options(ExecutionOptions::default())
+                    .await;
+                take_command_log().await;
+                test().await;
+                crate::api::zmc_close().await.unwrap();
+            })
+        });
+    }
+
+    // 每行之间至少间隔一段时间的程序，运行中途有时间暂停或停止；这些行不移动轴
+    async fn load_slow_program(lines: usize) {
+        G_CODE_MANAGER
+            .load_gcode(vec!["G90"; lines].join("\n"))
+            .await
+            .unwrap();
+        G_CODE_MANAGER
+            .set_options(ExecutionOptions {
+                min_line_interval_ms: 100,
+                ..Default::default()
+            })
+            .await;
+    }
+
+    // 等待运行任务结束(完成、出错、断点暂停或安全停止)，返回之后的运行状态
+    async fn settled_state() -> RunState {
+        let settled = async {
+            while G_CODE_MANAGER.is_running().await {
+                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(tokio::time::Duration::from_secs(10), settled)
+            .await
+            .expect("G-code run did not finish");
+        G_CODE_MANAGER.run_state.get_untracked()
+    }
+
+    #[test]
+    fn a_run_executes_every_line_then_completes() {
+        with_fake_controller(|| async {
+            let program = "G90\nG1 X1 F600\nG1 Y1\nG0 X0 Y0";
+            G_CODE_MANAGER
+                .load_gcode(program.to_string())
+                .await
+                .unwrap();
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+            assert_eq!(G_CODE_MANAGER.current_line.get_untracked(), 4);
+            let stats = G_CODE_MANAGER.last_job_stats().await.unwrap();
+            assert_eq!(stats.lines_executed, 4);
+            assert_eq!(stats.status, RunState::Completed);
+        });
+    }
+
+    #[test]
+    fn job_statistics_add_up_the_lines_and_distance_of_a_run() {
+        with_fake_controller(|| async {
+            let program = "G90\nG1 X3 F600\nG1 Y4\nG0 X0 Y0";
+            G_CODE_MANAGER
+                .load_gcode(program.to_string())
+                .await
+                .unwrap();
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+            let stats = G_CODE_MANAGER.last_job_stats().await.unwrap();
+            assert_eq!(stats.lines_executed, 4);
+            // 3 + 4 + 斜边5
+            assert!((stats.distance - 12.0).abs() < 1e-4, "{}", stats.distance);
+            assert!(stats.runtime_secs > 0.0);
+        });
+    }
+
+    #[test]
+    fn a_throttled_run_takes_at_least_the_min_interval_per_line() {
+        with_fake_controller(|| async {
+            load_slow_program(5).await;
+            let started = std::time::Instant::now();
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+            let elapsed = started.elapsed();
+            assert!(
+                elapsed >= std::time::Duration::from_millis(500),
+                "{:?}",
+                elapsed
+            );
+        });
+    }
+
+    #[test]
+    fn pausing_keeps_the_current_line() {
+        with_fake_controller(|| async {
+            load_slow_program(10).await;
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+            G_CODE_MANAGER.pause().await;
+            assert_eq!(settled_state().await, RunState::Paused);
+            let paused_at = G_CODE_MANAGER.current_line.get_untracked();
+            assert!((1..10).contains(&paused_at), "paused at line {}", paused_at);
+            tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+            assert_eq!(G_CODE_MANAGER.current_line.get_untracked(), paused_at);
+            // 暂停的运行还没有结束，不能加载别的程序
+            assert!(G_CODE_MANAGER.load_gcode("G90".to_string()).await.is_err());
+        });
+    }
+
+    #[test]
+    fn resuming_continues_from_the_paused_line_and_keeps_the_statistics() {
+        with_fake_controller(|| async {
+            load_slow_program(6).await;
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+            G_CODE_MANAGER.pause().await;
+            assert_eq!(settled_state().await, RunState::Paused);
+            assert!(G_CODE_MANAGER.current_line.get_untracked() > 0);
+            // 暂停的运行只有开始它的客户端能继续
+            assert!(G_CODE_MANAGER.resume("b").await.is_err());
+            G_CODE_MANAGER.resume("a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+            assert_eq!(G_CODE_MANAGER.current_line.get_untracked(), 6);
+            let stats = G_CODE_MANAGER.last_job_stats().await.unwrap();
+            assert_eq!(stats.lines_executed, 6);
+            assert!(stats.runtime_secs >= 0.5, "runtime {}", stats.runtime_secs);
+        });
+    }
+
+    #[test]
+    fn only_a_paused_run_can_be_resumed() {
+        with_fake_controller(|| async {
+            load_slow_program(2).await;
+            assert!(G_CODE_MANAGER.resume("a").await.is_err());
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            assert!(G_CODE_MANAGER.resume("a").await.is_err());
+            assert_eq!(settled_state().await, RunState::Completed);
+            assert!(G_CODE_MANAGER.resume("a").await.is_err());
+        });
+    }
+
+    #[test]
+    fn a_breakpoint_pauses_before_its_line_and_resuming_runs_it() {
+        with_fake_controller(|| async {
+            let program = "G90\nG1 X1 F600\nG1 Y1\nG0 X0 Y0";
+            G_CODE_MANAGER
+                .load_gcode(program.to_string())
+                .await
+                .unwrap();
+            G_CODE_MANAGER.set_breakpoints(vec![2]).await;
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Paused);
+            assert_eq!(G_CODE_MANAGER.current_line.get_untracked(), 2);
+            G_CODE_MANAGER.resume("a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+            assert_eq!(
+                G_CODE_MANAGER
+                    .last_job_stats()
+                    .await
+                    .unwrap()
+                    .lines_executed,
+                4
+            );
+        });
+    }
+
+    #[test]
+    fn loading_a_program_clears_the_breakpoints() {
+        with_fake_controller(|| async {
+            G_CODE_MANAGER
+                .load_gcode("G90\nG90".to_string())
+                .await
+                .unwrap();
+            G_CODE_MANAGER.set_breakpoints(vec![1]).await;
+            G_CODE_MANAGER
+                .load_gcode("G90\nG90".to_string())
+                .await
+                .unwrap();
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+        });
+    }
+
+    fn last_run_outcome() -> RunOutcome {
+        load_run_history().first().expect("no run record").status
+    }
+
+    #[test]
+    fn safe_stop_while_running_finishes_the_line_then_ends_the_run() {
+        with_fake_controller(|| async {
+            load_slow_program(10).await;
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+            G_CODE_MANAGER.safe_stop().await;
+            assert_eq!(settled_state().await, RunState::Idle);
+            assert_eq!(G_CODE_MANAGER.current_line.get_untracked(), 0);
+            assert_eq!(last_run_outcome(), RunOutcome::Stopped);
+        });
+    }
+
+    #[test]
+    fn safe_stop_while_paused_ends_the_run_as_stopped() {
+        with_fake_controller(|| async {
+            load_slow_program(4).await;
+            G_CODE_MANAGER.set_breakpoints(vec![2]).await;
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Paused);
+            G_CODE_MANAGER.safe_stop().await;
+            assert_eq!(G_CODE_MANAGER.run_state.get_untracked(), RunState::Idle);
+            assert_eq!(G_CODE_MANAGER.current_line.get_untracked(), 0);
+            assert_eq!(last_run_outcome(), RunOutcome::Stopped);
+            // 结束后不能再继续
+            assert!(G_CODE_MANAGER.resume("a").await.is_err());
+        });
+    }
+
+    #[test]
+    fn stopping_a_paused_run_records_it_as_aborted() {
+        with_fake_controller(|| async {
+            load_slow_program(4).await;
+            G_CODE_MANAGER.set_breakpoints(vec![2]).await;
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Paused);
+            G_CODE_MANAGER.stop().await;
+            assert_eq!(G_CODE_MANAGER.run_state.get_untracked(), RunState::Idle);
+            assert_eq!(last_run_outcome(), RunOutcome::Aborted);
+        });
+    }
+
+    // 模拟控制器的输入都是低电平，反转门开关电平后就相当于门打开
+    async fn set_door_open(open: bool) {
+        let mut params = fake_parameters();
+        params.door_interlock = true;
+        params.inverted_status.door_switch_level_inverted = open;
+        crate::api::zmc_set_parameters(params).await.unwrap();
+    }
+
+    #[test]
+    fn an_open_door_refuses_to_start_a_run_but_not_a_dry_run() {
+        with_fake_controller(|| async {
+            load_slow_program(2).await;
+            set_door_open(true).await;
+            assert!(super::super::zmc::door_interlock_engaged().await);
+            assert!(G_CODE_MANAGER.start(false, true, "a").await.is_err());
+            assert_eq!(G_CODE_MANAGER.run_state.get_untracked(), RunState::Idle);
+            G_CODE_MANAGER.start(true, true, "a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+        });
+    }
+
+    #[test]
+    fn opening_the_door_holds_the_run_until_it_is_closed() {
+        with_fake_controller(|| async {
+            load_slow_program(10).await;
+            set_door_open(false).await;
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+            // 轮询读到门打开后暂停运行
+            set_door_open(true).await;
+            assert_eq!(settled_state().await, RunState::Paused);
+            let held_at = G_CODE_MANAGER.current_line.get_untracked();
+            assert!(held_at < 10, "held at line {}", held_at);
+            assert!(G_CODE_MANAGER.resume("a").await.is_err());
+            set_door_open(false).await;
+            G_CODE_MANAGER.resume("a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+            assert_eq!(G_CODE_MANAGER.current_line.get_untracked(), 10);
+        });
+    }
+
+    #[test]
+    fn expired_sessions_are_dropped_except_the_owner() {
+        let now = std::time::Instant::now();
+        let old = now - SESSION_EXPIRY;
+        let mut sessions = HashMap::from([
+            ("old".to_string(), old),
+            ("owner".to_string(), old),
+            ("recent".to_string(), now),
+        ]);
+        prune_sessions(&mut sessions, Some("owner"), now);
+        let mut ids: Vec<&str> = sessions.keys().map(|id| id.as_str()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec!["owner", "recent"]);
+    }
+
+    #[test]
+    fn the_least_recently_used_sessions_are_dropped_beyond_the_cap() {
+        let now = std::time::Instant::now();
+        let mut sessions: HashMap<String, std::time::Instant> = (0..MAX_SESSIONS + 2)
+            .map(|i| {
+                (
+                    i.to_string(),
+                    now - std::time::Duration::from_secs(i as u64),
+                )
+            })
+            .collect();
+        let owner = (MAX_SESSIONS + 1).to_string();
+        prune_sessions(&mut sessions, Some(&owner), now);
+        assert_eq!(sessions.len(), MAX_SESSIONS);
+        assert!(sessions.contains_key(&owner));
+        assert!(sessions.contains_key("0"));
+        assert!(!sessions.contains_key(&MAX_SESSIONS.to_string()));
+    }
+
+    #[test]
+    fn session_ids_are_random_128_bit_hex() {
+        let (a, b) = (new_session_id().unwrap(), new_session_id().unwrap());
+        assert_eq!(a.len(), 32);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    // 让之后的server函数调用看起来来自带有该会话cookie的请求
+    async fn as_client(id: &str) {
+        G_CODE_MANAGER
+            .sessions
+            .lock()
+            .await
+            .insert(id.to_string(), std::time::Instant::now());
+        let request = axum::http::Request::builder()
+            .header(
+                axum::http::header::COOKIE,
+                format!("{}={}", SESSION_COOKIE, id),
+            )
+            .body(())
+            .unwrap();
+        provide_context(request.into_parts().0);
+        provide_context(leptos_axum::ResponseOptions::default());
+    }
+
+    #[test]
+    fn another_client_cannot_load_a_program_during_a_run() {
+        with_fake_controller(|| async {
+            as_client("first").await;
+            load_gcode(["G90"; 10].join("\n")).await.unwrap();
+            G_CODE_MANAGER
+                .set_options(ExecutionOptions {
+                    min_line_interval_ms: 100,
+                    ..Default::default()
+                })
+                .await;
+            start_gcode_execution(true).await.unwrap();
+            as_client("second").await;
+            let error = load_gcode("G90".to_string()).await.unwrap_err();
+            assert!(error.to_string().contains("another client"), "{}", error);
+            assert!(start_gcode_execution(true).await.is_err());
+            // 任何客户端都能让机床停下，停下后其他客户端可以加载
+            stop_gcode_execution().await.unwrap();
+            assert_eq!(settled_state().await, RunState::Idle);
+            load_gcode("G90".to_string()).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn another_client_cannot_change_options_or_drive_the_machine_during_a_run() {
+        with_fake_controller(|| async {
+            as_client("first").await;
+            load_slow_program(10).await;
+            start_gcode_execution(false).await.unwrap();
+            as_client("second").await;
+            let options = ExecutionOptions {
+                block_delete: true,
+                ..Default::default()
+            };
+            let error = set_execution_options(options).await.unwrap_err();
+            assert!(error.to_string().contains("another client"), "{}", error);
+            assert!(set_preview_style(PathImageStyle::default()).await.is_err());
+            assert!(crate::api::zmc_move_abs(vec![0], vec![1.0]).await.is_err());
+            assert!(crate::api::zmc_manual_move(0, 1, 10.0).await.is_err());
+            assert!(crate::api::zmc_converter_run(false).await.is_err());
+            // 启动运行的客户端仍然可以修改
+            as_client("first").await;
+            set_preview_style(PathImageStyle::default()).await.unwrap();
+            stop_gcode_execution().await.unwrap();
+            assert_eq!(settled_state().await, RunState::Idle);
+        });
+    }
+
+    #[test]
+    fn a_dry_run_sends_no_motion_or_spindle_commands() {
+        with_fake_controller(|| async {
+            let program = "G0 X1\nG1 X2 Y3 F600\nM3 S1000\nG2 X4 Y3 I1 J0\nM5";
+            G_CODE_MANAGER
+                .load_gcode(program.to_string())
+                .await
+                .unwrap();
+            G_CODE_MANAGER.start(true, true, "a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+            assert_eq!(G_CODE_MANAGER.current_line.get_untracked(), 5);
+            let commands: Vec<String> = take_command_log()
+                .await
+                .into_iter()
+                .map(|entry| entry.command)
+                .collect();
+            assert!(commands.is_empty(), "{:?}", commands);
+        });
+    }
+
+    // X轴软件限位为0~100，MDI可以在其中移动
+    async fn allow_x_moves() {
+        let mut params = fake_parameters();
+        params.x.software_positive_limit = 100.0;
+        crate::api::zmc_set_parameters(params).await.unwrap();
+    }
+
+    #[test]
+    fn a_valid_mdi_line_moves_the_axis() {
+        with_fake_controller(|| async {
+            allow_x_moves().await;
+            G_CODE_MANAGER.execute_mdi("G1 X10 F500").await.unwrap();
+            let moves: Vec<String> = take_command_log()
+                .await
+                .into_iter()
+                .filter(|entry| entry.command == "MOVE_ABS")
+                .map(|entry| entry.detail)
+                .collect();
+            assert_eq!(moves, ["axis=[0] pos=[10.0]"]);
+        });
+    }
+
+    #[test]
+    fn malformed_and_unsupported_mdi_lines_are_rejected_before_moving() {
+        with_fake_controller(|| async {
+            allow_x_moves().await;
+            let error = G_CODE_MANAGER.execute_mdi("G1 X1 ?").await.unwrap_err();
+            assert!(error.contains("Unrecognized text"), "{}", error);
+            for line in ["G2 X1 I1 J0", "G4 P100", "G28", "G91 G1 X1", "G92 X0"] {
+                let error = G_CODE_MANAGER.execute_mdi(line).await.unwrap_err();
+                assert!(error.contains("Unsupported code"), "{}: {}", line, error);
+            }
+            let commands: Vec<String> = take_command_log()
+                .await
+                .into_iter()
+                .map(|entry| entry.command)
+                .collect();
+            assert!(commands.is_empty(), "{:?}", commands);
+        });
+    }
+
+    #[test]
+    fn mdi_is_refused_while_a_program_is_paused() {
+        with_fake_controller(|| async {
+            load_slow_program(6).await;
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            tokio::time::sleep(tokio::time::Duration::from_millis(150)).await;
+            G_CODE_MANAGER.pause().await;
+            assert_eq!(settled_state().await, RunState::Paused);
+            let error = G_CODE_MANAGER.execute_mdi("G90").await.unwrap_err();
+            assert!(error.contains("running or paused"), "{}", error);
+            G_CODE_MANAGER.stop().await;
+        });
+    }
+
+    // 以给定的跳段设置跑完程序，返回发出的MOVE_ABS记录
+    async fn absolute_moves(program: &str, block_delete: bool) -> Vec<String> {
+        G_CODE_MANAGER
+            .set_options(ExecutionOptions {
+                block_delete,
+                ..Default::default()
+            })
+            .await;
+        G_CODE_MANAGER
+            .load_gcode(program.to_string())
+            .await
+            .unwrap();
+        G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+        assert_eq!(settled_state().await, RunState::Completed);
+        take_command_log()
+            .await
+            .into_iter()
+            .filter(|entry| entry.command == "MOVE_ABS")
+            .map(|entry| entry.detail)
+            .collect()
+    }
+
+    #[test]
+    fn slashed_lines_are_skipped_only_with_block_delete_on() {
+        with_fake_controller(|| async {
+            let program = "G90\n/G1 X5 F600\n  /G1 Z-1\nG1 Y7 F600";
+            assert_eq!(absolute_moves(program, true).await, ["axis=[1] pos=[7.0]"]);
+            assert_eq!(
+                absolute_moves(program, false).await,
+                [
+                    "axis=[0] pos=[5.0]",
+                    "axis=[2] pos=[-1.0]",
+                    "axis=[1] pos=[7.0]"
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn resuming_with_block_delete_on_does_not_restore_a_slashed_spindle_line() {
+        with_fake_controller(|| async {
+            let program = ["/M3 S12000", "/G91"]
+                .into_iter()
+                .chain(["G90"; 4])
+                .collect::<Vec<_>>()
+                .join("\n");
+            G_CODE_MANAGER.load_gcode(program).await.unwrap();
+            G_CODE_MANAGER
+                .set_options(ExecutionOptions {
+                    block_delete: true,
+                    min_line_interval_ms: 100,
+                    ..Default::default()
+                })
+                .await;
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+            G_CODE_MANAGER.pause().await;
+            assert_eq!(settled_state().await, RunState::Paused);
+            assert!(G_CODE_MANAGER.current_line.get_untracked() > 2);
+            G_CODE_MANAGER.resume("a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+            let spindle: Vec<String> = take_command_log()
+                .await
+                .into_iter()
+                .map(|entry| entry.command)
+                .filter(|command| command == "CONVERTER_RUN")
+                .collect();
+            assert!(spindle.is_empty(), "{:?}", spindle);
+        });
+    }
+
+    #[test]
+    fn a_two_axis_feed_move_is_one_multi_axis_command() {
+        with_fake_controller(|| async {
+            G_CODE_MANAGER
+                .load_gcode("G90\nG1 X10 Y10 F600".to_string())
+                .await
+                .unwrap();
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            assert_eq!(settled_state().await, RunState::Completed);
+            let moves: Vec<String> = take_command_log()
+                .await
+                .into_iter()
+                .filter(|entry| entry.command.starts_with("MOVE"))
+                .map(|entry| format!("{} {}", entry.command, entry.detail))
+                .collect();
+            assert_eq!(moves, ["MOVE_ABS axis=[0, 1] pos=[10.0, 10.0]"]);
+        });
+    }
+
+    // 把解析结果写回成"G1 X10 Y10"这样的字，方便比较顺序和参数归属
+    fn words(line: &str) -> Vec<String> {
+        parse_gcode_line(line)
+            .iter()
+            .map(|command| {
+                let mut word = format!("{}{}", command.command_type, command.command_number);
+                for (letter, value) in &command.parameters {
+                    word += &format!(" {}{}", letter, value);
+                }
+                word
+            })
+            .collect()
+    }
+
+    #[test]
+    fn several_words_on_one_line_run_the_non_motion_ones_first() {
+        assert_eq!(
+            words("G1 X10 Y5 M3 S1000 G90"),
+            ["M3 S1000", "G90", "G1 X10 Y5"]
+        );
+        assert_eq!(
+            words("G0 Z5 G21 G1 X1 F300"),
+            ["G21", "G0 Z5", "G1 X1 F300"]
+        );
+    }
+
+    #[test]
+    fn parameters_before_the_first_word_belong_to_it() {
+        assert_eq!(words("X3 Y4 G1 F200 M5"), ["M5", "G1 X3 Y4 F200"]);
+        assert_eq!(words("g90 g0 x1 ; go"), ["G90", "G0 X1"]);
+    }
+
+    #[test]
+    fn lines_without_command_words_parse_to_nothing() {
+        assert!(words("").is_empty());
+        assert!(words("; G1 X10").is_empty());
+        assert!(words("X10 Y10").is_empty());
+    }
+
+    // 写一个2500行的临时文件，每行为自己的行号，最后一行没有换行符
+    fn numbered_file(name: &str) -> GCodeFile {
+        let path = std::env::temp_dir().join(format!("{}_{}.nc", name, std::process::id()));
+        let text = (0..2500)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        std::fs::write(&path, text).unwrap();
+        GCodeFile::open(path).unwrap()
+    }
+
+    fn numbers(range: std::ops::Range<usize>) -> Vec<String> {
+        range.map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn file_windows_start_at_the_first_line_and_stop_at_the_end() {
+        let file = numbered_file("window_ends");
+        assert_eq!(file.total, 2500);
+        assert_eq!(file.checkpoints.len(), 3);
+        assert_eq!(file.window(0, 3).unwrap(), numbers(0..3));
+        assert_eq!(file.window(2498, 10).unwrap(), numbers(2498..2500));
+        assert!(file.window(2500, 10).unwrap().is_empty());
+        assert!(file.window(9999, 10).unwrap().is_empty());
+        assert!(file.window(5, 0).unwrap().is_empty());
+        assert_eq!(file.window(2490, usize::MAX).unwrap(), numbers(2490..2500));
+        std::fs::remove_file(&file.path).unwrap();
+    }
+
+    #[test]
+    fn file_windows_cross_checkpoints() {
+        let file = numbered_file("window_checkpoints");
+        assert_eq!(file.window(999, 2).unwrap(), numbers(999..1001));
+        assert_eq!(file.window(1000, 1).unwrap(), numbers(1000..1001));
+        assert_eq!(file.window(1999, 1002).unwrap(), numbers(1999..2500));
+        assert_eq!(file.window(500, 1500).unwrap(), numbers(500..2000));
+        std::fs::remove_file(&file.path).unwrap();
+    }
+
+    #[test]
+    fn the_window_api_reports_the_total_and_caps_the_line_count() {
+        with_fake_controller(|| async {
+            let program = (0..1500).map(|_| "G90").collect::<Vec<_>>().join("\n");
+            G_CODE_MANAGER.load_gcode(program).await.unwrap();
+            let window = get_gcode_window(0, 5000).await.unwrap();
+            assert_eq!((window.start, window.total), (0, 1500));
+            assert_eq!(window.lines.len(), MAX_WINDOW_LINES);
+            let tail = get_gcode_window(1498, 10).await.unwrap();
+            assert_eq!(tail.lines, ["G90", "G90"]);
+            assert!(get_gcode_window(1500, 10).await.unwrap().lines.is_empty());
+        });
+    }
+
+    // 以给定的主轴启动、停止等待时间跑完程序，返回运行用时
+    async fn spindle_run_time(program: &str, dry_run: bool, up: f32, down: f32) -> Duration {
+        let mut params = fake_parameters();
+        (params.spindle.spin_up_secs, params.spindle.spin_down_secs) = (up, down);
+        crate::api::zmc_set_parameters(params).await.unwrap();
+        G_CODE_MANAGER
+            .load_gcode(program.to_string())
+            .await
+            .unwrap();
+        let started = std::time::Instant::now();
+        G_CODE_MANAGER.start(dry_run, true, "a").await.unwrap();
+        assert_eq!(settled_state().await, RunState::Completed);
+        started.elapsed()
+    }
+
+    #[test]
+    fn spindle_starts_and_stops_wait_for_their_own_dwell() {
+        with_fake_controller(|| async {
+            let elapsed = spindle_run_time("M3 S1000", false, 0.3, 0.0).await;
+            assert!(elapsed >= Duration::from_millis(300), "{:?}", elapsed);
+            let elapsed = spindle_run_time("M4 S1000\nM5", false, 0.2, 0.3).await;
+            assert!(elapsed >= Duration::from_millis(500), "{:?}", elapsed);
+        });
+    }
+
+    #[test]
+    fn a_dry_run_does_not_wait_for_the_spindle() {
+        with_fake_controller(|| async {
+            let elapsed = spindle_run_time("M3 S1000\nM5", true, 1.0, 1.0).await;
+            assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+        });
+    }
+
+    // 通过POST /api/job的处理函数提交任务，失败时返回状态码
+    async fn submit(program: &str, validate: bool) -> Result<u64, axum::http::StatusCode> {
+        job_handler(
+            axum::extract::Query(JobQuery { validate }),
+            program.to_string(),
+        )
+        .await
+        .map(|axum::Json(submission)| submission.job_id)
+        .map_err(|(status, _)| status)
+    }
+
+    #[test]
+    fn a_submitted_job_is_loaded_started_and_numbered() {
+        with_fake_controller(|| async {
+            let job_id = submit("G90\nG1 X0 F600", true).await.unwrap();
+            assert_eq!(
+                *G_CODE_MANAGER.program_name.lock().await,
+                format!("job #{}", job_id)
+            );
+            assert_eq!(settled_state().await, RunState::Completed);
+            assert_eq!(G_CODE_MANAGER.current_line.get_untracked(), 2);
+            assert_eq!(submit("G90", false).await, Ok(job_id + 1));
+            assert_eq!(settled_state().await, RunState::Completed);
+        });
+    }
+
+    #[test]
+    fn jobs_are_rejected_with_a_conflict_while_a_run_is_active() {
+        with_fake_controller(|| async {
+            load_slow_program(10).await;
+            G_CODE_MANAGER.start(false, true, "a").await.unwrap();
+            assert_eq!(
+                submit("G90", false).await,
+                Err(axum::http::StatusCode::CONFLICT)
+            );
+            // 其他客户端暂停的运行同样占用机床
+            G_CODE_MANAGER.pause().await;
+            assert_eq!(settled_state().await, RunState::Paused);
+            assert_eq!(
+                submit("G90", false).await,
+                Err(axum::http::StatusCode::CONFLICT)
+            );
+            G_CODE_MANAGER.stop().await;
+        });
+    }
+
+    #[test]
+    fn empty_or_invalid_jobs_are_bad_requests() {
+        with_fake_controller(|| async {
+            let bad_request = Err(axum::http::StatusCode::BAD_REQUEST);
+            assert_eq!(submit(" \n", false).await, bad_request);
+            assert_eq!(submit("G90\nnot gcode", true).await, bad_request);
+            // 任务接口不允许超出软件限位
+            assert_eq!(submit("G90\nG1 X1 F600", false).await, bad_request);
+            assert_eq!(G_CODE_MANAGER.run_state.get_untracked(), RunState::Idle);
+        });
+    }
 }
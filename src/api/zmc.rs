@@ -1,19 +1,49 @@
 use leptos::prelude::*;
 use leptos_ws::ServerSignal;
 
+#[cfg(feature = "ssr")]
+use crate::model::fitted_bitmap_size;
+#[cfg(feature = "ssr")]
+use crate::model::step_target;
 use crate::model::AxisMoveStatus;
+use crate::model::ConnectionSnapshot;
+use crate::model::ControllerInfo;
+#[cfg(feature = "ssr")]
+use crate::model::ControllerKind;
+#[cfg(feature = "ssr")]
+use crate::model::ErrorEvent;
 use crate::model::LimitStatus;
+use crate::model::LogEntry;
+#[cfg(feature = "ssr")]
+use crate::model::MachineStatus;
+use crate::model::MacroCommand;
+#[cfg(feature = "ssr")]
+use crate::model::MotionKind;
 use crate::model::MoveStatus;
 use crate::model::Parameters;
+#[cfg(feature = "ssr")]
+use crate::model::PathImageDelta;
+use crate::model::PathImageSnapshot;
+use crate::model::PathImageStyle;
+#[cfg(feature = "ssr")]
+use crate::model::PathTile;
+#[cfg(feature = "ssr")]
+use crate::model::StallDetector;
+#[cfg(feature = "ssr")]
+use crate::model::CONTROLLER_AXIS_COUNT;
 
 #[cfg(feature = "ssr")]
-use crate::utils::Bitmap;
+use crate::utils::{parameters_to_basic, path_to_dxf, Bitmap};
+#[cfg(feature = "ssr")]
+use std::collections::{HashMap, VecDeque};
 #[cfg(feature = "ssr")]
 use std::sync::Arc;
 #[cfg(feature = "ssr")]
 use std::sync::LazyLock;
 use std::time::Duration;
 #[cfg(feature = "ssr")]
+use std::time::Instant;
+#[cfg(feature = "ssr")]
 use tokio::sync::Mutex;
 #[cfg(feature = "ssr")]
 use tokio::task::JoinSet;
@@ -30,6 +60,12 @@ pub enum ControllerType {
 #[cfg(feature = "ssr")]
 pub struct ZmcManager {
     controller: Arc<Mutex<Option<Box<dyn Controller + Send>>>>,
+    // 当前连接的控制器IP
+    controller_ip: Arc<Mutex<Option<String>>>,
+    // 当前连接的是真实控制器还是模拟控制器
+    controller_kind: Arc<Mutex<ControllerKind>>,
+    // 连接时读取的控制器型号、固件和轴数，读取失败时为None
+    controller_info: Arc<Mutex<Option<ControllerInfo>>>,
     parameters: Arc<Mutex<Parameters>>,
 
     polling_interval: Arc<Mutex<Duration>>,
@@ -40,23 +76,260 @@ pub struct ZmcManager {
     // For drawing the movement path
     path_img_update_counter: Arc<Mutex<u32>>,
     bitmap: Arc<Mutex<Bitmap>>, // 500x500 bitmap with scale 10.0
-    path_img: ServerSignal<String>,
+    // 只推送路径图中变化的区域，seq为最近一次推送的序号
+    path_img_delta: ServerSignal<PathImageDelta>,
+    path_img_seq: Arc<Mutex<u64>>,
+    // 当前的路径图样式，发布路径图时据此决定是否在下方叠加G代码预览
+    path_style: Arc<Mutex<PathImageStyle>>,
+    // 走过的路径点(X, Y, Z)和运动类型，用于导出DXF和重绘路径图
+    path_points: Arc<Mutex<Vec<([f32; 3], MotionKind)>>>,
+    // 点动看门狗：正在点动的轴、客户端最近一次心跳时间和超时时间
+    jogging_axes: Arc<Mutex<Vec<u8>>>,
+    last_heartbeat: Arc<Mutex<Instant>>,
+    // 最近发给控制器的点动指令，用于合并短时间内重复的点动
+    recent_jogs: Arc<Mutex<HashMap<u8, JogCommand>>>,
+    // 串行化点动加速和停止后的速度恢复，迟到的加速不会覆盖恢复的加工速度
+    jog_speed_lock: Arc<Mutex<()>>,
+    heartbeat_timeout: Arc<Mutex<Duration>>,
+    // 最近发送给控制器的指令记录，最多保存COMMAND_LOG_CAPACITY条
+    command_log: Arc<Mutex<VecDeque<LogEntry>>>,
+    // 空闲自动断开：最近一次指令或运动的时间、超时时间(0表示不断开)和推送给客户端的剩余秒数
+    last_activity: Arc<Mutex<Instant>>,
+    idle_timeout: Arc<Mutex<Duration>>,
+    idle_warning: ServerSignal<Option<u64>>,
+    // 状态读取失败时推送给客户端提示
+    controller_error: ServerSignal<ErrorEvent>,
+    // X、Y、Z轴的失速检测和推送给客户端的可能失速的轴名
+    stall_detectors: Arc<Mutex<[StallDetector; 3]>>,
+    stalled_axes: ServerSignal<Vec<String>>,
+    // 按轴号记录的反向间隙补偿状态
+    backlash: Arc<Mutex<HashMap<u8, BacklashState>>>,
+    // 记录的变频器运行状态，回零和设零前据此检查主轴
+    converter_running: Arc<Mutex<bool>>,
+    // 开发者模式，关闭时拒绝原始BASIC指令
+    developer_mode: Arc<Mutex<bool>>,
+}
+
+#[cfg(feature = "ssr")]
+const COMMAND_LOG_CAPACITY: usize = 1000;
+
+// 默认心跳超时，客户端每500ms发送一次心跳
+#[cfg(feature = "ssr")]
+const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_millis(1500);
+
+// 客户端可设置的心跳超时范围(ms)，下限要大于心跳间隔，上限保证看门狗仍然有效
+#[cfg(feature = "ssr")]
+const HEARTBEAT_TIMEOUT_RANGE: (u64, u64) = (1000, 10_000);
+
+// 服务启动时设置该环境变量为1才允许开启开发者模式，客户端不能自行解除限制
+#[cfg(feature = "ssr")]
+const DEVELOPER_MODE_ENV: &str = "ZMC_ALLOW_DEVELOPER_MODE";
+
+// 点动中且超过timeout没有收到心跳，认为客户端已断开
+#[cfg(feature = "ssr")]
+fn heartbeat_lapsed(
+    jogging: bool,
+    last_heartbeat: Instant,
+    timeout: Duration,
+    now: Instant,
+) -> bool {
+    jogging && now.duration_since(last_heartbeat) > timeout
+}
+
+// 按X、Y、Z的顺序返回第一个在运动的启用轴，is_idle读取轴是否空闲
+#[cfg(feature = "ssr")]
+fn first_moving_axis<E>(
+    params: &Parameters,
+    mut is_idle: impl FnMut(u8) -> Result<bool, E>,
+) -> Result<Option<u8>, E> {
+    for axis in [&params.x, &params.y, &params.z] {
+        if axis.enabled && !is_idle(axis.axis_num)? {
+            return Ok(Some(axis.axis_num));
+        }
+    }
+    Ok(None)
+}
+
+// 所有启用轴都空闲时才能修改参数；读取失败时运动状态未知，同样拒绝
+#[cfg(feature = "ssr")]
+fn check_axes_idle<E: std::fmt::Display>(
+    params: &Parameters,
+    is_idle: impl FnMut(u8) -> Result<bool, E>,
+) -> Result<(), ServerFnError> {
+    match first_moving_axis(params, is_idle) {
+        Ok(None) => Ok(()),
+        Ok(Some(axis)) => Err(ServerFnError::ServerError(format!(
+            "Axis {} is moving, stop all motion before applying parameters",
+            axis
+        ))),
+        Err(e) => Err(ServerFnError::ServerError(format!(
+            "Failed to read whether the axes are idle: {}",
+            e
+        ))),
+    }
+}
+
+// 同一轴以相同方向和速度重复点动时，间隔小于该时间的指令不再发给控制器
+#[cfg(feature = "ssr")]
+const JOG_COALESCE_WINDOW: Duration = Duration::from_millis(200);
+
+// 单步点动等待运动完成时，在预计时间之外额外等待的时间
+#[cfg(feature = "ssr")]
+const JOG_STEP_TIMEOUT_MARGIN: Duration = Duration::from_secs(5);
+
+#[cfg(feature = "ssr")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct JogCommand {
+    direction: i8,
+    speed: f32,
+    issued: Instant,
+}
+
+// 各轴都在合并窗口内以相同方向和速度点动过时，这次点动是重复的。
+// 停止会清除记录，所以停止后的点动总会发出
+#[cfg(feature = "ssr")]
+fn jog_redundant(
+    recent: &HashMap<u8, JogCommand>,
+    axis_list: &[u8],
+    directions: &[i8],
+    speed: f32,
+    now: Instant,
+) -> bool {
+    axis_list.iter().zip(directions).all(|(axis, direction)| {
+        recent.get(axis).is_some_and(|jog| {
+            jog.direction == *direction
+                && jog.speed == speed
+                && now.duration_since(jog.issued) < JOG_COALESCE_WINDOW
+        })
+    })
+}
+
+// 空闲断开前提前警告的时间
+#[cfg(feature = "ssr")]
+const IDLE_WARNING_PERIOD: Duration = Duration::from_secs(60);
+
+// 距离空闲自动断开还剩的时间，timeout为0时不自动断开
+#[cfg(feature = "ssr")]
+fn idle_remaining(last_activity: Instant, timeout: Duration, now: Instant) -> Option<Duration> {
+    if timeout.is_zero() {
+        return None;
+    }
+    Some(timeout.saturating_sub(now.duration_since(last_activity)))
+}
+
+// 把IO号整理成去重后的连续区间，如[3, 1, 2, 7, 3] -> [(1, 3), (7, 7)]
+#[cfg(feature = "ssr")]
+fn contiguous_ranges(ios: &[u16]) -> Vec<(u16, u16)> {
+    let mut sorted = ios.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for io in sorted {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == io => *end = io,
+            _ => ranges.push((io, io)),
+        }
+    }
+    ranges
+}
+
+// IN(起始, 结束)一次最多读取的输入个数
+#[cfg(feature = "ssr")]
+const INPUT_BATCH_SIZE: u16 = 32;
+
+// 解析?IN(起始, 结束)打印的值，第0位为起始IO，不是count位以内的整数时返回None
+#[cfg(feature = "ssr")]
+fn parse_input_bits(reply: &str, count: u16) -> Option<u32> {
+    let value = reply.trim().parse::<f64>().ok()?;
+    let limit = 2f64.powi(count as i32);
+    (value >= 0.0 && value < limit && value.fract() == 0.0).then_some(value as u32)
+}
+
+// 按连续区间读取输入IO，每个IO只读一次，结果按ios的顺序返回。
+// 每段用一条IN(起始, 结束)指令读取，读取或解析失败时该段退回逐个读取
+#[cfg(feature = "ssr")]
+fn read_inputs<const N: usize>(
+    controller: &mut Box<dyn Controller + Send>,
+    ios: &[u16; N],
+) -> Result<[bool; N], ControllerError> {
+    let mut values = std::collections::HashMap::new();
+    for (start, end) in contiguous_ranges(ios) {
+        let mut batch_start = start;
+        loop {
+            let batch_end = end.min(batch_start.saturating_add(INPUT_BATCH_SIZE - 1));
+            let count = batch_end - batch_start + 1;
+            let bits = if count > 1 {
+                let command = format!("?IN({},{})", batch_start, batch_end);
+                let reply = controller.execute(&command).ok();
+                reply.and_then(|reply| parse_input_bits(&reply, count))
+            } else {
+                None
+            };
+            for io in batch_start..=batch_end {
+                let value = match bits {
+                    Some(bits) => (bits >> (io - batch_start)) & 1 == 1,
+                    None => controller.direct_get_in(io)?,
+                };
+                values.insert(io, value);
+            }
+            if batch_end == end {
+                break;
+            }
+            batch_start = batch_end + 1;
+        }
+    }
+    Ok(ios.map(|io| values[&io]))
+}
+
+// 重试前的等待时间，每次失败后加倍
+#[cfg(feature = "ssr")]
+const READ_RETRY_BACKOFF_MS: u64 = 5;
+
+// 读取失败时短暂等待后重试，最多读取attempts次，消除网络偶发丢包造成的失败。
+// read每次重新加锁，等待期间不占用控制器
+#[cfg(feature = "ssr")]
+async fn read_with_retry<T, E, F>(attempts: u32, mut read: impl FnMut() -> F) -> Result<T, E>
+where
+    E: std::fmt::Debug,
+    F: std::future::Future<Output = Result<T, E>>,
+{
+    let mut backoff = Duration::from_millis(READ_RETRY_BACKOFF_MS);
+    let mut attempt = 1;
+    loop {
+        match read().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < attempts => {
+                println!(
+                    "Controller read failed (attempt {}), retrying: {:?}",
+                    attempt, e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
 }
 
 #[cfg(feature = "ssr")]
-async fn update_limit_status(
+fn update_limit_status(
     controller: &mut Box<dyn Controller + Send>,
     params: &Parameters,
-    limit_status: &mut ServerSignal<LimitStatus>,
+    limit_status: &ServerSignal<LimitStatus>,
 ) -> Result<(), ControllerError> {
-    let emer = controller.direct_get_in(params.emergency_stop_io)?;
-    let door_switch = controller.direct_get_in(params.door_switch_io)?;
-    let x_plus = controller.direct_get_in(params.x.positive_limit_io)?;
-    let x_minus = controller.direct_get_in(params.x.negative_limit_io)?;
-    let y_plus = controller.direct_get_in(params.y.positive_limit_io)?;
-    let y_minus = controller.direct_get_in(params.y.negative_limit_io)?;
-    let z_plus = controller.direct_get_in(params.z.positive_limit_io)?;
-    let z_minus = controller.direct_get_in(params.z.negative_limit_io)?;
+    let ios = [
+        params.emergency_stop_io,
+        params.door_switch_io,
+        params.x.positive_limit_io,
+        params.x.negative_limit_io,
+        params.y.positive_limit_io,
+        params.y.negative_limit_io,
+        params.z.positive_limit_io,
+        params.z.negative_limit_io,
+    ];
+    let [emer, door_switch, x_plus, x_minus, y_plus, y_minus, z_plus, z_minus] =
+        read_inputs(controller, &ios)?;
     // HACK: Should not use set() to update here, or it will cause the signal not to track changes
     // Maybe it is a bug in leptos_ws ?
     limit_status.update(|status| {
@@ -74,8 +347,38 @@ async fn update_limit_status(
     Ok(())
 }
 
+// 变频器输出频率寄存器，读回后存放在控制器本地MODBUS_REG(CONVERTER_FREQ_LOCAL_REG)
+#[cfg(feature = "ssr")]
+const CONVERTER_OUTPUT_FREQ_REG: u16 = 103;
+#[cfg(feature = "ssr")]
+const CONVERTER_FREQ_LOCAL_REG: u16 = 4;
+
+// 读取变频器实际输出频率并换算为主轴转速
+#[cfg(feature = "ssr")]
+fn read_spindle_rpm(
+    controller: &mut Box<dyn Controller + Send>,
+    params: &Parameters,
+) -> Result<f32, ControllerError> {
+    controller.execute(&format!(
+        "MODBUSM_REGGET({},1,{})",
+        CONVERTER_OUTPUT_FREQ_REG, CONVERTER_FREQ_LOCAL_REG
+    ))?;
+    let mut frequency = [0i32; 1];
+    controller.modbus_get4x_long(CONVERTER_FREQ_LOCAL_REG, 1, &mut frequency)?;
+    Ok(params.spindle.rpm(frequency[0] as f32))
+}
+
+// AXISSTATUS中表示报警的位：通讯错误(bit2)、驱动器报警(bit3)、跟随误差超限(bit8)
+#[cfg(feature = "ssr")]
+const AXIS_ALARM_MASK: i32 = (1 << 2) | (1 << 3) | (1 << 8);
+
+#[cfg(feature = "ssr")]
+fn axis_in_alarm(axis_status: i32) -> bool {
+    axis_status & AXIS_ALARM_MASK != 0
+}
+
 #[cfg(feature = "ssr")]
-async fn update_move_status(
+fn update_move_status(
     controller: &mut Box<dyn Controller + Send>,
     params: &Parameters,
     move_status: &mut MoveStatus,
@@ -94,68 +397,562 @@ async fn update_move_status(
     move_status.x.pos = x_pos;
     move_status.y.pos = y_pos;
     move_status.z.pos = z_pos;
+    let x_demand = controller.direct_get_d_pos(x_axis)?;
+    let y_demand = controller.direct_get_d_pos(y_axis)?;
+    let z_demand = controller.direct_get_d_pos(z_axis)?;
+    move_status.x.following_error = x_demand - x_pos;
+    move_status.y.following_error = y_demand - y_pos;
+    move_status.z.following_error = z_demand - z_pos;
     move_status.x.is_idle = controller.direct_get_if_idle(x_axis)?;
     move_status.y.is_idle = controller.direct_get_if_idle(y_axis)?;
     move_status.z.is_idle = controller.direct_get_if_idle(z_axis)?;
+    let x_status = controller.direct_get_axis_status(x_axis)?;
+    let y_status = controller.direct_get_axis_status(y_axis)?;
+    let z_status = controller.direct_get_axis_status(z_axis)?;
+    move_status.x.in_alarm = axis_in_alarm(x_status);
+    move_status.y.in_alarm = axis_in_alarm(y_status);
+    move_status.z.in_alarm = axis_in_alarm(z_status);
     // Update the SVG path for visualization
     // 80x80 to 500x500 bitmap with scale 10.0
-    bitmap.set_pixel(x_pos, y_pos, (-z_pos) * 75.0);
+    bitmap.set_motion_pixel(x_pos, y_pos, (-z_pos) * 75.0, move_status.motion);
+    Ok(())
+}
+
+// 限位轮询：读取限位IO，门禁联锁生效时暂停运动，再读取主轴转速并推送状态。
+// 主轴转速只用于显示和回零前的检查，变频器不在总线上或未上电时读取失败不能中断限位监控
+#[cfg(feature = "ssr")]
+async fn poll_limits<F, E>(
+    controller: &Mutex<Option<Box<dyn Controller + Send>>>,
+    parameters: &Mutex<Parameters>,
+    move_status: &Mutex<MoveStatus>,
+    converter_running: &Mutex<bool>,
+    limit_status: &ServerSignal<LimitStatus>,
+    move_status_signal: &ServerSignal<MoveStatus>,
+    read_spindle: F,
+) -> Result<(), ControllerError>
+where
+    F: Fn(&mut Box<dyn Controller + Send>, &Parameters) -> Result<f32, E>,
+    E: std::fmt::Display,
+{
+    let mut controller = controller.lock().await;
+    // 断开前会先停止轮询，下一轮开始时再检查控制器
+    let Some(controller) = controller.as_mut() else {
+        return Ok(());
+    };
+    let parameters = parameters.lock().await;
+    let mut move_status = move_status.lock().await;
+    update_limit_status(controller, &parameters, limit_status)?;
+    if parameters.door_interlock_engaged(&limit_status.get_untracked()) {
+        door_feed_hold(controller, &parameters, &move_status).await;
+    }
+    // 变频器运行中或停止后仍在减速时才读取，读取失败保留上次的转速
+    let running = *converter_running.lock().await;
+    if running || move_status.spindle_rpm > 0.0 {
+        match read_spindle(controller, &parameters) {
+            Ok(rpm) => move_status.spindle_rpm = rpm,
+            Err(e) => eprintln!("Failed to read the spindle speed: {}", e),
+        }
+    }
+    move_status_signal.update(|status| {
+        *status = move_status.clone();
+    });
+    Ok(())
+}
+
+// 运动状态轮询：读取各轴状态，记录路径并检测失速
+#[cfg(feature = "ssr")]
+async fn poll_move_status(
+    controller: &Mutex<Option<Box<dyn Controller + Send>>>,
+    parameters: &Mutex<Parameters>,
+    bitmap: &Mutex<Bitmap>,
+    move_status: &Mutex<MoveStatus>,
+    path_points: &Mutex<Vec<([f32; 3], MotionKind)>>,
+    stall_detectors: &Mutex<[StallDetector; 3]>,
+    stalled_axes: &ServerSignal<Vec<String>>,
+) -> Result<(), ControllerError> {
+    let mut controller = controller.lock().await;
+    let Some(controller) = controller.as_mut() else {
+        return Ok(());
+    };
+    let parameters = parameters.lock().await;
+    let mut bitmap = bitmap.lock().await;
+    let mut move_status = move_status.lock().await;
+    let mut path_points = path_points.lock().await;
+    // Read into a copy so a failed read keeps the last good status
+    let mut next_status = move_status.clone();
+    update_move_status(controller, &parameters, &mut next_status, &mut bitmap)?;
+    let point = [next_status.x.pos, next_status.y.pos, next_status.z.pos];
+    record_path_point(&mut path_points, point, next_status.motion);
+    *move_status = next_status;
+    let stalled = detect_stalls(
+        &mut *stall_detectors.lock().await,
+        &move_status,
+        &parameters,
+    );
+    if stalled_axes.get_untracked() != stalled {
+        stalled_axes.update(|axes| *axes = stalled);
+    }
     Ok(())
 }
 
+// 叠加显示时预览路径的不透明度
+#[cfg(feature = "ssr")]
+const PREVIEW_OVERLAY_OPACITY: f32 = 0.3;
+
+// 实时路径在上，G代码预览以淡色画在实时路径下方
+#[cfg(feature = "ssr")]
+async fn composite_with_preview(live: &Bitmap) -> Bitmap {
+    let mut composite = live.clone();
+    // 适配加工区域后比例与预览不同，按零点对齐会错位，此时不叠加
+    let preview = super::g_code::preview_bitmap().await;
+    if let Some(preview) = preview.filter(|preview| preview.scale() == live.scale()) {
+        composite.underlay(&preview, PREVIEW_OVERLAY_OPACITY);
+    }
+    composite
+}
+
+// 实时路径图的大小(像素)和默认比例(像素/毫米)
+#[cfg(feature = "ssr")]
+const PATH_BITMAP_SIZE: usize = 500;
+#[cfg(feature = "ssr")]
+const PATH_BITMAP_SCALE: f32 = 4.0;
+
+// 把路径图中变化的区域分块编码后推送给客户端，叠加预览时从合成后的图像中截取
+#[cfg(feature = "ssr")]
+async fn publish_path_delta(
+    bitmap: &mut Bitmap,
+    overlay_preview: bool,
+    seq: &mut u64,
+    signal: &ServerSignal<PathImageDelta>,
+) {
+    let regions = bitmap.take_dirty_regions();
+    if regions.is_empty() {
+        return;
+    }
+    let composite = if overlay_preview {
+        Some(composite_with_preview(bitmap).await)
+    } else {
+        None
+    };
+    let source = composite.as_ref().unwrap_or(bitmap);
+    let tiles = regions
+        .into_iter()
+        .map(|(x, y, width, height)| PathTile {
+            x: x as u32,
+            y: y as u32,
+            width: width as u32,
+            height: height as u32,
+            data_url: source.crop(x, y, width, height).to_data_url(),
+        })
+        .collect();
+    *seq += 1;
+    let (width, height) = bitmap.size();
+    let delta = PathImageDelta {
+        seq: *seq,
+        width: width as u32,
+        height: height as u32,
+        tiles,
+    };
+    signal.update(move |value| *value = delta);
+}
+
+// 服务退出时调用，见ZmcManager::park
+#[cfg(feature = "ssr")]
+pub async fn park_machine() -> Result<(), ServerFnError> {
+    ZMC_MANAGER.park().await
+}
+
+// 停车时对控制器执行的一步操作
+#[cfg(feature = "ssr")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StopStep {
+    Spindle,
+    Cancel(u8),
+}
+
+#[cfg(feature = "ssr")]
+impl std::fmt::Display for StopStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopStep::Spindle => write!(f, "stop the spindle"),
+            StopStep::Cancel(axis) => write!(f, "cancel axis {}", axis),
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+fn issue_stop_step(
+    controller: &mut Box<dyn Controller + Send>,
+    step: StopStep,
+) -> Result<(), ControllerError> {
+    match step {
+        StopStep::Spindle => controller.execute(CONVERTER_STOP_COMMAND).map(|_| ()),
+        StopStep::Cancel(axis) => controller.direct_single_cancel(axis, 2).map(|_| ()),
+    }
+}
+
+// 门禁联锁：开启联锁时门打开则暂停G代码，并停止正在运动的轴(减速停止)，
+// 之后需要关门再继续。停止失败只打印错误，不算作状态读取失败，门仍打开时下一次轮询再试
+#[cfg(feature = "ssr")]
+async fn door_feed_hold(
+    controller: &mut Box<dyn Controller + Send>,
+    params: &Parameters,
+    move_status: &MoveStatus,
+) {
+    super::g_code::hold_for_interlock().await;
+    // 门打开时主轴也必须停下，继续G代码时再按模态状态恢复
+    let mut converter_running = ZMC_MANAGER.converter_running.lock().await;
+    if *converter_running || move_status.spindle_rpm > 0.0 {
+        ZMC_MANAGER
+            .log_command("DOOR_INTERLOCK", "spindle stop".to_string())
+            .await;
+        match controller.execute(CONVERTER_STOP_COMMAND) {
+            Ok(_) => *converter_running = false,
+            Err(e) => eprintln!("Failed to stop the spindle with the door open: {}", e),
+        }
+    }
+    drop(converter_running);
+    // 门打开时不能点动，之前的点动记录都作废
+    ZMC_MANAGER.recent_jogs.lock().await.clear();
+    let axes = [
+        (params.x.axis_num, &move_status.x),
+        (params.y.axis_num, &move_status.y),
+        (params.z.axis_num, &move_status.z),
+    ];
+    for (axis, status) in axes {
+        if !status.is_idle {
+            ZMC_MANAGER
+                .log_command("DOOR_INTERLOCK", format!("axis={}", axis))
+                .await;
+            if let Err(e) = controller.direct_single_cancel(axis, 2) {
+                eprintln!("Failed to stop axis {} with the door open: {}", axis, e);
+            }
+        }
+    }
+}
+
+// 当前是否因门打开而禁止运动
+#[cfg(feature = "ssr")]
+pub(crate) async fn door_interlock_engaged() -> bool {
+    let status = ZMC_MANAGER.limit_status.get_untracked();
+    ZMC_MANAGER
+        .parameters
+        .lock()
+        .await
+        .door_interlock_engaged(&status)
+}
+
+// G代码执行到G0~G3时设置当前的运动类型，轮询时据此绘制路径
+#[cfg(feature = "ssr")]
+pub(crate) async fn set_motion_kind(motion: MotionKind) {
+    ZMC_MANAGER.move_status.lock().await.motion = motion;
+}
+
+// G代码预览改变后，叠加预览的实时路径图需要整幅重新推送
+#[cfg(feature = "ssr")]
+pub(crate) async fn refresh_path_image() {
+    ZMC_MANAGER.bitmap.lock().await.mark_all_dirty();
+}
+
+// 导出路径最多保存的点数，相邻点距离小于PATH_POINT_MIN_DISTANCE时不记录
+#[cfg(feature = "ssr")]
+const PATH_POINTS_CAPACITY: usize = 200_000;
+#[cfg(feature = "ssr")]
+const PATH_POINT_MIN_DISTANCE: f32 = 0.01;
+
+#[cfg(feature = "ssr")]
+fn record_path_point(
+    path_points: &mut Vec<([f32; 3], MotionKind)>,
+    point: [f32; 3],
+    motion: MotionKind,
+) {
+    let moved = path_points.last().is_none_or(|(last, _)| {
+        last.iter()
+            .zip(point)
+            .map(|(a, b)| (b - a).powi(2))
+            .sum::<f32>()
+            .sqrt()
+            >= PATH_POINT_MIN_DISTANCE
+    });
+    if moved && path_points.len() < PATH_POINTS_CAPACITY {
+        path_points.push((point, motion));
+    }
+}
+
+// 反向间隙补偿：轴最近的运动方向和已经累加到指令位置上的补偿量
+#[cfg(feature = "ssr")]
+#[derive(Debug, Default, Clone, Copy)]
+struct BacklashState {
+    direction: i8,
+    offset: f32,
+}
+
+#[cfg(feature = "ssr")]
+impl BacklashState {
+    // 根据本次移动量更新方向，反向时返回需要额外移动的补偿距离
+    fn correction(&mut self, delta: f32, backlash: f32) -> f32 {
+        let direction = match delta {
+            d if d > 0.0 => 1,
+            d if d < 0.0 => -1,
+            _ => return 0.0,
+        };
+        let reversed = self.direction != 0 && self.direction != direction;
+        self.direction = direction;
+        if !reversed {
+            return 0.0;
+        }
+        let extra = backlash * direction as f32;
+        self.offset += extra;
+        extra
+    }
+}
+
+// 连续读取失败超过该次数才认为控制器已断开，偶发的失败保留上一次的状态
+#[cfg(feature = "ssr")]
+const MAX_CONSECUTIVE_READ_ERRORS: u32 = 2;
+
+#[cfg(feature = "ssr")]
+fn tolerate_read_error<E: std::fmt::Debug>(
+    result: Result<(), E>,
+    consecutive_errors: &mut u32,
+) -> Result<(), E> {
+    match result {
+        Ok(()) => {
+            *consecutive_errors = 0;
+            Ok(())
+        }
+        Err(e) if *consecutive_errors < MAX_CONSECUTIVE_READ_ERRORS => {
+            *consecutive_errors += 1;
+            println!(
+                "Status read failed ({} in a row), keeping the last status: {:?}",
+                consecutive_errors, e
+            );
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// 读取控制器的型号、固件版本和轴数
+#[cfg(feature = "ssr")]
+fn read_controller_info(
+    controller: &mut Box<dyn Controller + Send>,
+) -> Result<ControllerInfo, ControllerError> {
+    let (model, firmware, _id) = controller.get_controller_info()?;
+    let axis_count = controller.direct_get_max_axis()?;
+    Ok(ControllerInfo {
+        model,
+        firmware,
+        axis_count,
+    })
+}
+
+// 读取失败时通知客户端，客户端负责合并连续的相同错误
+#[cfg(feature = "ssr")]
+fn publish_read_error(signal: &ServerSignal<ErrorEvent>, result: &Result<(), ControllerError>) {
+    if let Err(e) = result {
+        let message = format!("{:?}", e);
+        signal.update(move |event| {
+            event.seq += 1;
+            event.message = message;
+        });
+    }
+}
+
+// 更新各轴的失速检测，返回可能失速的启用轴的轴名
+#[cfg(feature = "ssr")]
+fn detect_stalls(
+    detectors: &mut [StallDetector; 3],
+    status: &MoveStatus,
+    params: &Parameters,
+) -> Vec<String> {
+    status
+        .clone()
+        .axes()
+        .into_iter()
+        .zip(params.enabled_axes())
+        .zip(detectors.iter_mut())
+        .filter_map(|(((name, axis), enabled), detector)| {
+            let stalled = detector.update(axis.is_idle, axis.pos, params.stall_cycles);
+            (enabled && stalled).then(|| name.to_string())
+        })
+        .collect()
+}
+
 const MOVE_STATUS_UPDATE_INTERVAL: u32 = 5; // Update every 50ms
-const UPDATE_COUNT: u32 = 100 / MOVE_STATUS_UPDATE_INTERVAL; // Update every 100ms
+
+// 客户端可请求的状态推送间隔范围(ms)
+#[cfg(feature = "ssr")]
+const POLLING_INTERVAL_RANGE: (u64, u64) = (20, 1000);
 #[cfg(feature = "ssr")]
 impl ZmcManager {
     pub async fn start_polling(&self) -> Result<(), ServerFnError> {
         let controller = self.controller.clone();
         let parameters = self.parameters.clone();
-        let mut limit_status = self.limit_status.clone();
+        let limit_status = self.limit_status.clone();
         let move_status = self.move_status.clone();
         let move_status_signal = self.move_status_signal.clone();
-        let path_img = self.path_img.clone();
+        let path_img_delta = self.path_img_delta.clone();
+        let path_img_seq = self.path_img_seq.clone();
+        let path_style = self.path_style.clone();
         let bitmap = self.bitmap.clone();
         let counter = self.path_img_update_counter.clone();
+        let jogging_axes = self.jogging_axes.clone();
+        let last_heartbeat = self.last_heartbeat.clone();
+        let heartbeat_timeout = self.heartbeat_timeout.clone();
+        let polling_interval = self.polling_interval.clone();
+        let path_points = self.path_points.clone();
+        let last_activity = self.last_activity.clone();
+        let idle_timeout = self.idle_timeout.clone();
+        let idle_warning = self.idle_warning.clone();
+        let controller_error = self.controller_error.clone();
+        let stall_detectors = self.stall_detectors.clone();
+        let stalled_axes = self.stalled_axes.clone();
+        let converter_running = self.converter_running.clone();
 
         self.polling_tasks.lock().await.spawn(async move {
+            let mut read_errors = 0;
             loop {
-                {
+                let limit_cycle = {
                     let mut controller = controller.lock().await;
                     if controller.is_none() {
                         return Err(ServerFnError::ServerError(
                             "Controller is not initialized".to_string(),
                         ));
                     }
-                    let mut controller = controller.as_mut().unwrap();
-                    let mut parameters = parameters.lock().await;
+                    let controller = controller.as_mut().unwrap();
+                    let parameters = parameters.lock().await;
                     let mut bitmap = bitmap.lock().await;
                     let mut counter = counter.lock().await;
-                    let mut move_status = move_status.lock().await;
+                    let move_status = move_status.lock().await;
+                    // Stop jogging if the client that started it has gone away
+                    let mut jogging_axes = jogging_axes.lock().await;
+                    if heartbeat_lapsed(
+                        !jogging_axes.is_empty(),
+                        *last_heartbeat.lock().await,
+                        *heartbeat_timeout.lock().await,
+                        Instant::now(),
+                    ) {
+                        println!("Heartbeat lost while jogging, cancelling all axes");
+                        ZMC_MANAGER
+                            .log_command("WATCHDOG_CANCEL", format!("axis={:?}", jogging_axes))
+                            .await;
+                        // 取消失败时保留点动记录，下一次轮询再试
+                        let mut cancelled = true;
+                        for axis in [
+                            parameters.x.axis_num,
+                            parameters.y.axis_num,
+                            parameters.z.axis_num,
+                        ] {
+                            if let Err(e) = controller.direct_single_cancel(axis, 2) {
+                                eprintln!("Failed to cancel axis {}: {}", axis, e);
+                                cancelled = false;
+                            }
+                        }
+                        if cancelled {
+                            jogging_axes.clear();
+                        }
+                        // 被取消的点动不再算作重复，之后的点动要重新发出
+                        ZMC_MANAGER.recent_jogs.lock().await.clear();
+                    }
+                    drop(jogging_axes);
+                    // Moving axes count as activity, then disconnect after the idle timeout
+                    // 暂停中的程序还要继续执行，也不算空闲
+                    let moving = [&move_status.x, &move_status.y, &move_status.z]
+                        .iter()
+                        .any(|axis| !axis.is_idle);
+                    if moving || super::g_code::program_active() {
+                        *last_activity.lock().await = Instant::now();
+                    }
+                    let remaining = idle_remaining(
+                        *last_activity.lock().await,
+                        *idle_timeout.lock().await,
+                        Instant::now(),
+                    );
+                    let warning = remaining
+                        .filter(|remaining| *remaining <= IDLE_WARNING_PERIOD)
+                        .map(|remaining| remaining.as_secs());
+                    if idle_warning.get_untracked() != warning {
+                        idle_warning.update(|value| *value = warning);
+                    }
+                    if remaining.is_some_and(|remaining| remaining.is_zero()) {
+                        println!("Idle timeout reached, disconnecting the controller");
+                        ZMC_MANAGER
+                            .log_command("IDLE_DISCONNECT", String::new())
+                            .await;
+                        // 与zmc_close相同的断开流程，要等轮询结束，所以在另一个任务中执行
+                        tokio::spawn(async {
+                            if let Err(e) = ZMC_MANAGER.shutdown().await {
+                                eprintln!("Failed to disconnect idle controller: {}", e);
+                            }
+                        });
+                        return Ok(());
+                    }
                     // Update the move status
                     // Don't update the limit status and path img too frequently
-                    if *counter > UPDATE_COUNT {
-                        path_img.update(move |path| {
-                            *path = bitmap.to_data_url();
-                        });
+                    let update_count = polling_interval.lock().await.as_millis() as u32
+                        / MOVE_STATUS_UPDATE_INTERVAL;
+                    if *counter > update_count {
+                        publish_path_delta(
+                            &mut bitmap,
+                            path_style.lock().await.overlay_preview,
+                            &mut *path_img_seq.lock().await,
+                            &path_img_delta,
+                        )
+                        .await;
                         *counter = 0;
-                        update_limit_status(&mut controller, &mut parameters, &mut limit_status)
-                            .await
-                            .expect("Failed to update limit status");
-                        move_status_signal.update(|status| {
-                            *status = move_status.clone();
-                        });
+                        true
                     } else {
                         // println!("Skipping limit status update, counter: {}", *counter);
                         *counter += 1;
-                        update_move_status(
-                            &mut controller,
-                            &mut parameters,
-                            &mut move_status,
-                            &mut bitmap,
-                        )
-                        .await
-                        .expect("Failed to update move status");
+                        false
                     }
+                };
+                // 每次尝试重新加锁，重试前的等待期间点动等请求可以使用控制器
+                let attempts = parameters.lock().await.read_attempts;
+                let result = if limit_cycle {
+                    read_with_retry(attempts, || {
+                        poll_limits(
+                            &controller,
+                            &parameters,
+                            &move_status,
+                            &converter_running,
+                            &limit_status,
+                            &move_status_signal,
+                            read_spindle_rpm,
+                        )
+                    })
+                    .await
+                } else {
+                    read_with_retry(attempts, || {
+                        poll_move_status(
+                            &controller,
+                            &parameters,
+                            &bitmap,
+                            &move_status,
+                            &path_points,
+                            &stall_detectors,
+                            &stalled_axes,
+                        )
+                    })
+                    .await
+                };
+                publish_read_error(&controller_error, &result);
+                // 连续读取失败说明控制器已不可用，继续显示旧的状态会让界面以为机床正常，
+                // 丢弃连接后界面显示未连接，需要重新连接
+                if let Err(e) = tolerate_read_error(result, &mut read_errors) {
+                    eprintln!(
+                        "Status reads keep failing, dropping the connection: {:?}",
+                        e
+                    );
+                    ZMC_MANAGER
+                        .log_command("CONNECTION_LOST", format!("{:?}", e))
+                        .await;
+                    ZMC_MANAGER.mark_connection_lost().await;
+                    return Err(ServerFnError::ServerError(format!(
+                        "Controller stopped responding: {:?}",
+                        e
+                    )));
                 }
                 tokio::time::sleep(Duration::from_millis(MOVE_STATUS_UPDATE_INTERVAL as u64)).await;
             }
@@ -166,46 +963,390 @@ impl ZmcManager {
         self.polling_tasks.lock().await.shutdown().await;
         Ok(())
     }
-    pub async fn clear_path(&self) -> Result<(), ServerFnError> {
+
+    // 断开控制器：停止轮询，先停主轴，再取消各轴运动，最后关闭连接。
+    // 停主轴和取消失败只打印错误，仍然关闭连接
+    pub async fn shutdown(&self) -> Result<(), ServerFnError> {
+        self.stop_polling().await?;
+        let axes = {
+            let params = self.parameters.lock().await;
+            [params.x.axis_num, params.y.axis_num, params.z.axis_num]
+        };
+        let result = self
+            .with_controller(|controller| {
+                if let Err(e) = controller.execute(CONVERTER_STOP_COMMAND) {
+                    eprintln!("Failed to stop the spindle before disconnecting: {}", e);
+                }
+                for axis in axes {
+                    if let Err(e) = controller.direct_single_cancel(axis, 2) {
+                        eprintln!("Failed to cancel axis {} before disconnecting: {}", axis, e);
+                    }
+                }
+                controller.close()
+            })
+            .await;
+        *self.converter_running.lock().await = false;
+        self.controller_info.lock().await.take();
+        self.jogging_axes.lock().await.clear();
+        self.recent_jogs.lock().await.clear();
+        result
+    }
+    pub async fn set_path_style(&self, style: PathImageStyle) {
+        let old = std::mem::replace(&mut *self.path_style.lock().await, style.clone());
+        // 方向、零点位置、比例或快移画法改变后需要重新生成路径图
+        if old.orientation != style.orientation
+            || old.origin != style.origin
+            || old.fit_work_area != style.fit_work_area
+            || old.distinguish_rapids != style.distinguish_rapids
+        {
+            self.rebuild_path_bitmap().await;
+            return;
+        }
         let mut bitmap = self.bitmap.lock().await;
-        bitmap.clear();
-        self.path_img.set(String::new());
-        Ok(())
+        bitmap.set_background(style.background);
+        bitmap.set_line_width(style.line_width);
+        bitmap.set_format(style.format, style.jpeg_quality);
+        // 叠加预览开关改变后整幅图像都不同了
+        if old.overlay_preview != style.overlay_preview {
+            bitmap.mark_all_dirty();
+        }
     }
 
-    pub async fn init(&self, controller_type: ControllerType) -> Result<(), ServerFnError> {
-        let mut controller = self.controller.lock().await;
-        if controller.is_some() {
+    // 按当前样式重新生成路径图：适配加工区域时比例和零点由X/Y软件限位决定，
+    // 否则使用默认比例和选择的零点位置，然后用记录的路径点重新绘制已走过的路径
+    pub async fn rebuild_path_bitmap(&self) {
+        let style = self.path_style.lock().await.clone();
+        let work_area = self
+            .parameters
+            .lock()
+            .await
+            .work_area()
+            .oriented(style.orientation);
+        let x_range = (work_area.min_x, work_area.max_x);
+        let y_range = (work_area.min_y, work_area.max_y);
+        // 适配时按加工区域大小重新分配图像，保持大致固定的像素/毫米
+        let (width, height) = if style.fit_work_area {
+            fitted_bitmap_size(x_range, y_range)
+        } else {
+            (PATH_BITMAP_SIZE, PATH_BITMAP_SIZE)
+        };
+        let mut bitmap = self.bitmap.lock().await;
+        if bitmap.size() != (width, height) {
+            println!("Reallocating path image at {}x{}", width, height);
+        }
+        let fitted = if style.fit_work_area {
+            Bitmap::fit_to_range(width, height, x_range, y_range)
+        } else {
+            None
+        };
+        let mut rebuilt = fitted.unwrap_or_else(|| {
+            let mut rebuilt = Bitmap::new(width, height, PATH_BITMAP_SCALE);
+            let origin = style.origin.fraction();
+            rebuilt.set_origin(origin.0, origin.1);
+            rebuilt
+        });
+        rebuilt.set_background(style.background);
+        rebuilt.set_line_width(style.line_width);
+        rebuilt.set_orientation(style.orientation);
+        rebuilt.set_format(style.format, style.jpeg_quality);
+        // 记录的路径点已满时，之后的移动没有记录，重绘的图像中会缺少这部分
+        let path_points = self.path_points.lock().await;
+        if path_points.len() >= PATH_POINTS_CAPACITY {
+            println!("Path history is full, later movement is missing from the rebuilt image");
+        }
+        rebuilt.set_distinguish_rapids(style.distinguish_rapids);
+        for ([x, y, z], motion) in path_points.iter() {
+            rebuilt.set_motion_pixel(*x, *y, (-z) * 75.0, *motion);
+        }
+        *bitmap = rebuilt;
+    }
+    pub async fn log_command(&self, command: &str, detail: String) {
+        *self.last_activity.lock().await = Instant::now();
+        let mut command_log = self.command_log.lock().await;
+        if command_log.len() >= COMMAND_LOG_CAPACITY {
+            command_log.pop_front();
+        }
+        command_log.push_back(LogEntry {
+            timestamp_ms: chrono::Utc::now().timestamp_millis(),
+            command: command.to_string(),
+            detail,
+        });
+    }
+    pub async fn status(&self) -> MachineStatus {
+        MachineStatus {
+            move_status: self.move_status.lock().await.clone(),
+            limit_status: self.limit_status.get_untracked(),
+        }
+    }
+    // 有轴报警时拒绝新的运动指令，需先复位报警
+    pub async fn ensure_no_alarm(&self) -> Result<(), ServerFnError> {
+        if self.move_status.lock().await.any_alarm() {
             return Err(ServerFnError::ServerError(
-                "Controller is already initialized".to_string(),
+                "An axis is in alarm, reset the alarm before moving".to_string(),
             ));
         }
-        match controller_type {
-            ControllerType::Zmc(ip) => {
-                let mut zmc_controller = ZmcController::new();
-                zmc_controller.open_eth(&ip)?;
-                *controller = Some(Box::new(zmc_controller));
-            }
-            ControllerType::Fake => {
-                *controller = Some(Box::new(FakeController::new()));
-            }
+        Ok(())
+    }
+    // 门禁联锁生效时拒绝新的运动指令，需先关门
+    pub async fn ensure_door_closed(&self) -> Result<(), ServerFnError> {
+        if door_interlock_engaged().await {
+            return Err(ServerFnError::ServerError(
+                "Door is open, close it before moving".to_string(),
+            ));
         }
         Ok(())
     }
-
-    pub async fn deinit(&self) -> Result<(), ServerFnError> {
+    // 运动中重新设置轴类型、速度和加减速会造成突变，有启用的轴在运动或读不到运动状态时返回错误。
+    // 未连接时只保存参数，不需要检查
+    pub async fn ensure_idle(&self) -> Result<(), ServerFnError> {
+        let params = self.parameters.lock().await.clone();
         let mut controller = self.controller.lock().await;
-        if controller.is_none() {
+        let Some(controller) = controller
+            .as_mut()
+            .filter(|controller| controller.is_open())
+        else {
             return Ok(());
-        }
+        };
+        check_axes_idle(&params, |axis| controller.direct_get_if_idle(axis))
+    }
+    // 给移动目标加上反向间隙补偿，absolute为false时pos_list为相对移动量
+    pub async fn compensate_backlash(
+        &self,
+        axis_list: &[u8],
+        pos_list: &mut [f32],
+        absolute: bool,
+    ) -> Result<(), ServerFnError> {
+        let backlash: Vec<f32> = {
+            let parameters = self.parameters.lock().await;
+            axis_list
+                .iter()
+                .map(|axis| parameters.axis_by_num(*axis).map_or(0.0, |p| p.backlash))
+                .collect()
+        };
+        if backlash.iter().all(|b| *b == 0.0) {
+            return Ok(());
+        }
+        let current = if absolute {
+            let axis_list = axis_list.to_vec();
+            self.with_controller(move |controller| {
+                let mut current = Vec::with_capacity(axis_list.len());
+                for axis in axis_list {
+                    current.push(controller.direct_get_d_pos(axis)?);
+                }
+                Ok(current)
+            })
+            .await?
+        } else {
+            vec![0.0; axis_list.len()]
+        };
+        let mut states = self.backlash.lock().await;
+        for (i, axis) in axis_list.iter().enumerate() {
+            let state = states.entry(*axis).or_default();
+            // 绝对位置要先加上之前累计的补偿，才能和控制器的位置比较
+            if absolute {
+                pos_list[i] += state.offset;
+            }
+            pos_list[i] += state.correction(pos_list[i] - current[i], backlash[i]);
+        }
+        Ok(())
+    }
+
+    // 回零或清零后坐标重新建立，之前的补偿状态不再有效
+    pub async fn reset_backlash(&self, axis: u8) {
+        self.backlash.lock().await.remove(&axis);
+    }
+
+    pub async fn clear_path(&self) -> Result<(), ServerFnError> {
+        let mut bitmap = self.bitmap.lock().await;
+        bitmap.clear();
+        self.path_points.lock().await.clear();
+        // 未在轮询时也要让客户端立即看到清除后的图像
+        publish_path_delta(
+            &mut bitmap,
+            self.path_style.lock().await.overlay_preview,
+            &mut *self.path_img_seq.lock().await,
+            &self.path_img_delta,
+        )
+        .await;
+        Ok(())
+    }
+
+    // 完整的实时路径图，尚未推送的变化会在下一次增量中重复发送，客户端覆盖即可
+    pub async fn path_snapshot(&self) -> PathImageSnapshot {
+        let bitmap = self.bitmap.lock().await;
+        let seq = *self.path_img_seq.lock().await;
+        let data_url = if self.path_style.lock().await.overlay_preview {
+            composite_with_preview(&bitmap).await.to_data_url()
+        } else {
+            bitmap.to_data_url()
+        };
+        let (width, height) = bitmap.size();
+        PathImageSnapshot {
+            seq,
+            width: width as u32,
+            height: height as u32,
+            data_url,
+        }
+    }
+
+    pub async fn init(&self, controller_type: ControllerType) -> Result<(), ServerFnError> {
+        let mut controller = self.controller.lock().await;
+        if controller.is_some() {
+            return Err(ServerFnError::ServerError(
+                "Controller is already initialized".to_string(),
+            ));
+        }
+        *self.last_activity.lock().await = Instant::now();
+        self.idle_warning.update(|value| *value = None);
+        *self.stall_detectors.lock().await = Default::default();
+        self.stalled_axes.update(|axes| axes.clear());
+        match controller_type {
+            ControllerType::Zmc(ip) => {
+                let mut zmc_controller = ZmcController::new();
+                zmc_controller.open_eth(&ip)?;
+                *controller = Some(Box::new(zmc_controller));
+                *self.controller_ip.lock().await = Some(ip);
+                *self.controller_kind.lock().await = ControllerKind::Real;
+            }
+            ControllerType::Fake => {
+                *controller = Some(Box::new(FakeController::new()));
+                *self.controller_kind.lock().await = ControllerKind::Fake;
+            }
+        }
+        let info = controller.as_mut().map(read_controller_info);
+        *self.controller_info.lock().await = match info {
+            Some(Ok(info)) => Some(info),
+            Some(Err(e)) => {
+                println!("Failed to read controller info: {:?}", e);
+                None
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    // 断开旧连接后重新连接。路径图和路径点只有clear_path会清除，
+    // 掉线重连后继续在原来的轨迹上绘制
+    pub async fn reconnect(&self, controller_type: ControllerType) -> Result<(), ServerFnError> {
+        // 先停掉旧的轮询，避免新旧两个轮询同时绘制
+        self.stop_polling().await?;
+        self.deinit().await?;
+        self.init(controller_type).await?;
+        self.start_polling().await
+    }
+
+    pub async fn deinit(&self) -> Result<(), ServerFnError> {
+        let mut controller = self.controller.lock().await;
+        if controller.is_none() {
+            return Ok(());
+        }
         let controller_unwrapped = controller.as_mut().unwrap();
         if controller_unwrapped.is_open() {
             controller_unwrapped.close()?;
         }
         controller.take(); // Clear the controller
+        self.controller_ip.lock().await.take();
+        *self.controller_kind.lock().await = ControllerKind::None;
+        self.controller_info.lock().await.take();
         Ok(())
     }
 
+    // 轮询连续读取失败后丢弃连接。控制器可能已经不响应，只尝试关闭一次；
+    // 保留IP以便重新连接
+    pub async fn mark_connection_lost(&self) {
+        if let Some(mut controller) = self.controller.lock().await.take() {
+            if let Err(e) = controller.close() {
+                eprintln!("Failed to close the unresponsive controller: {}", e);
+            }
+        }
+        *self.controller_kind.lock().await = ControllerKind::None;
+        self.controller_info.lock().await.take();
+        *self.converter_running.lock().await = false;
+        self.jogging_axes.lock().await.clear();
+        self.recent_jogs.lock().await.clear();
+    }
+
+    // 关闭服务前让机床处于安全状态：中止G代码、停主轴、取消各轴运动，再断开控制器。
+    // 每一步失败都只打印错误并继续，最后总是断开控制器，返回第一个错误
+    pub async fn park(&self) -> Result<(), ServerFnError> {
+        self.park_with(issue_stop_step).await
+    }
+
+    async fn park_with<F, E>(&self, issue: F) -> Result<(), ServerFnError>
+    where
+        F: Fn(&mut Box<dyn Controller + Send>, StopStep) -> Result<(), E>,
+        E: std::fmt::Display,
+    {
+        let mut result = super::g_code::abort_gcode_execution().await;
+        if let Err(e) = &result {
+            eprintln!("Failed to abort the G-code run before parking: {}", e);
+        }
+        if let Err(e) = self.stop_polling().await {
+            eprintln!("Failed to stop polling before parking: {}", e);
+            result = result.and(Err(e));
+        }
+        let connected = self
+            .controller
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|controller| controller.is_open());
+        if connected {
+            self.log_command("SHUTDOWN_PARK", String::new()).await;
+            let params = self.parameters.lock().await.clone();
+            let steps = [
+                StopStep::Spindle,
+                StopStep::Cancel(params.x.axis_num),
+                StopStep::Cancel(params.y.axis_num),
+                StopStep::Cancel(params.z.axis_num),
+            ];
+            let stopped = self
+                .with_controller(|controller| {
+                    let mut first_error = None;
+                    for step in steps {
+                        if let Err(e) = issue(controller, step) {
+                            eprintln!("Failed to {} while parking: {}", step, e);
+                            first_error.get_or_insert_with(|| e.to_string());
+                        }
+                    }
+                    Ok(first_error)
+                })
+                .await;
+            let stopped = match stopped {
+                Ok(Some(e)) => Err(ServerFnError::ServerError(e)),
+                Ok(None) => Ok(()),
+                Err(e) => Err(e),
+            };
+            result = result.and(stopped);
+        }
+        *self.converter_running.lock().await = false;
+        if let Err(e) = self.deinit().await {
+            eprintln!("Failed to disconnect the controller while parking: {}", e);
+            result = result.and(Err(e));
+        }
+        result
+    }
+
+    pub async fn connection_snapshot(&self) -> ConnectionSnapshot {
+        let connected = self
+            .controller
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|controller| controller.is_open());
+        let kind = if connected {
+            *self.controller_kind.lock().await
+        } else {
+            ControllerKind::None
+        };
+        ConnectionSnapshot {
+            connected,
+            kind,
+            ip: self.controller_ip.lock().await.clone(),
+            parameters: self.parameters.lock().await.clone(),
+        }
+    }
+
     /// Helper function to execute operations that require controller
     /// return error if the controller is not open
     pub async fn with_controller<F, R>(&self, op: F) -> Result<R, ServerFnError>
@@ -231,6 +1372,9 @@ impl ZmcManager {
 #[cfg(feature = "ssr")]
 static ZMC_MANAGER: LazyLock<ZmcManager> = LazyLock::new(|| ZmcManager {
     controller: Arc::new(Mutex::new(None)),
+    controller_ip: Arc::new(Mutex::new(None)),
+    controller_kind: Arc::new(Mutex::new(ControllerKind::None)),
+    controller_info: Arc::new(Mutex::new(None)),
     parameters: Arc::new(Mutex::new(Parameters::default())),
     polling_interval: Arc::new(Mutex::new(Duration::from_millis(100))),
     polling_tasks: Arc::new(Mutex::new(JoinSet::new())),
@@ -239,35 +1383,69 @@ static ZMC_MANAGER: LazyLock<ZmcManager> = LazyLock::new(|| ZmcManager {
     move_status_signal: ServerSignal::new("move_status".to_string(), MoveStatus::default())
         .unwrap(),
     path_img_update_counter: Arc::new(Mutex::new(0)),
-    path_img: ServerSignal::new("path_img".to_string(), String::new()).unwrap(),
-    bitmap: Arc::new(Mutex::new(Bitmap::new(500, 500, 4.0))), // 500x500 bitmap with scale 10.0
+    path_img_delta: ServerSignal::new("path_img_delta".to_string(), PathImageDelta::default())
+        .unwrap(),
+    path_img_seq: Arc::new(Mutex::new(0)),
+    path_style: Arc::new(Mutex::new(PathImageStyle::default())),
+    path_points: Arc::new(Mutex::new(Vec::new())),
+    bitmap: Arc::new(Mutex::new(Bitmap::new(
+        PATH_BITMAP_SIZE,
+        PATH_BITMAP_SIZE,
+        PATH_BITMAP_SCALE,
+    ))),
+    jogging_axes: Arc::new(Mutex::new(Vec::new())),
+    recent_jogs: Arc::new(Mutex::new(HashMap::new())),
+    jog_speed_lock: Arc::new(Mutex::new(())),
+    last_heartbeat: Arc::new(Mutex::new(Instant::now())),
+    heartbeat_timeout: Arc::new(Mutex::new(DEFAULT_HEARTBEAT_TIMEOUT)),
+    command_log: Arc::new(Mutex::new(VecDeque::with_capacity(COMMAND_LOG_CAPACITY))),
+    last_activity: Arc::new(Mutex::new(Instant::now())),
+    idle_timeout: Arc::new(Mutex::new(Duration::ZERO)),
+    idle_warning: ServerSignal::new("idle_warning".to_string(), None).unwrap(),
+    controller_error: ServerSignal::new("controller_error".to_string(), ErrorEvent::default())
+        .unwrap(),
+    stall_detectors: Arc::new(Mutex::new([StallDetector::default(); 3])),
+    stalled_axes: ServerSignal::new("stalled_axes".to_string(), Vec::new()).unwrap(),
+    backlash: Arc::new(Mutex::new(HashMap::new())),
+    converter_running: Arc::new(Mutex::new(false)),
+    developer_mode: Arc::new(Mutex::new(false)),
 });
 
 #[server]
 pub async fn zmc_init_eth(ip: String) -> Result<(), ServerFnError> {
-    ZMC_MANAGER.deinit().await?;
-    ZMC_MANAGER.init(ControllerType::Zmc(ip)).await?;
-    ZMC_MANAGER.start_polling().await
+    ZMC_MANAGER.reconnect(ControllerType::Zmc(ip)).await
 }
 
 #[server]
 pub async fn zmc_init_fake() -> Result<(), ServerFnError> {
-    ZMC_MANAGER.deinit().await?;
-    ZMC_MANAGER.init(ControllerType::Fake).await?;
-    ZMC_MANAGER.start_polling().await
+    ZMC_MANAGER.reconnect(ControllerType::Fake).await
 }
 
 #[server]
 pub async fn zmc_close() -> Result<(), ServerFnError> {
-    ZMC_MANAGER.stop_polling().await?;
-    ZMC_MANAGER.with_controller(|c| Ok(c.close()?)).await
+    ZMC_MANAGER.shutdown().await
 }
 
 // 设定参数
 #[server]
 pub async fn zmc_set_parameters(params: Parameters) -> Result<(), ServerFnError> {
     println!("Setting parameters: {:?}", params);
+    // 轴号重复或越界会把参数设置到错误的轴上
+    let axis_count = ZMC_MANAGER
+        .controller_info
+        .lock()
+        .await
+        .as_ref()
+        .map_or(CONTROLLER_AXIS_COUNT, |info| info.axis_count);
+    params
+        .validate_axis_nums(axis_count)
+        .map_err(ServerFnError::new)?;
+    ZMC_MANAGER.ensure_idle().await?;
     *ZMC_MANAGER.parameters.lock().await = params.clone();
+    // 软件限位改变后重新适配路径图的比例
+    if ZMC_MANAGER.path_style.lock().await.fit_work_area {
+        ZMC_MANAGER.rebuild_path_bitmap().await;
+    }
     ZMC_MANAGER
         .with_controller(|controller| {
             // 设置输入IO的电平反转
@@ -292,10 +1470,12 @@ pub async fn zmc_set_parameters(params: Parameters) -> Result<(), ServerFnError>
                     .direct_set_invert_in(io, params.inverted_status.limit_io_level_inverted)?;
             }
 
-            let axis_num_list = [params.x.axis_num, params.y.axis_num, params.z.axis_num];
-            let axis_params = [params.x, params.y, params.z];
-
-            for i in axis_num_list {
+            for axis in [params.x, params.y, params.z] {
+                let i = axis.axis_num;
+                // 停用的轴不做设置
+                if !axis.enabled {
+                    continue;
+                }
                 // TODO: Change to 65 after simulation
                 // controller.direct_set_a_type(i, 0)?;
                 controller.direct_set_a_type(i, 65)?;
@@ -305,21 +1485,22 @@ pub async fn zmc_set_parameters(params: Parameters) -> Result<(), ServerFnError>
                 // 设置加速度和减速度
                 controller.direct_set_accel(i, params.speed.acceleration)?;
                 controller.direct_set_decel(i, params.speed.deceleration)?;
-                // 设置梯形速度
-                controller.direct_set_sramp(i, 20.0)?;
-                controller.direct_set_units(i, axis_params[i as usize].pulse_equivalent)?;
+                // 设置S曲线加减速时间
+                controller.direct_set_sramp(i, params.speed.sramp_time)?;
+                controller.direct_set_units(i, axis.pulse_equivalent)?;
                 // 设置软件正限位
-                controller
-                    .direct_set_fs_limit(i, axis_params[i as usize].software_positive_limit)?;
+                controller.direct_set_fs_limit(i, axis.software_positive_limit)?;
                 // 设置软件负限位
-                controller
-                    .direct_set_rs_limit(i, axis_params[i as usize].software_negative_limit)?;
+                controller.direct_set_rs_limit(i, axis.software_negative_limit)?;
                 // 设置硬件正限位IO
-                controller.direct_set_fwd_in(i, axis_params[i as usize].positive_limit_io)?;
+                controller.direct_set_fwd_in(i, axis.positive_limit_io)?;
                 // 设置硬件负限位IO
-                controller.direct_set_rev_in(i, axis_params[i as usize].negative_limit_io)?;
-                // 设置回零开关IO
-                // controller.direct_set_datum_in(i, axis_params[i as usize].zero_point_io)?;
+                controller.direct_set_rev_in(i, axis.negative_limit_io)?;
+                // 设置回零开关IO，0表示未配置回零开关
+                let zero_point_io = axis.zero_point_io;
+                if zero_point_io != 0 {
+                    controller.direct_set_datum_in(i, zero_point_io)?;
+                }
                 controller.direct_set_alm_in(i, params.emergency_stop_io)?;
                 // TODO: 设置PID参数
             }
@@ -328,6 +1509,12 @@ pub async fn zmc_set_parameters(params: Parameters) -> Result<(), ServerFnError>
         .await
 }
 
+// 将当前生效的参数导出为ZMC BASIC初始化程序，便于直接给控制器编程
+#[server]
+pub async fn export_parameters_as_basic() -> Result<String, ServerFnError> {
+    Ok(parameters_to_basic(&*ZMC_MANAGER.parameters.lock().await))
+}
+
 #[server]
 pub async fn zmc_get_idle(axis: u8) -> Result<bool, ServerFnError> {
     ZMC_MANAGER
@@ -338,9 +1525,40 @@ pub async fn zmc_get_idle(axis: u8) -> Result<bool, ServerFnError> {
         .await
 }
 
+// 轴列表和位置列表须一一对应且不为空，否则控制器库会按轴数越界读取位置
+#[cfg(feature = "ssr")]
+fn check_move_lists(axis_list: &[u8], pos_list: &[f32]) -> Result<(), ServerFnError> {
+    if axis_list.len() != pos_list.len() {
+        return Err(ServerFnError::ServerError(
+            "Axis list and position list must have the same length".to_string(),
+        ));
+    }
+    if axis_list.is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Axis list cannot be empty".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 // 绝对移动
 #[server]
 pub async fn zmc_move_abs(axis_list: Vec<u8>, pos_list: Vec<f32>) -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    // 先检查再记录，指令记录中只有实际发给控制器的运动
+    check_move_lists(&axis_list, &pos_list)?;
+    ZMC_MANAGER.ensure_no_alarm().await?;
+    ZMC_MANAGER.ensure_door_closed().await?;
+    let mut pos_list = pos_list;
+    ZMC_MANAGER
+        .compensate_backlash(&axis_list, &mut pos_list, true)
+        .await?;
+    ZMC_MANAGER
+        .log_command(
+            "MOVE_ABS",
+            format!("axis={:?} pos={:?}", axis_list, pos_list),
+        )
+        .await;
     ZMC_MANAGER
         .with_controller(|controller| {
             controller.direct_move_abs(
@@ -355,16 +1573,18 @@ pub async fn zmc_move_abs(axis_list: Vec<u8>, pos_list: Vec<f32>) -> Result<(),
 // 相对移动
 #[server]
 pub async fn zmc_move(axis_list: Vec<u8>, pos_list: Vec<f32>) -> Result<(), ServerFnError> {
-    if axis_list.len() != pos_list.len() {
-        return Err(ServerFnError::ServerError(
-            "Axis list and position list must have the same length".to_string(),
-        ));
-    }
-    if axis_list.is_empty() {
-        return Err(ServerFnError::ServerError(
-            "Axis list cannot be empty".to_string(),
-        ));
-    }
+    super::g_code::check_controlling_client().await?;
+    // 与绝对移动相同，检查通过后才记录
+    check_move_lists(&axis_list, &pos_list)?;
+    ZMC_MANAGER.ensure_no_alarm().await?;
+    ZMC_MANAGER.ensure_door_closed().await?;
+    let mut pos_list = pos_list;
+    ZMC_MANAGER
+        .compensate_backlash(&axis_list, &mut pos_list, false)
+        .await?;
+    ZMC_MANAGER
+        .log_command("MOVE", format!("axis={:?} pos={:?}", axis_list, pos_list))
+        .await;
     ZMC_MANAGER
         .with_controller(|controller| {
             controller.direct_move(axis_list.len() as u8, axis_list.as_ref(), pos_list.as_ref())?;
@@ -375,11 +1595,15 @@ pub async fn zmc_move(axis_list: Vec<u8>, pos_list: Vec<f32>) -> Result<(), Serv
 // 设置速度
 #[server]
 pub async fn zmc_set_speed(axis: u8, speed: f32) -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
     if speed < 0.0 {
         return Err(ServerFnError::ServerError(
             "Speed cannot be negative".to_string(),
         ));
     }
+    ZMC_MANAGER
+        .log_command("SET_SPEED", format!("axis={} speed={}", axis, speed))
+        .await;
     ZMC_MANAGER
         .with_controller(|controller| {
             controller.direct_set_speed(axis, speed)?;
@@ -390,6 +1614,10 @@ pub async fn zmc_set_speed(axis: u8, speed: f32) -> Result<(), ServerFnError> {
 // 变频器运行
 #[server]
 pub async fn zmc_converter_set_freq(freq: u32) -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    ZMC_MANAGER
+        .log_command("CONVERTER_SET_FREQ", format!("freq={}", freq))
+        .await;
     ZMC_MANAGER
         .with_controller(|controller| {
             controller.modbus_set4x_long(3, 1, &[freq as i32])?;
@@ -399,8 +1627,19 @@ pub async fn zmc_converter_set_freq(freq: u32) -> Result<(), ServerFnError> {
         .await
 }
 
+// 变频器停止指令，关闭服务时也会发送
+#[cfg(feature = "ssr")]
+const CONVERTER_STOP_COMMAND: &str = "MODBUSM_REGSET(99,1,1)";
+
 #[server]
 pub async fn zmc_converter_run(inverted: bool) -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    // 与运动相同的联锁：门打开或有轴报警时不启动主轴
+    ZMC_MANAGER.ensure_door_closed().await?;
+    ZMC_MANAGER.ensure_no_alarm().await?;
+    ZMC_MANAGER
+        .log_command("CONVERTER_RUN", format!("inverted={}", inverted))
+        .await;
     ZMC_MANAGER
         .with_controller(|controller| {
             if inverted {
@@ -410,23 +1649,88 @@ pub async fn zmc_converter_run(inverted: bool) -> Result<(), ServerFnError> {
             }
             Ok(())
         })
-        .await
+        .await?;
+    *ZMC_MANAGER.converter_running.lock().await = true;
+    Ok(())
 }
 
 // 变频器停止
 #[server]
 pub async fn zmc_converter_stop() -> Result<(), ServerFnError> {
+    ZMC_MANAGER
+        .log_command("CONVERTER_STOP", String::new())
+        .await;
     ZMC_MANAGER
         .with_controller(|controller| {
-            controller.execute("MODBUSM_REGSET(99,1,1)")?;
+            controller.execute(CONVERTER_STOP_COMMAND)?;
             Ok(())
         })
+        .await?;
+    *ZMC_MANAGER.converter_running.lock().await = false;
+    Ok(())
+}
+
+// 开启或关闭开发者模式，由界面上的开关同步；开启需要服务端允许，关闭总是可以
+#[server]
+pub async fn zmc_set_developer_mode(enabled: bool) -> Result<(), ServerFnError> {
+    if enabled && std::env::var_os(DEVELOPER_MODE_ENV).is_none_or(|value| value != "1") {
+        return Err(ServerFnError::ServerError(format!(
+            "Developer mode is not allowed on this server, start it with {}=1",
+            DEVELOPER_MODE_ENV
+        )));
+    }
+    ZMC_MANAGER
+        .log_command("DEVELOPER_MODE", format!("enabled={}", enabled))
+        .await;
+    *ZMC_MANAGER.developer_mode.lock().await = enabled;
+    Ok(())
+}
+
+// 原始指令和直接设置输出绕过所有检查，只在开发者模式下、没有程序运行或暂停、
+// 门关闭且没有报警时执行
+#[cfg(feature = "ssr")]
+async fn ensure_developer_access() -> Result<(), ServerFnError> {
+    if !*ZMC_MANAGER.developer_mode.lock().await {
+        return Err(ServerFnError::ServerError(
+            "Developer mode is off".to_string(),
+        ));
+    }
+    if super::g_code::program_active() {
+        return Err(ServerFnError::ServerError(
+            "A G-code program is running or paused".to_string(),
+        ));
+    }
+    ZMC_MANAGER.ensure_door_closed().await?;
+    ZMC_MANAGER.ensure_no_alarm().await
+}
+
+// 直接执行一段ZMC BASIC，供集成调试使用，返回控制器的应答
+#[server]
+pub async fn zmc_execute_raw(cmd: String) -> Result<String, ServerFnError> {
+    let cmd = cmd.trim();
+    if cmd.is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Command cannot be empty".to_string(),
+        ));
+    }
+    ensure_developer_access().await?;
+    ZMC_MANAGER
+        .log_command("EXECUTE_RAW", cmd.to_string())
+        .await;
+    ZMC_MANAGER
+        .with_controller(|controller| controller.execute(cmd))
         .await
 }
 
 // 设置输入轴的电平反转
 #[server]
 pub async fn zmc_set_in_inverted(in_num: u16, inverted: bool) -> Result<(), ServerFnError> {
+    ZMC_MANAGER
+        .log_command(
+            "SET_IN_INVERTED",
+            format!("in={} inverted={}", in_num, inverted),
+        )
+        .await;
     ZMC_MANAGER
         .with_controller(|controller| {
             controller.direct_set_invert_in(in_num, inverted)?;
@@ -435,28 +1739,368 @@ pub async fn zmc_set_in_inverted(in_num: u16, inverted: bool) -> Result<(), Serv
         .await
 }
 
-// 手动移动轴,输入轴和运动的正负，
+// 读取任意输入IO的状态，用于调试时检查接线
+#[server]
+pub async fn zmc_get_in(io: u16) -> Result<bool, ServerFnError> {
+    ZMC_MANAGER
+        .with_controller(|controller| {
+            let value = controller.direct_get_in(io)?;
+            Ok(value)
+        })
+        .await
+}
+
+// 设置任意输出IO，Controller没有直接设置输出的接口，通过BASIC的OP指令设置。
+// 输出可能接着主轴变频器等设备，与原始指令一样受开发者模式等限制
 #[server]
-pub async fn zmc_manual_move(axis: u8, direction: i8) -> Result<(), ServerFnError> {
+pub async fn zmc_set_out(io: u16, on: bool) -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    ensure_developer_access().await?;
+    ZMC_MANAGER
+        .log_command("SET_OUT", format!("out={} on={}", io, on))
+        .await;
     ZMC_MANAGER
         .with_controller(|controller| {
-            controller.direct_single_v_move(axis, direction)?;
+            controller.execute(&format!("OP({},{})", io, on as u8))?;
             Ok(())
         })
         .await
 }
 
-// 手动停止轴
+// 手动移动轴,输入轴和运动的正负，
+#[server]
+pub async fn zmc_manual_move(axis: u8, direction: i8, jog_speed: f32) -> Result<(), ServerFnError> {
+    zmc_manual_move_axes(vec![axis], vec![direction], jog_speed).await
+}
+
+#[cfg(feature = "ssr")]
+fn check_jog_speed(jog_speed: f32) -> Result<(), ServerFnError> {
+    if jog_speed > 0.0 && jog_speed.is_finite() {
+        Ok(())
+    } else {
+        Err(ServerFnError::ServerError(
+            "Jog speed must be positive".to_string(),
+        ))
+    }
+}
+
+// 多轴同时点动，用于XY斜向点动；各轴先设为点动速度，合成方向为45°
+#[server]
+pub async fn zmc_manual_move_axes(
+    axis_list: Vec<u8>,
+    directions: Vec<i8>,
+    jog_speed: f32,
+) -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    if axis_list.len() != directions.len() || axis_list.is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Axis list and direction list must be non-empty and have the same length".to_string(),
+        ));
+    }
+    check_jog_speed(jog_speed)?;
+    // 按键连发或卡键时重复的点动只刷新心跳，不占用控制器
+    let now = Instant::now();
+    if jog_redundant(
+        &*ZMC_MANAGER.recent_jogs.lock().await,
+        &axis_list,
+        &directions,
+        jog_speed,
+        now,
+    ) {
+        *ZMC_MANAGER.last_heartbeat.lock().await = now;
+        return Ok(());
+    }
+    ZMC_MANAGER.ensure_no_alarm().await?;
+    ZMC_MANAGER.ensure_door_closed().await?;
+    // 点动没有终点，反向时先单独走一段补偿距离
+    let mut compensation = Vec::with_capacity(axis_list.len());
+    for (axis, direction) in axis_list.iter().zip(&directions) {
+        let mut step = [direction.signum() as f32];
+        ZMC_MANAGER
+            .compensate_backlash(&[*axis], &mut step, false)
+            .await?;
+        compensation.push(step[0] - direction.signum() as f32);
+    }
+    // 检查通过后才记录，被拒绝的点动不出现在指令记录中
+    ZMC_MANAGER
+        .log_command(
+            "MANUAL_MOVE",
+            format!(
+                "axis={:?} direction={:?} speed={}",
+                axis_list, directions, jog_speed
+            ),
+        )
+        .await;
+    let jog_axes = axis_list.clone();
+    let directions_issued = directions.clone();
+    ZMC_MANAGER
+        .with_controller(move |controller| {
+            for (i, (axis, direction)) in axis_list.iter().zip(&directions).enumerate() {
+                controller.direct_set_speed(*axis, jog_speed)?;
+                if compensation[i] != 0.0 {
+                    controller.direct_move(1, &[*axis], &[compensation[i]])?;
+                }
+                controller.direct_single_v_move(*axis, *direction)?;
+            }
+            Ok(())
+        })
+        .await?;
+    *ZMC_MANAGER.last_heartbeat.lock().await = Instant::now();
+    // 轮询先锁jogging_axes再锁recent_jogs，这里不能同时持有两者
+    {
+        let mut recent_jogs = ZMC_MANAGER.recent_jogs.lock().await;
+        for (axis, direction) in jog_axes.iter().zip(directions_issued) {
+            recent_jogs.insert(
+                *axis,
+                JogCommand {
+                    direction,
+                    speed: jog_speed,
+                    issued: now,
+                },
+            );
+        }
+    }
+    ZMC_MANAGER.jogging_axes.lock().await.extend(jog_axes);
+    Ok(())
+}
+
+// 手动停止轴，停止后恢复加工速度
 #[server]
 pub async fn zmc_manual_stop(axis: u8) -> Result<(), ServerFnError> {
+    let _jog_speed = ZMC_MANAGER.jog_speed_lock.lock().await;
+    // 停止从不合并，之后的点动也要重新发出
+    ZMC_MANAGER.recent_jogs.lock().await.remove(&axis);
+    ZMC_MANAGER
+        .log_command("MANUAL_STOP", format!("axis={}", axis))
+        .await;
+    let speed = ZMC_MANAGER.parameters.lock().await.speed.processing_speed;
     ZMC_MANAGER
         .with_controller(|controller| {
             controller.direct_single_cancel(axis, 2)?;
+            controller.direct_set_speed(axis, speed)?;
+            Ok(())
+        })
+        .await?;
+    ZMC_MANAGER.jogging_axes.lock().await.retain(|a| *a != axis);
+    Ok(())
+}
+
+// 点动加速：只改变仍在点动的轴的速度，已经停止的轴保持恢复后的加工速度
+#[server]
+pub async fn zmc_set_jog_speed(axis_list: Vec<u8>, jog_speed: f32) -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    check_jog_speed(jog_speed)?;
+    let _jog_speed = ZMC_MANAGER.jog_speed_lock.lock().await;
+    let jogging: Vec<u8> = {
+        let jogging_axes = ZMC_MANAGER.jogging_axes.lock().await;
+        axis_list
+            .into_iter()
+            .filter(|axis| jogging_axes.contains(axis))
+            .collect()
+    };
+    if jogging.is_empty() {
+        return Ok(());
+    }
+    ZMC_MANAGER
+        .log_command(
+            "SET_JOG_SPEED",
+            format!("axis={:?} speed={}", jogging, jog_speed),
+        )
+        .await;
+    ZMC_MANAGER
+        .with_controller(|controller| {
+            for axis in jogging {
+                controller.direct_set_speed(axis, jog_speed)?;
+            }
+            Ok(())
+        })
+        .await
+}
+
+// 发出单步点动：各轴沿方向移动distance，目标超出软件限位时拒绝执行。
+// 轴速度留在点动速度，由调用者在运动结束后恢复
+#[cfg(feature = "ssr")]
+async fn issue_jog_step(
+    axis_list: Vec<u8>,
+    directions: Vec<i8>,
+    distance: f32,
+    jog_speed: f32,
+) -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    if axis_list.len() != directions.len() || axis_list.is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Axis list and direction list must be non-empty and have the same length".to_string(),
+        ));
+    }
+    check_jog_speed(jog_speed)?;
+    // 不在参数中的轴不检查软件限位，非有限的距离会一直传到控制器
+    if !distance.is_finite() {
+        return Err(ServerFnError::ServerError(
+            "Jog distance must be a finite number".to_string(),
+        ));
+    }
+    ZMC_MANAGER.ensure_door_closed().await?;
+    let params = ZMC_MANAGER.parameters.lock().await.clone();
+    let mut delta_list = Vec::with_capacity(axis_list.len());
+    for (axis, direction) in axis_list.iter().zip(&directions) {
+        let current = zmc_get_axis_position(*axis).await?;
+        let target = step_target(current, *direction, distance);
+        if !params.within_soft_limits(*axis, target) {
+            return Err(ServerFnError::ServerError(format!(
+                "Axis {} target {:.3} is outside the software limits",
+                axis, target
+            )));
+        }
+        delta_list.push(target - current);
+    }
+    ZMC_MANAGER
+        .with_controller(|controller| {
+            for axis in &axis_list {
+                controller.direct_set_speed(*axis, jog_speed)?;
+            }
+            Ok(())
+        })
+        .await?;
+    zmc_move(axis_list, delta_list).await
+}
+
+// 单步点动，发出后立即返回，各轴停下后在后台恢复加工速度。
+// 需要知道这一步何时走完时使用zmc_jog_step_blocking
+#[server]
+pub async fn zmc_jog_step(
+    axis_list: Vec<u8>,
+    directions: Vec<i8>,
+    distance: f32,
+    jog_speed: f32,
+) -> Result<(), ServerFnError> {
+    if let Err(e) = issue_jog_step(axis_list.clone(), directions, distance, jog_speed).await {
+        // 发出前可能已经改成点动速度
+        let _ = restore_processing_speed(&axis_list).await;
+        return Err(e);
+    }
+    tokio::spawn(async move {
+        let timeout = step_timeout(distance, jog_speed);
+        if tokio::time::timeout(timeout, wait_axes_idle(&axis_list))
+            .await
+            .is_err()
+        {
+            eprintln!("Step jog on axes {:?} did not finish in time", axis_list);
+        }
+        if let Err(e) = restore_processing_speed(&axis_list).await {
+            eprintln!("Failed to restore the processing speed: {}", e);
+        }
+    });
+    Ok(())
+}
+
+// 等待单步点动完成的时间：按点动速度走完距离所需时间的两倍，再加上余量
+#[cfg(feature = "ssr")]
+fn step_timeout(distance: f32, jog_speed: f32) -> Duration {
+    Duration::try_from_secs_f32(distance.abs() / jog_speed * 2.0)
+        .unwrap_or(Duration::MAX)
+        .saturating_add(JOG_STEP_TIMEOUT_MARGIN)
+}
+
+// 单步点动并等待各轴停下后才返回，界面据此在一步走完后再允许下一步。
+// 超时仍未停下时停止这些轴并返回错误；无论结果如何都恢复加工速度
+#[server]
+pub async fn zmc_jog_step_blocking(
+    axis_list: Vec<u8>,
+    directions: Vec<i8>,
+    distance: f32,
+    jog_speed: f32,
+) -> Result<(), ServerFnError> {
+    let timeout = step_timeout(distance, jog_speed);
+    jog_step_and_wait(
+        axis_list,
+        directions,
+        distance,
+        jog_speed,
+        timeout,
+        zmc_get_idle,
+    )
+    .await
+}
+
+// is_idle读取轴是否停下，测试中可以模拟一直在运动的轴
+#[cfg(feature = "ssr")]
+async fn jog_step_and_wait<F, Fut>(
+    axis_list: Vec<u8>,
+    directions: Vec<i8>,
+    distance: f32,
+    jog_speed: f32,
+    timeout: Duration,
+    is_idle: F,
+) -> Result<(), ServerFnError>
+where
+    F: FnMut(u8) -> Fut,
+    Fut: std::future::Future<Output = Result<bool, ServerFnError>>,
+{
+    let result = match issue_jog_step(axis_list.clone(), directions, distance, jog_speed).await {
+        Ok(()) => match tokio::time::timeout(timeout, wait_idle_with(&axis_list, is_idle)).await {
+            Ok(result) => result,
+            Err(_) => {
+                for axis in &axis_list {
+                    let _ = zmc_manual_stop(*axis).await;
+                }
+                Err(ServerFnError::new(
+                    "Timed out waiting for the step jog to finish",
+                ))
+            }
+        },
+        Err(e) => Err(e),
+    };
+    let restored = restore_processing_speed(&axis_list).await;
+    result.and(restored)
+}
+
+// 点动把轴速度设成了点动速度，结束后恢复加工速度，避免影响之后的运动
+#[cfg(feature = "ssr")]
+async fn restore_processing_speed(axis_list: &[u8]) -> Result<(), ServerFnError> {
+    let speed = ZMC_MANAGER.parameters.lock().await.speed.processing_speed;
+    ZMC_MANAGER
+        .with_controller(|controller| {
+            for axis in axis_list {
+                controller.direct_set_speed(*axis, speed)?;
+            }
             Ok(())
         })
         .await
 }
 
+// 客户端心跳，点动期间超时未收到会自动停止所有轴
+#[server]
+pub async fn zmc_heartbeat() -> Result<(), ServerFnError> {
+    *ZMC_MANAGER.last_heartbeat.lock().await = Instant::now();
+    Ok(())
+}
+
+// 设置点动看门狗的心跳超时时间，超出范围时取最近的边界
+#[server]
+pub async fn zmc_set_heartbeat_timeout(timeout_ms: u64) -> Result<(), ServerFnError> {
+    let (min, max) = HEARTBEAT_TIMEOUT_RANGE;
+    let timeout = Duration::from_millis(timeout_ms.clamp(min, max));
+    *ZMC_MANAGER.heartbeat_timeout.lock().await = timeout;
+    Ok(())
+}
+
+// 设置空闲自动断开的时间(分钟)，0表示不自动断开
+#[server]
+pub async fn zmc_set_idle_timeout(minutes: u64) -> Result<(), ServerFnError> {
+    *ZMC_MANAGER.idle_timeout.lock().await = Duration::from_secs(minutes.saturating_mul(60));
+    *ZMC_MANAGER.last_activity.lock().await = Instant::now();
+    Ok(())
+}
+
+// 设置状态推送到客户端的间隔，越小越流畅但网络负载越高
+#[server]
+pub async fn zmc_set_polling_interval(interval_ms: u64) -> Result<(), ServerFnError> {
+    let (min, max) = POLLING_INTERVAL_RANGE;
+    let interval = Duration::from_millis(interval_ms.clamp(min, max));
+    *ZMC_MANAGER.polling_interval.lock().await = interval;
+    Ok(())
+}
+
 // 获取当前轴位置
 #[server]
 pub async fn zmc_get_axis_position(axis: u8) -> Result<f32, ServerFnError> {
@@ -471,6 +2115,28 @@ pub async fn zmc_get_axis_position(axis: u8) -> Result<f32, ServerFnError> {
 //  寻找零点
 #[server]
 pub async fn zmc_datum(axis: u8) -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    ZMC_MANAGER.ensure_no_alarm().await?;
+    ZMC_MANAGER.ensure_door_closed().await?;
+    ensure_spindle_stopped().await?;
+    // 回零依赖zmc_set_parameters中注册的回零开关，停用的轴不回零
+    let has_datum_switch = ZMC_MANAGER
+        .parameters
+        .lock()
+        .await
+        .axis_by_num(axis)
+        .is_some_and(|params| params.enabled && params.zero_point_io != 0);
+    if !has_datum_switch {
+        return Err(ServerFnError::ServerError(format!(
+            "Axis {} is disabled or has no zero point IO configured",
+            axis
+        )));
+    }
+    // 检查都通过后才清除补偿状态并记录，被拒绝的回零不改变任何状态
+    ZMC_MANAGER.reset_backlash(axis).await;
+    ZMC_MANAGER
+        .log_command("DATUM", format!("axis={}", axis))
+        .await;
     ZMC_MANAGER
         .with_controller(|controller| {
             // 获取当前轴的正负
@@ -485,10 +2151,284 @@ pub async fn zmc_datum(axis: u8) -> Result<(), ServerFnError> {
         .await
 }
 
-// 轴坐标清零
+// 一键回工件零点：先抬Z到安全高度，等Z停下后再将X/Y移动到0
 #[server]
-pub async fn zmc_set_zero(axis_list: Vec<u8>) -> Result<(), ServerFnError> {
-    ZMC_MANAGER
+pub async fn zmc_goto_zero() -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    ZMC_MANAGER.ensure_door_closed().await?;
+    let params = ZMC_MANAGER.parameters.lock().await.clone();
+    for (axis_list, pos_list) in params.goto_zero_moves() {
+        zmc_move_abs(axis_list.clone(), pos_list).await?;
+        // 等待本段运动完成再执行下一段
+        wait_axes_idle(&axis_list).await?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+async fn wait_axes_idle(axis_list: &[u8]) -> Result<(), ServerFnError> {
+    wait_idle_with(axis_list, zmc_get_idle).await
+}
+
+#[cfg(feature = "ssr")]
+async fn wait_idle_with<F, Fut>(axis_list: &[u8], mut is_idle: F) -> Result<(), ServerFnError>
+where
+    F: FnMut(u8) -> Fut,
+    Fut: std::future::Future<Output = Result<bool, ServerFnError>>,
+{
+    for axis in axis_list {
+        while !is_idle(*axis).await? {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+    Ok(())
+}
+
+// 等待主轴停转时在停转时间之外多等的时间
+#[cfg(feature = "ssr")]
+const SPINDLE_STOP_MARGIN: Duration = Duration::from_secs(2);
+
+// 轮询读到的主轴转速，包括从控制器面板或其他途径启动的主轴
+#[cfg(feature = "ssr")]
+async fn spindle_turning() -> bool {
+    ZMC_MANAGER.move_status.lock().await.spindle_rpm > 0.0
+}
+
+// 主轴转动时回零或设零很危险：按参数拒绝执行，或先停止变频器并等到轮询读到主轴停转
+#[cfg(feature = "ssr")]
+async fn ensure_spindle_stopped() -> Result<(), ServerFnError> {
+    if !spindle_turning().await {
+        return Ok(());
+    }
+    let spindle = ZMC_MANAGER.parameters.lock().await.spindle.clone();
+    if !spindle.stop_before_homing {
+        return Err(ServerFnError::ServerError(
+            "Spindle is running, stop it before homing or zeroing".to_string(),
+        ));
+    }
+    zmc_converter_stop().await?;
+    let deadline = Instant::now()
+        + Duration::from_secs_f32(spindle.spin_down_secs.max(0.0))
+        + SPINDLE_STOP_MARGIN;
+    while spindle_turning().await {
+        if Instant::now() >= deadline {
+            return Err(ServerFnError::ServerError(
+                "Spindle is still turning after stopping it".to_string(),
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(MOVE_STATUS_UPDATE_INTERVAL as u64)).await;
+    }
+    Ok(())
+}
+
+// 调机宏：按顺序回机械零点，全部停下后把当前位置设为工件零点，任一步失败即停止
+#[server]
+pub async fn zmc_setup_sequence() -> Result<(), ServerFnError> {
+    ZMC_MANAGER
+        .log_command("SETUP_SEQUENCE", String::new())
+        .await;
+    ZMC_MANAGER.ensure_door_closed().await?;
+    ensure_spindle_stopped().await?;
+    let axis_list = ZMC_MANAGER.parameters.lock().await.homing_order();
+    for axis in &axis_list {
+        zmc_datum(*axis).await?;
+        wait_axes_idle(&[*axis]).await?;
+    }
+    zmc_set_zero(axis_list).await
+}
+
+// 按顺序重放录制的手动操作宏，每步运动完成后再执行下一步，任一步失败即停止。
+// 单步点动同样经过软件限位检查
+#[server]
+pub async fn run_macro(commands: Vec<MacroCommand>) -> Result<(), ServerFnError> {
+    ZMC_MANAGER
+        .log_command("RUN_MACRO", format!("steps={}", commands.len()))
+        .await;
+    ZMC_MANAGER.ensure_door_closed().await?;
+    for command in commands {
+        match command {
+            MacroCommand::JogStep {
+                axis_list,
+                directions,
+                distance,
+                jog_speed,
+            } => {
+                zmc_jog_step_blocking(axis_list, directions, distance, jog_speed).await?;
+            }
+            MacroCommand::GotoZero => zmc_goto_zero().await?,
+            MacroCommand::SetZero { axis_list } => zmc_set_zero(axis_list).await?,
+        }
+    }
+    Ok(())
+}
+
+// 将走过的路径导出为DXF，按Z深度每1mm分一层
+#[server]
+pub async fn export_path_dxf() -> Result<String, ServerFnError> {
+    let points: Vec<[f32; 3]> = ZMC_MANAGER
+        .path_points
+        .lock()
+        .await
+        .iter()
+        .map(|(point, _)| *point)
+        .collect();
+    Ok(path_to_dxf(&points, 1.0))
+}
+
+// 探测循环（类似G38.2）：以爬行速度向direction方向移动，探针IO触发时停止并返回触发位置，
+// 超过max_distance仍未触发则返回错误
+#[server]
+pub async fn zmc_probe(
+    axis: u8,
+    direction: i8,
+    max_distance: f32,
+    probe_io: u16,
+) -> Result<f32, ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    // 与单步点动相同的检查，全部通过后才记录
+    if !max_distance.is_finite() {
+        return Err(ServerFnError::ServerError(
+            "Probe distance must be a finite number".to_string(),
+        ));
+    }
+    ZMC_MANAGER.ensure_no_alarm().await?;
+    ZMC_MANAGER.ensure_door_closed().await?;
+    let params = ZMC_MANAGER.parameters.lock().await.clone();
+    if !params.axis_by_num(axis).is_some_and(|axis| axis.enabled) {
+        return Err(ServerFnError::ServerError(format!(
+            "Axis {} is disabled or not configured",
+            axis
+        )));
+    }
+    let crawling_speed = params.speed.crawling_speed;
+    if crawling_speed <= 0.0 {
+        return Err(ServerFnError::new(
+            "Crawling speed must be positive to probe",
+        ));
+    }
+    let distance = max_distance.abs() * direction.signum() as f32;
+    let target = zmc_get_axis_position(axis).await? + distance;
+    if !params.within_soft_limits(axis, target) {
+        return Err(ServerFnError::ServerError(format!(
+            "Axis {} probe end {:.3} is outside the software limits",
+            axis, target
+        )));
+    }
+    ZMC_MANAGER
+        .log_command(
+            "PROBE",
+            format!(
+                "axis={} direction={} max_distance={} probe_io={}",
+                axis, direction, max_distance, probe_io
+            ),
+        )
+        .await;
+    let moved = ZMC_MANAGER
+        .with_controller(|controller| {
+            controller.direct_set_speed(axis, crawling_speed)?;
+            controller.direct_move(1, &[axis], &[distance])?;
+            Ok(())
+        })
+        .await;
+    // 控制器没有报告停下或触发时不会一直等下去，超时后停止该轴
+    let result = match moved {
+        Ok(()) => {
+            let timeout = step_timeout(max_distance, crawling_speed);
+            wait_probe(axis, max_distance, timeout, || sample_probe(axis, probe_io)).await
+        }
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        let cancelled = ZMC_MANAGER
+            .with_controller(|controller| controller.direct_single_cancel(axis, 2))
+            .await;
+        if let Err(e) = cancelled {
+            eprintln!("Failed to cancel axis {} after probing: {}", axis, e);
+        }
+    }
+    // 无论结果如何都恢复加工速度
+    let restored = restore_processing_speed(&[axis]).await;
+    result.and_then(|pos| restored.map(|_| pos))
+}
+
+// 读取一次探针：返回(是否触发, 轴是否停下, 当前位置)，触发时停止该轴
+#[cfg(feature = "ssr")]
+async fn sample_probe(axis: u8, probe_io: u16) -> Result<(bool, bool, f32), ServerFnError> {
+    ZMC_MANAGER
+        .with_controller(|controller| {
+            let triggered = controller.direct_get_in(probe_io)?;
+            if triggered {
+                controller.direct_single_cancel(axis, 2)?;
+            }
+            let idle = controller.direct_get_if_idle(axis)?;
+            let pos = controller.direct_get_m_pos(axis)?;
+            Ok((triggered, idle, pos))
+        })
+        .await
+}
+
+// 反复读取探针，直到触发或轴走完最大距离，返回触发位置；超过timeout仍未结束时返回错误
+#[cfg(feature = "ssr")]
+async fn wait_probe<F>(
+    axis: u8,
+    max_distance: f32,
+    timeout: Duration,
+    mut sample: impl FnMut() -> F,
+) -> Result<f32, ServerFnError>
+where
+    F: std::future::Future<Output = Result<(bool, bool, f32), ServerFnError>>,
+{
+    let wait = async {
+        loop {
+            let (triggered, idle, pos) = sample().await?;
+            if triggered {
+                return Ok(pos);
+            }
+            if idle {
+                return Err(ServerFnError::ServerError(format!(
+                    "Probe did not trigger within {} on axis {}",
+                    max_distance, axis
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(MOVE_STATUS_UPDATE_INTERVAL as u64)).await;
+        }
+    };
+    tokio::time::timeout(timeout, wait)
+        .await
+        .unwrap_or_else(|_| {
+            Err(ServerFnError::new(format!(
+                "Timed out waiting for the probe on axis {}",
+                axis
+            )))
+        })
+}
+
+// 复位轴报警，DATUM(0)清除轴的错误状态
+#[server]
+pub async fn zmc_reset_alarm(axis: u8) -> Result<(), ServerFnError> {
+    ZMC_MANAGER
+        .log_command("RESET_ALARM", format!("axis={}", axis))
+        .await;
+    ZMC_MANAGER
+        .with_controller(|controller| {
+            controller.execute(&format!("DATUM(0) AXIS({})", axis))?;
+            Ok(())
+        })
+        .await
+}
+
+// 轴坐标清零
+#[server]
+pub async fn zmc_set_zero(axis_list: Vec<u8>) -> Result<(), ServerFnError> {
+    super::g_code::check_controlling_client().await?;
+    ZMC_MANAGER
+        .log_command("SET_ZERO", format!("axis={:?}", axis_list))
+        .await;
+    ensure_spindle_stopped().await?;
+    for axis in &axis_list {
+        ZMC_MANAGER.reset_backlash(*axis).await;
+    }
+    ZMC_MANAGER
         .with_controller(|controller| {
             for axis in axis_list {
                 controller.direct_set_d_pos(axis, 0.0)?;
@@ -499,8 +2439,1220 @@ pub async fn zmc_set_zero(axis_list: Vec<u8>) -> Result<(), ServerFnError> {
         .await
 }
 
+// 获取服务端的连接状态，客户端页面加载时用它校正cookie中的状态
+#[server]
+pub async fn zmc_get_connection_state() -> Result<ConnectionSnapshot, ServerFnError> {
+    Ok(ZMC_MANAGER.connection_snapshot().await)
+}
+
+// 获取本次会话发送给控制器的指令记录
+#[server]
+pub async fn get_command_log() -> Result<Vec<LogEntry>, ServerFnError> {
+    Ok(ZMC_MANAGER
+        .command_log
+        .lock()
+        .await
+        .iter()
+        .cloned()
+        .collect())
+}
+
+// 获取连接时读取的控制器信息
+#[server]
+pub async fn zmc_get_controller_info() -> Result<ControllerInfo, ServerFnError> {
+    ZMC_MANAGER
+        .controller_info
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| ServerFnError::ServerError("Controller info is not available".to_string()))
+}
+
+// 获取当前生效的参数
+#[server]
+pub async fn zmc_get_parameters() -> Result<Parameters, ServerFnError> {
+    Ok(ZMC_MANAGER.parameters.lock().await.clone())
+}
+
+// 完整的实时路径图，客户端首次显示或发现增量缺失时获取
+#[server]
+pub async fn zmc_get_path_image() -> Result<PathImageSnapshot, ServerFnError> {
+    Ok(ZMC_MANAGER.path_snapshot().await)
+}
+
 // 清除路径图像
 #[server]
 pub async fn zmc_clear_path() -> Result<(), ServerFnError> {
     ZMC_MANAGER.clear_path().await
 }
+
+// 设置路径图像背景色和线宽
+#[server]
+pub async fn zmc_set_path_style(style: PathImageStyle) -> Result<(), ServerFnError> {
+    ZMC_MANAGER.set_path_style(style).await;
+    Ok(())
+}
+
+// 供外部监控轮询的只读状态接口：GET /api/status
+#[cfg(feature = "ssr")]
+pub async fn status_handler() -> axum::Json<MachineStatus> {
+    axum::Json(ZMC_MANAGER.status().await)
+}
+
+#[cfg(all(test, feature = "ssr"))]
+pub(crate) mod tests {
+    use super::super::g_code::tests::{fake_parameters, with_fake_controller};
+    use super::*;
+
+    #[test]
+    fn contiguous_ranges_merge_sorted_and_deduplicated_ios() {
+        assert_eq!(contiguous_ranges(&[3, 1, 2, 7, 3]), vec![(1, 3), (7, 7)]);
+    }
+
+    #[test]
+    fn contiguous_ranges_keep_gaps_between_ios() {
+        assert_eq!(
+            contiguous_ranges(&[10, 8, 12]),
+            vec![(8, 8), (10, 10), (12, 12)]
+        );
+    }
+
+    #[test]
+    fn contiguous_ranges_of_no_ios_are_empty() {
+        assert!(contiguous_ranges(&[]).is_empty());
+    }
+
+    #[test]
+    fn a_jog_stops_once_the_heartbeat_is_older_than_the_timeout() {
+        let last = Instant::now();
+        let timeout = DEFAULT_HEARTBEAT_TIMEOUT;
+        assert!(!heartbeat_lapsed(true, last, timeout, last));
+        // 刚好等于超时还不算断开
+        assert!(!heartbeat_lapsed(true, last, timeout, last + timeout));
+        let late = last + timeout + Duration::from_millis(1);
+        assert!(heartbeat_lapsed(true, last, timeout, late));
+    }
+
+    #[test]
+    fn a_lapsed_heartbeat_is_ignored_when_not_jogging() {
+        let last = Instant::now();
+        let late = last + Duration::from_secs(60);
+        assert!(!heartbeat_lapsed(
+            false,
+            last,
+            DEFAULT_HEARTBEAT_TIMEOUT,
+            late
+        ));
+    }
+
+    #[test]
+    fn a_heartbeat_newer_than_now_has_not_lapsed() {
+        // 轮询读取now之后客户端才刷新了心跳
+        let now = Instant::now();
+        let last = now + Duration::from_millis(10);
+        assert!(!heartbeat_lapsed(true, last, Duration::ZERO, now));
+    }
+
+    fn jogs(entries: &[(u8, i8, f32)], issued: Instant) -> HashMap<u8, JogCommand> {
+        entries
+            .iter()
+            .map(|(axis, direction, speed)| {
+                let jog = JogCommand {
+                    direction: *direction,
+                    speed: *speed,
+                    issued,
+                };
+                (*axis, jog)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_repeated_jog_within_the_window_is_redundant() {
+        let issued = Instant::now();
+        let recent = jogs(&[(0, 1, 10.0), (1, -1, 10.0)], issued);
+        let now = issued + JOG_COALESCE_WINDOW / 2;
+        assert!(jog_redundant(&recent, &[0, 1], &[1, -1], 10.0, now));
+        assert!(jog_redundant(&recent, &[1], &[-1], 10.0, now));
+    }
+
+    #[test]
+    fn a_jog_after_the_window_is_issued_again() {
+        let issued = Instant::now();
+        let recent = jogs(&[(0, 1, 10.0)], issued);
+        assert!(!jog_redundant(
+            &recent,
+            &[0],
+            &[1],
+            10.0,
+            issued + JOG_COALESCE_WINDOW
+        ));
+    }
+
+    #[test]
+    fn a_jog_with_another_direction_speed_or_axis_is_not_redundant() {
+        let issued = Instant::now();
+        let recent = jogs(&[(0, 1, 10.0)], issued);
+        assert!(!jog_redundant(&recent, &[0], &[-1], 10.0, issued));
+        assert!(!jog_redundant(&recent, &[0], &[1], 12.0, issued));
+        assert!(!jog_redundant(&recent, &[0, 2], &[1, 1], 10.0, issued));
+    }
+
+    #[test]
+    fn step_timeout_allows_twice_the_travel_time_plus_the_margin() {
+        assert_eq!(
+            step_timeout(10.0, 5.0),
+            Duration::from_secs(4) + JOG_STEP_TIMEOUT_MARGIN
+        );
+        assert_eq!(step_timeout(-10.0, 5.0), step_timeout(10.0, 5.0));
+    }
+
+    #[test]
+    fn step_timeout_of_no_distance_is_the_margin() {
+        assert_eq!(step_timeout(0.0, 5.0), JOG_STEP_TIMEOUT_MARGIN);
+    }
+
+    #[test]
+    fn step_timeouts_too_long_to_represent_do_not_panic() {
+        assert_eq!(step_timeout(f32::MAX, f32::MIN_POSITIVE), Duration::MAX);
+    }
+
+    #[test]
+    fn step_jogs_with_a_non_finite_distance_are_rejected() {
+        with_fake_controller(|| async {
+            // 轴7不在参数中，不会被软件限位拦下
+            for distance in [f32::NAN, f32::INFINITY] {
+                let error = zmc_jog_step_blocking(vec![7], vec![1], distance, 10.0)
+                    .await
+                    .unwrap_err();
+                assert!(error.to_string().contains("finite"), "{}", error);
+            }
+        });
+    }
+
+    #[test]
+    fn backlash_is_not_corrected_on_the_first_move_or_when_continuing() {
+        let mut state = BacklashState::default();
+        assert_eq!(state.correction(5.0, 0.1), 0.0);
+        assert_eq!(state.correction(2.0, 0.1), 0.0);
+        assert_eq!(state.offset, 0.0);
+    }
+
+    #[test]
+    fn backlash_is_corrected_in_the_new_direction_on_each_reversal() {
+        let mut state = BacklashState::default();
+        state.correction(5.0, 0.1);
+        assert_eq!(state.correction(-1.0, 0.1), -0.1);
+        assert_eq!(state.correction(3.0, 0.1), 0.1);
+        assert_eq!(state.direction, 1);
+        assert!(state.offset.abs() < 1e-6);
+    }
+
+    #[test]
+    fn a_zero_move_keeps_the_last_direction() {
+        let mut state = BacklashState::default();
+        state.correction(-2.0, 0.2);
+        assert_eq!(state.correction(0.0, 0.2), 0.0);
+        assert_eq!(state.direction, -1);
+        assert_eq!(state.correction(1.0, 0.2), 0.2);
+    }
+
+    // 取出并清空命令日志，用来检查测试期间发给控制器的指令
+    pub(crate) async fn take_command_log() -> Vec<LogEntry> {
+        ZMC_MANAGER.command_log.lock().await.drain(..).collect()
+    }
+
+    // 命令日志中某条指令的记录数，用来数实际发给控制器的指令
+    async fn logged(command: &str) -> usize {
+        let log = ZMC_MANAGER.command_log.lock().await;
+        log.iter().filter(|entry| entry.command == command).count()
+    }
+
+    #[test]
+    fn a_burst_of_identical_jogs_reaches_the_controller_once_and_the_stop_still_fires() {
+        with_fake_controller(|| async {
+            let (moves, stops) = (logged("MANUAL_MOVE").await, logged("MANUAL_STOP").await);
+            for _ in 0..5 {
+                zmc_manual_move(0, 1, 10.0).await.unwrap();
+            }
+            assert_eq!(logged("MANUAL_MOVE").await, moves + 1);
+            assert_eq!(*ZMC_MANAGER.jogging_axes.lock().await, vec![0]);
+            zmc_manual_stop(0).await.unwrap();
+            assert_eq!(logged("MANUAL_STOP").await, stops + 1);
+            assert!(ZMC_MANAGER.jogging_axes.lock().await.is_empty());
+            // 停止后的点动不再算作重复
+            zmc_manual_move(0, 1, 10.0).await.unwrap();
+            assert_eq!(logged("MANUAL_MOVE").await, moves + 2);
+            zmc_manual_stop(0).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn the_connection_snapshot_reflects_an_initialized_controller() {
+        with_fake_controller(|| async {
+            let mut params = fake_parameters();
+            params.safe_z_height = 12.5;
+            zmc_set_parameters(params.clone()).await.unwrap();
+            let snapshot = zmc_get_connection_state().await.unwrap();
+            assert!(snapshot.connected);
+            assert_eq!(snapshot.kind, ControllerKind::Fake);
+            assert_eq!(snapshot.ip, None);
+            assert_eq!(snapshot.parameters, params);
+        });
+    }
+
+    #[test]
+    fn inputs_can_be_read_from_the_fake_controller() {
+        with_fake_controller(|| async {
+            assert!(zmc_get_in(3).await.is_ok());
+        });
+    }
+
+    #[test]
+    fn outputs_are_set_only_in_developer_mode_with_the_door_closed() {
+        with_fake_controller(|| async {
+            let set_outs = logged("SET_OUT").await;
+            zmc_set_developer_mode(false).await.unwrap();
+            assert!(zmc_set_out(4, true).await.is_err());
+            zmc_set_developer_mode(true).await.unwrap();
+            zmc_set_out(4, true).await.unwrap();
+            // 被拒绝的设置不写入指令记录
+            assert_eq!(logged("SET_OUT").await, set_outs + 1);
+            // 模拟控制器的输入都是低电平，反转门开关电平后相当于门打开
+            let mut params = fake_parameters();
+            params.door_interlock = true;
+            params.inverted_status.door_switch_level_inverted = true;
+            zmc_set_parameters(params).await.unwrap();
+            assert!(zmc_set_out(4, false).await.is_err());
+            assert_eq!(logged("SET_OUT").await, set_outs + 1);
+            zmc_set_developer_mode(false).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn the_spindle_does_not_start_with_the_door_open_or_an_axis_in_alarm() {
+        with_fake_controller(|| async {
+            let mut params = fake_parameters();
+            params.door_interlock = true;
+            params.inverted_status.door_switch_level_inverted = true;
+            zmc_set_parameters(params).await.unwrap();
+            let error = zmc_converter_run(false).await.unwrap_err();
+            assert!(error.to_string().contains("Door is open"), "{}", error);
+            zmc_set_parameters(fake_parameters()).await.unwrap();
+            set_y_alarm(true).await;
+            assert!(is_alarm_error(zmc_converter_run(false).await));
+            set_y_alarm(false).await;
+            assert_eq!(logged("CONVERTER_RUN").await, 0);
+            assert!(!*ZMC_MANAGER.converter_running.lock().await);
+        });
+    }
+
+    #[test]
+    fn the_idle_countdown_runs_down_to_zero_and_is_off_for_a_zero_timeout() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(600);
+        assert_eq!(idle_remaining(start, Duration::ZERO, start), None);
+        assert_eq!(idle_remaining(start, timeout, start), Some(timeout));
+        let later = start + Duration::from_secs(150);
+        assert_eq!(
+            idle_remaining(start, timeout, later),
+            Some(Duration::from_secs(450))
+        );
+        let long_after = start + Duration::from_secs(3600);
+        assert_eq!(
+            idle_remaining(start, timeout, long_after),
+            Some(Duration::ZERO)
+        );
+        // 活动时间晚于当前时间时按刚刚活动处理
+        assert_eq!(idle_remaining(later, timeout, start), Some(timeout));
+    }
+
+    #[test]
+    fn a_huge_idle_timeout_saturates_instead_of_overflowing() {
+        with_fake_controller(|| async {
+            zmc_set_idle_timeout(u64::MAX).await.unwrap();
+            let timeout = *ZMC_MANAGER.idle_timeout.lock().await;
+            assert_eq!(timeout, Duration::from_secs(u64::MAX));
+            let remaining = idle_remaining(Instant::now(), timeout, Instant::now());
+            assert!(remaining.unwrap() > Duration::from_secs(u64::MAX / 2));
+            zmc_set_idle_timeout(0).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn the_controller_info_is_read_on_connect_and_cleared_on_close() {
+        with_fake_controller(|| async {
+            let mut fake: Box<dyn Controller + Send> = Box::new(FakeController::new());
+            let expected = read_controller_info(&mut fake).unwrap();
+            assert_eq!(zmc_get_controller_info().await.unwrap(), expected);
+            zmc_close().await.unwrap();
+            assert!(zmc_get_controller_info().await.is_err());
+            zmc_init_fake().await.unwrap();
+            assert_eq!(zmc_get_controller_info().await.unwrap(), expected);
+        });
+    }
+
+    #[test]
+    fn input_ranges_are_read_as_bits_from_the_first_input() {
+        assert_eq!(parse_input_bits("5", 3), Some(0b101));
+        assert_eq!(parse_input_bits(" 255\r\n", 8), Some(255));
+        assert_eq!(parse_input_bits("4294967295", 32), Some(u32::MAX));
+        assert_eq!(parse_input_bits("0", 2), Some(0));
+    }
+
+    #[test]
+    fn unexpected_input_replies_fall_back_to_single_reads() {
+        for reply in ["", "8", "-1", "1.5", "nan", "IN(0,2)", "error"] {
+            assert_eq!(parse_input_bits(reply, 3), None, "{}", reply);
+        }
+    }
+
+    #[test]
+    fn batched_input_reads_match_single_reads() {
+        with_fake_controller(|| async {
+            // 一段连续区间、超过一条指令的长区间、单个IO和重复的IO
+            let ios = [
+                0, 1, 2, 3, 10, 2, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55,
+                56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73,
+            ];
+            let mut controller = ZMC_MANAGER.controller.lock().await;
+            let controller = controller.as_mut().unwrap();
+            let batched = read_inputs(controller, &ios).unwrap();
+            let single = ios.map(|io| controller.direct_get_in(io).unwrap());
+            assert_eq!(batched, single);
+        });
+    }
+
+    // 前failures次读取失败，返回结果和读取的次数
+    fn read_failing(attempts: u32, failures: u32) -> (Result<u32, String>, u32) {
+        let reads = std::cell::Cell::new(0);
+        let result = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(read_with_retry(attempts, || {
+                reads.set(reads.get() + 1);
+                let read = reads.get();
+                async move {
+                    if read <= failures {
+                        Err(format!("read {} failed", read))
+                    } else {
+                        Ok(read)
+                    }
+                }
+            }));
+        (result, reads.get())
+    }
+
+    #[test]
+    fn a_successful_read_is_not_repeated() {
+        assert_eq!(read_failing(3, 0), (Ok(1), 1));
+    }
+
+    #[test]
+    fn failed_reads_are_retried_until_one_succeeds() {
+        assert_eq!(read_failing(3, 2), (Ok(3), 3));
+    }
+
+    #[test]
+    fn reads_give_up_after_the_attempts_with_the_last_error() {
+        assert_eq!(read_failing(3, 10), (Err("read 3 failed".to_string()), 3));
+    }
+
+    #[test]
+    fn one_or_zero_attempts_read_once_without_retrying() {
+        assert_eq!(read_failing(1, 1), (Err("read 1 failed".to_string()), 1));
+        assert_eq!(read_failing(0, 1), (Err("read 1 failed".to_string()), 1));
+    }
+
+    #[test]
+    fn the_first_moving_enabled_axis_is_reported() {
+        let params = fake_parameters();
+        assert_eq!(
+            first_moving_axis(&params, |axis| Ok::<_, ()>(axis != 1)),
+            Ok(Some(1))
+        );
+        assert_eq!(
+            first_moving_axis(&params, |_| Ok::<_, ()>(false)),
+            Ok(Some(0))
+        );
+        assert_eq!(first_moving_axis(&params, |_| Ok::<_, ()>(true)), Ok(None));
+    }
+
+    #[test]
+    fn moving_disabled_axes_do_not_block_parameter_changes() {
+        let mut params = fake_parameters();
+        params.y.enabled = false;
+        assert_eq!(
+            first_moving_axis(&params, |axis| Ok::<_, ()>(axis != 1)),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn a_failed_idle_read_refuses_parameter_changes() {
+        let params = fake_parameters();
+        let error = check_axes_idle(&params, |axis| {
+            if axis == 1 {
+                Err("read failed")
+            } else {
+                Ok(true)
+            }
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("read failed"), "{}", error);
+        assert!(check_axes_idle(&params, |_| Ok::<_, &str>(true)).is_ok());
+    }
+
+    #[test]
+    fn idle_reads_stop_at_the_first_error() {
+        let params = fake_parameters();
+        let mut reads = Vec::new();
+        let result = first_moving_axis(&params, |axis| {
+            reads.push(axis);
+            if axis == 1 {
+                Err("read failed")
+            } else {
+                Ok(true)
+            }
+        });
+        assert_eq!(result, Err("read failed"));
+        assert_eq!(reads, vec![0, 1]);
+    }
+
+    #[test]
+    fn parameters_apply_while_every_axis_is_idle() {
+        with_fake_controller(|| async {
+            let mut params = fake_parameters();
+            params.speed.max_speed = 123.0;
+            ZMC_MANAGER.ensure_idle().await.unwrap();
+            zmc_set_parameters(params.clone()).await.unwrap();
+            assert_eq!(*ZMC_MANAGER.parameters.lock().await, params);
+        });
+    }
+
+    // 停止轮询后设置主轴转速，避免轮询读回的转速把它覆盖
+    async fn spindle_running(stop_before_homing: bool, spin_down_secs: f32) {
+        let mut params = fake_parameters();
+        params.spindle.stop_before_homing = stop_before_homing;
+        params.spindle.spin_down_secs = spin_down_secs;
+        zmc_set_parameters(params).await.unwrap();
+        ZMC_MANAGER.stop_polling().await.unwrap();
+        ZMC_MANAGER.move_status.lock().await.spindle_rpm = 3000.0;
+    }
+
+    async fn spindle_stops() {
+        ZMC_MANAGER.move_status.lock().await.spindle_rpm = 0.0;
+    }
+
+    #[test]
+    fn a_stopped_spindle_needs_no_stop_command() {
+        with_fake_controller(|| async {
+            spindle_running(true, 0.0).await;
+            spindle_stops().await;
+            ensure_spindle_stopped().await.unwrap();
+            assert_eq!(logged("CONVERTER_STOP").await, 0);
+        });
+    }
+
+    #[test]
+    fn homing_is_refused_while_the_spindle_turns() {
+        with_fake_controller(|| async {
+            spindle_running(false, 0.0).await;
+            let error = ensure_spindle_stopped().await.unwrap_err();
+            assert!(
+                error.to_string().contains("Spindle is running"),
+                "{}",
+                error
+            );
+            assert_eq!(logged("CONVERTER_STOP").await, 0);
+            spindle_stops().await;
+        });
+    }
+
+    #[test]
+    fn homing_stops_the_spindle_and_waits_for_it_to_stop_turning() {
+        with_fake_controller(|| async {
+            spindle_running(true, 1.0).await;
+            let started = Instant::now();
+            // 相当于轮询在停止指令之后读到主轴停转
+            tokio::spawn(async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                spindle_stops().await;
+            });
+            ensure_spindle_stopped().await.unwrap();
+            assert!(started.elapsed() >= Duration::from_millis(100));
+            assert_eq!(logged("CONVERTER_STOP").await, 1);
+            assert!(!*ZMC_MANAGER.converter_running.lock().await);
+        });
+    }
+
+    #[test]
+    fn homing_fails_when_the_spindle_keeps_turning_after_the_stop() {
+        with_fake_controller(|| async {
+            spindle_running(true, 0.0).await;
+            let started = Instant::now();
+            let error = ensure_spindle_stopped().await.unwrap_err();
+            assert!(error.to_string().contains("still turning"), "{}", error);
+            assert!(started.elapsed() >= SPINDLE_STOP_MARGIN);
+            assert_eq!(logged("CONVERTER_STOP").await, 1);
+            spindle_stops().await;
+        });
+    }
+
+    #[test]
+    fn moves_with_mismatched_lists_are_rejected() {
+        with_fake_controller(|| async {
+            for (axis_list, pos_list) in [(vec![0, 1], vec![1.0]), (vec![0], vec![1.0, 2.0])] {
+                let error = zmc_move_abs(axis_list.clone(), pos_list.clone())
+                    .await
+                    .unwrap_err();
+                assert!(error.to_string().contains("same length"), "{}", error);
+                let error = zmc_move(axis_list, pos_list).await.unwrap_err();
+                assert!(error.to_string().contains("same length"), "{}", error);
+            }
+        });
+    }
+
+    #[test]
+    fn moves_without_axes_are_rejected() {
+        with_fake_controller(|| async {
+            let error = zmc_move_abs(vec![], vec![]).await.unwrap_err();
+            assert!(error.to_string().contains("cannot be empty"), "{}", error);
+            let error = zmc_move(vec![], vec![]).await.unwrap_err();
+            assert!(error.to_string().contains("cannot be empty"), "{}", error);
+        });
+    }
+
+    #[test]
+    fn matching_move_lists_are_accepted() {
+        with_fake_controller(|| async {
+            zmc_move_abs(vec![0, 1], vec![1.0, 2.0]).await.unwrap();
+            zmc_move(vec![2], vec![-1.0]).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn the_status_endpoint_returns_axis_and_limit_status_as_json() {
+        with_fake_controller(|| async {
+            ZMC_MANAGER.stop_polling().await.unwrap();
+            {
+                let mut status = ZMC_MANAGER.move_status.lock().await;
+                *status = MoveStatus::default();
+                status.x.is_idle = true;
+                status.x.pos = 12.5;
+                status.y.speed = -4.0;
+                status.z.in_alarm = true;
+                status.spindle_rpm = 6000.0;
+                status.motion = MotionKind::Rapid;
+            }
+            let axum::Json(status) = status_handler().await;
+            let axis = |is_idle: bool, pos: f32, speed: f32, in_alarm: bool| {
+                serde_json::json!({
+                    "is_idle": is_idle,
+                    "speed": speed,
+                    "pos": pos,
+                    "in_alarm": in_alarm,
+                    "following_error": 0.0,
+                })
+            };
+            assert_eq!(
+                serde_json::to_value(&status).unwrap(),
+                serde_json::json!({
+                    "move_status": {
+                        "x": axis(true, 12.5, 0.0, false),
+                        "y": axis(false, 0.0, -4.0, false),
+                        "z": axis(false, 0.0, 0.0, true),
+                        "spindle_rpm": 6000.0,
+                        "motion": "Rapid",
+                    },
+                    "limit_status": {
+                        "emergency_stop": false,
+                        "door_switch": false,
+                        "x_plus": false,
+                        "x_minus": false,
+                        "y_plus": false,
+                        "y_minus": false,
+                        "z_plus": false,
+                        "z_minus": false,
+                    },
+                })
+            );
+            *ZMC_MANAGER.move_status.lock().await = MoveStatus::default();
+        });
+    }
+
+    #[test]
+    fn the_command_log_drops_the_oldest_entries_at_capacity() {
+        with_fake_controller(|| async {
+            for i in 0..COMMAND_LOG_CAPACITY + 5 {
+                ZMC_MANAGER.log_command("FILL", i.to_string()).await;
+            }
+            let log = take_command_log().await;
+            assert_eq!(log.len(), COMMAND_LOG_CAPACITY);
+            assert_eq!(log[0].detail, "5");
+            assert_eq!(
+                log[COMMAND_LOG_CAPACITY - 1].detail,
+                (COMMAND_LOG_CAPACITY + 4).to_string()
+            );
+        });
+    }
+
+    // 停止轮询后设置报警状态，避免轮询读回的状态把它覆盖
+    async fn set_y_alarm(in_alarm: bool) {
+        ZMC_MANAGER.stop_polling().await.unwrap();
+        ZMC_MANAGER.move_status.lock().await.y.in_alarm = in_alarm;
+    }
+
+    fn is_alarm_error<T>(result: Result<T, ServerFnError>) -> bool {
+        result.is_err_and(|e| e.to_string().contains("in alarm"))
+    }
+
+    #[test]
+    fn moves_are_refused_while_an_axis_is_in_alarm() {
+        with_fake_controller(|| async {
+            set_y_alarm(true).await;
+            assert!(is_alarm_error(zmc_move_abs(vec![0], vec![1.0]).await));
+            assert!(is_alarm_error(zmc_move(vec![0], vec![1.0]).await));
+            assert!(is_alarm_error(zmc_manual_move(0, 1, 10.0).await));
+            assert!(is_alarm_error(zmc_datum(0).await));
+            assert!(is_alarm_error(zmc_probe(2, -1, 5.0, 0).await));
+            assert!(ZMC_MANAGER.jogging_axes.lock().await.is_empty());
+            set_y_alarm(false).await;
+        });
+    }
+
+    #[test]
+    fn rejected_moves_are_not_logged_as_sent() {
+        with_fake_controller(|| async {
+            assert!(zmc_move_abs(vec![0, 1], vec![1.0]).await.is_err());
+            assert!(zmc_move(vec![0], vec![]).await.is_err());
+            set_y_alarm(true).await;
+            assert!(is_alarm_error(zmc_move_abs(vec![0], vec![1.0]).await));
+            assert!(is_alarm_error(zmc_move(vec![0], vec![1.0]).await));
+            set_y_alarm(false).await;
+            assert_eq!(logged("MOVE_ABS").await + logged("MOVE").await, 0);
+            zmc_move_abs(vec![0], vec![1.0]).await.unwrap();
+            assert_eq!(logged("MOVE_ABS").await, 1);
+        });
+    }
+
+    #[test]
+    fn refused_homing_and_speed_changes_leave_no_trace() {
+        with_fake_controller(|| async {
+            let state = BacklashState {
+                direction: 1,
+                offset: 0.5,
+            };
+            ZMC_MANAGER.backlash.lock().await.insert(0, state);
+            set_y_alarm(true).await;
+            assert!(is_alarm_error(zmc_datum(0).await));
+            set_y_alarm(false).await;
+            assert!(ZMC_MANAGER.backlash.lock().await.contains_key(&0));
+            assert!(zmc_set_speed(0, -1.0).await.is_err());
+            assert_eq!(logged("DATUM").await + logged("SET_SPEED").await, 0);
+            ZMC_MANAGER.reset_backlash(0).await;
+        });
+    }
+
+    #[test]
+    fn refused_jogs_are_not_logged_as_sent() {
+        with_fake_controller(|| async {
+            set_y_alarm(true).await;
+            assert!(is_alarm_error(zmc_manual_move(0, 1, 10.0).await));
+            set_y_alarm(false).await;
+            assert_eq!(logged("MANUAL_MOVE").await, 0);
+            zmc_manual_move(0, 1, 10.0).await.unwrap();
+            assert_eq!(logged("MANUAL_MOVE").await, 1);
+            zmc_manual_stop(0).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn moves_are_accepted_again_once_the_alarm_clears() {
+        with_fake_controller(|| async {
+            set_y_alarm(true).await;
+            assert!(is_alarm_error(zmc_move_abs(vec![1], vec![1.0]).await));
+            set_y_alarm(false).await;
+            zmc_move_abs(vec![1], vec![1.0]).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn occasional_read_errors_keep_polling_with_the_last_status() {
+        let mut errors = 0;
+        for _ in 0..MAX_CONSECUTIVE_READ_ERRORS {
+            assert_eq!(tolerate_read_error(Err("timeout"), &mut errors), Ok(()));
+        }
+        assert_eq!(errors, MAX_CONSECUTIVE_READ_ERRORS);
+        // 一次成功的读取重新开始计数
+        assert_eq!(tolerate_read_error::<&str>(Ok(()), &mut errors), Ok(()));
+        assert_eq!(errors, 0);
+        assert_eq!(tolerate_read_error(Err("timeout"), &mut errors), Ok(()));
+    }
+
+    #[test]
+    fn too_many_read_errors_in_a_row_stop_polling() {
+        let mut errors = 0;
+        for _ in 0..MAX_CONSECUTIVE_READ_ERRORS {
+            tolerate_read_error(Err("timeout"), &mut errors).unwrap();
+        }
+        assert_eq!(
+            tolerate_read_error(Err("disconnected"), &mut errors),
+            Err("disconnected")
+        );
+    }
+
+    #[test]
+    fn a_lost_connection_is_reported_as_disconnected() {
+        with_fake_controller(|| async {
+            ZMC_MANAGER.stop_polling().await.unwrap();
+            *ZMC_MANAGER.converter_running.lock().await = true;
+            ZMC_MANAGER.mark_connection_lost().await;
+            let state = zmc_get_connection_state().await.unwrap();
+            assert!(!state.connected);
+            assert!(!*ZMC_MANAGER.converter_running.lock().await);
+            assert!(zmc_move_abs(vec![0], vec![1.0]).await.is_err());
+            zmc_init_fake().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn a_door_hold_stops_the_spindle_without_failing_the_poll() {
+        with_fake_controller(|| async {
+            let mut params = fake_parameters();
+            params.door_interlock = true;
+            params.inverted_status.door_switch_level_inverted = true;
+            zmc_set_parameters(params).await.unwrap();
+            ZMC_MANAGER.stop_polling().await.unwrap();
+            *ZMC_MANAGER.converter_running.lock().await = true;
+            poll_limits_with(|_, _| Ok(0.0)).await.unwrap();
+            assert!(!*ZMC_MANAGER.converter_running.lock().await);
+            assert_eq!(logged("DOOR_INTERLOCK").await, 1);
+        });
+    }
+
+    // 按顺序返回给定的探针采样，用完后保持最后一次的采样
+    fn probe_with(samples: &[(bool, bool, f32)], timeout: Duration) -> Result<f32, String> {
+        let samples = std::cell::RefCell::new(samples.iter().copied().collect::<VecDeque<_>>());
+        let sample = || {
+            let mut samples = samples.borrow_mut();
+            let next = if samples.len() > 1 {
+                samples.pop_front()
+            } else {
+                samples.front().copied()
+            };
+            async move { Ok(next.unwrap()) }
+        };
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(wait_probe(2, 5.0, timeout, sample))
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn probing_returns_the_position_where_the_probe_triggered() {
+        let samples = [
+            (false, false, -1.0),
+            (false, false, -2.0),
+            (true, false, -2.5),
+        ];
+        assert_eq!(probe_with(&samples, Duration::from_secs(1)), Ok(-2.5));
+    }
+
+    #[test]
+    fn probing_fails_when_the_axis_stops_without_a_trigger() {
+        let samples = [(false, false, -1.0), (false, true, -5.0)];
+        let error = probe_with(&samples, Duration::from_secs(1)).unwrap_err();
+        assert!(error.contains("did not trigger within 5"), "{}", error);
+    }
+
+    #[test]
+    fn probing_times_out_when_the_axis_never_stops() {
+        let error = probe_with(&[(false, false, 0.0)], Duration::from_millis(50)).unwrap_err();
+        assert!(error.contains("Timed out"), "{}", error);
+    }
+
+    #[test]
+    fn probing_needs_a_crawling_speed() {
+        with_fake_controller(|| async {
+            let error = zmc_probe(2, -1, 5.0, 0).await.unwrap_err();
+            assert!(error.to_string().contains("Crawling speed"), "{}", error);
+        });
+    }
+
+    #[test]
+    fn probes_get_the_same_distance_axis_and_limit_checks_as_jogs() {
+        with_fake_controller(|| async {
+            let mut params = fake_parameters();
+            params.speed.crawling_speed = 5.0;
+            params.x.enabled = false;
+            zmc_set_parameters(params).await.unwrap();
+            for distance in [f32::NAN, f32::INFINITY] {
+                let error = zmc_probe(2, -1, distance, 0).await.unwrap_err();
+                assert!(error.to_string().contains("finite"), "{}", error);
+            }
+            let error = zmc_probe(0, -1, 5.0, 0).await.unwrap_err();
+            assert!(error.to_string().contains("disabled"), "{}", error);
+            // 模拟参数的软件限位都是0，向下探5mm超出限位
+            let error = zmc_probe(2, -1, 5.0, 0).await.unwrap_err();
+            assert!(error.to_string().contains("software limits"), "{}", error);
+            assert_eq!(logged("PROBE").await, 0);
+        });
+    }
+
+    #[test]
+    fn the_drawn_path_survives_a_reconnect() {
+        with_fake_controller(|| async {
+            ZMC_MANAGER.clear_path().await.unwrap();
+            // 停止轮询后手动画一个远离当前位置的点，轮询只会在当前位置(原点)作画
+            ZMC_MANAGER.stop_polling().await.unwrap();
+            let point = ([5.0, 5.0, -1.0], MotionKind::Feed);
+            ZMC_MANAGER.bitmap.lock().await.set_pixel(5.0, 5.0, -1.0);
+            ZMC_MANAGER.path_points.lock().await.push(point);
+
+            zmc_init_fake().await.unwrap();
+            ZMC_MANAGER.stop_polling().await.unwrap();
+
+            let bitmap = ZMC_MANAGER.bitmap.lock().await.clone();
+            let mut redrawn = bitmap.clone();
+            redrawn.set_pixel(5.0, 5.0, -1.0);
+            assert_eq!(redrawn.to_data_url(), bitmap.to_data_url());
+            let mut cleared = bitmap.clone();
+            cleared.clear();
+            assert_ne!(cleared.to_data_url(), bitmap.to_data_url());
+            assert!(ZMC_MANAGER.path_points.lock().await.contains(&point));
+            ZMC_MANAGER.clear_path().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn a_homing_failure_aborts_the_setup_sequence_before_zeroing() {
+        with_fake_controller(|| async {
+            // 模拟参数没有配置回零开关，第一个回零的轴就会失败
+            let error = zmc_setup_sequence().await.unwrap_err();
+            assert!(error.to_string().contains("Axis 2 "), "{}", error);
+            assert!(error.to_string().contains("zero point IO"), "{}", error);
+            let commands: Vec<String> = take_command_log()
+                .await
+                .into_iter()
+                .filter(|entry| ["DATUM", "SET_ZERO"].contains(&entry.command.as_str()))
+                .map(|entry| format!("{} {}", entry.command, entry.detail))
+                .collect();
+            // 被拒绝的回零也不记录
+            assert!(commands.is_empty(), "{:?}", commands);
+        });
+    }
+
+    #[test]
+    fn parking_stops_the_machine_once_and_disconnects() {
+        with_fake_controller(|| async {
+            let steps = std::sync::Mutex::new(Vec::new());
+            ZMC_MANAGER
+                .park_with(|controller, step| {
+                    steps.lock().unwrap().push(step);
+                    issue_stop_step(controller, step)
+                })
+                .await
+                .unwrap();
+            assert_eq!(
+                steps.into_inner().unwrap(),
+                [
+                    StopStep::Spindle,
+                    StopStep::Cancel(0),
+                    StopStep::Cancel(1),
+                    StopStep::Cancel(2)
+                ]
+            );
+            assert_eq!(logged("SHUTDOWN_PARK").await, 1);
+            assert!(!zmc_get_connection_state().await.unwrap().connected);
+            // 已断开时没有可停的机床
+            park_machine().await.unwrap();
+            assert_eq!(logged("SHUTDOWN_PARK").await, 1);
+            zmc_init_fake().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn a_failed_spindle_stop_still_cancels_the_axes_and_disconnects() {
+        with_fake_controller(|| async {
+            let steps = std::sync::Mutex::new(Vec::new());
+            let error = ZMC_MANAGER
+                .park_with(|controller, step| {
+                    steps.lock().unwrap().push(step);
+                    match step {
+                        StopStep::Spindle => Err("inverter not responding".to_string()),
+                        _ => issue_stop_step(controller, step).map_err(|e| e.to_string()),
+                    }
+                })
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("inverter"), "{}", error);
+            assert_eq!(steps.into_inner().unwrap().len(), 4);
+            assert!(!zmc_get_connection_state().await.unwrap().connected);
+            zmc_init_fake().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn raw_commands_round_trip_through_the_controller_in_developer_mode() {
+        with_fake_controller(|| async {
+            zmc_set_developer_mode(false).await.unwrap();
+            assert!(zmc_execute_raw("?VERSION".to_string()).await.is_err());
+            zmc_set_developer_mode(true).await.unwrap();
+            let direct = ZMC_MANAGER
+                .with_controller(|controller| controller.execute("?VERSION"))
+                .await
+                .ok();
+            let raw = zmc_execute_raw(" ?VERSION\n".to_string()).await.ok();
+            assert_eq!(raw, direct);
+            let error = zmc_execute_raw("  ".to_string()).await.unwrap_err();
+            assert!(error.to_string().contains("cannot be empty"), "{}", error);
+            zmc_set_developer_mode(false).await.unwrap();
+            // 被拒绝的指令不写入指令记录
+            assert_eq!(logged("EXECUTE_RAW").await, 1);
+        });
+    }
+
+    #[test]
+    fn developer_mode_can_only_be_enabled_when_the_server_allows_it() {
+        with_fake_controller(|| async {
+            std::env::remove_var(DEVELOPER_MODE_ENV);
+            let error = zmc_set_developer_mode(true).await.unwrap_err();
+            assert!(error.to_string().contains(DEVELOPER_MODE_ENV), "{}", error);
+            assert!(!*ZMC_MANAGER.developer_mode.lock().await);
+            zmc_set_developer_mode(false).await.unwrap();
+            std::env::set_var(DEVELOPER_MODE_ENV, "1");
+            zmc_set_developer_mode(true).await.unwrap();
+            zmc_set_developer_mode(false).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn heartbeat_timeouts_are_clamped_so_the_watchdog_stays_useful() {
+        with_fake_controller(|| async {
+            let (min, max) = HEARTBEAT_TIMEOUT_RANGE;
+            zmc_set_heartbeat_timeout(0).await.unwrap();
+            assert_eq!(
+                *ZMC_MANAGER.heartbeat_timeout.lock().await,
+                Duration::from_millis(min)
+            );
+            zmc_set_heartbeat_timeout(u64::MAX).await.unwrap();
+            assert_eq!(
+                *ZMC_MANAGER.heartbeat_timeout.lock().await,
+                Duration::from_millis(max)
+            );
+            zmc_set_heartbeat_timeout(1500).await.unwrap();
+            assert_eq!(
+                *ZMC_MANAGER.heartbeat_timeout.lock().await,
+                DEFAULT_HEARTBEAT_TIMEOUT
+            );
+        });
+    }
+
+    #[test]
+    fn a_macro_replays_its_steps_in_order_and_stops_at_a_failing_step() {
+        with_fake_controller(|| async {
+            let commands = vec![
+                MacroCommand::SetZero { axis_list: vec![1] },
+                MacroCommand::SetZero { axis_list: vec![0] },
+                // 方向列表和轴列表长度不一致，这一步失败
+                MacroCommand::JogStep {
+                    axis_list: vec![0, 1],
+                    directions: vec![1],
+                    distance: 1.0,
+                    jog_speed: 10.0,
+                },
+                MacroCommand::SetZero { axis_list: vec![2] },
+            ];
+            assert!(run_macro(commands).await.is_err());
+            let zeroed: Vec<String> = take_command_log()
+                .await
+                .into_iter()
+                .filter(|entry| entry.command == "SET_ZERO")
+                .map(|entry| entry.detail)
+                .collect();
+            assert_eq!(zeroed, ["axis=[1]", "axis=[0]"]);
+        });
+    }
+
+    #[test]
+    fn a_jog_runs_at_the_jog_speed_and_the_stop_restores_the_processing_speed() {
+        with_fake_controller(|| async {
+            let axis_speed = |axis: u8| {
+                ZMC_MANAGER.with_controller(move |controller| controller.direct_get_speed(axis))
+            };
+            zmc_manual_move(0, 1, 37.5).await.unwrap();
+            assert_eq!(axis_speed(0).await.unwrap(), 37.5);
+            zmc_manual_stop(0).await.unwrap();
+            let processing_speed = fake_parameters().speed.processing_speed;
+            assert_eq!(axis_speed(0).await.unwrap(), processing_speed);
+        });
+    }
+
+    #[test]
+    fn a_diagonal_jog_moves_both_axes_at_the_same_speed() {
+        with_fake_controller(|| async {
+            zmc_manual_move_axes(vec![0, 1], vec![1, -1], 20.0)
+                .await
+                .unwrap();
+            assert_eq!(*ZMC_MANAGER.jogging_axes.lock().await, vec![0, 1]);
+            let speeds = ZMC_MANAGER
+                .with_controller(|controller| {
+                    Ok([
+                        controller.direct_get_speed(0)?,
+                        controller.direct_get_speed(1)?,
+                    ])
+                })
+                .await
+                .unwrap();
+            assert_eq!(speeds, [20.0, 20.0]);
+            zmc_manual_stop(0).await.unwrap();
+            zmc_manual_stop(1).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn the_simulated_controller_is_reported_until_it_is_closed() {
+        with_fake_controller(|| async {
+            assert_eq!(
+                zmc_get_connection_state().await.unwrap().kind,
+                ControllerKind::Fake
+            );
+            zmc_close().await.unwrap();
+            // 关闭后控制器对象还在但已断开，不再显示为模拟控制器
+            assert_eq!(
+                zmc_get_connection_state().await.unwrap().kind,
+                ControllerKind::None
+            );
+            zmc_init_fake().await.unwrap();
+        });
+    }
+
+    // 用给定的主轴转速读取函数执行一次限位轮询
+    async fn poll_limits_with<F>(read_spindle: F) -> Result<(), ControllerError>
+    where
+        F: Fn(&mut Box<dyn Controller + Send>, &Parameters) -> Result<f32, &'static str>,
+    {
+        poll_limits(
+            &ZMC_MANAGER.controller,
+            &ZMC_MANAGER.parameters,
+            &ZMC_MANAGER.move_status,
+            &ZMC_MANAGER.converter_running,
+            &ZMC_MANAGER.limit_status,
+            &ZMC_MANAGER.move_status_signal,
+            read_spindle,
+        )
+        .await
+    }
+
+    #[test]
+    fn a_failed_spindle_read_keeps_limit_polling_running() {
+        with_fake_controller(|| async {
+            ZMC_MANAGER.stop_polling().await.unwrap();
+            *ZMC_MANAGER.converter_running.lock().await = true;
+            ZMC_MANAGER.move_status.lock().await.spindle_rpm = 1200.0;
+            for _ in 0..=MAX_CONSECUTIVE_READ_ERRORS {
+                poll_limits_with(|_, _| Err("inverter not responding"))
+                    .await
+                    .unwrap();
+            }
+            // 读取失败保留上次的转速，状态照常推送
+            assert_eq!(ZMC_MANAGER.move_status.lock().await.spindle_rpm, 1200.0);
+            assert_eq!(
+                ZMC_MANAGER.move_status_signal.get_untracked().spindle_rpm,
+                1200.0
+            );
+            *ZMC_MANAGER.converter_running.lock().await = false;
+            ZMC_MANAGER.move_status.lock().await.spindle_rpm = 0.0;
+        });
+    }
+
+    #[test]
+    fn the_inverter_is_not_queried_while_the_spindle_is_stopped() {
+        with_fake_controller(|| async {
+            ZMC_MANAGER.stop_polling().await.unwrap();
+            let reads = std::cell::Cell::new(0);
+            let read_spindle =
+                |_: &mut Box<dyn Controller + Send>, _: &Parameters| -> Result<f32, &'static str> {
+                    reads.set(reads.get() + 1);
+                    Ok(0.0)
+                };
+            poll_limits_with(read_spindle).await.unwrap();
+            assert_eq!(reads.get(), 0);
+            // 停止后仍在减速时继续读取，直到读到停转
+            ZMC_MANAGER.move_status.lock().await.spindle_rpm = 500.0;
+            poll_limits_with(read_spindle).await.unwrap();
+            poll_limits_with(read_spindle).await.unwrap();
+            assert_eq!(reads.get(), 1);
+        });
+    }
+
+    #[test]
+    fn a_step_jog_restores_the_processing_speed_once_the_axis_stops() {
+        with_fake_controller(|| async {
+            let mut params = fake_parameters();
+            params.x.software_negative_limit = -1000.0;
+            params.x.software_positive_limit = 1000.0;
+            params.speed.processing_speed = 20.0;
+            zmc_set_parameters(params.clone()).await.unwrap();
+            zmc_jog_step(vec![0], vec![1], 1.0, 37.5).await.unwrap();
+            let restored = async {
+                while ZMC_MANAGER
+                    .with_controller(|controller| controller.direct_get_speed(0))
+                    .await
+                    .unwrap()
+                    != params.speed.processing_speed
+                {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            };
+            tokio::time::timeout(Duration::from_secs(5), restored)
+                .await
+                .expect("processing speed was not restored");
+        });
+    }
+
+    // 轴在前busy_polls次读取中仍在运动，返回点动结果和读取的次数
+    async fn step_with_busy_axis(
+        busy_polls: u32,
+        timeout: Duration,
+    ) -> (Result<(), ServerFnError>, u32) {
+        let mut params = fake_parameters();
+        params.x.software_negative_limit = -1000.0;
+        params.x.software_positive_limit = 1000.0;
+        zmc_set_parameters(params).await.unwrap();
+        let polls = std::cell::Cell::new(0);
+        let result = jog_step_and_wait(vec![0], vec![1], 1.0, 10.0, timeout, |_| {
+            polls.set(polls.get() + 1);
+            let idle = polls.get() > busy_polls;
+            async move { Ok(idle) }
+        })
+        .await;
+        (result, polls.get())
+    }
+
+    #[test]
+    fn a_blocking_step_jog_returns_only_after_the_axis_reports_idle() {
+        with_fake_controller(|| async {
+            let (result, polls) = step_with_busy_axis(5, Duration::from_secs(5)).await;
+            result.unwrap();
+            assert_eq!(polls, 6);
+            assert_eq!(logged("MANUAL_STOP").await, 0);
+        });
+    }
+
+    #[test]
+    fn a_blocking_step_jog_that_never_finishes_stops_the_axis() {
+        with_fake_controller(|| async {
+            let (result, _) = step_with_busy_axis(u32::MAX, Duration::from_millis(100)).await;
+            let error = result.unwrap_err();
+            assert!(error.to_string().contains("Timed out"), "{}", error);
+            let stops: Vec<String> = take_command_log()
+                .await
+                .into_iter()
+                .filter(|entry| entry.command == "MANUAL_STOP")
+                .map(|entry| entry.detail)
+                .collect();
+            assert_eq!(stops, ["axis=0"]);
+        });
+    }
+}
@@ -1,4 +1,4 @@
-use leptos::{prelude::*, server::codee::string::JsonSerdeCodec};
+use leptos::{logging, prelude::*, server::codee::string::JsonSerdeCodec, task::spawn_local};
 use leptos_meta::{provide_meta_context, MetaTags, Stylesheet, Title};
 use leptos_router::{
     components::{Outlet, ParentRoute, Route, Router, Routes},
@@ -9,11 +9,17 @@ use leptos_ws::ServerSignal;
 use thaw::ssr::SSRMountStyleProvider;
 use thaw::*;
 
+use crate::api::zmc_get_connection_state;
 use crate::components::*;
+use crate::i18n::{provide_i18n, use_i18n};
+use crate::model::{ControllerKind, ErrorEvent, Parameters, RunState};
 
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
 pub struct GlobalState {
     pub connected: bool,
+    // 连接的是真实控制器还是模拟控制器，用于连接状态标签
+    #[serde(default)]
+    pub controller_kind: ControllerKind,
 }
 
 pub fn shell(options: LeptosOptions) -> impl IntoView {
@@ -42,6 +48,8 @@ pub fn App() -> impl IntoView {
     provide_meta_context();
     // Provides context for WebSocket connections
     leptos_ws::provide_websocket("ws://localhost:3000/ws");
+    // Provides the UI language, persisted in a cookie
+    provide_i18n();
 
     view! {
         // injects a stylesheet into the document <head>
@@ -60,6 +68,8 @@ pub fn App() -> impl IntoView {
                                 <Route path=StaticSegment("manual") view=ManualView />
                                 <Route path=StaticSegment("about") view=AboutView />
                                 <Route path=StaticSegment("auto") view=AutoModeView />
+                                <Route path=StaticSegment("log") view=CommandLogView />
+                                <Route path=StaticSegment("history") view=RunHistoryView />
                             </ParentRoute>
                         </Routes>
                     </main>
@@ -83,32 +93,145 @@ fn HomePage() -> impl IntoView {
     let _ = ServerSignal::new("current_line".to_string(), 0usize).unwrap();
     let _ = ServerSignal::new("preview_processed_line".to_string(), 0usize)
         .expect("Failed to create client signal");
+    let _ = ServerSignal::new("run_state".to_string(), RunState::Idle)
+        .expect("Failed to create client signal");
+
+    // 页面刷新后cookie可能与服务端不一致，以服务端的连接状态和参数为准
+    let (_, set_parameters) = use_cookie::<Parameters, JsonSerdeCodec>("parameters_cookie");
+    Effect::new(move |_| {
+        spawn_local(async move {
+            match zmc_get_connection_state().await {
+                Ok(snapshot) => {
+                    set_global_state.update(|state| {
+                        let state = state.get_or_insert_with(GlobalState::default);
+                        state.connected = snapshot.connected;
+                        state.controller_kind = snapshot.kind;
+                    });
+                    if snapshot.connected {
+                        set_parameters.set(Some(snapshot.parameters));
+                    }
+                }
+                Err(e) => logging::error!("Failed to get connection state: {}", e),
+            }
+        });
+    });
 
     let connected = move || global_state.get().unwrap().connected;
+    let simulated = move || global_state.get().unwrap().controller_kind == ControllerKind::Fake;
+    let i18n = use_i18n();
+
+    // 控制器状态读取失败时提示，连续的相同错误合并显示
+    provide_error_notifier();
+    let notifier = use_error_notifier();
+    let controller_error = ServerSignal::new("controller_error".to_string(), ErrorEvent::default())
+        .expect("Failed to create client signal");
+    Effect::watch(
+        move || controller_error.get(),
+        move |event, _, _| {
+            if event.seq > 0 {
+                notifier.notify(
+                    "controller_error",
+                    i18n.t("controller_error").to_string(),
+                    event.message.clone(),
+                );
+            }
+        },
+        false,
+    );
+
+    // 轴在运动但位置不变时提示可能失速，新出现失速的轴时才提示
+    let stalled_axes = ServerSignal::new("stalled_axes".to_string(), Vec::<String>::new())
+        .expect("Failed to create client signal");
+    Effect::watch(
+        move || stalled_axes.get(),
+        move |axes, previous, _| {
+            let new_stall = axes
+                .iter()
+                .any(|axis| previous.is_none_or(|previous| !previous.contains(axis)));
+            if new_stall {
+                notifier.notify(
+                    "possible_stall",
+                    i18n.t("possible_stall").to_string(),
+                    axes.join(", "),
+                );
+            }
+        },
+        false,
+    );
+
+    // 空闲自动断开倒计时，Some(0)表示服务端已经断开
+    let idle_warning = ServerSignal::new("idle_warning".to_string(), None::<u64>)
+        .expect("Failed to create client signal");
+    let idle_warning = Memo::new(move |_| idle_warning.get());
+    Effect::new(move |_| {
+        if idle_warning.get() == Some(0) {
+            set_global_state.update(|state| {
+                *state.get_or_insert_with(GlobalState::default) = GlobalState::default();
+            });
+        }
+    });
 
     view! {
         <Flex>
             <Flex align=FlexAlign::Start class="flex-left">
                 <NavDrawer>
                     <NavItem value="parameters" href="/parameters">
-                        "Parameters"
+                        {move || i18n.t("parameters")}
                     </NavItem>
                     <NavItem value="manual" href="/manual">
-                        "Manual Control"
+                        {move || i18n.t("manual_control")}
                     </NavItem>
                     <NavItem value="auto" href="/auto">
-                        "Auto mode"
+                        {move || i18n.t("auto_mode")}
+                    </NavItem>
+                    <NavItem value="log" href="/log">
+                        {move || i18n.t("command_log")}
+                    </NavItem>
+                    <NavItem value="history" href="/history">
+                        {move || i18n.t("run_history")}
                     </NavItem>
                     <NavItem value="about" href="about">
-                        "About"
+                        {move || i18n.t("about")}
                     </NavItem>
                     <NavDrawerFooter slot>
                         <LimitStatusView />
+                        {move || {
+                            idle_warning
+                                .get()
+                                .filter(|secs| *secs > 0 && connected())
+                                .map(|secs| {
+                                    view! {
+                                        <div class="idle-warning">
+                                            {format!("{} {}s", i18n.t("idle_disconnect_in"), secs)}
+                                        </div>
+                                    }
+                                })
+                        }}
                         <Badge color=Signal::derive(move || {
-                            if connected() { BadgeColor::Success } else { BadgeColor::Severe }
+                            if !connected() {
+                                BadgeColor::Severe
+                            } else if simulated() {
+                                BadgeColor::Warning
+                            } else {
+                                BadgeColor::Success
+                            }
                         })>
-                            {move || { if connected() { "Connected" } else { "Disconnected" } }}
+                            {move || {
+                                if !connected() {
+                                    i18n.t("disconnected")
+                                } else if simulated() {
+                                    i18n.t("connected_simulated")
+                                } else {
+                                    i18n.t("connected")
+                                }
+                            }}
                         </Badge>
+                        <Button
+                            appearance=ButtonAppearance::Subtle
+                            on_click=move |_| i18n.toggle()
+                        >
+                            {move || i18n.t("switch_language")}
+                        </Button>
                     </NavDrawerFooter>
                 </NavDrawer>
             </Flex>
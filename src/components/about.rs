@@ -1,8 +1,25 @@
 use leptos::prelude::*;
+use leptos::{logging, task::spawn_local};
 use chrono::Datelike;
 
+use crate::api::zmc_get_controller_info;
+use crate::i18n::use_i18n;
+use crate::model::ControllerInfo;
+
 #[component]
 pub fn AboutView() -> impl IntoView {
+    let i18n = use_i18n();
+    // 已连接控制器的型号、固件和轴数，未连接时不显示
+    let controller_info = RwSignal::new(None::<ControllerInfo>);
+    Effect::new(move |_| {
+        spawn_local(async move {
+            match zmc_get_controller_info().await {
+                Ok(info) => controller_info.set(Some(info)),
+                Err(e) => logging::log!("Controller info unavailable: {}", e),
+            }
+        });
+    });
+
     view! {
         <div class="about-container" style="max-width: 600px; margin: 40px auto; padding: 32px; background: #f8fafc; border-radius: 16px; box-shadow: 0 4px 24px rgba(0,0,0,0.08); text-align: center;">
             <h1 style="font-size: 2.0rem; color: #1e293b; margin-bottom: 16px;">Zmc Controller Upper</h1>
@@ -12,6 +29,28 @@ pub fn AboutView() -> impl IntoView {
             <p style="font-size: 1.1rem; color: #64748b; margin-bottom: 32px;">
                 Made by <a href="https://your-profile-link" style="color:#2563eb; text-decoration:underline;">Group B12</a>
             </p>
+            {move || {
+                controller_info
+                    .get()
+                    .map(|info| {
+                        view! {
+                            <table class="controller-info">
+                                <tr>
+                                    <td>{move || i18n.t("controller_model")}</td>
+                                    <td>{info.model}</td>
+                                </tr>
+                                <tr>
+                                    <td>{move || i18n.t("controller_firmware")}</td>
+                                    <td>{info.firmware}</td>
+                                </tr>
+                                <tr>
+                                    <td>{move || i18n.t("controller_axis_count")}</td>
+                                    <td>{info.axis_count}</td>
+                                </tr>
+                            </table>
+                        }
+                    })
+            }}
             <div style="font-size: 0.95rem; color: #94a3b8;">
                 copy; {chrono::Utc::now().year()} All rights reserved.
             </div>
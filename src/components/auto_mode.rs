@@ -1,8 +1,12 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use crate::model::Parameters;
-use crate::{app::GlobalState, model::LimitStatus};
+use crate::components::use_error_notifier;
+use crate::model::{
+    format_ignored_codes, ExecutionOptions, GCodeWindow, IgnoredCode, JobStats, RecentGCodeFile,
+    RunState,
+};
+use crate::{app::GlobalState, i18n::use_i18n, model::LimitStatus};
 use leptos::{logging, prelude::*, server::codee::string::JsonSerdeCodec};
 use leptos::{
     task::spawn_local,
@@ -15,10 +19,130 @@ use thaw::*;
 use web_sys::{HtmlElement, MouseEvent, ScrollToOptions};
 
 use crate::api::{
-    debug_update_line, generate_path_preview, load_gcode, start_gcode_execution,
-    stop_gcode_execution, zmc_init_eth, zmc_init_fake,
+    abort_gcode_execution, append_gcode_upload, begin_gcode_upload, debug_update_line,
+    finish_gcode_upload, generate_path_preview, get_gcode_window, get_last_job_stats,
+    get_path_limit_violations, get_recent_gcode_files, load_recent_gcode, pause_gcode_execution,
+    reset_gcode_execution, resume_gcode_execution, set_breakpoints, set_execution_options,
+    start_gcode_execution, start_gcode_execution_dry_run, stop_gcode_execution, zmc_init_eth,
+    zmc_init_fake,
 };
 
+// 可上传的G代码文件扩展名
+const GCODE_EXTENSIONS: [&str; 5] = ["nc", "gcode", "gc", "ngc", "tap"];
+
+fn is_gcode_file_name(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, extension)| {
+        GCODE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+    })
+}
+
+// 上传时每块读取的字节数，浏览器只需要保存当前一块
+const UPLOAD_CHUNK_SIZE: f64 = 1024.0 * 1024.0;
+// G代码显示窗口的行数，以及当前行距离窗口边缘多少行时才移动窗口
+const VISIBLE_WINDOW: usize = 100;
+const BUFFER_ZONE: usize = 10;
+
+// 从offset开始读取一块文件发给服务端，发送完再读下一块，全部发送后返回总行数
+fn upload_chunk(file: web_sys::File, offset: f64, on_done: Callback<usize>) {
+    if offset >= file.size() {
+        spawn_local(async move {
+            match finish_gcode_upload(file.name()).await {
+                Ok(total_lines) => on_done.run(total_lines),
+                Err(e) => logging::error!("Failed to finish G-code upload: {}", e),
+            }
+        });
+        return;
+    }
+    let end = (offset + UPLOAD_CHUNK_SIZE).min(file.size());
+    let chunk = file
+        .slice_with_f64_and_f64(offset, end)
+        .expect("Failed to slice file");
+    let reader = web_sys::FileReader::new().expect("Failed to create FileReader");
+    let next_file = file.clone();
+    let chunk_loaded = Closure::once_into_js(move |event: web_sys::ProgressEvent| {
+        let target = event.target().expect("Event should have a target");
+        let reader: web_sys::FileReader = target.dyn_into().expect("Target should be a FileReader");
+        let bytes = match reader.result() {
+            Ok(buffer) => web_sys::js_sys::Uint8Array::new(&buffer).to_vec(),
+            Err(e) => {
+                logging::error!("Error reading file: {:?}", e);
+                return;
+            }
+        };
+        spawn_local(async move {
+            match append_gcode_upload(bytes).await {
+                Ok(_) => upload_chunk(next_file, end, on_done),
+                Err(e) => logging::error!("Failed to upload G-code chunk: {}", e),
+            }
+        });
+    });
+    reader.set_onload(Some(chunk_loaded.unchecked_ref()));
+    if let Err(e) = reader.read_as_array_buffer(&chunk) {
+        logging::error!("Error initiating file read: {:?}", e);
+    }
+}
+
+// 12345 -> "12,345"
+fn format_thousands(value: usize) -> String {
+    let digits = value.to_string();
+    let mut formatted = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(c);
+    }
+    formatted
+}
+
+// 4800.0 -> "1h20m"
+fn format_runtime(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}h{}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+// 已执行行数占总行数的百分比，保留两位小数，没有加载文件时视为100%
+fn progress_percent(current: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        ((current.min(total) as f64 / total as f64) * 100.0 * 100.0).round() / 100.0
+    }
+}
+
+// 进度环中央的文字：百分比和已执行/总行数，没有加载文件时显示—
+fn progress_label(current: usize, total: usize) -> (String, String) {
+    if total == 0 {
+        ("\u{2014}".to_string(), String::new())
+    } else {
+        (
+            format!("{:.1}%", progress_percent(current, total)),
+            format!("{}/{}", current.min(total), total),
+        )
+    }
+}
+
+fn format_job_stats(stats: &JobStats) -> String {
+    let status = match stats.status {
+        RunState::Error => " (failed)",
+        _ => "",
+    };
+    format!(
+        "Last job: {} lines in {}, {:.1}mm travelled{}",
+        format_thousands(stats.lines_executed),
+        format_runtime(stats.runtime_secs),
+        stats.distance,
+        status
+    )
+}
+
 fn highlight_gcode(line: &str) -> impl IntoView {
     // Skip empty lines
     if line.trim().is_empty() {
@@ -120,8 +244,19 @@ pub fn AutoModeView() -> impl IntoView {
         set_global_state.set(Some(GlobalState::default()));
     }
     let connected = move || global_state.get().unwrap().connected;
+    let i18n = use_i18n();
+    let error_notifier = use_error_notifier();
+    // 其他客户端的程序运行或暂停时，服务端会拒绝修改执行状态的请求
+    let report_rejected = move |e: ServerFnError| {
+        error_notifier.notify(
+            "gcode_control",
+            i18n.t("gcode_control_rejected").to_string(),
+            e.to_string(),
+        )
+    };
 
-    let file_content = RwSignal::new(String::new());
+    // The program stays on the server, only its length and the visible window are kept here
+    let total_lines = RwSignal::new(0usize);
     let current_line = ServerSignal::new("current_line".to_string(), 0usize).unwrap();
     // let current_line = use_context::<ServerSignal<Cu>>();
 
@@ -133,44 +268,84 @@ pub fn AutoModeView() -> impl IntoView {
         set_ip_addr.set(Some(String::new()));
     }
 
-    let custom_request = move |file_list: web_sys::FileList| {
-        if file_list.length() > 0 {
-            let file = file_list.get(0).expect("Failed to get file");
-            // Create a closure to handle the file content
-            let file_loaded = Closure::wrap(Box::new(move |event: web_sys::ProgressEvent| {
-                let target = event.target().expect("Event should have a target");
-                let reader: web_sys::FileReader =
-                    target.dyn_into().expect("Target should be a FileReader");
-
-                match reader.result() {
-                    Ok(content) => {
-                        // For text files
-                        if let Some(text) = content.as_string() {
-                            let text_clone = text.clone();
-                            spawn_local(async move {
-                                load_gcode(text_clone).await.expect("Failed to load G-code");
-                            });
-                            file_content.set(text);
-                        }
-                        // For binary files (as ArrayBuffer)
-                        else {
-                            logging::log!("Binary file loaded");
-                            // Handle binary content
-                        }
-                    }
-                    Err(e) => logging::error!("Error reading file: {:?}", e),
+    // 最近上传的文件，选择后直接从服务端的副本重新加载
+    let recent_files = RwSignal::new(Vec::<RecentGCodeFile>::new());
+    let refresh_recent_files = move || {
+        spawn_local(async move {
+            match get_recent_gcode_files().await {
+                Ok(files) => recent_files.set(files),
+                Err(e) => logging::error!("Failed to get recent G-code files: {}", e),
+            }
+        });
+    };
+    Effect::new(move |_| refresh_recent_files());
+    let on_recent_change = move |ev: web_sys::Event| {
+        let name = event_target_value(&ev);
+        if name.is_empty() {
+            return;
+        }
+        spawn_local(async move {
+            match load_recent_gcode(name).await {
+                Ok(lines) => {
+                    total_lines.set(lines);
+                    refresh_recent_files();
                 }
-            })
-                as Box<dyn FnMut(web_sys::ProgressEvent)>);
-            // Create FileReader and set up the onload handler
-            let reader = web_sys::FileReader::new().expect("Failed to create FileReader");
-            reader.set_onload(Some(file_loaded.as_ref().unchecked_ref()));
-            // Start reading the file as text
-            if let Err(e) = reader.read_as_text(&file) {
-                logging::error!("Error initiating file read: {:?}", e);
+                Err(e) => report_rejected(e),
+            }
+        });
+    };
+
+    // 按钮上传和拖放上传共用：检查扩展名后分块上传
+    let upload_toaster = ToasterInjection::expect_context();
+    let upload_file = move |file: web_sys::File| {
+        if !is_gcode_file_name(&file.name()) {
+            let body = format!("{} ({})", file.name(), GCODE_EXTENSIONS.join(", "));
+            upload_toaster.dispatch_toast(
+                move || {
+                    view! {
+                        <Toast>
+                            <ToastTitle>{i18n.t("invalid_gcode_file")}</ToastTitle>
+                            <ToastBody>{body}</ToastBody>
+                        </Toast>
+                    }
+                },
+                ToastOptions::default().with_intent(ToastIntent::Error),
+            );
+            return;
+        }
+        spawn_local(async move {
+            if let Err(e) = begin_gcode_upload().await {
+                report_rejected(e);
+                return;
             }
-            // Keep the closure alive
-            file_loaded.forget();
+            let on_done = Callback::new(move |lines: usize| {
+                total_lines.set(lines);
+                refresh_recent_files();
+            });
+            upload_chunk(file, 0.0, on_done);
+        });
+    };
+    let upload_dropped = upload_file.clone();
+    let custom_request = move |file_list: web_sys::FileList| {
+        if let Some(file) = file_list.get(0) {
+            upload_file(file);
+        }
+    };
+    let accept = GCODE_EXTENSIONS
+        .map(|extension| format!(".{}", extension))
+        .join(",");
+
+    // 拖放G代码文件到内容区域上传
+    let drag_over = RwSignal::new(false);
+    let on_drop = move |ev: web_sys::DragEvent| {
+        ev.prevent_default();
+        drag_over.set(false);
+        if let Some(file) = ev
+            .data_transfer()
+            .and_then(|data| data.files())
+            .and_then(|files| files.get(0))
+        {
+            upload_dropped(file);
         }
     };
 
@@ -198,22 +373,262 @@ pub fn AutoModeView() -> impl IntoView {
     );
     interval_pause();
 
+    // Stop timing and notify the operator when the job finishes on the server
+    let run_state = ServerSignal::new("run_state".to_string(), RunState::Idle)
+        .expect("Failed to create client signal");
+    let run_state = Memo::new(move |_| run_state.get());
+    let toaster = ToasterInjection::expect_context();
+
+    // 运行中跳过的未实现代码，不影响执行，只提示操作员
+    let ignored_codes = ServerSignal::new("ignored_gcodes".to_string(), Vec::<IgnoredCode>::new())
+        .expect("Failed to create client signal");
+    // 超过最大速度被限制的F值，机床按最大速度运行，提醒操作员检查程序
+    let clamped_feeds = ServerSignal::new("clamped_feeds".to_string(), Vec::<IgnoredCode>::new())
+        .expect("Failed to create client signal");
+    // 断点行索引(从0开始)，由服务端推送，点击行号切换
+    let breakpoint_lines = ServerSignal::new("breakpoints".to_string(), Vec::<usize>::new())
+        .expect("Failed to create client signal");
+    let breakpoints = Memo::new(move |_| breakpoint_lines.get());
+    let toggle_breakpoint = Callback::new(move |line: usize| {
+        let mut lines = breakpoints.get_untracked();
+        match lines.iter().position(|l| *l == line) {
+            Some(index) => {
+                lines.remove(index);
+            }
+            None => lines.push(line),
+        }
+        spawn_local(async move {
+            if let Err(e) = set_breakpoints(lines).await {
+                report_rejected(e);
+            }
+        });
+    });
+    let paused_line = current_line.clone();
+    let last_job_stats = RwSignal::new(None::<JobStats>);
+    let refresh_job_stats = move || {
+        spawn_local(async move {
+            match get_last_job_stats().await {
+                Ok(stats) => last_job_stats.set(stats),
+                Err(e) => logging::error!("Failed to get last job stats: {}", e),
+            }
+        });
+    };
+    Effect::new(move |_| refresh_job_stats());
+
+    // 计时只跟随服务端的执行状态，避免界面与服务端不一致
+    Effect::watch(
+        move || run_state.get(),
+        move |state, prev_state, _| {
+            if prev_state == Some(state) {
+                return;
+            }
+            if *state == RunState::Running {
+                interval_resume();
+            } else {
+                interval_pause();
+            }
+            let (title, body, intent) = match state {
+                RunState::Completed => (
+                    i18n.t("job_completed"),
+                    format!(
+                        "{} lines executed in {}s",
+                        total_lines.get_untracked(),
+                        time_used.get_untracked()
+                    ),
+                    ToastIntent::Success,
+                ),
+                // 错误提示经过合并，反复失败时不会刷屏
+                RunState::Error => {
+                    refresh_job_stats();
+                    error_notifier.notify(
+                        "job_failed",
+                        i18n.t("job_failed").to_string(),
+                        i18n.t("job_failed_body").to_string(),
+                    );
+                    return;
+                }
+                RunState::Paused => {
+                    let line = paused_line.get_untracked();
+                    if !breakpoints.get_untracked().contains(&line) {
+                        return;
+                    }
+                    let body = format!("{} {}", i18n.t("breakpoint_line"), line + 1);
+                    toaster.dispatch_toast(
+                        move || {
+                            view! {
+                                <Toast>
+                                    <ToastTitle>{i18n.t("breakpoint_reached")}</ToastTitle>
+                                    <ToastBody>{body}</ToastBody>
+                                </Toast>
+                            }
+                        },
+                        ToastOptions::default().with_intent(ToastIntent::Warning),
+                    );
+                    return;
+                }
+                _ => return,
+            };
+            refresh_job_stats();
+            toaster.dispatch_toast(
+                move || {
+                    view! {
+                        <Toast>
+                            <ToastTitle>{title}</ToastTitle>
+                            <ToastBody>{body}</ToastBody>
+                        </Toast>
+                    }
+                },
+                ToastOptions::default().with_intent(intent),
+            );
+        },
+        false,
+    );
+
+    // Dry run only advances the progress without commanding the controller
+    let dry_run = RwSignal::new(false);
+
+    let (execution_options, set_execution_options_cookie) =
+        use_cookie::<ExecutionOptions, JsonSerdeCodec>("execution_options_cookie");
+    // Ensure execution options are initialized
+    if execution_options.read_untracked().is_none() {
+        set_execution_options_cookie.set(Some(ExecutionOptions::default()));
+    }
+    let block_delete = RwSignal::new(
+        execution_options
+            .get_untracked()
+            .unwrap_or_default()
+            .block_delete,
+    );
+    let safe_rapids = RwSignal::new(
+        execution_options
+            .get_untracked()
+            .unwrap_or_default()
+            .safe_rapids,
+    );
+    let speed_per_minute = RwSignal::new(
+        execution_options
+            .get_untracked()
+            .unwrap_or_default()
+            .speed_per_minute,
+    );
+    let end_retract = RwSignal::new(
+        execution_options
+            .get_untracked()
+            .unwrap_or_default()
+            .end_retract,
+    );
+    let end_return_xy = RwSignal::new(
+        execution_options
+            .get_untracked()
+            .unwrap_or_default()
+            .end_return_xy,
+    );
+    let coordinate_decimals = RwSignal::new(
+        execution_options
+            .get_untracked()
+            .unwrap_or_default()
+            .coordinate_decimals,
+    );
+    let min_line_interval_ms = RwSignal::new(
+        execution_options
+            .get_untracked()
+            .unwrap_or_default()
+            .min_line_interval_ms,
+    );
+    // Keep the cookie and the server side executor in sync with the controls
+    Effect::watch(
+        move || ExecutionOptions {
+            block_delete: block_delete.get(),
+            speed_per_minute: speed_per_minute.get(),
+            safe_rapids: safe_rapids.get(),
+            end_retract: end_retract.get(),
+            end_return_xy: end_return_xy.get(),
+            min_line_interval_ms: min_line_interval_ms.get(),
+            coordinate_decimals: coordinate_decimals.get(),
+            ..execution_options.get_untracked().unwrap_or_default()
+        },
+        move |options, _, _| {
+            set_execution_options_cookie.set(Some(options.clone()));
+            let options = options.clone();
+            spawn_local(async move {
+                set_execution_options(options)
+                    .await
+                    .expect("Failed to set execution options");
+            });
+        },
+        true,
+    );
+
+    // 路径包围盒由服务端在加载程序时计算，超出软限位时先让操作者确认
+    let limit_violations = RwSignal::new(Vec::<String>::new());
+    let confirm_open = RwSignal::new(false);
+
+    let start_job = move |allow_out_of_limits: bool| {
+        let dry_run = dry_run.get_untracked();
+        spawn_local(async move {
+            let started = if dry_run {
+                start_gcode_execution_dry_run(allow_out_of_limits).await
+            } else {
+                start_gcode_execution(allow_out_of_limits).await
+            };
+            if let Err(e) = started {
+                report_rejected(e);
+            }
+        });
+    };
     let on_start_click = move |_: MouseEvent| {
-        interval_resume();
         spawn_local(async move {
-            start_gcode_execution()
+            match get_path_limit_violations().await {
+                Ok(violations) if violations.is_empty() => start_job(false),
+                Ok(violations) => {
+                    limit_violations.set(violations);
+                    confirm_open.set(true);
+                }
+                Err(e) => report_rejected(e),
+            }
+        });
+    };
+    let on_confirm_start_click = move |_: MouseEvent| {
+        confirm_open.set(false);
+        start_job(true);
+    };
+    let on_resume_click = move |_: MouseEvent| {
+        spawn_local(async move {
+            if let Err(e) = resume_gcode_execution().await {
+                report_rejected(e);
+            }
+        });
+    };
+    let on_pause_click = move |_: MouseEvent| {
+        spawn_local(async move {
+            pause_gcode_execution()
                 .await
-                .expect("Failed to start G-code execution");
+                .expect("Failed to pause G-code execution");
         });
     };
     let on_stop_click = move |_: MouseEvent| {
-        interval_pause();
         spawn_local(async move {
             stop_gcode_execution()
                 .await
                 .expect("Failed to stop G-code execution");
         });
     };
+    // 紧急中止不等待当前运动完成，刀具可能停在材料中
+    let on_abort_click = move |_: MouseEvent| {
+        spawn_local(async move {
+            abort_gcode_execution()
+                .await
+                .expect("Failed to abort G-code execution");
+        });
+    };
+    let on_reset_click = move |_: MouseEvent| {
+        time_used.set(0);
+        spawn_local(async move {
+            if let Err(e) = reset_gcode_execution().await {
+                report_rejected(e);
+            }
+        });
+    };
 
     let on_debug_click = move |_: MouseEvent| {
         spawn_local(async move {
@@ -255,8 +670,43 @@ pub fn AutoModeView() -> impl IntoView {
         });
     });
 
+    // Lines around the current one, moved only when the current line nears the window edge
+    let current_line_window = current_line.clone();
+    let window_bounds = Memo::new(move |prev: Option<&(usize, usize, usize)>| {
+        let current = current_line_window.get();
+        let total = total_lines.get();
+        let ideal_start = current.saturating_sub(VISIBLE_WINDOW / 2);
+        let ideal_end = (ideal_start + VISIBLE_WINDOW).min(total);
+        if let Some(&(prev_start, prev_end, prev_total)) = prev {
+            let distance_from_start = current.saturating_sub(prev_start);
+            let distance_from_end = prev_end.saturating_sub(current);
+            if prev_total == total
+                && distance_from_start >= BUFFER_ZONE
+                && distance_from_end >= BUFFER_ZONE
+            {
+                return (prev_start, prev_end, total);
+            }
+        }
+        (ideal_start, ideal_end, total)
+    });
+    let gcode_window = RwSignal::new(GCodeWindow::default());
+    Effect::watch(
+        move || window_bounds.get(),
+        move |&(start, end, _), _, _| {
+            spawn_local(async move {
+                match get_gcode_window(start, end - start).await {
+                    Ok(window) => gcode_window.set(window),
+                    Err(e) => logging::error!("Failed to get G-code window: {}", e),
+                }
+            });
+        },
+        true,
+    );
+
     let preview_processed_line_clone = preview_processed_line.clone();
     let current_line_clone = current_line.clone();
+    let current_line_resume = current_line.clone();
+    let current_line_progress = current_line.clone();
     view! {
         <Flex>
             <Flex vertical=true>
@@ -268,7 +718,7 @@ pub fn AutoModeView() -> impl IntoView {
                 </Label>
                 <div class="auto-mode-label">
                     {move || {
-                        let total_lines = file_content.read().lines().count();
+                        let total_lines = total_lines.get();
                         let lines_per_second = lines_per_second.get();
                         if lines_per_second == 0.0 {
                             "infinity".to_string()
@@ -286,10 +736,12 @@ pub fn AutoModeView() -> impl IntoView {
                 </div>
             </Flex>
             <Flex vertical=true>
-                <Button on_click=on_genenrate_preview_click>"Generate"</Button>
+                <Button on_click=on_genenrate_preview_click>
+                    {move || i18n.t("generate")}
+                </Button>
                 {move || {
                     let preview_processed_line = *preview_processed_line_clone.read();
-                    let total_lines = file_content.read().lines().count();
+                    let total_lines = total_lines.get();
                     if preview_processed_line < total_lines {
                         view! {
                             <div>
@@ -312,31 +764,202 @@ pub fn AutoModeView() -> impl IntoView {
             <div class="status-container">
                 <ProgressCircle
                     value=Signal::derive(move || {
-                        let total_lines = file_content.get().lines().count() as f64;
-                        if total_lines == 0.0 {
-                            100.0
-                        } else {
-                            ((current_line.get() as f64 / total_lines) * 100.0 * 100.0).round()
-                                / 100.0
-                        }
+                        progress_percent(current_line.get(), total_lines.get())
                     })
                     color=ProgressCircleColor::Success
-                />
+                >
+                    {move || {
+                        let (percent, fraction) = progress_label(
+                            current_line_progress.get(),
+                            total_lines.get(),
+                        );
+                        view! {
+                            <div class="progress-label">
+                                <span class="progress-percent">{percent}</span>
+                                <span class="progress-fraction">{fraction}</span>
+                            </div>
+                        }
+                    }}
+                </ProgressCircle>
+                <p class="job-stats">
+                    {move || last_job_stats.get().map(|stats| format_job_stats(&stats))}
+                </p>
+                {move || {
+                    format_ignored_codes(&ignored_codes.get(), i18n.t("at_lines"))
+                        .map(|codes| {
+                            view! {
+                                <p class="ignored-codes">
+                                    {format!("{} {}", i18n.t("ignored_codes"), codes)}
+                                </p>
+                            }
+                        })
+                }}
+                {move || {
+                    format_ignored_codes(&clamped_feeds.get(), i18n.t("at_lines"))
+                        .map(|feeds| {
+                            view! {
+                                <p class="clamped-feeds">
+                                    {format!("{} {}", i18n.t("clamped_feeds"), feeds)}
+                                </p>
+                            }
+                        })
+                }}
             </div>
             <div class="control-container">
-                <Upload custom_request>
-                    <Button>"upload"</Button>
+                <Upload custom_request accept>
+                    <Button>{move || i18n.t("upload")}</Button>
                 </Upload>
-                <Button on_click=on_start_click disabled=Signal::derive(move || !connected())>
-                    "Start"
+                <select class="recent-files" on:change=on_recent_change>
+                    <option value="">{move || i18n.t("recent_files")}</option>
+                    {move || {
+                        recent_files
+                            .get()
+                            .into_iter()
+                            .map(|file| {
+                                let label = format!(
+                                    "{} ({} lines)",
+                                    file.name,
+                                    format_thousands(file.lines),
+                                );
+                                view! { <option value=file.name>{label}</option> }
+                            })
+                            .collect_view()
+                    }}
+                </select>
+                <Button
+                    on_click=on_start_click
+                    disabled=Signal::derive(move || {
+                        (!connected() && !dry_run.get()) || !run_state.get().can_start()
+                    })
+                >
+                    {move || i18n.t("start")}
+                </Button>
+                <Button
+                    on_click=on_pause_click
+                    disabled=Signal::derive(move || !run_state.get().can_pause())
+                >
+                    {move || i18n.t("pause")}
                 </Button>
-                <Button on_click=on_stop_click disabled=Signal::derive(move || !connected())>
-                    "Stop"
+                <Button
+                    on_click=on_stop_click
+                    disabled=Signal::derive(move || !run_state.get().can_stop())
+                >
+                    {move || i18n.t("stop")}
                 </Button>
+                <Button
+                    on_click=on_abort_click
+                    class="emergency-abort"
+                    disabled=Signal::derive(move || !run_state.get().can_stop())
+                >
+                    {move || i18n.t("emergency_abort")}
+                </Button>
+                <Button
+                    on_click=on_resume_click
+                    disabled=Signal::derive(move || {
+                        !connected() || !run_state.get().can_resume()
+                            || current_line_resume.get() == 0
+                    })
+                >
+                    {move || i18n.t("resume")}
+                </Button>
+                <Button
+                    on_click=on_reset_click
+                    disabled=Signal::derive(move || !run_state.get().can_reset())
+                >
+                    {move || i18n.t("reset")}
+                </Button>
+                <Dialog open=confirm_open>
+                    <DialogSurface>
+                        <DialogBody>
+                            <DialogTitle>{move || i18n.t("path_exceeds_limits")}</DialogTitle>
+                            <DialogContent>
+                                <ul>
+                                    {move || {
+                                        limit_violations
+                                            .get()
+                                            .into_iter()
+                                            .map(|violation| view! { <li>{violation}</li> })
+                                            .collect_view()
+                                    }}
+                                </ul>
+                            </DialogContent>
+                            <DialogActions>
+                                <Button on_click=move |_| confirm_open.set(false)>
+                                    {move || i18n.t("cancel")}
+                                </Button>
+                                <Button
+                                    appearance=ButtonAppearance::Primary
+                                    on_click=on_confirm_start_click
+                                >
+                                    {move || i18n.t("start_anyway")}
+                                </Button>
+                            </DialogActions>
+                        </DialogBody>
+                    </DialogSurface>
+                </Dialog>
+                <Checkbox checked=dry_run label=i18n.tr("dry_run") />
+                <Checkbox checked=block_delete label=i18n.tr("block_delete") />
+                <Checkbox checked=speed_per_minute label=i18n.tr("speed_per_minute") />
+                <Checkbox checked=safe_rapids label=i18n.tr("safe_rapids") />
+                <Checkbox checked=end_retract label=i18n.tr("end_retract") />
+                <Checkbox checked=end_return_xy label=i18n.tr("end_return_xy") />
+                <label>
+                    {move || i18n.t("coordinate_decimals")}
+                    " "
+                    <select on:change=move |ev| {
+                        coordinate_decimals.set(event_target_value(&ev).parse().ok());
+                    }>
+                        <option value="" selected=move || coordinate_decimals.get().is_none()>
+                            {move || i18n.t("no_rounding")}
+                        </option>
+                        {ExecutionOptions::COORDINATE_DECIMALS
+                            .into_iter()
+                            .map(|decimals| {
+                                view! {
+                                    <option
+                                        value=decimals.to_string()
+                                        selected=move || {
+                                            coordinate_decimals.get() == Some(decimals)
+                                        }
+                                    >
+                                        {decimals}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                // 在真机上验证程序时放慢逐行执行，0表示不限速
+                <label title=move || i18n.t("min_line_interval_hint")>
+                    {move || i18n.t("min_line_interval")}
+                    " "
+                    <input
+                        type="range"
+                        min="0"
+                        max="2000"
+                        step="50"
+                        prop:value=move || min_line_interval_ms.get().to_string()
+                        on:input=move |ev| {
+                            if let Ok(value) = event_target_value(&ev).parse() {
+                                min_line_interval_ms.set(value);
+                            }
+                        }
+                    />
+                    {move || format!(" {}ms", min_line_interval_ms.get())}
+                </label>
             </div>
         </Flex>
-        <div class="file-content">
-            <p>"G-code Content:"</p>
+        <div
+            class="file-content"
+            class:drag-over=move || drag_over.get()
+            on:dragover=move |ev: web_sys::DragEvent| {
+                ev.prevent_default();
+                drag_over.set(true);
+            }
+            on:dragleave=move |_| drag_over.set(false)
+            on:drop=on_drop
+        >
+            <p>{move || i18n.t("gcode_content")}</p>
             <Scrollbar
                 style="max-height: 300px;"
                 class="gcode-scrollbar"
@@ -345,74 +968,57 @@ pub fn AutoModeView() -> impl IntoView {
             >
                 <pre style="text-align: left;" class="gcode-display">
                     {move || {
-                        let content = file_content.get();
+                        let window = gcode_window.get();
                         let current = current_line_clone.get();
-                        const VISIBLE_WINDOW: usize = 100;
-                        const BUFFER_ZONE: usize = 10;
-                        let total_lines = content.lines().count();
-                        let (start_line, end_line) = Memo::new(move |
-                                prev_bounds: Option<&(usize, usize)>|
-                            {
-                                let ideal_start = current.saturating_sub(VISIBLE_WINDOW / 2);
-                                let ideal_end = (ideal_start + VISIBLE_WINDOW).min(total_lines);
-                                if let Some(&(prev_start, prev_end)) = prev_bounds {
-                                    let distance_from_start = if current >= prev_start {
-                                        current - prev_start
-                                    } else {
-                                        0
-                                    };
-                                    let distance_from_end = if current < prev_end {
-                                        prev_end - current
-                                    } else {
-                                        0
-                                    };
-                                    if distance_from_start >= BUFFER_ZONE
-                                        && distance_from_end >= BUFFER_ZONE
-                                    {
-                                        return (prev_start, prev_end);
-                                    }
-                                }
-                                (ideal_start, ideal_end)
-                            })
-                            .get();
-                        let before_placeholder = if start_line > 0 {
+                        let end_line = window.start + window.lines.len();
+                        let before_placeholder = if window.start > 0 {
                             Some(
-                                // Use saturating arithmetic for all distance calculations
                                 view! {
                                     <div class="gcode-line-placeholder">
-                                        <span>{format!("... {} more lines ...", start_line)}</span>
+                                        <span>
+                                            {format!("... {} more lines ...", window.start)}
+                                        </span>
                                     </div>
                                 },
                             )
                         } else {
                             None
                         };
-                        let visible_lines: Vec<_> = content
-                            .lines()
-                            .skip(start_line)
-                            .take(end_line.saturating_sub(start_line))
+                        let visible_lines: Vec<_> = window
+                            .lines
+                            .iter()
                             .enumerate()
                             .map(|(rel_i, line)| {
-                                let i = rel_i + start_line;
+                                let i = rel_i + window.start;
                                 let is_current = i == current;
+                                let is_breakpoint = breakpoints.get().contains(&i);
                                 view! {
-                                    <div class=if is_current {
-                                        "gcode-line current-line"
-                                    } else {
-                                        "gcode-line"
-                                    }>
-                                        <span class="line-number">{format!("{:4}: ", i + 1)}</span>
+                                    <div
+                                        class="gcode-line"
+                                        class:current-line=is_current
+                                        class:breakpoint=is_breakpoint
+                                    >
+                                        <span
+                                            class="line-number"
+                                            title=i18n.t("toggle_breakpoint")
+                                            on:click=move |_| toggle_breakpoint.run(i)
+                                        >
+                                            {format!("{:4}: ", i + 1)}
+                                        </span>
                                         <span class="line-content">{highlight_gcode(line)}</span>
                                     </div>
                                 }
                             })
                             .collect();
-                        let after_placeholder = if end_line < total_lines {
+                        let after_placeholder = if end_line < window.total {
                             Some(
                                 view! {
                                     <div class="gcode-line-placeholder">
                                         <span>
-                                            {format!("... {} more lines ...", total_lines - end_line)}
+                                            {format!(
+                                                "... {} more lines ...",
+                                                window.total - end_line,
+                                            )}
                                         </span>
                                     </div>
                                 },
@@ -429,3 +1035,87 @@ pub fn AutoModeView() -> impl IntoView {
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_is_rounded_to_two_decimals() {
+        assert_eq!(progress_percent(0, 3), 0.0);
+        assert_eq!(progress_percent(1, 3), 33.33);
+        assert_eq!(progress_percent(2, 3), 66.67);
+        assert_eq!(progress_percent(3, 3), 100.0);
+    }
+
+    #[test]
+    fn progress_without_a_file_is_complete() {
+        assert_eq!(progress_percent(0, 0), 100.0);
+        assert_eq!(
+            progress_label(0, 0),
+            ("\u{2014}".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn progress_past_the_last_line_is_capped() {
+        assert_eq!(progress_percent(12, 10), 100.0);
+        assert_eq!(
+            progress_label(12, 10),
+            ("100.0%".to_string(), "10/10".to_string())
+        );
+    }
+
+    #[test]
+    fn progress_labels_show_the_percent_and_line_counts() {
+        assert_eq!(
+            progress_label(1, 3),
+            ("33.3%".to_string(), "1/3".to_string())
+        );
+    }
+
+    #[test]
+    fn thousands_are_separated_with_commas() {
+        for (value, formatted) in [
+            (0, "0"),
+            (999, "999"),
+            (1000, "1,000"),
+            (12345, "12,345"),
+            (123456, "123,456"),
+            (1234567, "1,234,567"),
+        ] {
+            assert_eq!(format_thousands(value), formatted);
+        }
+    }
+
+    #[test]
+    fn gcode_extensions_are_accepted_in_any_case() {
+        for name in [
+            "part.nc",
+            "part.gcode",
+            "part.gc",
+            "part.ngc",
+            "part.tap",
+            "PART.NC",
+            "Part.GCode",
+            "v1.2.tap",
+        ] {
+            assert!(is_gcode_file_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn other_file_names_are_rejected() {
+        for name in [
+            "part.txt",
+            "part",
+            "nc",
+            "part.nc.bak",
+            "part.",
+            ".nc.zip",
+            "",
+        ] {
+            assert!(!is_gcode_file_name(name), "{}", name);
+        }
+    }
+}
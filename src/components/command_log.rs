@@ -0,0 +1,231 @@
+use crate::api::{get_command_log, zmc_execute_raw, zmc_set_developer_mode};
+use crate::i18n::use_i18n;
+use crate::model::LogEntry;
+use leptos::{logging, prelude::*, server::codee::string::JsonSerdeCodec, task::spawn_local};
+use leptos_use::use_cookie;
+use thaw::*;
+
+// 原始指令控制台最多保留的记录数
+const CONSOLE_CAPACITY: usize = 100;
+
+pub(crate) fn format_timestamp(timestamp_ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(timestamp_ms)
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+        .unwrap_or_else(|| timestamp_ms.to_string())
+}
+
+// 双引号包裹并转义，避免detail中的逗号破坏CSV格式
+fn csv_field(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+fn to_csv(entries: &[LogEntry]) -> String {
+    let mut csv = String::from("timestamp,command,detail\n");
+    for entry in entries {
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            csv_field(&format_timestamp(entry.timestamp_ms)),
+            csv_field(&entry.command),
+            csv_field(&entry.detail)
+        ));
+    }
+    csv
+}
+
+fn to_json(entries: &[LogEntry]) -> String {
+    serde_json::to_string_pretty(entries).unwrap_or_default()
+}
+
+pub(crate) fn data_url(mime: &str, content: &str) -> String {
+    format!(
+        "data:{};charset=utf-8,{}",
+        mime,
+        String::from(web_sys::js_sys::encode_uri_component(content))
+    )
+}
+
+// 原始BASIC指令控制台，只在开启开发者模式后显示，避免误操作
+#[component]
+fn RawCommandConsole() -> impl IntoView {
+    let i18n = use_i18n();
+    let (developer_mode, set_developer_mode) =
+        use_cookie::<bool, JsonSerdeCodec>("developer_mode_cookie");
+    let enabled = RwSignal::new(developer_mode.get_untracked().unwrap_or(false));
+    // 服务端据此决定是否接受原始指令，页面加载时也同步一次
+    Effect::watch(
+        move || enabled.get(),
+        move |enabled, _, _| {
+            let enabled = *enabled;
+            set_developer_mode.set(Some(enabled));
+            spawn_local(async move {
+                if let Err(e) = zmc_set_developer_mode(enabled).await {
+                    logging::error!("Failed to set developer mode: {}", e);
+                }
+            });
+        },
+        true,
+    );
+
+    let command = RwSignal::new(String::new());
+    // (指令, 应答或错误)
+    let history = RwSignal::new(Vec::<(String, Result<String, String>)>::new());
+    let on_send = move |_| {
+        let cmd = command.get_untracked();
+        if cmd.trim().is_empty() {
+            return;
+        }
+        spawn_local(async move {
+            let response = zmc_execute_raw(cmd.clone())
+                .await
+                .map_err(|e| e.to_string());
+            history.update(|history| {
+                history.push((cmd, response));
+                let overflow = history.len().saturating_sub(CONSOLE_CAPACITY);
+                history.drain(..overflow);
+            });
+        });
+    };
+
+    view! {
+        <div class="raw-console">
+            <Switch checked=enabled value="developer_mode" label=i18n.tr("developer_mode") />
+            <Show when=move || enabled.get()>
+                <div class="control-panel">
+                    <Input value=command placeholder=i18n.tr("raw_command") />
+                    <Button on_click=on_send>{move || i18n.t("send_command")}</Button>
+                    <Button on_click=move |_| history.set(Vec::new())>
+                        {move || i18n.t("clear_console")}
+                    </Button>
+                </div>
+                <pre class="raw-console-output">
+                    {move || {
+                        history
+                            .get()
+                            .into_iter()
+                            .map(|(cmd, response)| match response {
+                                Ok(response) => format!("> {}\n{}\n", cmd, response),
+                                Err(e) => {
+                                    format!("> {}\n{}: {}\n", cmd, i18n.t("command_error"), e)
+                                }
+                            })
+                            .collect::<String>()
+                    }}
+                </pre>
+            </Show>
+        </div>
+    }
+}
+
+#[component]
+pub fn CommandLogView() -> impl IntoView {
+    let i18n = use_i18n();
+    let entries = RwSignal::new(Vec::<LogEntry>::new());
+    let refresh = move || {
+        spawn_local(async move {
+            match get_command_log().await {
+                Ok(log) => entries.set(log),
+                Err(e) => logging::error!("Failed to get command log: {}", e),
+            }
+        });
+    };
+    Effect::new(move |_| refresh());
+
+    view! {
+        <div class="command-log-container">
+            <RawCommandConsole />
+            <div class="control-panel">
+                <Button on_click=move |_| refresh()>{move || i18n.t("refresh")}</Button>
+                <a
+                    href=move || data_url("text/csv", &to_csv(&entries.get()))
+                    download="command_log.csv"
+                >
+                    {move || i18n.t("download_csv")}
+                </a>
+                <a
+                    href=move || data_url("application/json", &to_json(&entries.get()))
+                    download="command_log.json"
+                >
+                    {move || i18n.t("download_json")}
+                </a>
+            </div>
+            <Table>
+                <TableHeader>
+                    <TableRow>
+                        <TableCell>{move || i18n.t("time")}</TableCell>
+                        <TableCell>{move || i18n.t("command")}</TableCell>
+                        <TableCell>{move || i18n.t("detail")}</TableCell>
+                    </TableRow>
+                </TableHeader>
+                <TableBody>
+                    {move || {
+                        entries
+                            .get()
+                            .into_iter()
+                            .rev()
+                            .map(|entry| {
+                                view! {
+                                    <TableRow>
+                                        <TableCell>{format_timestamp(entry.timestamp_ms)}</TableCell>
+                                        <TableCell>{entry.command}</TableCell>
+                                        <TableCell>{entry.detail}</TableCell>
+                                    </TableRow>
+                                }
+                            })
+                            .collect_view()
+                    }}
+                </TableBody>
+            </Table>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp_ms: i64, command: &str, detail: &str) -> LogEntry {
+        LogEntry {
+            timestamp_ms,
+            command: command.to_string(),
+            detail: detail.to_string(),
+        }
+    }
+
+    #[test]
+    fn csv_rows_quote_every_field() {
+        let csv = to_csv(&[entry(0, "MOVE_ABS", "axis=[0, 1] pos=[10.0, 10.0]")]);
+        assert_eq!(
+            csv,
+            "timestamp,command,detail\n\
+             \"1970-01-01 00:00:00.000\",\"MOVE_ABS\",\"axis=[0, 1] pos=[10.0, 10.0]\"\n"
+        );
+    }
+
+    #[test]
+    fn quotes_in_csv_fields_are_doubled() {
+        let csv = to_csv(&[entry(1500, "RAW", r#"cmd="?MPOS(0)""#)]);
+        assert!(
+            csv.ends_with("\"RAW\",\"cmd=\"\"?MPOS(0)\"\"\"\n"),
+            "{}",
+            csv
+        );
+    }
+
+    #[test]
+    fn an_empty_log_exports_only_the_csv_header() {
+        assert_eq!(to_csv(&[]), "timestamp,command,detail\n");
+    }
+
+    #[test]
+    fn json_exports_round_trip() {
+        let entries = vec![entry(1, "DATUM", "axis=2"), entry(2, "RAW", "a,\"b\"")];
+        let parsed: Vec<LogEntry> = serde_json::from_str(&to_json(&entries)).unwrap();
+        assert_eq!(parsed, entries);
+        assert_eq!(to_json(&[]), "[]");
+    }
+
+    #[test]
+    fn invalid_timestamps_are_exported_as_numbers() {
+        assert_eq!(format_timestamp(i64::MAX), i64::MAX.to_string());
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use leptos::prelude::*;
+use thaw::*;
+
+// 同一种错误在该时间内最多弹出一次提示(ms)
+const ERROR_TOAST_INTERVAL_MS: f64 = 5000.0;
+
+// 按错误种类记录上次提示的时间和之后被合并的次数
+#[derive(Default)]
+struct ErrorThrottle {
+    errors: HashMap<String, (f64, usize)>,
+}
+
+impl ErrorThrottle {
+    // 记录一次错误，需要弹出提示时返回合并的次数(包括本次)，否则计入下一次提示
+    fn record(&mut self, key: &str, now_ms: f64) -> Option<usize> {
+        match self.errors.get_mut(key) {
+            Some((shown_at, suppressed)) if now_ms - *shown_at < ERROR_TOAST_INTERVAL_MS => {
+                *suppressed += 1;
+                None
+            }
+            Some((shown_at, suppressed)) => {
+                let count = *suppressed + 1;
+                *shown_at = now_ms;
+                *suppressed = 0;
+                Some(count)
+            }
+            None => {
+                self.errors.insert(key.to_string(), (now_ms, 0));
+                Some(1)
+            }
+        }
+    }
+
+    // 窗口内第一次被合并时返回窗口结束的时间，到时调用flush补发，
+    // 否则一阵错误过后被合并的次数要等到下一次出错才会显示
+    fn flush_due(&self, key: &str) -> Option<f64> {
+        match self.errors.get(key) {
+            Some(&(shown_at, 1)) => Some(shown_at + ERROR_TOAST_INTERVAL_MS),
+            _ => None,
+        }
+    }
+
+    // 窗口结束时返回被合并的次数，补发的提示开始新的窗口
+    fn flush(&mut self, key: &str, now_ms: f64) -> Option<usize> {
+        let (shown_at, suppressed) = self.errors.get_mut(key)?;
+        if *suppressed == 0 || now_ms - *shown_at < ERROR_TOAST_INTERVAL_MS {
+            return None;
+        }
+        let count = std::mem::take(suppressed);
+        *shown_at = now_ms;
+        Some(count)
+    }
+}
+
+// 通过context共享的错误提示，故障连续发生时合并相同的错误并限制弹出频率
+#[derive(Clone, Copy)]
+pub struct ErrorNotifier {
+    throttle: StoredValue<ErrorThrottle, LocalStorage>,
+    toaster: ToasterInjection,
+}
+
+impl ErrorNotifier {
+    // key区分错误种类，合并多次时在标题后显示次数
+    pub fn notify(&self, key: &str, title: String, body: String) {
+        let now = web_sys::js_sys::Date::now();
+        let Some(count) = self
+            .throttle
+            .try_update_value(|t| t.record(key, now))
+            .flatten()
+        else {
+            self.schedule_flush(key, title, body, now);
+            return;
+        };
+        self.show(title, body, count);
+    }
+
+    fn schedule_flush(&self, key: &str, title: String, body: String, now: f64) {
+        let Some(due) = self.throttle.try_with_value(|t| t.flush_due(key)).flatten() else {
+            return;
+        };
+        let (notifier, key) = (*self, key.to_string());
+        let delay = std::time::Duration::from_millis((due - now).max(0.0).ceil() as u64);
+        set_timeout(
+            move || {
+                let now = web_sys::js_sys::Date::now();
+                let flushed = notifier.throttle.try_update_value(|t| t.flush(&key, now));
+                if let Some(count) = flushed.flatten() {
+                    notifier.show(title, body, count);
+                }
+            },
+            delay,
+        );
+    }
+
+    fn show(&self, title: String, body: String, count: usize) {
+        let title = if count > 1 {
+            format!("{title} (×{count})")
+        } else {
+            title
+        };
+        self.toaster.dispatch_toast(
+            move || {
+                view! {
+                    <Toast>
+                        <ToastTitle>{title}</ToastTitle>
+                        <ToastBody>{body}</ToastBody>
+                    </Toast>
+                }
+            },
+            ToastOptions::default().with_intent(ToastIntent::Error),
+        );
+    }
+}
+
+// 需要在ToasterProvider内调用
+pub fn provide_error_notifier() {
+    provide_context(ErrorNotifier {
+        throttle: StoredValue::new_local(ErrorThrottle::default()),
+        toaster: ToasterInjection::expect_context(),
+    });
+}
+
+pub fn use_error_notifier() -> ErrorNotifier {
+    expect_context::<ErrorNotifier>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_burst_of_the_same_error_is_shown_once_and_flushed_at_the_end_of_the_window() {
+        let mut throttle = ErrorThrottle::default();
+        assert_eq!(throttle.record("read", 0.0), Some(1));
+        assert_eq!(throttle.flush_due("read"), None);
+        for now in [100.0, 200.0, 300.0] {
+            assert_eq!(throttle.record("read", now), None);
+        }
+        // 只在第一次被合并时安排补发
+        assert_eq!(throttle.errors["read"], (0.0, 3));
+        assert_eq!(throttle.flush_due("read"), None);
+        assert_eq!(throttle.flush("read", ERROR_TOAST_INTERVAL_MS - 1.0), None);
+        assert_eq!(throttle.flush("read", ERROR_TOAST_INTERVAL_MS), Some(3));
+        assert_eq!(throttle.flush("read", 2.0 * ERROR_TOAST_INTERVAL_MS), None);
+        // 补发开始了新的窗口
+        assert_eq!(
+            throttle.record("read", ERROR_TOAST_INTERVAL_MS + 10.0),
+            None
+        );
+        assert_eq!(
+            throttle.flush_due("read"),
+            Some(2.0 * ERROR_TOAST_INTERVAL_MS)
+        );
+    }
+
+    #[test]
+    fn the_first_suppression_asks_for_a_flush() {
+        let mut throttle = ErrorThrottle::default();
+        throttle.record("read", 0.0);
+        throttle.record("read", 100.0);
+        assert_eq!(throttle.flush_due("read"), Some(ERROR_TOAST_INTERVAL_MS));
+    }
+
+    #[test]
+    fn errors_after_a_quiet_window_show_with_the_suppressed_count() {
+        let mut throttle = ErrorThrottle::default();
+        throttle.record("read", 0.0);
+        throttle.record("read", 100.0);
+        assert_eq!(
+            throttle.record("read", ERROR_TOAST_INTERVAL_MS + 1.0),
+            Some(2)
+        );
+        assert_eq!(throttle.flush("read", 3.0 * ERROR_TOAST_INTERVAL_MS), None);
+    }
+
+    #[test]
+    fn different_errors_are_throttled_separately() {
+        let mut throttle = ErrorThrottle::default();
+        assert_eq!(throttle.record("read", 0.0), Some(1));
+        assert_eq!(throttle.record("write", 10.0), Some(1));
+        assert_eq!(throttle.record("read", 20.0), None);
+        assert_eq!(
+            throttle.flush("write", ERROR_TOAST_INTERVAL_MS + 10.0),
+            None
+        );
+        assert_eq!(throttle.flush("unknown", 0.0), None);
+    }
+}
@@ -1,8 +1,17 @@
-use crate::{app::GlobalState, model::LimitStatus};
+use std::collections::HashMap;
+
+use crate::{
+    app::GlobalState,
+    i18n::use_i18n,
+    model::{LimitStatus, Parameters},
+};
 use leptos::{logging, prelude::*, server::codee::string::JsonSerdeCodec};
 use leptos_use::use_cookie;
 use thaw::*;
 
+// 同一输入两次报警之间的最短间隔，避免开关抖动时反复报警
+const ALARM_DEBOUNCE_MS: i64 = 2000;
+
 fn status_to_badge(status: bool) -> BadgeColor {
     if status {
         BadgeColor::Success
@@ -11,6 +20,35 @@ fn status_to_badge(status: bool) -> BadgeColor {
     }
 }
 
+// 只返回从未触发变为触发(false->true)的输入
+fn newly_triggered(prev: &[&'static str], next: &[&'static str]) -> Vec<&'static str> {
+    next.iter()
+        .filter(|name| !prev.contains(name))
+        .copied()
+        .collect()
+}
+
+// 使用Web Audio播放一段短促的报警音
+fn play_alarm_sound() {
+    let Ok(audio) = web_sys::AudioContext::new() else {
+        logging::error!("Failed to create AudioContext");
+        return;
+    };
+    let Ok(oscillator) = audio.create_oscillator() else {
+        return;
+    };
+    oscillator.set_type(web_sys::OscillatorType::Square);
+    oscillator.frequency().set_value(880.0);
+    if oscillator
+        .connect_with_audio_node(&audio.destination())
+        .is_err()
+    {
+        return;
+    }
+    let _ = oscillator.start();
+    let _ = oscillator.stop_with_when(audio.current_time() + 0.3);
+}
+
 #[component]
 pub fn LimitStatusView() -> impl IntoView {
     let (global_state, set_global_state) =
@@ -19,30 +57,90 @@ pub fn LimitStatusView() -> impl IntoView {
     if global_state.read_untracked().is_none() {
         set_global_state.set(Some(GlobalState::default()));
     }
+    let (parameters, set_parameters) =
+        use_cookie::<Parameters, JsonSerdeCodec>("parameters_cookie");
+    // Ensure parameters are initialized
+    if parameters.read_untracked().is_none() {
+        set_parameters.set(Some(Parameters::default()));
+    }
 
     let connected = move || global_state.get().unwrap().connected;
+    let i18n = use_i18n();
 
     let limit_status =
         leptos_ws::ServerSignal::new("limit_status".to_string(), LimitStatus::default()).unwrap();
 
+    // 当前触发的输入，用于显示报警横幅
+    let alarms = RwSignal::new(Vec::<&'static str>::new());
+    // 每个输入上次报警的时间
+    let last_alarm_at = StoredValue::new(HashMap::<&'static str, i64>::new());
+
+    let limit_status_clone = limit_status.clone();
+    Effect::watch(
+        move || {
+            let inverted = parameters.get().unwrap_or_default().inverted_status;
+            limit_status_clone.get().triggered(&inverted)
+        },
+        move |triggered, prev, _| {
+            if !connected() {
+                alarms.set(Vec::new());
+                return;
+            }
+            let prev = prev.cloned().unwrap_or_default();
+            let now = chrono::Utc::now().timestamp_millis();
+            let mut should_sound = false;
+            last_alarm_at.update_value(|last| {
+                for name in newly_triggered(&prev, triggered) {
+                    let debounced = last
+                        .get(name)
+                        .is_some_and(|at| now - at < ALARM_DEBOUNCE_MS);
+                    if !debounced {
+                        logging::warn!("Limit triggered: {}", name);
+                        last.insert(name, now);
+                        should_sound = true;
+                    }
+                }
+            });
+            if should_sound {
+                play_alarm_sound();
+            }
+            alarms.set(triggered.clone());
+        },
+        false,
+    );
+
     view! {
         <Transition fallback=move || {
-            view! { <p>"Loading..."</p> }
+            view! { <p>{move || i18n.t("loading")}</p> }
         }>
+            <Show when=move || !alarms.read().is_empty()>
+                <div class="alarm-banner">
+                    {move || {
+                        let names = alarms
+                            .get()
+                            .into_iter()
+                            .map(|name| i18n.t(name))
+                            .collect::<Vec<_>>();
+                        format!("{}: {}", i18n.t("alarm"), names.join(", "))
+                    }}
+                </div>
+            </Show>
             <div class="limit-status-container">
                 {move || {
                     if !connected() {
-                        view! { <div class="not-connected-text">"Waitting for connected"</div> }
+                        view! { <div class="not-connected-text">
+                                {move || i18n.t("waiting_for_connection")}
+                            </div> }
                     } else {
                         let status = limit_status.get();
                         view! {
                             <div class="status-badge">
                                 <Badge color=Signal::derive(move || {
                                     status_to_badge(status.emergency_stop)
-                                })>"急停"</Badge>
+                                })>{move || i18n.t("emergency_stop")}</Badge>
                                 <Badge color=Signal::derive(move || {
                                     status_to_badge(status.door_switch)
-                                })>"门限位"</Badge>
+                                })>{move || i18n.t("door_switch")}</Badge>
                                 <Badge color=Signal::derive(move || {
                                     status_to_badge(status.x_plus)
                                 })>"X+"</Badge>
@@ -69,3 +167,21 @@ pub fn LimitStatusView() -> impl IntoView {
         </Transition>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_inputs_that_start_triggering_raise_an_alarm() {
+        assert_eq!(newly_triggered(&[], &["X+"]), ["X+"]);
+        assert_eq!(newly_triggered(&["X+"], &["X+", "急停"]), ["急停"]);
+    }
+
+    #[test]
+    fn held_or_released_inputs_raise_no_alarm() {
+        assert!(newly_triggered(&["X+"], &["X+"]).is_empty());
+        assert!(newly_triggered(&["X+", "Z-"], &["Z-"]).is_empty());
+        assert!(newly_triggered(&["X+"], &[]).is_empty());
+    }
+}
@@ -1,22 +1,34 @@
-use crate::model::{ManualControl, Parameters};
+use crate::model::{
+    step_target, JogMacro, JogPreview, MacroCommand, ManualControl, MoveStatus, Parameters,
+    UiPreferences,
+};
 use crate::{
     api::{
-        zmc_converter_run, zmc_converter_set_freq, zmc_converter_stop, zmc_manual_move,
-        zmc_manual_stop, zmc_set_zero,
+        execute_mdi_line, run_macro, zmc_converter_run, zmc_converter_set_freq, zmc_converter_stop,
+        zmc_get_in, zmc_goto_zero, zmc_heartbeat, zmc_jog_step_blocking, zmc_manual_move_axes,
+        zmc_manual_stop, zmc_probe, zmc_set_heartbeat_timeout, zmc_set_idle_timeout,
+        zmc_set_in_inverted, zmc_set_jog_speed, zmc_set_out, zmc_set_zero, zmc_setup_sequence,
     },
     app::GlobalState,
+    components::{NumericInput, NumericKind},
+    i18n::use_i18n,
 };
 use leptos::{
     ev::MouseEvent, logging, prelude::*, reactive::spawn_local,
     server::codee::string::JsonSerdeCodec,
 };
-use leptos_use::use_cookie;
+use leptos_use::storage::{use_storage, StorageType};
+use leptos_use::{use_cookie, use_interval_fn};
 use thaw::*;
 
-fn manual_move(axis: u8, direction: i8) {
+// 点动：moves为(轴号, 方向)，两个轴同时点动时为斜向移动
+fn manual_move(moves: Vec<(u8, i8)>, jog_speed: f32) {
     spawn_local(async move {
-        logging::log!("Moving axis {} in direction {}", axis, direction);
-        zmc_manual_move(axis, direction).await.unwrap();
+        logging::log!("Moving axes {:?} at {}", moves, jog_speed);
+        let (axis_list, directions) = moves.into_iter().unzip();
+        zmc_manual_move_axes(axis_list, directions, jog_speed)
+            .await
+            .unwrap();
     });
 }
 fn manual_stop(axis: u8) {
@@ -42,6 +54,388 @@ fn ControlView() -> impl IntoView {
     }
 
     let connected = move || global_state.get().unwrap().connected;
+    let i18n = use_i18n();
+
+    let (manual_control, set_manual_control) =
+        use_cookie::<ManualControl, JsonSerdeCodec>("manual_control_cookie");
+    // Ensure manual control is initialized
+    if manual_control.read_untracked().is_none() {
+        set_manual_control.set(Some(ManualControl::default()));
+    }
+    let watchdog_timeout = RwSignal::new(
+        manual_control
+            .get_untracked()
+            .unwrap_or_default()
+            .watchdog_timeout_ms
+            .to_string(),
+    );
+    Effect::watch(
+        move || watchdog_timeout.get().parse::<u64>().unwrap_or(1500),
+        move |timeout_ms, _, _| {
+            let timeout_ms = *timeout_ms;
+            set_manual_control.update(|manual_control| {
+                if let Some(manual_control) = manual_control.as_mut() {
+                    manual_control.watchdog_timeout_ms = timeout_ms;
+                }
+            });
+            spawn_local(async move {
+                zmc_set_heartbeat_timeout(timeout_ms)
+                    .await
+                    .expect("Failed to set heartbeat timeout");
+            });
+        },
+        true,
+    );
+    let idle_timeout = RwSignal::new(
+        manual_control
+            .get_untracked()
+            .unwrap_or_default()
+            .idle_timeout_min
+            .to_string(),
+    );
+    Effect::watch(
+        move || idle_timeout.get().parse::<u64>().unwrap_or(0),
+        move |minutes, _, _| {
+            let minutes = *minutes;
+            set_manual_control.update(|manual_control| {
+                if let Some(manual_control) = manual_control.as_mut() {
+                    manual_control.idle_timeout_min = minutes;
+                }
+            });
+            spawn_local(async move {
+                zmc_set_idle_timeout(minutes)
+                    .await
+                    .expect("Failed to set idle timeout");
+            });
+        },
+        true,
+    );
+    // Heartbeat so the server stops jogging if this page goes away
+    use_interval_fn(
+        move || {
+            if connected() {
+                spawn_local(async move {
+                    if let Err(e) = zmc_heartbeat().await {
+                        logging::error!("Failed to send heartbeat: {}", e);
+                    }
+                });
+            }
+        },
+        500,
+    );
+
+    // Z轴对刀：向下探测到探针触发，显示触发位置
+    let probe_result = RwSignal::new(None::<Result<f32, String>>);
+    let probe_z = move |_ev: MouseEvent| {
+        let params = parameters.get_untracked().unwrap_or_default();
+        probe_result.set(None);
+        spawn_local(async move {
+            let (axis, max_distance) = (params.z.axis_num, params.probe_max_distance);
+            let result = zmc_probe(axis, -1, max_distance, params.probe_io)
+                .await
+                .map_err(|e| e.to_string());
+            probe_result.set(Some(result));
+        });
+    };
+
+    // 调机：全部回零后设置工件零点，显示执行结果
+    let setup_result = RwSignal::new(None::<Result<(), String>>);
+    let setup_running = RwSignal::new(false);
+    let run_setup = move |_ev: MouseEvent| {
+        setup_result.set(None);
+        setup_running.set(true);
+        spawn_local(async move {
+            let result = zmc_setup_sequence().await.map_err(|e| e.to_string());
+            setup_running.set(false);
+            setup_result.set(Some(result));
+        });
+    };
+
+    // 点动加速设置
+    let jog_ramp = RwSignal::new(manual_control.get_untracked().unwrap_or_default().jog_ramp);
+    let jog_initial_speed = RwSignal::new(
+        manual_control
+            .get_untracked()
+            .unwrap_or_default()
+            .jog_initial_speed
+            .to_string(),
+    );
+    let jog_ramp_rate = RwSignal::new(
+        manual_control
+            .get_untracked()
+            .unwrap_or_default()
+            .jog_ramp_rate
+            .to_string(),
+    );
+    Effect::watch(
+        move || (jog_ramp.get(), jog_initial_speed.get(), jog_ramp_rate.get()),
+        move |(ramp, initial, rate), _, _| {
+            set_manual_control.update(|manual_control| {
+                if let Some(manual_control) = manual_control.as_mut() {
+                    manual_control.jog_ramp = *ramp;
+                    manual_control.jog_initial_speed = initial.parse().unwrap_or(5.0);
+                    manual_control.jog_ramp_rate = rate.parse().unwrap_or(10.0);
+                }
+            });
+        },
+        false,
+    );
+
+    // 宏录制：录制期间记录成功执行的单步点动、回零和置零，保存后可按顺序重放
+    let recording = RwSignal::new(None::<Vec<MacroCommand>>);
+    let record = move |command: MacroCommand| {
+        recording.update(|commands| {
+            if let Some(commands) = commands.as_mut() {
+                commands.push(command);
+            }
+        });
+    };
+    let (macros, set_macros, _) =
+        use_storage::<Vec<JogMacro>, JsonSerdeCodec>(StorageType::Local, "jog_macros");
+    let macro_name = RwSignal::new(String::new());
+    let selected_macro = RwSignal::new(String::new());
+    let macro_result = RwSignal::new(None::<Result<(), String>>);
+    let toggle_recording = move |_ev: MouseEvent| match recording.get_untracked() {
+        None => {
+            macro_result.set(None);
+            recording.set(Some(Vec::new()));
+        }
+        Some(commands) => {
+            recording.set(None);
+            let name = macro_name.get_untracked().trim().to_string();
+            if name.is_empty() || commands.is_empty() {
+                return;
+            }
+            // 同名宏覆盖旧的
+            set_macros.update(|macros| {
+                macros.retain(|m| m.name != name);
+                macros.push(JogMacro {
+                    name: name.clone(),
+                    commands,
+                });
+            });
+            selected_macro.set(name);
+        }
+    };
+    let run_selected_macro = move |_ev: MouseEvent| {
+        let name = selected_macro.get_untracked();
+        let Some(jog_macro) = macros.get_untracked().into_iter().find(|m| m.name == name) else {
+            return;
+        };
+        macro_result.set(None);
+        spawn_local(async move {
+            let result = run_macro(jog_macro.commands)
+                .await
+                .map_err(|e| e.to_string());
+            macro_result.set(Some(result));
+        });
+    };
+    let delete_selected_macro = move |_ev: MouseEvent| {
+        let name = selected_macro.get_untracked();
+        set_macros.update(|macros| macros.retain(|m| m.name != name));
+        selected_macro.set(String::new());
+    };
+
+    // 点动速度，滑块上限为参数中的最大速度
+    let jog_speed = RwSignal::new(manual_control.get_untracked().unwrap_or_default().jog_speed);
+    let jog_speed_max = move || {
+        let max_speed = parameters.get().unwrap_or_default().speed.max_speed;
+        if max_speed > 0.0 {
+            max_speed
+        } else {
+            100.0
+        }
+    };
+    Effect::watch(
+        move || jog_speed.get(),
+        move |speed, _, _| {
+            set_manual_control.update(|manual_control| {
+                if let Some(manual_control) = manual_control.as_mut() {
+                    manual_control.jog_speed = *speed;
+                }
+            });
+        },
+        false,
+    );
+
+    // 单步点动设置
+    let step_jog = RwSignal::new(manual_control.get_untracked().unwrap_or_default().step_jog);
+    let step_distance = RwSignal::new(
+        manual_control
+            .get_untracked()
+            .unwrap_or_default()
+            .step_distance
+            .to_string(),
+    );
+    let step_preview = RwSignal::new(
+        manual_control
+            .get_untracked()
+            .unwrap_or_default()
+            .step_preview,
+    );
+    Effect::watch(
+        move || (step_jog.get(), step_distance.get(), step_preview.get()),
+        move |(step, distance, preview), _, _| {
+            set_manual_control.update(|manual_control| {
+                if let Some(manual_control) = manual_control.as_mut() {
+                    manual_control.step_jog = *step;
+                    manual_control.step_distance = distance.parse().unwrap_or(1.0);
+                    manual_control.step_preview = *preview;
+                }
+            });
+        },
+        false,
+    );
+
+    // 预览模式下等待确认的单步点动，目标位置通过cookie显示在路径画布上
+    let move_status =
+        leptos_ws::ServerSignal::new("move_status".to_string(), MoveStatus::default()).unwrap();
+    let move_status = Signal::derive(move || move_status.get());
+    let (jog_preview, set_jog_preview) =
+        use_cookie::<JogPreview, JsonSerdeCodec>("jog_preview_cookie");
+    let pending_step = RwSignal::new(None::<Vec<(u8, i8)>>);
+    // 页面刷新后待确认的点动已丢失，清除残留的预览
+    set_jog_preview.set(None);
+    let step_error = RwSignal::new(None::<String>);
+    // 单步点动走完之前不接受下一步，避免连续点击时目标位置叠加出错
+    let stepping = RwSignal::new(false);
+    let jog_disabled = Signal::derive(move || !connected() || stepping.get());
+    let jog_step = move |moves: Vec<(u8, i8)>| {
+        if stepping.get_untracked() {
+            return;
+        }
+        let control = manual_control.get_untracked().unwrap_or_default();
+        let (distance, jog_speed) = (control.step_distance, control.jog_speed);
+        step_error.set(None);
+        stepping.set(true);
+        spawn_local(async move {
+            let (axis_list, directions): (Vec<u8>, Vec<i8>) = moves.into_iter().unzip();
+            let result =
+                zmc_jog_step_blocking(axis_list.clone(), directions.clone(), distance, jog_speed)
+                    .await;
+            stepping.set(false);
+            match result {
+                Ok(()) => record(MacroCommand::JogStep {
+                    axis_list,
+                    directions,
+                    distance,
+                    jog_speed,
+                }),
+                Err(e) => {
+                    logging::error!("Failed to step jog: {}", e);
+                    step_error.set(Some(e.to_string()));
+                }
+            }
+        });
+    };
+    let preview_step = move |moves: &[(u8, i8)]| {
+        let params = parameters.get_untracked().unwrap_or_default();
+        let status = move_status.get_untracked();
+        let distance = manual_control
+            .get_untracked()
+            .unwrap_or_default()
+            .step_distance;
+        let mut target = JogPreview {
+            x: status.x.pos,
+            y: status.y.pos,
+            z: status.z.pos,
+        };
+        for (axis, direction) in moves {
+            if *axis == params.x.axis_num {
+                target.x = step_target(target.x, *direction, distance);
+            } else if *axis == params.y.axis_num {
+                target.y = step_target(target.y, *direction, distance);
+            } else if *axis == params.z.axis_num {
+                target.z = step_target(target.z, *direction, distance);
+            }
+        }
+        set_jog_preview.set(Some(target));
+        pending_step.set(Some(moves.to_vec()));
+    };
+    let confirm_step = move |_ev: MouseEvent| {
+        if let Some(moves) = pending_step.get_untracked() {
+            jog_step(moves);
+        }
+        pending_step.set(None);
+        set_jog_preview.set(None);
+    };
+    let cancel_step = move |_ev: MouseEvent| {
+        pending_step.set(None);
+        set_jog_preview.set(None);
+    };
+    let (ui_preferences, _) = use_cookie::<UiPreferences, JsonSerdeCodec>("ui_preferences_cookie");
+    let preview_text = move || {
+        jog_preview.get().map(|target| {
+            let params = parameters.get().unwrap_or_default();
+            let within = params.within_soft_limits(params.x.axis_num, target.x)
+                && params.within_soft_limits(params.y.axis_num, target.y)
+                && params.within_soft_limits(params.z.axis_num, target.z);
+            let prefs = ui_preferences.get().unwrap_or_default();
+            let text = format!(
+                "X {}  Y {}  Z {}",
+                prefs.format_position(target.x as f64),
+                prefs.format_position(target.y as f64),
+                prefs.format_position(target.z as f64),
+            );
+            (text, within)
+        })
+    };
+
+    // 正在点动的轴和按下的时间(ms)，开启加速时按住越久速度越快
+    let jogging = RwSignal::new(None::<(Vec<u8>, f64)>);
+    let jog_start = move |moves: &[(u8, i8)]| {
+        if step_jog.get_untracked() {
+            if step_preview.get_untracked() {
+                preview_step(moves);
+            } else {
+                jog_step(moves.to_vec());
+            }
+            return;
+        }
+        let moves = moves.to_vec();
+        let control = manual_control.get_untracked().unwrap_or_default();
+        if control.jog_ramp {
+            let axes: Vec<u8> = moves.iter().map(|(axis, _)| *axis).collect();
+            jogging.set(Some((axes, web_sys::js_sys::Date::now())));
+            manual_move(moves, control.jog_initial_speed);
+        } else {
+            manual_move(moves, control.jog_speed);
+        }
+    };
+    let jog_stop = move |axes: &[u8]| {
+        // 单步点动走完固定距离后自行停止，松开按钮不取消
+        if step_jog.get_untracked() {
+            return;
+        }
+        // 服务端停止后恢复加工速度，避免点动速度影响后续运动
+        for axis in axes {
+            manual_stop(*axis);
+        }
+        jogging.set(None);
+    };
+    use_interval_fn(
+        move || {
+            if let Some((axes, pressed_at)) = jogging.get_untracked() {
+                let held_secs = ((web_sys::js_sys::Date::now() - pressed_at) / 1000.0) as f32;
+                let max_speed = parameters
+                    .get_untracked()
+                    .unwrap_or_default()
+                    .speed
+                    .max_speed;
+                let speed = manual_control
+                    .get_untracked()
+                    .unwrap_or_default()
+                    .jog_speed(held_secs, max_speed);
+                // 服务端只对仍在点动的轴生效，松开按钮后迟到的请求被忽略
+                spawn_local(async move {
+                    if let Err(e) = zmc_set_jog_speed(axes, speed).await {
+                        logging::error!("Failed to set jog speed: {}", e);
+                    }
+                });
+            }
+        },
+        200,
+    );
 
     view! {
         <div class="manual-view-container">
@@ -50,90 +444,317 @@ fn ControlView() -> impl IntoView {
                     disabled=Signal::derive(move || !connected())
                     on_click=move |_ev: MouseEvent| {
                         let params = parameters.get_untracked().expect("parameters should exist");
+                        let axis_list = vec![
+                            params.x.axis_num,
+                            params.y.axis_num,
+                            params.z.axis_num,
+                        ];
                         spawn_local(async move {
-                            zmc_set_zero(
-                                    vec![params.x.axis_num, params.y.axis_num, params.z.axis_num],
-                                )
+                            zmc_set_zero(axis_list.clone())
                                 .await
                                 .expect("Failed to set zero position");
+                            record(MacroCommand::SetZero { axis_list });
+                        });
+                    }
+                >
+                    {move || i18n.t("set_zero")}
+                </Button>
+                <Button
+                    disabled=Signal::derive(move || !connected())
+                    on_click=move |_ev: MouseEvent| {
+                        spawn_local(async move {
+                            match zmc_goto_zero().await {
+                                Ok(()) => record(MacroCommand::GotoZero),
+                                Err(e) => logging::error!("Failed to return to zero: {}", e),
+                            }
                         });
                     }
                 >
-                    "坐标置零"
+                    {move || i18n.t("goto_zero")}
+                </Button>
+                <Button
+                    disabled=Signal::derive(move || !connected() || setup_running.get())
+                    on_click=run_setup
+                >
+                    {move || i18n.t("setup_sequence")}
                 </Button>
+                {move || {
+                    setup_result
+                        .get()
+                        .map(|result| match result {
+                            Ok(()) => i18n.t("setup_sequence_done").to_string(),
+                            Err(e) => format!("{}: {}", i18n.t("setup_sequence_failed"), e),
+                        })
+                }}
+                <Button disabled=Signal::derive(move || !connected()) on_click=probe_z>
+                    {move || i18n.t("probe_z")}
+                </Button>
+                {move || {
+                    probe_result
+                        .get()
+                        .map(|result| match result {
+                            Ok(pos) => {
+                                let prefs = ui_preferences.get().unwrap_or_default();
+                                format!("Z = {}", prefs.format_position(pos as f64))
+                            }
+                            Err(e) => format!("{}: {}", i18n.t("probe_failed"), e),
+                        })
+                }}
+                <Input
+                    value=watchdog_timeout
+                    input_type=InputType::Number
+                    placeholder=i18n.tr("watchdog_timeout")
+                />
+                <Input
+                    value=idle_timeout
+                    input_type=InputType::Number
+                    placeholder=i18n.tr("idle_timeout")
+                />
+                <label class="jog-speed">
+                    {move || i18n.t("jog_speed")}
+                    " "
+                    <input
+                        type="range"
+                        min="0.1"
+                        max=move || jog_speed_max().to_string()
+                        step="0.1"
+                        prop:value=move || jog_speed.get().to_string()
+                        on:input=move |ev| {
+                            if let Ok(speed) = event_target_value(&ev).parse::<f32>() {
+                                jog_speed.set(speed.max(0.1));
+                            }
+                        }
+                    />
+                    {move || format!(" {:.1}", jog_speed.get())}
+                </label>
+                <Switch checked=jog_ramp label=i18n.tr("jog_ramp") />
+                <Input
+                    value=jog_initial_speed
+                    input_type=InputType::Number
+                    placeholder=i18n.tr("jog_initial_speed")
+                />
+                <Input
+                    value=jog_ramp_rate
+                    input_type=InputType::Number
+                    placeholder=i18n.tr("jog_ramp_rate")
+                />
+                <Switch checked=step_jog label=i18n.tr("step_jog") />
+                <Input
+                    value=step_distance
+                    input_type=InputType::Number
+                    placeholder=i18n.tr("step_distance")
+                />
+                <Switch checked=step_preview label=i18n.tr("step_preview") />
+                {move || {
+                    preview_text()
+                        .map(|(text, within)| {
+                            view! {
+                                <div class="jog-preview" class:out-of-limits=!within>
+                                    {move || i18n.t("step_target")}
+                                    {format!(": {text} ")}
+                                    {(!within).then(|| i18n.t("step_out_of_limits"))}
+                                    <Button
+                                        disabled=Signal::derive(move || !connected())
+                                        on_click=confirm_step
+                                    >
+                                        {move || i18n.t("confirm")}
+                                    </Button>
+                                    <Button on_click=cancel_step>
+                                        {move || i18n.t("cancel")}
+                                    </Button>
+                                </div>
+                            }
+                        })
+                }}
+                <div class="jog-macros">
+                    <Input value=macro_name placeholder=i18n.tr("macro_name") />
+                    <Button on_click=toggle_recording>
+                        {move || {
+                            if recording.get().is_some() {
+                                i18n.t("stop_recording")
+                            } else {
+                                i18n.t("record_macro")
+                            }
+                        }}
+                    </Button>
+                    {move || {
+                        recording
+                            .get()
+                            .map(|commands| {
+                                format!("{} {}", i18n.t("recorded_steps"), commands.len())
+                            })
+                    }}
+                    <select on:change=move |ev| selected_macro.set(event_target_value(&ev))>
+                        <option value="" selected=move || selected_macro.get().is_empty()>
+                            {move || i18n.t("saved_macros")}
+                        </option>
+                        {move || {
+                            macros
+                                .get()
+                                .into_iter()
+                                .map(|m| {
+                                    let name = m.name.clone();
+                                    let selected = name.clone();
+                                    view! {
+                                        <option
+                                            value=name.clone()
+                                            selected=move || selected_macro.get() == selected
+                                        >
+                                            {format!("{} ({})", name, m.commands.len())}
+                                        </option>
+                                    }
+                                })
+                                .collect_view()
+                        }}
+                    </select>
+                    <Button
+                        disabled=Signal::derive(move || {
+                            !connected() || selected_macro.get().is_empty()
+                                || recording.get().is_some()
+                        })
+                        on_click=run_selected_macro
+                    >
+                        {move || i18n.t("run_macro")}
+                    </Button>
+                    <Button
+                        disabled=Signal::derive(move || selected_macro.get().is_empty())
+                        on_click=delete_selected_macro
+                    >
+                        {move || i18n.t("delete_macro")}
+                    </Button>
+                    {move || {
+                        macro_result
+                            .get()
+                            .and_then(|result| result.err())
+                            .map(|e| format!("{}: {}", i18n.t("macro_failed"), e))
+                    }}
+                </div>
+                {move || {
+                    step_error
+                        .get()
+                        .map(|e| format!("{}: {}", i18n.t("step_jog_failed"), e))
+                }}
             </div>
             <div class="joystick-container">
                 <Flex>
                     <Flex vertical=true>
                         <Flex justify=FlexJustify::Center>
                             <Button
-                                disabled=Signal::derive(move || !connected())
+                                disabled=jog_disabled
+                                class="diagonal-jog"
+                                on:mousedown=move |_| {
+                                    jog_start(&[(0, -1), (1, 1)]);
+                                }
+                                on:mouseup=move |_| {
+                                    jog_stop(&[0, 1]);
+                                }
+                            >
+                                "↖"
+                            </Button>
+                            <Button
+                                disabled=jog_disabled
                                 icon=icondata::AiUpOutlined
                                 on:mousedown=move |_| {
-                                    manual_move(1, 1);
+                                    jog_start(&[(1, 1)]);
                                 }
                                 on:mouseup=move |_| {
-                                    manual_stop(1);
+                                    jog_stop(&[1]);
                                 }
                             />
+                            <Button
+                                disabled=jog_disabled
+                                class="diagonal-jog"
+                                on:mousedown=move |_| {
+                                    jog_start(&[(0, 1), (1, 1)]);
+                                }
+                                on:mouseup=move |_| {
+                                    jog_stop(&[0, 1]);
+                                }
+                            >
+                                "↗"
+                            </Button>
                         </Flex>
                         <Flex justify=FlexJustify::Center>
                             <Button
-                                disabled=Signal::derive(move || !connected())
+                                disabled=jog_disabled
                                 icon=icondata::AiLeftOutlined
                                 on:mousedown=move |_| {
-                                    manual_move(0, -1);
+                                    jog_start(&[(0, -1)]);
                                 }
                                 on:mouseup=move |_| {
-                                    manual_stop(0);
+                                    jog_stop(&[0]);
                                 }
                             />
                             <div style="width: 30px;" />
                             <Button
-                                disabled=Signal::derive(move || !connected())
+                                disabled=jog_disabled
                                 icon=icondata::AiRightOutlined
                                 on:mousedown=move |_| {
-                                    manual_move(0, 1);
+                                    jog_start(&[(0, 1)]);
                                 }
                                 on:mouseup=move |_| {
-                                    manual_stop(0);
+                                    jog_stop(&[0]);
                                 }
                             />
                         </Flex>
                         <Flex justify=FlexJustify::Center>
                             <Button
-                                disabled=Signal::derive(move || !connected())
+                                disabled=jog_disabled
+                                class="diagonal-jog"
+                                on:mousedown=move |_| {
+                                    jog_start(&[(0, -1), (1, -1)]);
+                                }
+                                on:mouseup=move |_| {
+                                    jog_stop(&[0, 1]);
+                                }
+                            >
+                                "↙"
+                            </Button>
+                            <Button
+                                disabled=jog_disabled
                                 icon=icondata::AiDownOutlined
                                 on:mousedown=move |_| {
-                                    manual_move(1, -1);
+                                    jog_start(&[(1, -1)]);
                                 }
                                 on:mouseup=move |_| {
-                                    manual_stop(1);
+                                    jog_stop(&[1]);
                                 }
                             />
+                            <Button
+                                disabled=jog_disabled
+                                class="diagonal-jog"
+                                on:mousedown=move |_| {
+                                    jog_start(&[(0, 1), (1, -1)]);
+                                }
+                                on:mouseup=move |_| {
+                                    jog_stop(&[0, 1]);
+                                }
+                            >
+                                "↘"
+                            </Button>
                         </Flex>
                     </Flex>
                     <div style="width: 20px;" />
                     <Flex vertical=true justify=FlexJustify::Center>
                         <Button
-                            disabled=Signal::derive(move || !connected())
+                            disabled=jog_disabled
                             icon=icondata::AiArrowUpOutlined
                             on:mousedown=move |_| {
-                                manual_move(2, 1);
+                                jog_start(&[(2, 1)]);
                             }
                             on:mouseup=move |_| {
-                                manual_stop(2);
+                                jog_stop(&[2]);
                             }
                         />
                         <div style="height: 10px;" />
                         <Button
-                            disabled=Signal::derive(move || !connected())
+                            disabled=jog_disabled
                             icon=icondata::AiArrowDownOutlined
                             on:mousedown=move |_| {
-                                manual_move(2, -1);
+                                jog_start(&[(2, -1)]);
                             }
                             on:mouseup=move |_| {
-                                manual_stop(2);
+                                jog_stop(&[2]);
                             }
                         />
                     </Flex>
@@ -189,8 +810,7 @@ fn ConverterControlView() -> impl IntoView {
                         converter_frequency: f.parse().unwrap_or(0),
                         converter_inverted: *i,
                         converter_enabled: *e,
-                        pos_store_x: 0.0,
-                        pos_store_y: 0.0,
+                        ..Default::default()
                     });
                 } else {
                     let manual_control = manual_control
@@ -249,10 +869,24 @@ fn ConverterControlView() -> impl IntoView {
     };
 
     let enabled = move || manual_control.get().unwrap_or_default().converter_enabled;
+    let i18n = use_i18n();
+
+    // 设定转速由输入频率换算，实际转速来自服务端读回的变频器输出频率
+    let (parameters, _) = use_cookie::<Parameters, JsonSerdeCodec>("parameters_cookie");
+    let move_status =
+        leptos_ws::ServerSignal::new("move_status".to_string(), MoveStatus::default()).unwrap();
+    let commanded_rpm = move || {
+        let frequency = frequency.get().parse::<f32>().unwrap_or(0.0);
+        parameters.get().unwrap_or_default().spindle.rpm(frequency)
+    };
 
     view! {
-        <Input value=frequency input_type=InputType::Number placeholder="输入频率" />
-        <Switch checked=inverted value="inverted" label="反转" />
+        <Input
+            value=frequency
+            input_type=InputType::Number
+            placeholder=i18n.tr("frequency")
+        />
+        <Switch checked=inverted value="inverted" label=i18n.tr("reverse") />
         <Button
             disabled=Signal::derive(move || !connected())
             on_click=on_control_click
@@ -260,8 +894,162 @@ fn ConverterControlView() -> impl IntoView {
                 if enabled() { ButtonAppearance::Primary } else { ButtonAppearance::Secondary }
             })
         >
-            {move || { if enabled() { "停止" } else { "启动" } }}
+            {move || { if enabled() { i18n.t("stop") } else { i18n.t("start") } }}
         </Button>
+        <span class="spindle-rpm">
+            {move || i18n.t("commanded_rpm")}
+            {move || format!(": {:.0}  ", commanded_rpm())}
+            {move || i18n.t("actual_rpm")}
+            {move || format!(": {:.0}", move_status.get().spindle_rpm)}
+        </span>
+    }
+}
+
+// MDI：输入一行G/M代码立即执行，解析或执行错误显示在输入框旁
+#[component]
+fn MdiView() -> impl IntoView {
+    let (global_state, _) = use_cookie::<GlobalState, JsonSerdeCodec>("global_state_cookie");
+    let connected = move || global_state.get().unwrap_or_default().connected;
+    let i18n = use_i18n();
+
+    let line = RwSignal::new(String::new());
+    let running = RwSignal::new(false);
+    let result = RwSignal::new(None::<Result<(), String>>);
+    let on_run = move |_ev: MouseEvent| {
+        let line = line.get_untracked();
+        if line.trim().is_empty() {
+            return;
+        }
+        running.set(true);
+        result.set(None);
+        spawn_local(async move {
+            let outcome = execute_mdi_line(line).await.map_err(|e| e.to_string());
+            result.set(Some(outcome));
+            running.set(false);
+        });
+    };
+
+    view! {
+        <Flex>
+            <Input value=line placeholder=i18n.tr("mdi_placeholder") />
+            <Button
+                disabled=Signal::derive(move || !connected() || running.get())
+                on_click=on_run
+            >
+                {move || i18n.t("mdi_run")}
+            </Button>
+            {move || {
+                result
+                    .get()
+                    .map(|result| match result {
+                        Ok(()) => i18n.t("mdi_done").to_string(),
+                        Err(e) => format!("{}: {}", i18n.t("mdi_failed"), e),
+                    })
+            }}
+        </Flex>
+    }
+}
+
+// 输入IO状态的刷新间隔(ms)
+const IO_TEST_POLL_MS: u64 = 200;
+
+// IO测试：按编号读取任意输入、开关任意输出，与参数中的IO分配无关，用于调试时确认接线
+#[component]
+fn IoTestView() -> impl IntoView {
+    let (global_state, _) = use_cookie::<GlobalState, JsonSerdeCodec>("global_state_cookie");
+    let connected = move || global_state.get().unwrap_or_default().connected;
+    let i18n = use_i18n();
+    let error_text = move |e: ServerFnError| format!("{}: {}", i18n.t("io_test_failed"), e);
+
+    let input_io = RwSignal::new(0u16);
+    let input_state = RwSignal::new(None::<Result<bool, String>>);
+    let input_inverted = RwSignal::new(false);
+    use_interval_fn(
+        move || {
+            if !connected() {
+                return;
+            }
+            let io = input_io.get_untracked();
+            spawn_local(async move {
+                input_state.set(Some(zmc_get_in(io).await.map_err(error_text)));
+            });
+        },
+        IO_TEST_POLL_MS,
+    );
+    // 只在切换开关时设置反转，改变IO号时不把开关状态带到新的IO上
+    let toggle_inverted = move |_ev: MouseEvent| {
+        let (io, inverted) = (input_io.get_untracked(), !input_inverted.get_untracked());
+        input_inverted.set(inverted);
+        spawn_local(async move {
+            if let Err(e) = zmc_set_in_inverted(io, inverted).await {
+                input_state.set(Some(Err(error_text(e))));
+            }
+        });
+    };
+
+    let output_io = RwSignal::new(0u16);
+    // 最近一次设置的输出：(IO号, 是否打开)或错误
+    let output_state = RwSignal::new(None::<Result<(u16, bool), String>>);
+    let set_output = move |on: bool| {
+        let io = output_io.get_untracked();
+        spawn_local(async move {
+            let result = zmc_set_out(io, on)
+                .await
+                .map(|_| (io, on))
+                .map_err(error_text);
+            output_state.set(Some(result));
+        });
+    };
+    let on_off = move |on: bool| i18n.t(if on { "io_on" } else { "io_off" });
+
+    view! {
+        <Flex vertical=true class="io-test">
+            <h3>{move || i18n.t("io_test")}</h3>
+            <Flex>
+                {move || i18n.t("input_io")}
+                <NumericInput value=input_io kind=NumericKind::Io unit="IO#" />
+                <span class="io-state">
+                    {move || match input_state.get() {
+                        Some(Ok(on)) => on_off(on).to_string(),
+                        Some(Err(e)) => e,
+                        None => String::new(),
+                    }}
+                </span>
+                <Button disabled=Signal::derive(move || !connected()) on_click=toggle_inverted>
+                    {move || {
+                        if input_inverted.get() {
+                            i18n.t("io_uninvert")
+                        } else {
+                            i18n.t("io_invert")
+                        }
+                    }}
+                </Button>
+            </Flex>
+            <Flex>
+                {move || i18n.t("output_io")}
+                <NumericInput value=output_io kind=NumericKind::Io unit="IO#" />
+                <Button
+                    disabled=Signal::derive(move || !connected())
+                    on_click=move |_| set_output(true)
+                >
+                    {move || i18n.t("io_on")}
+                </Button>
+                <Button
+                    disabled=Signal::derive(move || !connected())
+                    on_click=move |_| set_output(false)
+                >
+                    {move || i18n.t("io_off")}
+                </Button>
+                <span class="io-state">
+                    {move || match output_state.get() {
+                        Some(Ok((io, on))) => format!("OP{}: {}", io, on_off(on)),
+                        Some(Err(e)) => e,
+                        None => String::new(),
+                    }}
+                </span>
+            </Flex>
+            <span class="io-hint">{move || i18n.t("outputs_need_developer_mode")}</span>
+        </Flex>
     }
 }
 
@@ -271,6 +1059,8 @@ pub fn ManualView() -> impl IntoView {
         <Flex vertical=true>
             <ControlView />
             <ConverterControlView />
+            <MdiView />
+            <IoTestView />
         </Flex>
     }
 }
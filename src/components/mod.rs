@@ -1,13 +1,25 @@
 mod about;
 mod auto_mode;
+mod command_log;
+mod error_toast;
 mod limit_status;
 mod manual;
+mod numeric_input;
 mod parameters;
+mod path_tiles;
+mod run_history;
+mod speed_chart;
 mod visual;
 
 pub use about::*;
 pub use auto_mode::*;
+pub use command_log::*;
+pub use error_toast::*;
 pub use limit_status::*;
 pub use manual::*;
+pub use numeric_input::*;
 pub use parameters::*;
+pub use path_tiles::*;
+pub use run_history::*;
+pub use speed_chart::*;
 pub use visual::*;
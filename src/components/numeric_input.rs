@@ -0,0 +1,134 @@
+use std::str::FromStr;
+
+use leptos::prelude::*;
+use thaw::*;
+
+// 数值输入框允许输入的内容，输入过程中的中间状态(如"-"、"1e")也要允许
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumericKind {
+    Float,
+    Integer,
+    // IO编号，不能为负
+    Io,
+}
+
+impl NumericKind {
+    pub fn allows(self, value: &str) -> bool {
+        match self {
+            // Allow only digits, a decimal point, an exponent and a minus sign
+            NumericKind::Float => value
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == '.' || c == 'e' || c == '-'),
+            // Allow only digits and an optional leading minus sign
+            NumericKind::Integer => value.chars().all(|c| c.is_ascii_digit() || c == '-'),
+            NumericKind::Io => value.chars().all(|c| c.is_ascii_digit()),
+        }
+    }
+
+    fn placeholder(self) -> &'static str {
+        match self {
+            NumericKind::Float => "float",
+            NumericKind::Integer | NumericKind::Io => "int",
+        }
+    }
+}
+
+// 去掉首尾空白后解析，不能解析时为None
+fn parse_numeric<T: FromStr>(text: &str) -> Option<T> {
+    text.trim().parse().ok()
+}
+
+// 绑定到数值类型信号的输入框：只能输入kind允许的字符，能解析时才写回value，
+// 不能解析时保留上一次的值并标红，unit显示在输入框后
+#[component]
+pub fn NumericInput<T>(
+    value: RwSignal<T>,
+    kind: NumericKind,
+    #[prop(optional)] unit: &'static str,
+    #[prop(optional, into)] class: String,
+) -> impl IntoView
+where
+    T: FromStr + ToString + Send + Sync + 'static,
+{
+    let text = RwSignal::new(value.with_untracked(|value| value.to_string()));
+    let parsed = move || text.with(|text| parse_numeric::<T>(text));
+    let invalid = Signal::derive(move || parsed().is_none());
+    Effect::watch(
+        move || text.get(),
+        move |text, _, _| {
+            if let Some(parsed) = parse_numeric(text) {
+                value.set(parsed);
+            }
+        },
+        false,
+    );
+    // value被外部改变(如从服务端重新填入)时更新输入框，与输入框内容相同时不动，避免打断输入
+    Effect::watch(
+        move || value.with(|value| value.to_string()),
+        move |new_text, _, _| {
+            let same = text.with_untracked(|text| {
+                parse_numeric::<T>(text).is_some_and(|parsed| parsed.to_string() == *new_text)
+            });
+            if !same {
+                text.set(new_text.clone());
+            }
+        },
+        false,
+    );
+
+    view! {
+        <span class="numeric-input" class:invalid=invalid>
+            <Input
+                class=class
+                value=text
+                placeholder=kind.placeholder()
+                allow_value=move |value: String| kind.allows(&value)
+            />
+            <span class="numeric-unit">{unit}</span>
+        </span>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floats_allow_partial_input_but_not_letters() {
+        for value in ["", "-", "1.", "-0.5", "1e", "1e-3"] {
+            assert!(NumericKind::Float.allows(value), "{:?}", value);
+        }
+        for value in ["1,5", "x", "1 ", "+1"] {
+            assert!(!NumericKind::Float.allows(value), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn integers_allow_a_sign_but_not_a_decimal_point() {
+        assert!(NumericKind::Integer.allows("-12"));
+        assert!(!NumericKind::Integer.allows("1.5"));
+        assert!(!NumericKind::Integer.allows("1e3"));
+    }
+
+    #[test]
+    fn io_numbers_allow_only_digits() {
+        assert!(NumericKind::Io.allows("12"));
+        assert!(!NumericKind::Io.allows("-1"));
+        assert!(!NumericKind::Io.allows("1.0"));
+    }
+
+    #[test]
+    fn parsing_ignores_surrounding_whitespace() {
+        assert_eq!(parse_numeric::<f32>(" 1.5 "), Some(1.5));
+        assert_eq!(parse_numeric::<u16>("7"), Some(7));
+    }
+
+    #[test]
+    fn partial_or_out_of_range_input_does_not_parse() {
+        assert_eq!(parse_numeric::<f32>("-"), None);
+        assert_eq!(parse_numeric::<f32>("1e"), None);
+        assert_eq!(parse_numeric::<u8>("300"), None);
+        assert_eq!(parse_numeric::<u16>("-1"), None);
+        assert_eq!(parse_numeric::<u16>(""), None);
+    }
+}
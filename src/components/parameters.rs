@@ -1,13 +1,22 @@
-use crate::model::Parameters;
+use crate::model::{ConnectionSnapshot, ControllerKind, Parameters, RunState};
 use leptos::logging::{self, log};
 use leptos::prelude::*;
 use leptos::server::codee::string::JsonSerdeCodec;
 use leptos::{ev::MouseEvent, reactive::spawn_local};
 use leptos_use::use_cookie;
+use leptos_ws::ServerSignal;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use thaw::ssr::SSRMountStyleProvider;
 use thaw::*;
 
-use crate::api::{zmc_close, zmc_set_parameters};
+use crate::api::{
+    export_parameters_as_basic, zmc_close, zmc_get_connection_state, zmc_set_in_inverted,
+    zmc_set_parameters,
+};
+use crate::components::command_log::data_url;
+use crate::components::{use_error_notifier, NumericInput, NumericKind};
+use crate::i18n::use_i18n;
 use crate::{api::zmc_init_eth, app::GlobalState};
 
 #[component]
@@ -62,69 +71,69 @@ fn ParametersInput() -> impl IntoView {
 
     let connected = move || global_state.get().unwrap().connected;
 
-    let allow_float = |value: String| {
-        // Allow only digits and a single decimal point
-        value
-            .chars()
-            .all(|c| c.is_digit(10) || c == '.' || c == 'e' || c == '-')
-    };
-    let allow_integer = |value: String| {
-        // Allow only digits and an optional leading minus sign
-        value.chars().all(|c| c.is_digit(10) || c == '-')
-    };
-    let allow_io_integer = |value: String| {
-        // Allow only digits and an optional leading minus sign
-        value.chars().all(|c| c.is_digit(10))
-    };
-
     let parameters_tracked = move || parameters.get().unwrap();
+    let saved_parameters = move || parameters.get_untracked().unwrap();
+
+    let i18n = use_i18n();
 
     let parameters = parameters.get_untracked().unwrap();
-    // Shit code :(
     // signals to bind to input fields
-    let v_p = RwSignal::new(parameters.pid.p.to_string());
-    let v_i = RwSignal::new(parameters.pid.i.to_string());
-    let v_d = RwSignal::new(parameters.pid.d.to_string());
-
-    let v_x_axis_num = RwSignal::new(parameters.x.axis_num.to_string());
-    let v_y_axis_num = RwSignal::new(parameters.y.axis_num.to_string());
-    let v_z_axis_num = RwSignal::new(parameters.z.axis_num.to_string());
-
-    let v_pulse_equivalent_x = RwSignal::new(parameters.x.pulse_equivalent.to_string());
-    let v_pulse_equivalent_y = RwSignal::new(parameters.y.pulse_equivalent.to_string());
-    let v_pulse_equivalent_z = RwSignal::new(parameters.z.pulse_equivalent.to_string());
-
-    let v_positive_limit_io_x = RwSignal::new(parameters.x.positive_limit_io.to_string());
-    let v_negative_limit_io_x = RwSignal::new(parameters.x.negative_limit_io.to_string());
-    let v_zero_point_io_x = RwSignal::new(parameters.x.zero_point_io.to_string());
-    let v_software_positive_limit_x =
-        RwSignal::new(parameters.x.software_positive_limit.to_string());
-    let v_software_negative_limit_x =
-        RwSignal::new(parameters.x.software_negative_limit.to_string());
-    let v_positive_limit_io_y = RwSignal::new(parameters.y.positive_limit_io.to_string());
-    let v_negative_limit_io_y = RwSignal::new(parameters.y.negative_limit_io.to_string());
-    let v_zero_point_io_y = RwSignal::new(parameters.y.zero_point_io.to_string());
-    let v_software_positive_limit_y =
-        RwSignal::new(parameters.y.software_positive_limit.to_string());
-    let v_software_negative_limit_y =
-        RwSignal::new(parameters.y.software_negative_limit.to_string());
-    let v_positive_limit_io_z = RwSignal::new(parameters.z.positive_limit_io.to_string());
-    let v_negative_limit_io_z = RwSignal::new(parameters.z.negative_limit_io.to_string());
-    let v_zero_point_io_z = RwSignal::new(parameters.z.zero_point_io.to_string());
-    let v_software_positive_limit_z =
-        RwSignal::new(parameters.z.software_positive_limit.to_string());
-    let v_software_negative_limit_z =
-        RwSignal::new(parameters.z.software_negative_limit.to_string());
-
-    let v_processing_speed = RwSignal::new(parameters.speed.processing_speed.to_string());
-    let v_max_speed = RwSignal::new(parameters.speed.max_speed.to_string());
-    let v_acceleration = RwSignal::new(parameters.speed.acceleration.to_string());
-    let v_deceleration = RwSignal::new(parameters.speed.deceleration.to_string());
-    let v_transition_time = RwSignal::new(parameters.speed.transition_time.to_string());
-    let v_crawling_speed = RwSignal::new(parameters.speed.crawling_speed.to_string());
-
-    let v_emergency_stop_io = RwSignal::new(parameters.emergency_stop_io.to_string());
-    let v_door_switch_io = RwSignal::new(parameters.door_switch_io.to_string());
+    let v_p = RwSignal::new(parameters.pid.p);
+    let v_i = RwSignal::new(parameters.pid.i);
+    let v_d = RwSignal::new(parameters.pid.d);
+
+    let v_x_enabled = RwSignal::new(parameters.x.enabled);
+    let v_y_enabled = RwSignal::new(parameters.y.enabled);
+    let v_z_enabled = RwSignal::new(parameters.z.enabled);
+
+    let v_x_axis_num = RwSignal::new(parameters.x.axis_num);
+    let v_y_axis_num = RwSignal::new(parameters.y.axis_num);
+    let v_z_axis_num = RwSignal::new(parameters.z.axis_num);
+
+    let v_pulse_equivalent_x = RwSignal::new(parameters.x.pulse_equivalent);
+    let v_pulse_equivalent_y = RwSignal::new(parameters.y.pulse_equivalent);
+    let v_pulse_equivalent_z = RwSignal::new(parameters.z.pulse_equivalent);
+
+    let v_positive_limit_io_x = RwSignal::new(parameters.x.positive_limit_io);
+    let v_negative_limit_io_x = RwSignal::new(parameters.x.negative_limit_io);
+    let v_zero_point_io_x = RwSignal::new(parameters.x.zero_point_io);
+    let v_software_positive_limit_x = RwSignal::new(parameters.x.software_positive_limit);
+    let v_software_negative_limit_x = RwSignal::new(parameters.x.software_negative_limit);
+    let v_backlash_x = RwSignal::new(parameters.x.backlash);
+    let v_positive_limit_io_y = RwSignal::new(parameters.y.positive_limit_io);
+    let v_negative_limit_io_y = RwSignal::new(parameters.y.negative_limit_io);
+    let v_zero_point_io_y = RwSignal::new(parameters.y.zero_point_io);
+    let v_software_positive_limit_y = RwSignal::new(parameters.y.software_positive_limit);
+    let v_software_negative_limit_y = RwSignal::new(parameters.y.software_negative_limit);
+    let v_backlash_y = RwSignal::new(parameters.y.backlash);
+    let v_positive_limit_io_z = RwSignal::new(parameters.z.positive_limit_io);
+    let v_negative_limit_io_z = RwSignal::new(parameters.z.negative_limit_io);
+    let v_zero_point_io_z = RwSignal::new(parameters.z.zero_point_io);
+    let v_software_positive_limit_z = RwSignal::new(parameters.z.software_positive_limit);
+    let v_software_negative_limit_z = RwSignal::new(parameters.z.software_negative_limit);
+    let v_backlash_z = RwSignal::new(parameters.z.backlash);
+
+    let v_processing_speed = RwSignal::new(parameters.speed.processing_speed);
+    let v_max_speed = RwSignal::new(parameters.speed.max_speed);
+    let v_acceleration = RwSignal::new(parameters.speed.acceleration);
+    let v_deceleration = RwSignal::new(parameters.speed.deceleration);
+    let v_transition_time = RwSignal::new(parameters.speed.transition_time);
+    let v_crawling_speed = RwSignal::new(parameters.speed.crawling_speed);
+    let v_sramp_time = RwSignal::new(parameters.speed.sramp_time);
+    let v_spindle_poles = RwSignal::new(parameters.spindle.poles);
+    let v_spindle_ratio = RwSignal::new(parameters.spindle.ratio);
+    let v_spin_up_secs = RwSignal::new(parameters.spindle.spin_up_secs);
+    let v_spin_down_secs = RwSignal::new(parameters.spindle.spin_down_secs);
+    let v_stop_before_homing = RwSignal::new(parameters.spindle.stop_before_homing);
+    let v_safe_z_height = RwSignal::new(parameters.safe_z_height);
+    let v_probe_io = RwSignal::new(parameters.probe_io);
+    let v_probe_max_distance = RwSignal::new(parameters.probe_max_distance);
+    let v_read_attempts = RwSignal::new(parameters.read_attempts);
+    let v_following_error_limit = RwSignal::new(parameters.following_error_limit);
+    let v_stall_cycles = RwSignal::new(parameters.stall_cycles);
+
+    let v_emergency_stop_io = RwSignal::new(parameters.emergency_stop_io);
+    let v_door_switch_io = RwSignal::new(parameters.door_switch_io);
 
     let v_emergency_stop_level_inverted =
         RwSignal::new(parameters.inverted_status.emergency_stop_level_inverted);
@@ -132,61 +141,240 @@ fn ParametersInput() -> impl IntoView {
         RwSignal::new(parameters.inverted_status.door_switch_level_inverted);
     let v_limit_io_level_inverted =
         RwSignal::new(parameters.inverted_status.limit_io_level_inverted);
+    let v_door_interlock = RwSignal::new(parameters.door_interlock);
+
+    // 页面刷新后HomePage异步取回服务端参数写入cookie时，输入框已经用旧cookie填好，
+    // cookie变化时重新填入，避免保存时把旧值推回服务端
+    let reseed = move |parameters: &Parameters| {
+        v_p.set(parameters.pid.p);
+        v_i.set(parameters.pid.i);
+        v_d.set(parameters.pid.d);
+        v_x_enabled.set(parameters.x.enabled);
+        v_y_enabled.set(parameters.y.enabled);
+        v_z_enabled.set(parameters.z.enabled);
+        v_x_axis_num.set(parameters.x.axis_num);
+        v_y_axis_num.set(parameters.y.axis_num);
+        v_z_axis_num.set(parameters.z.axis_num);
+        v_pulse_equivalent_x.set(parameters.x.pulse_equivalent);
+        v_pulse_equivalent_y.set(parameters.y.pulse_equivalent);
+        v_pulse_equivalent_z.set(parameters.z.pulse_equivalent);
+        v_positive_limit_io_x.set(parameters.x.positive_limit_io);
+        v_negative_limit_io_x.set(parameters.x.negative_limit_io);
+        v_zero_point_io_x.set(parameters.x.zero_point_io);
+        v_software_positive_limit_x.set(parameters.x.software_positive_limit);
+        v_software_negative_limit_x.set(parameters.x.software_negative_limit);
+        v_backlash_x.set(parameters.x.backlash);
+        v_positive_limit_io_y.set(parameters.y.positive_limit_io);
+        v_negative_limit_io_y.set(parameters.y.negative_limit_io);
+        v_zero_point_io_y.set(parameters.y.zero_point_io);
+        v_software_positive_limit_y.set(parameters.y.software_positive_limit);
+        v_software_negative_limit_y.set(parameters.y.software_negative_limit);
+        v_backlash_y.set(parameters.y.backlash);
+        v_positive_limit_io_z.set(parameters.z.positive_limit_io);
+        v_negative_limit_io_z.set(parameters.z.negative_limit_io);
+        v_zero_point_io_z.set(parameters.z.zero_point_io);
+        v_software_positive_limit_z.set(parameters.z.software_positive_limit);
+        v_software_negative_limit_z.set(parameters.z.software_negative_limit);
+        v_backlash_z.set(parameters.z.backlash);
+        v_processing_speed.set(parameters.speed.processing_speed);
+        v_max_speed.set(parameters.speed.max_speed);
+        v_acceleration.set(parameters.speed.acceleration);
+        v_deceleration.set(parameters.speed.deceleration);
+        v_transition_time.set(parameters.speed.transition_time);
+        v_crawling_speed.set(parameters.speed.crawling_speed);
+        v_sramp_time.set(parameters.speed.sramp_time);
+        v_spindle_poles.set(parameters.spindle.poles);
+        v_spindle_ratio.set(parameters.spindle.ratio);
+        v_spin_up_secs.set(parameters.spindle.spin_up_secs);
+        v_spin_down_secs.set(parameters.spindle.spin_down_secs);
+        v_stop_before_homing.set(parameters.spindle.stop_before_homing);
+        v_safe_z_height.set(parameters.safe_z_height);
+        v_probe_io.set(parameters.probe_io);
+        v_probe_max_distance.set(parameters.probe_max_distance);
+        v_read_attempts.set(parameters.read_attempts);
+        v_following_error_limit.set(parameters.following_error_limit);
+        v_stall_cycles.set(parameters.stall_cycles);
+        v_emergency_stop_io.set(parameters.emergency_stop_io);
+        v_door_switch_io.set(parameters.door_switch_io);
+        v_emergency_stop_level_inverted
+            .set(parameters.inverted_status.emergency_stop_level_inverted);
+        v_door_switch_level_inverted.set(parameters.inverted_status.door_switch_level_inverted);
+        v_limit_io_level_inverted.set(parameters.inverted_status.limit_io_level_inverted);
+        v_door_interlock.set(parameters.door_interlock);
+    };
+    Effect::watch(
+        parameters_tracked,
+        move |parameters, _, _| reseed(parameters),
+        false,
+    );
+
+    // 接线检查：切换电平反转后立即下发到控制器，限位状态标签随之变化，
+    // 保存参数时才写入cookie，离开页面或取消时恢复为已保存的设置
+    let inversion_preview = Arc::new(Mutex::new(InversionPreview::default()));
+    let preview_inversion = {
+        let inversion_preview = inversion_preview.clone();
+        move |ios: Vec<u16>, inverted: bool, saved: bool| {
+            if !connected() {
+                return;
+            }
+            send_inversions(
+                inversion_preview
+                    .lock()
+                    .unwrap()
+                    .toggle(&ios, inverted, saved),
+            );
+        }
+    };
+    on_cleanup({
+        let inversion_preview = inversion_preview.clone();
+        move || send_inversions(inversion_preview.lock().unwrap().revert())
+    });
+    Effect::watch(
+        move || v_emergency_stop_level_inverted.get(),
+        {
+            let preview_inversion = preview_inversion.clone();
+            move |inverted, _, _| {
+                let io = v_emergency_stop_io.get_untracked();
+                let saved = saved_parameters()
+                    .inverted_status
+                    .emergency_stop_level_inverted;
+                preview_inversion(vec![io], *inverted, saved);
+            }
+        },
+        false,
+    );
+    Effect::watch(
+        move || v_door_switch_level_inverted.get(),
+        {
+            let preview_inversion = preview_inversion.clone();
+            move |inverted, _, _| {
+                let io = v_door_switch_io.get_untracked();
+                let saved = saved_parameters()
+                    .inverted_status
+                    .door_switch_level_inverted;
+                preview_inversion(vec![io], *inverted, saved);
+            }
+        },
+        false,
+    );
+    Effect::watch(
+        move || v_limit_io_level_inverted.get(),
+        move |inverted, _, _| {
+            let ios = [
+                v_positive_limit_io_x,
+                v_negative_limit_io_x,
+                v_positive_limit_io_y,
+                v_negative_limit_io_y,
+                v_positive_limit_io_z,
+                v_negative_limit_io_z,
+            ]
+            .iter()
+            .map(|io| io.get_untracked())
+            .collect();
+            let saved = saved_parameters().inverted_status.limit_io_level_inverted;
+            preview_inversion(ios, *inverted, saved);
+        },
+        false,
+    );
+    // 放弃未保存的修改，接线检查中下发的反转也恢复
+    let on_cancel_click = {
+        let inversion_preview = inversion_preview.clone();
+        move |_| {
+            send_inversions(inversion_preview.lock().unwrap().revert());
+            reseed(&saved_parameters());
+        }
+    };
 
+    let error_notifier = use_error_notifier();
     let on_save_click = move |_| {
-        // Validate and parse the PID parameters
-        set_parameters.update(|params| {
-            let params = params.as_mut().expect("Parameters should not be None");
-            params.pid.p = v_p.get().parse().unwrap_or(0.5);
-            params.pid.i = v_i.get().parse().unwrap_or(0.5);
-            params.pid.d = v_d.get().parse().unwrap_or(0.5);
-            params.x.axis_num = v_x_axis_num.get().parse().unwrap_or(0);
-            params.y.axis_num = v_y_axis_num.get().parse().unwrap_or(1);
-            params.z.axis_num = v_z_axis_num.get().parse().unwrap_or(2);
-            params.x.pulse_equivalent = v_pulse_equivalent_x.get().parse().unwrap_or(0.0);
-            params.y.pulse_equivalent = v_pulse_equivalent_y.get().parse().unwrap_or(0.0);
-            params.z.pulse_equivalent = v_pulse_equivalent_z.get().parse().unwrap_or(0.0);
-            params.x.positive_limit_io = v_positive_limit_io_x.get().parse().unwrap_or(0);
-            params.x.negative_limit_io = v_negative_limit_io_x.get().parse().unwrap_or(0);
-            params.x.zero_point_io = v_zero_point_io_x.get().parse().unwrap_or(0);
-            params.x.software_positive_limit =
-                v_software_positive_limit_x.get().parse().unwrap_or(0.0);
-            params.x.software_negative_limit =
-                v_software_negative_limit_x.get().parse().unwrap_or(0.0);
-            params.y.positive_limit_io = v_positive_limit_io_y.get().parse().unwrap_or(0);
-            params.y.negative_limit_io = v_negative_limit_io_y.get().parse().unwrap_or(0);
-            params.y.zero_point_io = v_zero_point_io_y.get().parse().unwrap_or(0);
-            params.y.software_positive_limit =
-                v_software_positive_limit_y.get().parse().unwrap_or(0.0);
-            params.y.software_negative_limit =
-                v_software_negative_limit_y.get().parse().unwrap_or(0.0);
-            params.z.positive_limit_io = v_positive_limit_io_z.get().parse().unwrap_or(0);
-            params.z.negative_limit_io = v_negative_limit_io_z.get().parse().unwrap_or(0);
-            params.z.zero_point_io = v_zero_point_io_z.get().parse().unwrap_or(0);
-            params.z.software_positive_limit =
-                v_software_positive_limit_z.get().parse().unwrap_or(0.0);
-            params.z.software_negative_limit =
-                v_software_negative_limit_z.get().parse().unwrap_or(0.0);
-            params.speed.processing_speed = v_processing_speed.get().parse().unwrap_or(0.0);
-            params.speed.max_speed = v_max_speed.get().parse().unwrap_or(0.0);
-            params.speed.acceleration = v_acceleration.get().parse().unwrap_or(0.0);
-            params.speed.deceleration = v_deceleration.get().parse().unwrap_or(0.0);
-            params.speed.transition_time = v_transition_time.get().parse().unwrap_or(0.0);
-            params.speed.crawling_speed = v_crawling_speed.get().parse().unwrap_or(0.0);
-            params.emergency_stop_io = v_emergency_stop_io.get().parse().unwrap_or(0);
-            params.door_switch_io = v_door_switch_io.get().parse().unwrap_or(0);
-            params.inverted_status.emergency_stop_level_inverted =
-                v_emergency_stop_level_inverted.get();
-            params.inverted_status.door_switch_level_inverted = v_door_switch_level_inverted.get();
-            params.inverted_status.limit_io_level_inverted = v_limit_io_level_inverted.get();
+        let mut params = parameters_tracked();
+        params.pid.p = v_p.get();
+        params.pid.i = v_i.get();
+        params.pid.d = v_d.get();
+        params.x.enabled = v_x_enabled.get();
+        params.y.enabled = v_y_enabled.get();
+        params.z.enabled = v_z_enabled.get();
+        params.x.axis_num = v_x_axis_num.get();
+        params.y.axis_num = v_y_axis_num.get();
+        params.z.axis_num = v_z_axis_num.get();
+        params.x.pulse_equivalent = v_pulse_equivalent_x.get();
+        params.y.pulse_equivalent = v_pulse_equivalent_y.get();
+        params.z.pulse_equivalent = v_pulse_equivalent_z.get();
+        params.x.positive_limit_io = v_positive_limit_io_x.get();
+        params.x.negative_limit_io = v_negative_limit_io_x.get();
+        params.x.zero_point_io = v_zero_point_io_x.get();
+        params.x.software_positive_limit = v_software_positive_limit_x.get();
+        params.x.software_negative_limit = v_software_negative_limit_x.get();
+        params.x.backlash = v_backlash_x.get();
+        params.y.positive_limit_io = v_positive_limit_io_y.get();
+        params.y.negative_limit_io = v_negative_limit_io_y.get();
+        params.y.zero_point_io = v_zero_point_io_y.get();
+        params.y.software_positive_limit = v_software_positive_limit_y.get();
+        params.y.software_negative_limit = v_software_negative_limit_y.get();
+        params.y.backlash = v_backlash_y.get();
+        params.z.positive_limit_io = v_positive_limit_io_z.get();
+        params.z.negative_limit_io = v_negative_limit_io_z.get();
+        params.z.zero_point_io = v_zero_point_io_z.get();
+        params.z.software_positive_limit = v_software_positive_limit_z.get();
+        params.z.software_negative_limit = v_software_negative_limit_z.get();
+        params.z.backlash = v_backlash_z.get();
+        params.speed.processing_speed = v_processing_speed.get();
+        params.speed.max_speed = v_max_speed.get();
+        params.speed.acceleration = v_acceleration.get();
+        params.speed.deceleration = v_deceleration.get();
+        params.speed.transition_time = v_transition_time.get();
+        params.speed.crawling_speed = v_crawling_speed.get();
+        params.speed.sramp_time = v_sramp_time.get();
+        params.spindle.poles = v_spindle_poles.get();
+        params.spindle.ratio = v_spindle_ratio.get();
+        params.spindle.spin_up_secs = v_spin_up_secs.get();
+        params.spindle.spin_down_secs = v_spin_down_secs.get();
+        params.spindle.stop_before_homing = v_stop_before_homing.get();
+        params.safe_z_height = v_safe_z_height.get();
+        params.probe_io = v_probe_io.get();
+        params.probe_max_distance = v_probe_max_distance.get();
+        params.read_attempts = v_read_attempts.get().max(1);
+        params.following_error_limit = v_following_error_limit.get().max(0.0);
+        params.stall_cycles = v_stall_cycles.get();
+        params.emergency_stop_io = v_emergency_stop_io.get();
+        params.door_switch_io = v_door_switch_io.get();
+        params.inverted_status.emergency_stop_level_inverted =
+            v_emergency_stop_level_inverted.get();
+        params.inverted_status.door_switch_level_inverted = v_door_switch_level_inverted.get();
+        params.inverted_status.limit_io_level_inverted = v_limit_io_level_inverted.get();
+        params.door_interlock = v_door_interlock.get();
+        // 服务端接受后才保存到cookie，例如轴在运动时会被拒绝
+        let inversion_preview = inversion_preview.clone();
+        spawn_local(async move {
+            match zmc_set_parameters(params.clone()).await {
+                Ok(()) => {
+                    inversion_preview.lock().unwrap().save();
+                    set_parameters.set(Some(params));
+                    log!("Parameters saved");
+                }
+                Err(e) => error_notifier.notify(
+                    "set_parameters",
+                    i18n.t("parameters_rejected").to_string(),
+                    e.to_string(),
+                ),
+            }
         });
-        let p = parameters_tracked();
+    };
+
+    // 导出服务端当前生效的参数，未保存的修改不包含在内
+    let basic_link = NodeRef::<leptos::html::A>::new();
+    let export_basic = move |_| {
         spawn_local(async move {
-            zmc_set_parameters(p)
-                .await
-                .expect("Failed to set parameters");
+            match export_parameters_as_basic().await {
+                Ok(basic) => {
+                    if let Some(link) = basic_link.get_untracked() {
+                        link.set_href(&data_url("text/plain", &basic));
+                        link.click();
+                    }
+                }
+                Err(e) => logging::error!("Failed to export parameters: {}", e),
+            }
         });
-        log!("Parameters saved");
     };
 
     view! {
@@ -239,191 +427,415 @@ fn ParametersInput() -> impl IntoView {
                 </TableHeader>
                 <TableBody>
                     <TableRow>
-                        <TableCell>"轴号"</TableCell>
+                        <TableCell>{move || i18n.t("enabled")}</TableCell>
                         <TableCell>
-                            <Input class="axis-input" value=v_x_axis_num placeholder="float" />
+                            <Checkbox checked=v_x_enabled />
                         </TableCell>
                         <TableCell>
-                            <Input class="axis-input" value=v_y_axis_num placeholder="float" />
+                            <Checkbox checked=v_y_enabled />
                         </TableCell>
                         <TableCell>
-                            <Input class="axis-input" value=v_z_axis_num placeholder="float" />
+                            <Checkbox checked=v_z_enabled />
                         </TableCell>
                     </TableRow>
                     <TableRow>
-                        <TableCell>"脉冲当量"</TableCell>
+                        <TableCell>{move || i18n.t("axis_num")}</TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
+                                class="axis-input"
+                                value=v_x_axis_num
+                                kind=NumericKind::Integer
+                            />
+                        </TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="axis-input"
+                                value=v_y_axis_num
+                                kind=NumericKind::Integer
+                            />
+                        </TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="axis-input"
+                                value=v_z_axis_num
+                                kind=NumericKind::Integer
+                            />
+                        </TableCell>
+                    </TableRow>
+                    <TableRow>
+                        <TableCell>{move || i18n.t("pulse_equivalent")}</TableCell>
+                        <TableCell>
+                            <NumericInput
                                 class="limit-input"
                                 value=v_pulse_equivalent_x
-                                placeholder="float"
+                                kind=NumericKind::Float
+                                unit="pulse/mm"
                             />
                         </TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_pulse_equivalent_y
-                                placeholder="float"
+                                kind=NumericKind::Float
+                                unit="pulse/mm"
                             />
                         </TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_pulse_equivalent_z
-                                placeholder="float"
+                                kind=NumericKind::Float
+                                unit="pulse/mm"
                             />
                         </TableCell>
                     </TableRow>
                     <TableRow>
-                        <TableCell>"正限位IO"</TableCell>
+                        <TableCell>{move || i18n.t("positive_limit_io")}</TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_positive_limit_io_x
-                                placeholder="int"
+                                kind=NumericKind::Io
+                                unit="IO#"
                             />
                         </TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_positive_limit_io_y
-                                placeholder="int"
+                                kind=NumericKind::Io
+                                unit="IO#"
                             />
                         </TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_positive_limit_io_z
-                                placeholder="int"
+                                kind=NumericKind::Io
+                                unit="IO#"
                             />
                         </TableCell>
                     </TableRow>
                     <TableRow>
-                        <TableCell>"负限位IO"</TableCell>
+                        <TableCell>{move || i18n.t("negative_limit_io")}</TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_negative_limit_io_x
-                                placeholder="int"
+                                kind=NumericKind::Io
+                                unit="IO#"
                             />
                         </TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_negative_limit_io_y
-                                placeholder="int"
+                                kind=NumericKind::Io
+                                unit="IO#"
                             />
                         </TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_negative_limit_io_z
-                                placeholder="int"
+                                kind=NumericKind::Io
+                                unit="IO#"
                             />
                         </TableCell>
                     </TableRow>
                     <TableRow>
-                        <TableCell>"零点IO"</TableCell>
+                        <TableCell>{move || i18n.t("zero_point_io")}</TableCell>
                         <TableCell>
-                            <Input class="limit-input" value=v_zero_point_io_x placeholder="int" />
+                            <NumericInput
+                                class="limit-input"
+                                value=v_zero_point_io_x
+                                kind=NumericKind::Io
+                                unit="IO#"
+                            />
                         </TableCell>
                         <TableCell>
-                            <Input class="limit-input" value=v_zero_point_io_y placeholder="int" />
+                            <NumericInput
+                                class="limit-input"
+                                value=v_zero_point_io_y
+                                kind=NumericKind::Io
+                                unit="IO#"
+                            />
                         </TableCell>
                         <TableCell>
-                            <Input class="limit-input" value=v_zero_point_io_z placeholder="int" />
+                            <NumericInput
+                                class="limit-input"
+                                value=v_zero_point_io_z
+                                kind=NumericKind::Io
+                                unit="IO#"
+                            />
                         </TableCell>
                     </TableRow>
                     <TableRow>
-                        <TableCell>"软件正限位"</TableCell>
+                        <TableCell>{move || i18n.t("software_positive_limit")}</TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_software_positive_limit_x
-                                placeholder="int"
+                                kind=NumericKind::Float
+                                unit="mm"
                             />
                         </TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_software_positive_limit_y
-                                placeholder="int"
+                                kind=NumericKind::Float
+                                unit="mm"
                             />
                         </TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_software_positive_limit_z
-                                placeholder="int"
+                                kind=NumericKind::Float
+                                unit="mm"
                             />
                         </TableCell>
                     </TableRow>
                     <TableRow>
-                        <TableCell>"软件负限位"</TableCell>
+                        <TableCell>{move || i18n.t("software_negative_limit")}</TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_software_negative_limit_x
-                                placeholder="int"
+                                kind=NumericKind::Float
+                                unit="mm"
                             />
                         </TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_software_negative_limit_y
-                                placeholder="int"
+                                kind=NumericKind::Float
+                                unit="mm"
                             />
                         </TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_software_negative_limit_z
-                                placeholder="int"
+                                kind=NumericKind::Float
+                                unit="mm"
+                            />
+                        </TableCell>
+                    </TableRow>
+                    <TableRow>
+                        <TableCell>{move || i18n.t("backlash")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_backlash_x
+                                kind=NumericKind::Float
+                                unit="mm"
+                            />
+                        </TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_backlash_y
+                                kind=NumericKind::Float
+                                unit="mm"
+                            />
+                        </TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_backlash_z
+                                kind=NumericKind::Float
+                                unit="mm"
                             />
                         </TableCell>
                     </TableRow>
                     <TableRow>
-                        <TableCell>"急停IO"</TableCell>
+                        <TableCell>{move || i18n.t("emergency_stop_io")}</TableCell>
                         <TableCell>
-                            <Input
+                            <NumericInput
                                 class="limit-input"
                                 value=v_emergency_stop_io
-                                placeholder="int"
+                                kind=NumericKind::Io
+                                unit="IO#"
                             />
                         </TableCell>
-                        <TableCell>"门限位IO"</TableCell>
+                        <TableCell>{move || i18n.t("door_switch_io")}</TableCell>
                         <TableCell>
-                            <Input class="limit-input" value=v_door_switch_io placeholder="int" />
+                            <NumericInput
+                                class="limit-input"
+                                value=v_door_switch_io
+                                kind=NumericKind::Io
+                                unit="IO#"
+                            />
                         </TableCell>
                     </TableRow>
                     <TableRow>
-                        <TableCell>"加工速度"</TableCell>
+                        <TableCell>{move || i18n.t("processing_speed")}</TableCell>
                         <TableCell>
-                            <Input class="limit-input" value=v_processing_speed placeholder="int" />
+                            <NumericInput
+                                class="limit-input"
+                                value=v_processing_speed
+                                kind=NumericKind::Float
+                                unit="mm/s"
+                            />
                         </TableCell>
-                        <TableCell>"最大速度"</TableCell>
+                        <TableCell>{move || i18n.t("max_speed")}</TableCell>
                         <TableCell>
-                            <Input class="limit-input" value=v_max_speed placeholder="int" />
+                            <NumericInput
+                                class="limit-input"
+                                value=v_max_speed
+                                kind=NumericKind::Float
+                                unit="mm/s"
+                            />
                         </TableCell>
                     </TableRow>
                     <TableRow>
-                        <TableCell>"加速度"</TableCell>
+                        <TableCell>{move || i18n.t("acceleration")}</TableCell>
                         <TableCell>
-                            <Input class="limit-input" value=v_acceleration placeholder="int" />
+                            <NumericInput
+                                class="limit-input"
+                                value=v_acceleration
+                                kind=NumericKind::Float
+                                unit="mm/s²"
+                            />
                         </TableCell>
-                        <TableCell>"减速度"</TableCell>
+                        <TableCell>{move || i18n.t("deceleration")}</TableCell>
                         <TableCell>
-                            <Input class="limit-input" value=v_deceleration placeholder="int" />
+                            <NumericInput
+                                class="limit-input"
+                                value=v_deceleration
+                                kind=NumericKind::Float
+                                unit="mm/s²"
+                            />
                         </TableCell>
                     </TableRow>
                     <TableRow>
-                        <TableCell>"过渡时间"</TableCell>
+                        <TableCell>{move || i18n.t("transition_time")}</TableCell>
                         <TableCell>
-                            <Input class="limit-input" value=v_transition_time placeholder="int" />
+                            <NumericInput
+                                class="limit-input"
+                                value=v_transition_time
+                                kind=NumericKind::Float
+                                unit="ms"
+                            />
                         </TableCell>
-                        <TableCell>"爬行速度"</TableCell>
+                        <TableCell>{move || i18n.t("crawling_speed")}</TableCell>
                         <TableCell>
-                            <Input class="limit-input" value=v_crawling_speed placeholder="int" />
+                            <NumericInput
+                                class="limit-input"
+                                value=v_crawling_speed
+                                kind=NumericKind::Float
+                                unit="mm/s"
+                            />
+                        </TableCell>
+                    </TableRow>
+                    <TableRow>
+                        <TableCell>{move || i18n.t("sramp_time")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_sramp_time
+                                kind=NumericKind::Float
+                                unit="ms"
+                            />
+                        </TableCell>
+                        <TableCell>{move || i18n.t("spindle_poles")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_spindle_poles
+                                kind=NumericKind::Integer
+                            />
+                        </TableCell>
+                    </TableRow>
+                    <TableRow>
+                        <TableCell>{move || i18n.t("spindle_ratio")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_spindle_ratio
+                                kind=NumericKind::Float
+                            />
+                        </TableCell>
+                        <TableCell>{move || i18n.t("safe_z_height")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_safe_z_height
+                                kind=NumericKind::Float
+                                unit="mm"
+                            />
+                        </TableCell>
+                    </TableRow>
+                    <TableRow>
+                        <TableCell>{move || i18n.t("spin_up_secs")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_spin_up_secs
+                                kind=NumericKind::Float
+                                unit="s"
+                            />
+                        </TableCell>
+                        <TableCell>{move || i18n.t("spin_down_secs")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_spin_down_secs
+                                kind=NumericKind::Float
+                                unit="s"
+                            />
+                        </TableCell>
+                    </TableRow>
+                    <TableRow>
+                        <TableCell>{move || i18n.t("probe_io")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_probe_io
+                                kind=NumericKind::Io
+                                unit="IO#"
+                            />
+                        </TableCell>
+                        <TableCell>{move || i18n.t("probe_max_distance")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_probe_max_distance
+                                kind=NumericKind::Float
+                                unit="mm"
+                            />
+                        </TableCell>
+                    </TableRow>
+                    <TableRow>
+                        <TableCell>{move || i18n.t("read_attempts")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_read_attempts
+                                kind=NumericKind::Integer
+                            />
+                        </TableCell>
+                        <TableCell>{move || i18n.t("following_error_limit")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_following_error_limit
+                                kind=NumericKind::Float
+                                unit="mm"
+                            />
+                        </TableCell>
+                    </TableRow>
+                    <TableRow>
+                        <TableCell>{move || i18n.t("stall_cycles")}</TableCell>
+                        <TableCell>
+                            <NumericInput
+                                class="limit-input"
+                                value=v_stall_cycles
+                                kind=NumericKind::Io
+                            />
                         </TableCell>
                     </TableRow>
                 </TableBody>
@@ -433,17 +845,27 @@ fn ParametersInput() -> impl IntoView {
             <Switch
                 checked=v_emergency_stop_level_inverted
                 value="emergency_stop_level_inverted"
-                label="急停反向"
+                label=i18n.tr("emergency_stop_inverted")
             />
             <Switch
                 checked=v_door_switch_level_inverted
                 value="emergency_stop_level_inverted"
-                label="门限位反向"
+                label=i18n.tr("door_switch_inverted")
             />
             <Switch
                 checked=v_limit_io_level_inverted
                 value="emergency_stop_level_inverted"
-                label="限位IO反向"
+                label=i18n.tr("limit_io_inverted")
+            />
+            <Switch
+                checked=v_door_interlock
+                value="door_interlock"
+                label=i18n.tr("door_interlock")
+            />
+            <Switch
+                checked=v_stop_before_homing
+                value="stop_before_homing"
+                label=i18n.tr("stop_before_homing")
             />
         </div>
         <Button
@@ -451,11 +873,75 @@ fn ParametersInput() -> impl IntoView {
             on_click=on_save_click
             disabled=Signal::derive(move || !connected())
         >
-            "Save"
+            {move || i18n.t("save")}
         </Button>
+        <Button on_click=on_cancel_click>{move || i18n.t("cancel")}</Button>
+        <Button on_click=export_basic>{move || i18n.t("export_basic")}</Button>
+        <a node_ref=basic_link download="init.bas" style="display: none;"></a>
+    }
+}
+
+// 接线检查时临时下发到控制器的输入电平反转
+#[derive(Default, Debug)]
+struct InversionPreview {
+    // 输入IO -> (已保存的反转设置, 控制器上当前的设置)，只记录两者不同的IO
+    previewed: HashMap<u16, (bool, bool)>,
+}
+
+impl InversionPreview {
+    // 切换反转时需要下发的(IO, 反转)，控制器上已是该设置的IO不再下发
+    fn toggle(&mut self, ios: &[u16], inverted: bool, saved: bool) -> Vec<(u16, bool)> {
+        let mut calls = Vec::new();
+        for &io in ios {
+            let (original, applied) = self.previewed.get(&io).copied().unwrap_or((saved, saved));
+            if applied == inverted {
+                continue;
+            }
+            calls.push((io, inverted));
+            if inverted == original {
+                self.previewed.remove(&io);
+            } else {
+                self.previewed.insert(io, (original, inverted));
+            }
+        }
+        calls
+    }
+
+    // 离开页面或取消时把预览过的IO恢复为已保存的设置
+    fn revert(&mut self) -> Vec<(u16, bool)> {
+        let mut calls: Vec<_> = self
+            .previewed
+            .drain()
+            .map(|(io, (original, _))| (io, original))
+            .collect();
+        calls.sort_unstable();
+        calls
+    }
+
+    // 保存后控制器上的设置就是已保存的设置
+    fn save(&mut self) {
+        self.previewed.clear();
     }
 }
 
+fn send_inversions(calls: Vec<(u16, bool)>) {
+    if calls.is_empty() {
+        return;
+    }
+    spawn_local(async move {
+        for (io, inverted) in calls {
+            if let Err(e) = zmc_set_in_inverted(io, inverted).await {
+                logging::error!("Failed to preview input inversion: {}", e);
+            }
+        }
+    });
+}
+
+// 任务运行中断开连接会让机床处于未知状态，需要操作者确认
+fn disconnect_requires_confirmation(connected: bool, run_state: RunState) -> bool {
+    connected && run_state == RunState::Running
+}
+
 #[component]
 fn ConnectionInput() -> impl IntoView {
     let (global_state, set_global_state) =
@@ -481,6 +967,7 @@ fn ConnectionInput() -> impl IntoView {
     }
 
     let toaster = ToasterInjection::expect_context();
+    let i18n = use_i18n();
 
     let v_ip = RwSignal::new(ip_addr.get_untracked().unwrap());
 
@@ -492,6 +979,55 @@ fn ConnectionInput() -> impl IntoView {
         },
         false,
     );
+    // 服务端已连接时显示实际连接的IP
+    Effect::new(move |_| {
+        spawn_local(async move {
+            if let Ok(ConnectionSnapshot { ip: Some(ip), .. }) = zmc_get_connection_state().await {
+                v_ip.set(ip);
+            }
+        });
+    });
+
+    let run_state = ServerSignal::new("run_state".to_string(), RunState::Idle)
+        .expect("Failed to create client signal");
+    let confirm_disconnect_open = RwSignal::new(false);
+
+    let disconnect = move || {
+        spawn_local(async move {
+            log!("Disconnecting...");
+            match zmc_close().await {
+                Ok(_) => {
+                    log!("Disconnected successfully");
+                }
+                Err(e) => {
+                    log!("Failed to disconnect: {:?}", e);
+                    // Handle the error, e.g., show a notification or alert
+                    toaster.dispatch_toast(
+                        move || {
+                            view! {
+                                <Toast>
+                                    <ToastTitle>{i18n.t("disconnection")}</ToastTitle>
+                                    <ToastBody>
+                                        {i18n.t("disconnecting_failed")}
+                                        <ToastBodySubtitle slot>"Subtitle"</ToastBodySubtitle>
+                                    </ToastBody>
+                                    <ToastFooter>"Footer"</ToastFooter>
+                                </Toast>
+                            }
+                        },
+                        Default::default(),
+                    );
+                }
+            }
+            set_global_state.update(|state| {
+                *state.as_mut().unwrap() = GlobalState::default();
+            });
+        });
+    };
+    let on_confirm_disconnect_click = move |_: MouseEvent| {
+        confirm_disconnect_open.set(false);
+        disconnect();
+    };
 
     let on_connect_click = move |e: MouseEvent| {
         if !connected() {
@@ -502,7 +1038,9 @@ fn ConnectionInput() -> impl IntoView {
                     Ok(_) => {
                         log!("Connected successfully");
                         set_global_state.update(|state| {
-                            state.as_mut().unwrap().connected = true;
+                            let state = state.as_mut().unwrap();
+                            state.connected = true;
+                            state.controller_kind = ControllerKind::Real;
                         });
                     }
                     Err(e) => {
@@ -513,9 +1051,9 @@ fn ConnectionInput() -> impl IntoView {
                             move || {
                                 view! {
                                     <Toast>
-                                        <ToastTitle>"Connection"</ToastTitle>
+                                        <ToastTitle>{i18n.t("connection")}</ToastTitle>
                                         <ToastBody>
-                                            "Connecting failed"
+                                            {i18n.t("connecting_failed")}
                                             <ToastBodySubtitle slot>"Subtitle"</ToastBodySubtitle>
                                         </ToastBody>
                                         <ToastFooter>"Footer"</ToastFooter>
@@ -527,37 +1065,10 @@ fn ConnectionInput() -> impl IntoView {
                     }
                 }
             });
+        } else if disconnect_requires_confirmation(connected(), run_state.get_untracked()) {
+            confirm_disconnect_open.set(true);
         } else {
-            spawn_local(async move {
-                log!("Disconnecting...");
-                match zmc_close().await {
-                    Ok(_) => {
-                        log!("Disconnected successfully");
-                    }
-                    Err(e) => {
-                        log!("Failed to disconnect: {:?}", e);
-                        // Handle the error, e.g., show a notification or alert
-                        toaster.dispatch_toast(
-                            move || {
-                                view! {
-                                    <Toast>
-                                        <ToastTitle>"Disconnection"</ToastTitle>
-                                        <ToastBody>
-                                            "Disconnecting failed"
-                                            <ToastBodySubtitle slot>"Subtitle"</ToastBodySubtitle>
-                                        </ToastBody>
-                                        <ToastFooter>"Footer"</ToastFooter>
-                                    </Toast>
-                                }
-                            },
-                            Default::default(),
-                        );
-                    }
-                }
-                set_global_state.update(|state| {
-                    state.as_mut().unwrap().connected = false;
-                });
-            });
+            disconnect();
         }
     };
     view! {
@@ -568,7 +1079,88 @@ fn ConnectionInput() -> impl IntoView {
                 if connected() { ButtonAppearance::Primary } else { ButtonAppearance::Secondary }
             })
         >
-            {move || { if connected() { "Disconnect" } else { "Connect" } }}
+            {move || { if connected() { i18n.t("disconnect") } else { i18n.t("connect") } }}
         </Button>
+        <Dialog open=confirm_disconnect_open>
+            <DialogSurface>
+                <DialogBody>
+                    <DialogTitle>{move || i18n.t("confirm_disconnect")}</DialogTitle>
+                    <DialogContent>{move || i18n.t("confirm_disconnect_body")}</DialogContent>
+                    <DialogActions>
+                        <Button on_click=move |_| confirm_disconnect_open.set(false)>
+                            {move || i18n.t("cancel")}
+                        </Button>
+                        <Button
+                            appearance=ButtonAppearance::Primary
+                            on_click=on_confirm_disconnect_click
+                        >
+                            {move || i18n.t("disconnect")}
+                        </Button>
+                    </DialogActions>
+                </DialogBody>
+            </DialogSurface>
+        </Dialog>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggling_an_inversion_sends_one_call_per_input() {
+        let mut preview = InversionPreview::default();
+        // 两个限位接在同一个输入上时也只下发一次
+        assert_eq!(
+            preview.toggle(&[3, 4, 3], true, false),
+            [(3, true), (4, true)]
+        );
+        assert_eq!(preview.toggle(&[3, 4], true, false), []);
+        assert_eq!(preview.toggle(&[7], false, false), []);
+    }
+
+    #[test]
+    fn toggling_back_to_the_saved_setting_needs_no_revert() {
+        let mut preview = InversionPreview::default();
+        preview.toggle(&[3], true, false);
+        assert_eq!(preview.toggle(&[3], false, false), [(3, false)]);
+        assert_eq!(preview.revert(), []);
+    }
+
+    #[test]
+    fn reverting_restores_the_saved_settings_once() {
+        let mut preview = InversionPreview::default();
+        preview.toggle(&[3, 4], true, false);
+        preview.toggle(&[9], false, true);
+        assert_eq!(preview.revert(), [(3, false), (4, false), (9, true)]);
+        assert_eq!(preview.revert(), []);
+    }
+
+    #[test]
+    fn saved_previews_are_not_reverted() {
+        let mut preview = InversionPreview::default();
+        preview.toggle(&[3], true, false);
+        preview.save();
+        assert_eq!(preview.revert(), []);
+        // 保存后按新的设置比较
+        assert_eq!(preview.toggle(&[3], true, true), []);
+    }
+
+    #[test]
+    fn only_disconnecting_from_a_running_job_needs_confirmation() {
+        assert!(disconnect_requires_confirmation(true, RunState::Running));
+        for state in [
+            RunState::Idle,
+            RunState::Paused,
+            RunState::Completed,
+            RunState::Error,
+        ] {
+            assert!(
+                !disconnect_requires_confirmation(true, state),
+                "{:?}",
+                state
+            );
+        }
+        assert!(!disconnect_requires_confirmation(false, RunState::Running));
     }
 }
@@ -0,0 +1,225 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use leptos::logging;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use leptos_ws::ServerSignal;
+use web_sys::wasm_bindgen::{prelude::Closure, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
+
+use crate::api::zmc_get_path_image;
+use crate::model::{PathImageDelta, PathImageSnapshot, PathTile};
+
+// 等待绘制到画布上的图像
+enum PendingDraw {
+    // 完整图像，绘制前按图像大小重置画布
+    Full(PathImageSnapshot),
+    Tile(PathTile),
+}
+
+impl PendingDraw {
+    fn data_url(&self) -> &str {
+        match self {
+            PendingDraw::Full(snapshot) => &snapshot.data_url,
+            PendingDraw::Tile(tile) => &tile.data_url,
+        }
+    }
+}
+
+// 在离屏画布上合成实时路径图：先绘制完整图像，再依次覆盖服务端推送的变化区域。
+// 图像是异步加载的，逐个绘制以保证后推送的区域覆盖先推送的
+struct PathCompositor {
+    canvas: HtmlCanvasElement,
+    ctx: CanvasRenderingContext2d,
+    queue: VecDeque<PendingDraw>,
+    drawing: bool,
+    // 画布内容是否有尚未输出的变化
+    changed: bool,
+    // 已排入绘制队列的最新序号，None表示还没有完整图像
+    seq: Option<u64>,
+    // 最近一幅完整图像的大小
+    size: (u32, u32),
+    fetching: bool,
+    // 获取完整图像期间收到的增量
+    buffered: Vec<PathImageDelta>,
+    output: WriteSignal<String>,
+}
+
+type SharedCompositor = Rc<RefCell<PathCompositor>>;
+
+impl PathCompositor {
+    fn new(output: WriteSignal<String>) -> Option<SharedCompositor> {
+        let canvas: HtmlCanvasElement =
+            document().create_element("canvas").ok()?.dyn_into().ok()?;
+        let ctx = canvas.get_context("2d").ok()??.dyn_into().ok()?;
+        Some(Rc::new(RefCell::new(Self {
+            canvas,
+            ctx,
+            queue: VecDeque::new(),
+            drawing: false,
+            changed: false,
+            seq: None,
+            size: (0, 0),
+            fetching: false,
+            buffered: Vec::new(),
+            output,
+        })))
+    }
+
+    // 区域图像替换画布上对应的像素，包括透明部分
+    fn draw(&mut self, image: &HtmlImageElement, pending: &PendingDraw) {
+        let result = match pending {
+            PendingDraw::Full(snapshot) => {
+                // 重设大小会清空画布
+                self.canvas.set_width(snapshot.width);
+                self.canvas.set_height(snapshot.height);
+                self.ctx.draw_image_with_html_image_element(image, 0.0, 0.0)
+            }
+            PendingDraw::Tile(tile) => {
+                let (x, y) = (tile.x as f64, tile.y as f64);
+                self.ctx
+                    .clear_rect(x, y, tile.width as f64, tile.height as f64);
+                self.ctx.draw_image_with_html_image_element(image, x, y)
+            }
+        };
+        if let Err(e) = result {
+            logging::error!("Failed to draw path image: {:?}", e);
+        }
+        self.changed = true;
+    }
+}
+
+// 获取完整图像，之后只应用序号更大的增量
+fn request_snapshot(compositor: &SharedCompositor) {
+    {
+        let mut state = compositor.borrow_mut();
+        state.seq = None;
+        if state.fetching {
+            return;
+        }
+        state.fetching = true;
+    }
+    let compositor = compositor.clone();
+    spawn_local(async move {
+        let snapshot = zmc_get_path_image().await;
+        let buffered = {
+            let mut state = compositor.borrow_mut();
+            state.fetching = false;
+            match snapshot {
+                Ok(snapshot) => {
+                    state.seq = Some(snapshot.seq);
+                    state.size = (snapshot.width, snapshot.height);
+                    state.queue.clear();
+                    state.queue.push_back(PendingDraw::Full(snapshot));
+                }
+                Err(e) => logging::error!("Failed to get path image: {}", e),
+            }
+            std::mem::take(&mut state.buffered)
+        };
+        for delta in buffered {
+            apply_delta(&compositor, delta);
+        }
+        draw_next(&compositor);
+    });
+}
+
+fn apply_delta(compositor: &SharedCompositor, delta: PathImageDelta) {
+    {
+        let mut state = compositor.borrow_mut();
+        let (current, fetching) = (state.seq, state.fetching);
+        // 服务端重新分配了图像，旧图像上无法拼接
+        let resized = (delta.width, delta.height) != state.size;
+        match current {
+            None if fetching => {
+                state.buffered.push(delta);
+                return;
+            }
+            Some(_) if resized => {
+                drop(state);
+                request_snapshot(compositor);
+                return;
+            }
+            // 已包含在完整图像或之前的增量中
+            Some(seq) if delta.seq <= seq => return,
+            Some(seq) if delta.seq == seq + 1 => {
+                state.seq = Some(delta.seq);
+                state
+                    .queue
+                    .extend(delta.tiles.into_iter().map(PendingDraw::Tile));
+            }
+            // 缺少中间的增量或上次获取失败，重新获取完整图像
+            _ => {
+                drop(state);
+                request_snapshot(compositor);
+                return;
+            }
+        }
+    }
+    draw_next(compositor);
+}
+
+// 加载并绘制队列中的下一幅图像，队列清空后输出画布内容
+fn draw_next(compositor: &SharedCompositor) {
+    let pending = {
+        let mut state = compositor.borrow_mut();
+        if state.drawing {
+            return;
+        }
+        let Some(pending) = state.queue.pop_front() else {
+            if std::mem::take(&mut state.changed) {
+                let data_url = state.canvas.to_data_url().unwrap_or_default();
+                state.output.set(data_url);
+            }
+            return;
+        };
+        state.drawing = true;
+        pending
+    };
+    let Ok(image) = HtmlImageElement::new() else {
+        compositor.borrow_mut().drawing = false;
+        return;
+    };
+    let src = pending.data_url().to_string();
+    let loaded_image = image.clone();
+    let compositor = compositor.clone();
+    // 加载成功和失败只会触发一个，同一个回调同时用于两者
+    let on_done = Closure::once_into_js(move || {
+        {
+            let mut state = compositor.borrow_mut();
+            if loaded_image.natural_width() > 0 {
+                state.draw(&loaded_image, &pending);
+            }
+            state.drawing = false;
+        }
+        draw_next(&compositor);
+    });
+    image.set_onload(Some(on_done.unchecked_ref()));
+    image.set_onerror(Some(on_done.unchecked_ref()));
+    image.set_src(&src);
+}
+
+// 订阅服务端推送的路径图增量，返回合成后的完整路径图data URL，还没有图像时为空
+pub fn use_live_path_image() -> ReadSignal<String> {
+    let (image, set_image) = signal(String::new());
+    let delta = ServerSignal::new("path_img_delta".to_string(), PathImageDelta::default())
+        .expect("Failed to create client signal");
+    Effect::new(move |compositor: Option<Option<SharedCompositor>>| {
+        let compositor = compositor.unwrap_or_else(|| {
+            let compositor = PathCompositor::new(set_image);
+            if let Some(compositor) = &compositor {
+                request_snapshot(compositor);
+            }
+            compositor
+        });
+        let delta = delta.get();
+        if let Some(compositor) = &compositor {
+            if delta.seq > 0 {
+                apply_delta(compositor, delta);
+            }
+        }
+        compositor
+    });
+    image
+}
@@ -0,0 +1,70 @@
+use crate::api::get_run_history;
+use crate::components::command_log::format_timestamp;
+use crate::i18n::{use_i18n, I18n};
+use crate::model::{RunOutcome, RunRecord};
+use leptos::{logging, prelude::*, task::spawn_local};
+use thaw::*;
+
+fn status_text(i18n: I18n, status: RunOutcome) -> &'static str {
+    match status {
+        RunOutcome::Completed => i18n.t("run_completed"),
+        RunOutcome::Error => i18n.t("run_failed"),
+        RunOutcome::Stopped => i18n.t("run_stopped"),
+        RunOutcome::Aborted => i18n.t("run_aborted"),
+    }
+}
+
+// 历次G代码运行的生产记录，最新的在前
+#[component]
+pub fn RunHistoryView() -> impl IntoView {
+    let i18n = use_i18n();
+    let records = RwSignal::new(Vec::<RunRecord>::new());
+    let refresh = move || {
+        spawn_local(async move {
+            match get_run_history().await {
+                Ok(history) => records.set(history),
+                Err(e) => logging::error!("Failed to get run history: {}", e),
+            }
+        });
+    };
+    Effect::new(move |_| refresh());
+
+    view! {
+        <div class="run-history-container">
+            <div class="control-panel">
+                <Button on_click=move |_| refresh()>{move || i18n.t("refresh")}</Button>
+            </div>
+            <Table>
+                <TableHeader>
+                    <TableRow>
+                        <TableCell>{move || i18n.t("run_started")}</TableCell>
+                        <TableCell>{move || i18n.t("run_ended")}</TableCell>
+                        <TableCell>{move || i18n.t("file_name")}</TableCell>
+                        <TableCell>{move || i18n.t("run_result")}</TableCell>
+                        <TableCell>{move || i18n.t("line_count")}</TableCell>
+                    </TableRow>
+                </TableHeader>
+                <TableBody>
+                    {move || {
+                        records
+                            .get()
+                            .into_iter()
+                            .map(|record| {
+                                let status = record.status;
+                                view! {
+                                    <TableRow>
+                                        <TableCell>{format_timestamp(record.started_ms)}</TableCell>
+                                        <TableCell>{format_timestamp(record.ended_ms)}</TableCell>
+                                        <TableCell>{record.file_name}</TableCell>
+                                        <TableCell>{move || status_text(i18n, status)}</TableCell>
+                                        <TableCell>{record.lines}</TableCell>
+                                    </TableRow>
+                                }
+                            })
+                            .collect_view()
+                    }}
+                </TableBody>
+            </Table>
+        </div>
+    }
+}
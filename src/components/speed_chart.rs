@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+
+use leptos::{html::Canvas, prelude::*, server::codee::string::JsonSerdeCodec};
+use leptos_use::use_cookie;
+use leptos_ws::ServerSignal;
+use thaw::*;
+use web_sys::js_sys::Date;
+use web_sys::wasm_bindgen::JsCast;
+use web_sys::CanvasRenderingContext2d;
+
+use crate::i18n::use_i18n;
+use crate::model::{MoveStatus, UiPreferences};
+
+// 图表显示最近多长时间的速度(毫秒)
+const SPEED_WINDOW_MS: f64 = 30_000.0;
+const CHART_WIDTH: f64 = 400.0;
+const CHART_HEIGHT: f64 = 150.0;
+// X、Y、Z轴曲线的颜色
+const AXIS_COLORS: [&str; 3] = ["#dc2626", "#16a34a", "#2563eb"];
+
+// 各轴速度的采样(时间戳ms, [X, Y, Z])，只保留最近window_ms内的样本
+#[derive(Debug, Clone)]
+struct SpeedSamples {
+    window_ms: f64,
+    samples: VecDeque<(f64, [f32; 3])>,
+}
+
+impl SpeedSamples {
+    fn new(window_ms: f64) -> Self {
+        Self {
+            window_ms,
+            samples: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, time_ms: f64, speeds: [f32; 3]) {
+        self.samples.push_back((time_ms, speeds));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(t, _)| time_ms - t > self.window_ms)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    // 纵轴范围，总是包含0，没有速度时为0~1
+    fn range(&self) -> (f32, f32) {
+        let (low, high) = self
+            .samples
+            .iter()
+            .flat_map(|(_, speeds)| speeds.iter().copied())
+            .fold((0.0f32, 0.0f32), |(low, high), v| (low.min(v), high.max(v)));
+        if high - low < f32::EPSILON {
+            (0.0, 1.0)
+        } else {
+            (low, high)
+        }
+    }
+}
+
+// 最新的样本在最右侧，横轴为时间窗口
+fn draw_speeds(ctx: &CanvasRenderingContext2d, samples: &SpeedSamples, decimals: usize) {
+    ctx.clear_rect(0.0, 0.0, CHART_WIDTH, CHART_HEIGHT);
+    let Some((latest, _)) = samples.samples.back() else {
+        return;
+    };
+    let (low, high) = samples.range();
+    let to_x = |t: f64| CHART_WIDTH - (latest - t) / samples.window_ms * CHART_WIDTH;
+    let to_y = |v: f32| CHART_HEIGHT - ((v - low) / (high - low)) as f64 * CHART_HEIGHT;
+
+    // 零速度线
+    ctx.set_stroke_style(&"#ccc".into());
+    ctx.set_line_width(1.0);
+    ctx.begin_path();
+    ctx.move_to(0.0, to_y(0.0));
+    ctx.line_to(CHART_WIDTH, to_y(0.0));
+    ctx.stroke();
+
+    for (axis, color) in AXIS_COLORS.iter().enumerate() {
+        ctx.set_stroke_style(&(*color).into());
+        ctx.set_line_width(1.5);
+        ctx.begin_path();
+        for (i, (t, speeds)) in samples.samples.iter().enumerate() {
+            let (x, y) = (to_x(*t), to_y(speeds[axis]));
+            if i == 0 {
+                ctx.move_to(x, y);
+            } else {
+                ctx.line_to(x, y);
+            }
+        }
+        ctx.stroke();
+    }
+
+    ctx.set_fill_style(&"#666".into());
+    let _ = ctx.fill_text(&format!("{:.*}", decimals, high), 2.0, 12.0);
+    let _ = ctx.fill_text(&format!("{:.*}", decimals, low), 2.0, CHART_HEIGHT - 2.0);
+}
+
+// 各轴速度随时间变化的滚动曲线，用于诊断颤振等问题
+#[component]
+pub fn SpeedChart() -> impl IntoView {
+    let i18n = use_i18n();
+    let canvas_ref = NodeRef::<Canvas>::new();
+    let samples = StoredValue::new_local(SpeedSamples::new(SPEED_WINDOW_MS));
+    let paused = RwSignal::new(false);
+    let (ui_preferences, _) = use_cookie::<UiPreferences, JsonSerdeCodec>("ui_preferences_cookie");
+    let move_status = ServerSignal::new("move_status".to_string(), MoveStatus::default())
+        .expect("Failed to create client signal");
+
+    let redraw = move || {
+        let Some(canvas) = canvas_ref.get_untracked() else {
+            return;
+        };
+        let Some(ctx) = canvas
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .and_then(|ctx| ctx.dyn_into::<CanvasRenderingContext2d>().ok())
+        else {
+            return;
+        };
+        let decimals = ui_preferences
+            .get_untracked()
+            .unwrap_or_default()
+            .speed_decimals;
+        samples.with_value(|samples| draw_speeds(&ctx, samples, decimals));
+    };
+
+    // 暂停时不再采样，曲线停在暂停时的样子
+    Effect::new(move |_| {
+        let status = move_status.get();
+        if paused.get_untracked() {
+            return;
+        }
+        let speeds = [status.x.speed, status.y.speed, status.z.speed];
+        samples.update_value(|samples| samples.push(Date::now(), speeds));
+        redraw();
+    });
+
+    let clear = move |_| {
+        samples.update_value(|samples| samples.clear());
+        redraw();
+    };
+
+    view! {
+        <div class="speed-chart">
+            <div class="control-panel">
+                <span>{move || i18n.t("speed_chart")}</span>
+                <Button on_click=move |_| paused.update(|paused| *paused = !*paused)>
+                    {move || {
+                        if paused.get() { i18n.t("resume_chart") } else { i18n.t("pause_chart") }
+                    }}
+                </Button>
+                <Button on_click=clear>{move || i18n.t("clear_chart")}</Button>
+                {["X", "Y", "Z"]
+                    .into_iter()
+                    .zip(AXIS_COLORS)
+                    .map(|(axis, color)| {
+                        view! { <span style=format!("color: {}", color)>{axis}</span> }
+                    })
+                    .collect_view()}
+            </div>
+            <canvas
+                width=CHART_WIDTH.to_string()
+                height=CHART_HEIGHT.to_string()
+                style="border: 1px solid #ddd; background-color: #fafafa;"
+                node_ref=canvas_ref
+            />
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_older_than_the_window_are_dropped() {
+        let mut samples = SpeedSamples::new(1000.0);
+        for time_ms in [0.0, 400.0, 800.0, 1200.0, 1600.0] {
+            samples.push(time_ms, [time_ms as f32, 0.0, 0.0]);
+        }
+        let times: Vec<f64> = samples.samples.iter().map(|(t, _)| *t).collect();
+        assert_eq!(times, [800.0, 1200.0, 1600.0]);
+        // 刚好在窗口边上的样本保留
+        samples.push(1800.0, [0.0; 3]);
+        assert_eq!(samples.samples.front().unwrap().0, 800.0);
+    }
+
+    #[test]
+    fn the_range_always_includes_zero() {
+        let mut samples = SpeedSamples::new(1000.0);
+        assert_eq!(samples.range(), (0.0, 1.0));
+        samples.push(0.0, [5.0, -2.0, 3.0]);
+        assert_eq!(samples.range(), (-2.0, 5.0));
+        samples.clear();
+        samples.push(0.0, [4.0, 4.0, 4.0]);
+        assert_eq!(samples.range(), (0.0, 4.0));
+    }
+}
@@ -1,32 +1,176 @@
-use crate::api::zmc_clear_path;
+use crate::api::{
+    export_path_dxf, set_preview_style, zmc_clear_path, zmc_reset_alarm, zmc_set_path_style,
+    zmc_set_polling_interval,
+};
 use crate::app::GlobalState;
-use crate::model::MoveStatus;
+use crate::components::command_log::data_url;
+use crate::components::path_tiles::use_live_path_image;
+use crate::components::speed_chart::SpeedChart;
+use crate::i18n::{use_i18n, I18n};
+use crate::model::{
+    fit_to_range, fitted_bitmap_size, format_decimals, is_image_data_url, AxisMoveStatus,
+    DepthRange, JogPreview, MotionKind, MoveStatus, Parameters, PathBounds, PathImageFormat,
+    PathImageStyle, PathOrientation, PathOrigin, UiPreferences,
+};
 use lazy_static::lazy_static;
 use leptos::html::Canvas;
 use leptos::prelude::*;
 use leptos::task::spawn_local;
 use leptos::{logging, prelude::*, server::codee::string::JsonSerdeCodec};
-use leptos_use::storage::use_storage;
+use leptos_use::storage::{use_storage, StorageType};
 use leptos_use::{use_cookie, watch_debounced};
 use leptos_ws::ServerSignal;
 use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
 use thaw::*;
-use web_sys::wasm_bindgen::JsCast;
+use web_sys::js_sys::Array;
+use web_sys::wasm_bindgen::{prelude::Closure, JsCast};
 use web_sys::CanvasRenderingContext2d;
 
+// 画布上每毫米对应的单位数，与服务端路径图的默认比例一致
+const BITMAP_PX_PER_MM: f64 = 4.0;
+// 服务端实时路径图的边长(像素)
+const PATH_IMAGE_SIZE: usize = 500;
+// 每个坐标轴最多显示的刻度标签数
+const MAX_TICK_LABELS: usize = 10;
+
+// 取不小于raw的整齐步长（1、2、5乘以10的幂）
+fn nice_step(raw: f64) -> f64 {
+    if raw <= 0.0 || !raw.is_finite() {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let normalized = raw / magnitude;
+    let nice = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+// 生成[min, max]范围内对齐到整齐步长的刻度值，数量不超过max_ticks
+fn axis_ticks(min: f64, max: f64, max_ticks: usize) -> Vec<f64> {
+    if max <= min || max_ticks == 0 {
+        return Vec::new();
+    }
+    let step = nice_step((max - min) / max_ticks as f64);
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+// RGB转#rrggbb，供颜色选择框使用
+fn rgb_to_hex(color: [u8; 4]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+// #rrggbb转RGB，解析失败时返回None
+fn hex_to_rgb(hex: &str) -> Option<[u8; 3]> {
+    let hex = hex.strip_prefix('#')?;
+    // 只接受6位十六进制数字，按字节切分前要排除多字节字符和from_str_radix接受的+号
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+// 机床坐标(mm)按显示方向变换后转换为SVG屏幕坐标，机床Y轴向上而SVG的Y轴向下
+fn world_to_screen(
+    x: f64,
+    y: f64,
+    orientation: PathOrientation,
+    zoom: f64,
+    offset_x: f64,
+    offset_y: f64,
+) -> (f64, f64) {
+    let (x, y) = orientation.apply(x, y);
+    let ppm = zoom * BITMAP_PX_PER_MM;
+    (offset_x + x * ppm, offset_y - y * ppm)
+}
+
+fn format_tick(value: f64) -> String {
+    // 去掉浮点误差带来的多余小数
+    let rounded = (value * 1000.0).round() / 1000.0;
+    format!("{}", rounded)
+}
+
+// 视图的缩放和平移，保存在localStorage中，刷新页面后恢复
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+struct ViewPreferences {
+    zoom: f64,
+    offset_x: f64,
+    offset_y: f64,
+}
+
+impl Default for ViewPreferences {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            offset_x: 200.0,
+            offset_y: 200.0,
+        }
+    }
+}
+
+impl ViewPreferences {
+    // 需要写入localStorage的视图，默认视图返回None表示清除保存的值
+    fn to_store(self) -> Option<Self> {
+        (self != Self::default()).then_some(self)
+    }
+}
+
+// 保存视图时的防抖间隔(ms)，拖动平移时不必每次移动都写入
+const VIEW_SAVE_DEBOUNCE_MS: f64 = 300.0;
+
+// 返回缩放和X/Y偏移信号，挂载后恢复key下保存的视图，之后的改变写回localStorage。
+// 恢复为默认视图时清除保存的值
+fn use_view_preferences(key: &'static str) -> (RwSignal<f64>, RwSignal<f64>, RwSignal<f64>) {
+    let default = ViewPreferences::default();
+    let zoom = RwSignal::new(default.zoom);
+    let offset_x = RwSignal::new(default.offset_x);
+    let offset_y = RwSignal::new(default.offset_y);
+    let (stored, set_stored, clear_stored) =
+        use_storage::<ViewPreferences, JsonSerdeCodec>(StorageType::Local, key);
+    // 只在客户端运行，避免服务端渲染的默认视图与保存的视图不一致
+    Effect::new(move |_| {
+        let view = stored.get_untracked();
+        zoom.set(view.zoom);
+        offset_x.set(view.offset_x);
+        offset_y.set(view.offset_y);
+    });
+    let _ = watch_debounced(
+        move || ViewPreferences {
+            zoom: zoom.get(),
+            offset_x: offset_x.get(),
+            offset_y: offset_y.get(),
+        },
+        move |view, _, _| match view.to_store() {
+            Some(view) => set_stored.set(view),
+            None => clear_stored(),
+        },
+        VIEW_SAVE_DEBOUNCE_MS,
+    );
+    (zoom, offset_x, offset_y)
+}
+
 #[component]
 pub fn PathVisualizer() -> impl IntoView {
-    // Subscribe to the svg_path signal from the server
-    let path_img = ServerSignal::new("path_img".to_string(), String::new())
-        .expect("Failed to create client signal");
+    // Live path image, composited from the changed regions pushed by the server
+    let path_img = use_live_path_image();
     let path_img_preview = ServerSignal::new("path_img_preview".to_string(), String::new())
         .expect("Failed to create client signal");
 
-    // Create some states for visualization controls
-    let zoom = RwSignal::new(1.0);
-    let offset_x = RwSignal::new(200.0);
-    let offset_y = RwSignal::new(200.0);
+    let i18n = use_i18n();
+
+    // Create some states for visualization controls, restored from the last visit
+    let (zoom, offset_x, offset_y) = use_view_preferences("path_visualizer_view");
 
     // Mouse interaction states
     let dragging = RwSignal::new(false);
@@ -68,36 +212,430 @@ pub fn PathVisualizer() -> impl IntoView {
         dragging.set(false);
     };
 
-    // Function to reset the view
+    // Function to reset the view, which also clears the stored view
     let reset_view = move |_| {
-        zoom.set(1.0);
-        offset_x.set(200.0);
-        offset_y.set(200.0);
+        let default = ViewPreferences::default();
+        zoom.set(default.zoom);
+        offset_x.set(default.offset_x);
+        offset_y.set(default.offset_y);
+    };
+
+    // Soft limits of X/Y from the parameters cookie, drawn as the work area
+    let (parameters, _) = use_cookie::<Parameters, JsonSerdeCodec>("parameters_cookie");
+    let work_area = move || parameters.get().unwrap_or_default().work_area();
+    // Bounding box of the path the machine has travelled so far
+    let path_bounds = RwSignal::new(None::<PathBounds>);
+    // 颜色只能大致反映深度，另外统计走过的最浅和最深Z值
+    let depth_range = RwSignal::new(None::<DepthRange>);
+    let move_status = ServerSignal::new("move_status".to_string(), MoveStatus::default())
+        .expect("Failed to create client signal");
+    let move_status = Signal::derive(move || move_status.get());
+    Effect::new(move |_| {
+        let status = move_status.get();
+        let (x, y) = (status.x.pos, status.y.pos);
+        path_bounds.update(|bounds| match bounds {
+            Some(bounds) => bounds.include(x, y),
+            None => *bounds = Some(PathBounds::from_point(x, y)),
+        });
+        depth_range.update(|range| match range {
+            Some(range) => range.include(status.z.pos),
+            None => *range = Some(DepthRange::from_point(status.z.pos)),
+        });
+    });
+    let out_of_limits = move || {
+        path_bounds
+            .get()
+            .is_some_and(|bounds| !bounds.within(&work_area()))
     };
 
     let clear_view = move |_| {
+        path_bounds.set(None);
+        depth_range.set(None);
         // Clear the path image
         spawn_local(async move {
             zmc_clear_path().await.expect("Failed to clear path");
         });
     };
 
+    // Download the travelled path as DXF through a hidden link
+    let dxf_link = NodeRef::<leptos::html::A>::new();
+    let export_dxf = move |_| {
+        spawn_local(async move {
+            match export_path_dxf().await {
+                Ok(dxf) => {
+                    if let Some(link) = dxf_link.get_untracked() {
+                        link.set_href(&data_url("application/dxf", &dxf));
+                        link.click();
+                    }
+                }
+                Err(e) => logging::error!("Failed to export DXF: {}", e),
+            }
+        });
+    };
+
+    // Background and line width of the path images, stored in a cookie and pushed to the server
+    let (path_image_style, set_path_image_style) =
+        use_cookie::<PathImageStyle, JsonSerdeCodec>("path_image_style_cookie");
+    // Ensure path image style is initialized
+    if path_image_style.read_untracked().is_none() {
+        set_path_image_style.set(Some(PathImageStyle::default()));
+    }
+    let style = path_image_style.get_untracked().unwrap_or_default();
+    let background_color = RwSignal::new(rgb_to_hex(style.background));
+    let background_alpha = RwSignal::new(style.background[3]);
+    let line_width = RwSignal::new(style.line_width);
+    let orientation = RwSignal::new(style.orientation);
+    let overlay_preview = RwSignal::new(style.overlay_preview);
+    let origin = RwSignal::new(style.origin);
+    let fit_work_area = RwSignal::new(style.fit_work_area);
+    let image_format = RwSignal::new(style.format);
+    let jpeg_quality = RwSignal::new(style.jpeg_quality);
+    let distinguish_rapids = RwSignal::new(style.distinguish_rapids);
+    Effect::watch(
+        move || {
+            let [r, g, b] = hex_to_rgb(&background_color.get()).unwrap_or([255, 255, 255]);
+            PathImageStyle {
+                background: [r, g, b, background_alpha.get()],
+                line_width: line_width.get(),
+                orientation: orientation.get(),
+                overlay_preview: overlay_preview.get(),
+                origin: origin.get(),
+                fit_work_area: fit_work_area.get(),
+                format: image_format.get(),
+                jpeg_quality: jpeg_quality.get(),
+                distinguish_rapids: distinguish_rapids.get(),
+            }
+        },
+        move |style, _, _| {
+            set_path_image_style.set(Some(style.clone()));
+            let style = style.clone();
+            spawn_local(async move {
+                zmc_set_path_style(style.clone())
+                    .await
+                    .expect("Failed to set path style");
+                set_preview_style(style)
+                    .await
+                    .expect("Failed to set preview style");
+            });
+        },
+        true,
+    );
+
+    // 实时路径图在画布上的位置和边长：适配加工区域时按与服务端相同的方法计算比例和零点，
+    // 图像按比例缩放，使其与以BITMAP_PX_PER_MM绘制的网格、加工区域对齐
+    let image_placement = move || {
+        let area = work_area().oriented(orientation.get());
+        let (x_range, y_range) = ((area.min_x, area.max_x), (area.min_y, area.max_y));
+        let (width, height) = if fit_work_area.get() {
+            fitted_bitmap_size(x_range, y_range)
+        } else {
+            (PATH_IMAGE_SIZE, PATH_IMAGE_SIZE)
+        };
+        let fit = fit_work_area
+            .get()
+            .then(|| fit_to_range(width, height, x_range, y_range))
+            .flatten();
+        let (scale, (fraction_x, fraction_y)) = match fit {
+            Some(fit) => (fit.scale, fit.origin),
+            None => (BITMAP_PX_PER_MM as f32, origin.get().fraction()),
+        };
+        let width = width as f32 * BITMAP_PX_PER_MM as f32 / scale;
+        let height = height as f32 * BITMAP_PX_PER_MM as f32 / scale;
+        (-fraction_x * width, -fraction_y * height, width, height)
+    };
+
+    // Live tool position, drawn outside the transformed group so the crosshair keeps its size
+    let tool_screen_pos = move || {
+        let status = move_status.get();
+        world_to_screen(
+            status.x.pos as f64,
+            status.y.pos as f64,
+            orientation.get(),
+            zoom(),
+            offset_x(),
+            offset_y(),
+        )
+    };
+    // 单步点动预览的目标位置，确认前以虚线标记显示
+    let (jog_preview, _) = use_cookie::<JogPreview, JsonSerdeCodec>("jog_preview_cookie");
+    let jog_ghost = move || {
+        jog_preview.get().map(|target| {
+            let (x, y) = world_to_screen(
+                target.x as f64,
+                target.y as f64,
+                orientation.get(),
+                zoom(),
+                offset_x(),
+                offset_y(),
+            );
+            let within = PathBounds::from_point(target.x, target.y).within(&work_area());
+            (x, y, within)
+        })
+    };
+    let (ui_preferences, _) = use_cookie::<UiPreferences, JsonSerdeCodec>("ui_preferences_cookie");
+    let position_text = move || {
+        let status = move_status.get();
+        let prefs = ui_preferences.get().unwrap_or_default();
+        format!(
+            "X {}  Y {}  Z {}",
+            prefs.format_position(status.x.pos as f64),
+            prefs.format_position(status.y.pos as f64),
+            prefs.format_position(status.z.pos as f64),
+        )
+    };
+
+    let depth_text = move || {
+        let z = move_status.get().z.pos as f64;
+        let prefs = ui_preferences.get().unwrap_or_default();
+        match depth_range.get() {
+            Some(range) => format!(
+                "{} {}  {} {}  {} {}",
+                i18n.t("depth_current"),
+                prefs.format_position(z),
+                i18n.t("depth_min"),
+                prefs.format_position(range.min as f64),
+                i18n.t("depth_max"),
+                prefs.format_position(range.max as f64),
+            ),
+            None => format!("{} {}", i18n.t("depth_current"), prefs.format_position(z)),
+        }
+    };
+
     // Create a zooming status message
     let zoom_text = move || format!("Zoom: {}%", (zoom() * 100.0).round());
 
     // Calculate transform value for svg
     let transform = move || format!("translate({},{}) scale({})", offset_x(), offset_y(), zoom());
 
+    // Pixels on screen per machine millimeter at the current zoom
+    let px_per_mm = move || zoom() * BITMAP_PX_PER_MM;
+
+    // Axis tick labels in machine units, placed outside the transformed group
+    // so that the text keeps a constant size while zooming
+    let x_ticks = move || {
+        let (ox, ppm) = (offset_x(), px_per_mm());
+        axis_ticks(-ox / ppm, (400.0 - ox) / ppm, MAX_TICK_LABELS)
+            .into_iter()
+            .map(|tick| {
+                view! {
+                    <text x={ox + tick * ppm} y="395" class="axis-tick" text-anchor="middle">
+                        {format_tick(tick)}
+                    </text>
+                }
+            })
+            .collect_view()
+    };
+    let y_ticks = move || {
+        let (oy, ppm) = (offset_y(), px_per_mm());
+        // Machine Y points up while SVG Y points down
+        axis_ticks((oy - 400.0) / ppm, oy / ppm, MAX_TICK_LABELS)
+            .into_iter()
+            .map(|tick| {
+                view! {
+                    <text x="3" y={oy - tick * ppm} class="axis-tick">
+                        {format_tick(tick)}
+                    </text>
+                }
+            })
+            .collect_view()
+    };
+
+    // Scale bar about 80px long, rounded to a nice length in mm
+    let scale_bar = move || {
+        let length_mm = nice_step(80.0 / px_per_mm());
+        (
+            length_mm * px_per_mm(),
+            format!("{}mm", format_tick(length_mm)),
+        )
+    };
+
     view! {
         <div class="path-visualizer-container">
-            <h3>"Machine Path Visualization"</h3>
+            <h3>{move || i18n.t("machine_path")}</h3>
 
             // Controls for the visualization
             <div class="control-panel">
-                <button on:click=move |_| zoom.update(|z| *z *= 1.2)>"Zoom In"</button>
-                <button on:click=move |_| zoom.update(|z| *z /= 1.2)>"Zoom Out"</button>
-                <button on:click=reset_view>"Reset View"</button>
-                <button on:click=clear_view>"Clear View"</button>
+                <button on:click=move |_| zoom.update(|z| *z *= 1.2)>
+                    {move || i18n.t("zoom_in")}
+                </button>
+                <button on:click=move |_| zoom.update(|z| *z /= 1.2)>
+                    {move || i18n.t("zoom_out")}
+                </button>
+                <button on:click=reset_view>{move || i18n.t("reset_view")}</button>
+                <button on:click=clear_view>{move || i18n.t("clear_view")}</button>
+                <button on:click=export_dxf>{move || i18n.t("export_dxf")}</button>
+                <a node_ref=dxf_link download="path.dxf" style="display: none;"></a>
+                <label>
+                    {move || i18n.t("background")}
+                    " "
+                    <input
+                        type="color"
+                        prop:value=move || background_color.get()
+                        on:input=move |ev| background_color.set(event_target_value(&ev))
+                    />
+                </label>
+                <label>
+                    {move || i18n.t("opacity")}
+                    " "
+                    <input
+                        type="range"
+                        min="0"
+                        max="255"
+                        prop:value=move || background_alpha.get().to_string()
+                        on:input=move |ev| {
+                            background_alpha.set(event_target_value(&ev).parse().unwrap_or(0))
+                        }
+                    />
+                </label>
+                <label>
+                    {move || i18n.t("line_width")}
+                    " "
+                    <input
+                        type="number"
+                        min="1"
+                        max="9"
+                        prop:value=move || line_width.get().to_string()
+                        on:input=move |ev| {
+                            line_width.set(event_target_value(&ev).parse().unwrap_or(1).max(1))
+                        }
+                    />
+                </label>
+                <label>
+                    <input
+                        type="checkbox"
+                        prop:checked=move || orientation.get().mirror_x
+                        on:change=move |ev| {
+                            orientation.update(|o| o.mirror_x = event_target_checked(&ev))
+                        }
+                    />
+                    {move || i18n.t("mirror_x")}
+                </label>
+                <label>
+                    <input
+                        type="checkbox"
+                        prop:checked=move || orientation.get().mirror_y
+                        on:change=move |ev| {
+                            orientation.update(|o| o.mirror_y = event_target_checked(&ev))
+                        }
+                    />
+                    {move || i18n.t("mirror_y")}
+                </label>
+                <label>
+                    {move || i18n.t("rotation")}
+                    " "
+                    <select on:change=move |ev| {
+                        if let Ok(rotation) = event_target_value(&ev).parse() {
+                            orientation.update(|o| o.rotation = rotation);
+                        }
+                    }>
+                        {PathOrientation::ROTATIONS
+                            .into_iter()
+                            .map(|rotation| {
+                                view! {
+                                    <option
+                                        value=rotation.to_string()
+                                        selected=move || orientation.get().rotation == rotation
+                                    >
+                                        {format!("{}°", rotation)}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <label>
+                    <input
+                        type="checkbox"
+                        prop:checked=move || overlay_preview.get()
+                        on:change=move |ev| overlay_preview.set(event_target_checked(&ev))
+                    />
+                    {move || i18n.t("overlay_preview")}
+                </label>
+                <label>
+                    <input
+                        type="checkbox"
+                        prop:checked=move || distinguish_rapids.get()
+                        on:change=move |ev| distinguish_rapids.set(event_target_checked(&ev))
+                    />
+                    {move || i18n.t("distinguish_rapids")}
+                </label>
+                <label>
+                    {move || i18n.t("path_origin")}
+                    " "
+                    <select prop:disabled=move || fit_work_area.get() on:change=move |ev| {
+                        if let Ok(index) = event_target_value(&ev).parse::<usize>() {
+                            if let Some(selected) = PathOrigin::ALL.get(index) {
+                                origin.set(*selected);
+                            }
+                        }
+                    }>
+                        {PathOrigin::ALL
+                            .into_iter()
+                            .enumerate()
+                            .map(|(index, option)| {
+                                view! {
+                                    <option
+                                        value=index.to_string()
+                                        selected=move || origin.get() == option
+                                    >
+                                        {move || i18n.t(option.i18n_key())}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <label>
+                    <input
+                        type="checkbox"
+                        prop:checked=move || fit_work_area.get()
+                        on:change=move |ev| fit_work_area.set(event_target_checked(&ev))
+                    />
+                    {move || i18n.t("fit_work_area")}
+                </label>
+                <label>
+                    {move || i18n.t("path_image_format")}
+                    " "
+                    <select on:change=move |ev| {
+                        if let Ok(index) = event_target_value(&ev).parse::<usize>() {
+                            if let Some(selected) = PathImageFormat::ALL.get(index) {
+                                image_format.set(*selected);
+                            }
+                        }
+                    }>
+                        {PathImageFormat::ALL
+                            .into_iter()
+                            .enumerate()
+                            .map(|(index, option)| {
+                                view! {
+                                    <option
+                                        value=index.to_string()
+                                        selected=move || image_format.get() == option
+                                    >
+                                        {option.label()}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </label>
+                <Show when=move || image_format.get() == PathImageFormat::Jpeg>
+                    <label>
+                        {move || i18n.t("jpeg_quality")}
+                        " "
+                        <input
+                            type="number"
+                            min="1"
+                            max="100"
+                            prop:value=move || jpeg_quality.get()
+                            on:change=move |ev| {
+                                if let Ok(quality) = event_target_value(&ev).parse::<u8>() {
+                                    jpeg_quality.set(quality.clamp(1, 100));
+                                }
+                            }
+                        />
+                    </label>
+                </Show>
             </div>
 
             // SVG container
@@ -133,13 +671,30 @@ pub fn PathVisualizer() -> impl IntoView {
                         // Origin marker
                         <circle cx="0" cy="0" r="3" fill="red" />
 
+                        // Work area given by the soft limits, red when the path leaves it
+                        {move || {
+                            let area = work_area();
+                            let ppm = BITMAP_PX_PER_MM as f32;
+                            view! {
+                                <rect
+                                    class="work-area"
+                                    x={area.min_x * ppm}
+                                    y={-area.max_y * ppm}
+                                    width={(area.max_x - area.min_x) * ppm}
+                                    height={(area.max_y - area.min_y) * ppm}
+                                    fill="none"
+                                    stroke=move || if out_of_limits() { "red" } else { "#3b82f6" }
+                                    stroke-width="1"
+                                    stroke-dasharray="4 2"
+                                />
+                            }
+                        }}
+
                         // The machine path
                         {move || {
                             let path_img_url = path_img.get();
                             let path_img_preview_url = path_img_preview.get();
-                            if path_img_url.is_empty()
-                                || !path_img_url.starts_with("data:image/png;base64,")
-                            {
+                            if !is_image_data_url(&path_img_url) {
                                 view! {
                                     <g class="loading-message">
                                         <text
@@ -149,18 +704,24 @@ pub fn PathVisualizer() -> impl IntoView {
                                             font-family="sans-serif"
                                             fill="#666"
                                         >
-                                            "Waiting for machine data..."
+                                            {i18n.t("waiting_for_data")}
                                         </text>
                                     </g>
                                 }
                             } else {
+                                // 按零点位置摆放图像，使图像中的零点与画布原点重合
+                                let (fraction_x, fraction_y) = origin.get().fraction();
+                                let preview_x = -fraction_x * 500.0;
+                                let preview_y = -fraction_y * 500.0;
+                                let (image_x, image_y, image_width, image_height) =
+                                    image_placement();
                                 view! {
                                     <g class="bitmap-container">
-                                        // 预览图（高透明度）
+                                        // 预览图（高透明度），始终使用默认比例
                                         <image
                                             href=path_img_preview_url
-                                            x="-250"
-                                            y="-250"
+                                            x=preview_x
+                                            y=preview_y
                                             width="500"
                                             height="500"
                                             opacity="0.3"
@@ -168,16 +729,76 @@ pub fn PathVisualizer() -> impl IntoView {
                                         // 主图（不透明）
                                         <image
                                             href=path_img_url
-                                            x="-250"
-                                            y="-250"
-                                            width="500"
-                                            height="500"
+                                            x=image_x
+                                            y=image_y
+                                            width=image_width
+                                            height=image_height
                                         />
                                     </g>
                                 }
                             }
                         }}
                     </g>
+                    <g class="axis-ticks">{x_ticks} {y_ticks}</g>
+                    // Crosshair at the live tool position, red when outside the work area
+                    <g
+                        class="tool-crosshair"
+                        stroke=move || if out_of_limits() { "red" } else { "#16a34a" }
+                    >
+                        <line
+                            x1=move || tool_screen_pos().0 - 8.0
+                            y1=move || tool_screen_pos().1
+                            x2=move || tool_screen_pos().0 + 8.0
+                            y2=move || tool_screen_pos().1
+                        />
+                        <line
+                            x1=move || tool_screen_pos().0
+                            y1=move || tool_screen_pos().1 - 8.0
+                            x2=move || tool_screen_pos().0
+                            y2=move || tool_screen_pos().1 + 8.0
+                        />
+                        <circle
+                            cx=move || tool_screen_pos().0
+                            cy=move || tool_screen_pos().1
+                            r="4"
+                            fill="none"
+                        />
+                    </g>
+                    {move || {
+                        jog_ghost()
+                            .map(|(x, y, within)| {
+                                view! {
+                                    <circle
+                                        class="jog-ghost"
+                                        cx=x
+                                        cy=y
+                                        r="6"
+                                        fill="none"
+                                        stroke=if within { "#6b7280" } else { "red" }
+                                        stroke-dasharray="3 2"
+                                    />
+                                }
+                            })
+                    }}
+                    <text x="5" y="14" class="position-overlay">
+                        {position_text}
+                    </text>
+                    <text x="5" y="28" class="position-overlay depth-readout">
+                        {depth_text}
+                    </text>
+                    <g class="scale-bar">
+                        <line
+                            x1="300"
+                            y1="20"
+                            x2=move || 300.0 + scale_bar().0
+                            y2="20"
+                            stroke="#333"
+                            stroke-width="2"
+                        />
+                        <text x="300" y="14" class="axis-tick">
+                            {move || scale_bar().1}
+                        </text>
+                    </g>
                 </svg>
                 <div class="zoom-info">{move || zoom_text()}</div>
             </div>
@@ -185,1148 +806,2639 @@ pub fn PathVisualizer() -> impl IntoView {
     }
 }
 
-// #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq)]
-// struct Point {
-//     x: f64,
-//     y: f64,
-//     color: u8, //指定当前绘制的画笔的颜色
-// }
-//
-// // HSV转RGB颜色转换
-// fn hsv_to_rgb(h: f64, s: f64, v: f64) -> (u8, u8, u8) {
-//     let c = v * s;
-//     let h_prime = h / 60.0;
-//     let x = c * (1.0 - ((h_prime % 2.0) - 1.0).abs());
-//     let m = v - c;
-//
-//     let (r1, g1, b1) = match h_prime as u8 {
-//         0 => (c, x, 0.0),
-//         1 => (x, c, 0.0),
-//         2 => (0.0, c, x),
-//         3 => (0.0, x, c),
-//         4 => (x, 0.0, c),
-//         5 => (c, 0.0, x),
-//         _ => (0.0, 0.0, 0.0),
-//     };
-//
-//     let r = ((r1 + m) * 255.0) as u8;
-//     let g = ((g1 + m) * 255.0) as u8;
-//     let b = ((b1 + m) * 255.0) as u8;
-//
-//     (r, g, b)
-// }
-//
-// // 使用lazy_static预计算HSV->RGB转换的查表数组
-// lazy_static! {
-//     static ref RGB_TABLE_R: [u8; 256] = {
-//         let mut table = [0u8; 256];
-//         for i in 0..256 {
-//             let hue = (i as f64 / 255.0) * 360.0;
-//             let (r, _, _) = hsv_to_rgb(hue, 1.0, 0.9);
-//             table[i] = r;
-//         }
-//         table
-//     };
-//     static ref RGB_TABLE_G: [u8; 256] = {
-//         let mut table = [0u8; 256];
-//         for i in 0..256 {
-//             let hue = (i as f64 / 255.0) * 360.0;
-//             let (_, g, _) = hsv_to_rgb(hue, 1.0, 0.9);
-//             table[i] = g;
-//         }
-//         table
-//     };
-//     static ref RGB_TABLE_B: [u8; 256] = {
-//         let mut table = [0u8; 256];
-//         for i in 0..256 {
-//             let hue = (i as f64 / 255.0) * 360.0;
-//             let (_, _, b) = hsv_to_rgb(hue, 1.0, 0.9);
-//             table[i] = b;
-//         }
-//         table
-//     };
-// }
-// // 使用查表法获取RGB值
-// fn hsv_to_rgb_r(color_val: u8) -> u8 {
-//     RGB_TABLE_R[color_val as usize]
-// }
-//
-// fn hsv_to_rgb_g(color_val: u8) -> u8 {
-//     RGB_TABLE_G[color_val as usize]
-// }
-//
-// fn hsv_to_rgb_b(color_val: u8) -> u8 {
-//     RGB_TABLE_B[color_val as usize]
-// }
-//
-// impl Point {
-//     fn get_color(&self) -> String {
-//         // 将u8值映射到HSV色彩空间的色相(0-360度)，然后转为RGB
-//         let hue = (self.color as f64 / 255.0) * 360.0;
-//         // 固定饱和度和明度为高值，保证颜色鲜艳
-//         let (r, g, b) = hsv_to_rgb(hue, 1.0, 0.9);
-//         format!("#{:02x}{:02x}{:02x}", r, g, b)
-//     }
-// }
-// // 使用矢量存储所有已绘制的点，而不是依赖图像
-// #[derive(Clone, Debug)]
-// struct PathHistory {
-//     points: Vec<Point>,   // 存储已绘制的点
-//     current_index: usize, // 当前绘制到的索引
-//     // 用于减少存储和绘制的点数
-//     simplification_tolerance: f64,
-//     // 存储线段而非每个点以减少内存使用
-//     segments: Vec<PathSegment>,
-//     // 当前正在构建的线段
-//     current_segment: Option<PathSegment>,
-//     // 分块存储数据，用于快速渲染
-//     chunks: Vec<PathChunk>,
-// }
-//
-// // 存储连续颜色相近的线段
-// #[derive(Clone, Debug, serde::Deserialize, serde::Serialize, PartialEq)]
-// struct PathSegment {
-//     points: Vec<Point>,
-//     color: u8,
-// }
-//
-// // 用于优化渲染的数据块
-// #[derive(Clone, Debug)]
-// struct PathChunk {
-//     bounds: Rect,
-//     segments: Vec<usize>, // 索引到主segments列表
-// }
-//
-// // 简单的矩形结构，用于空间划分
-// #[derive(Clone, Debug)]
-// struct Rect {
-//     x_min: f64,
-//     y_min: f64,
-//     x_max: f64,
-//     y_max: f64,
-// }
-//
-// impl PathHistory {
-//     fn new() -> Self {
-//         Self {
-//             points: Vec::new(),
-//             current_index: 0,
-//             simplification_tolerance: 1.0, // 简化阈值，根据需要调整
-//             segments: Vec::new(),
-//             current_segment: None,
-//             chunks: Vec::new(),
-//         }
-//     }
-//
-//     fn add_point(&mut self, x: f64, y: f64, color: u8) {
-//         let new_point = Point { x, y, color };
-//
-//         // 仅当点与上一个点不在简化阈值内时才添加
-//         let should_add = self.points.last().map_or(true, |last_point| {
-//             let dx = last_point.x - x;
-//             let dy = last_point.y - y;
-//             let distance_squared = dx * dx + dy * dy;
-//
-//             // 距离超过阈值或颜色变化明显时才添加点
-//             let color_change = (last_point.color as i16 - color as i16).abs() > 10;
-//
-//             distance_squared > self.simplification_tolerance * self.simplification_tolerance
-//                 || color_change
-//         });
-//
-//         if should_add {
-//             // 添加到主点列表
-//             self.points.push(new_point.clone());
-//
-//             // 处理线段构建
-//             let color_changed = self.update_segments(new_point);
-//
-//             // 颜色变化时强制创建桥接点，确保实时绘制中线条不会断开
-//             if color_changed && self.points.len() >= 2 {
-//                 // 这里已在update_segments中处理
-//             }
-//
-//             // 定期重构空间分块数据（每100个点）
-//             if self.points.len() % 100 == 0 {
-//                 self.rebuild_spatial_chunks();
-//             }
-//         }
-//     }
-//
-//     // 返回布尔值表示颜色是否变化
-//     fn update_segments(&mut self, point: Point) -> bool {
-//         let mut color_changed = false;
-//
-//         match &mut self.current_segment {
-//             None => {
-//                 // 创建新线段
-//                 let mut new_segment = PathSegment {
-//                     points: Vec::with_capacity(100),
-//                     color: point.color,
-//                 };
-//                 new_segment.points.push(point);
-//                 self.current_segment = Some(new_segment);
-//             }
-//             Some(segment) => {
-//                 // 检查颜色是否有明显变化
-//                 color_changed = (segment.color as i16 - point.color as i16).abs() > 10;
-//
-//                 if color_changed {
-//                     // 颜色变化明显，创建桥接点并开始新线段
-//
-//                     // 重要：添加桥接点，确保线段连续
-//                     let bridge_point = Point {
-//                         x: point.x,
-//                         y: point.y,
-//                         color: segment.color, // 使用旧颜色
-//                     };
-//                     segment.points.push(bridge_point.clone());
-//
-//                     // 创建新线段并添加当前点
-//                     let mut new_segment = PathSegment {
-//                         points: Vec::with_capacity(100),
-//                         color: point.color,
-//                     };
-//                     // 先添加一个与桥接点坐标相同但颜色不同的点
-//                     new_segment.points.push(point);
-//
-//                     // 完成当前线段，移到segments中
-//                     if let Some(completed_segment) = self.current_segment.take() {
-//                         self.segments.push(completed_segment);
-//                     }
-//                     self.current_segment = Some(new_segment);
-//                 } else {
-//                     // 颜色相似，添加到当前线段
-//                     segment.points.push(point);
-//                 }
-//             }
-//         }
-//
-//         color_changed
-//     }
-//
-//     fn rebuild_spatial_chunks(&mut self) {
-//         // 确保当前线段已添加到segments
-//         if let Some(segment) = self.current_segment.take() {
-//             self.segments.push(segment.clone());
-//             self.current_segment = Some(segment);
-//         }
-//
-//         // 清除现有chunks
-//         self.chunks.clear();
-//
-//         // 如果segments太少，不需要空间分块
-//         if self.segments.len() < 10 {
-//             return;
-//         }
-//
-//         // 简单的空间分块策略：划分为16个区域
-//         let mut x_values: Vec<f64> = self.points.iter().map(|p| p.x).collect();
-//         let mut y_values: Vec<f64> = self.points.iter().map(|p| p.y).collect();
-//         x_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-//         y_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-//
-//         // 找到整体边界
-//         let x_min = *x_values.first().unwrap_or(&0.0);
-//         let x_max = *x_values.last().unwrap_or(&0.0);
-//         let y_min = *y_values.first().unwrap_or(&0.0);
-//         let y_max = *y_values.last().unwrap_or(&0.0);
-//
-//         // 分区创建chunks
-//         let div = 4; // 4x4 分块
-//         let x_step = (x_max - x_min) / div as f64;
-//         let y_step = (y_max - y_min) / div as f64;
-//
-//         for i in 0..div {
-//             for j in 0..div {
-//                 let chunk_x_min = x_min + i as f64 * x_step;
-//                 let chunk_x_max = chunk_x_min + x_step;
-//                 let chunk_y_min = y_min + j as f64 * y_step;
-//                 let chunk_y_max = chunk_y_min + y_step;
-//
-//                 let chunk_bounds = Rect {
-//                     x_min: chunk_x_min,
-//                     y_min: chunk_y_min,
-//                     x_max: chunk_x_max,
-//                     y_max: chunk_y_max,
-//                 };
-//
-//                 // 找出与此chunk相交的线段
-//                 let mut segment_indices = Vec::new();
-//                 for (idx, segment) in self.segments.iter().enumerate() {
-//                     if segment.points.iter().any(|p| {
-//                         p.x >= chunk_x_min
-//                             && p.x <= chunk_x_max
-//                             && p.y >= chunk_y_min
-//                             && p.y <= chunk_y_max
-//                     }) {
-//                         segment_indices.push(idx);
-//                     }
-//                 }
-//
-//                 if !segment_indices.is_empty() {
-//                     self.chunks.push(PathChunk {
-//                         bounds: chunk_bounds,
-//                         segments: segment_indices,
-//                     });
-//                 }
-//             }
-//         }
-//     }
-//
-//     fn draw_complete_path(&self, ctx: &CanvasRenderingContext2d, scale: f64, view_rect: &Rect) {
-//         if self.segments.len() < 1 && self.current_segment.is_none() {
-//             return;
-//         }
-//
-//         ctx.set_line_width(2.0 / scale);
-//
-//         // 高效绘制: 仅绘制可见区域内的块
-//         if !self.chunks.is_empty() {
-//             // 筛选可见的块
-//             let visible_chunks: Vec<&PathChunk> = self
-//                 .chunks
-//                 .iter()
-//                 .filter(|chunk| {
-//                     // 检查chunk是否与视图矩形相交
-//                     !(chunk.bounds.x_max < view_rect.x_min
-//                         || chunk.bounds.x_min > view_rect.x_max
-//                         || chunk.bounds.y_max < view_rect.y_min
-//                         || chunk.bounds.y_min > view_rect.y_max)
-//                 })
-//                 .collect();
-//
-//             // 绘制可见块中的线段
-//             for chunk in visible_chunks {
-//                 for &segment_idx in &chunk.segments {
-//                     if segment_idx < self.segments.len() {
-//                         let segment = &self.segments[segment_idx];
-//                         self.draw_segment(ctx, segment, scale);
-//                     }
-//                 }
-//             }
-//         } else {
-//             // 如果没有块数据，直接绘制所有线段
-//             for segment in &self.segments {
-//                 self.draw_segment(ctx, segment, scale);
-//             }
-//         }
-//
-//         // 绘制当前正在构建的线段
-//         if let Some(segment) = &self.current_segment {
-//             self.draw_segment(ctx, segment, scale);
-//         }
-//
-//         // 绘制终点标记
-//         if let Some(last_point) = self.points.last() {
-//             ctx.set_fill_style(&last_point.get_color().as_str().into());
-//             ctx.begin_path();
-//             ctx.arc(
-//                 last_point.x,
-//                 last_point.y,
-//                 3.0 / scale,
-//                 0.0,
-//                 2.0 * std::f64::consts::PI,
-//             )
-//             .unwrap();
-//             ctx.fill();
-//         }
-//     }
-//
-//     // 绘制单个线段
-//     fn draw_segment(&self, ctx: &CanvasRenderingContext2d, segment: &PathSegment, scale: f64) {
-//         if segment.points.len() < 2 {
-//             return;
-//         }
-//
-//         let color = format!(
-//             "#{:02x}{:02x}{:02x}",
-//             hsv_to_rgb_r(segment.color),
-//             hsv_to_rgb_g(segment.color),
-//             hsv_to_rgb_b(segment.color)
-//         );
-//         ctx.set_stroke_style(&color.as_str().into());
-//
-//         ctx.begin_path();
-//         ctx.move_to(segment.points[0].x, segment.points[0].y);
-//
-//         // 使用更高效的路径绘制方法
-//         if segment.points.len() > 100 {
-//             // 对于大量点，使用更高效的绘制策略
-//             let mut i = 0;
-//             while i < segment.points.len() {
-//                 ctx.line_to(segment.points[i].x, segment.points[i].y);
-//                 // 在大量点的情况下跳过一些点
-//                 i += if segment.points.len() > 1000 { 5 } else { 2 };
-//             }
-//             // 确保最后一个点被绘制
-//             if i > segment.points.len() && !segment.points.is_empty() {
-//                 let last = segment.points.last().unwrap();
-//                 ctx.line_to(last.x, last.y);
-//             }
-//         } else {
-//             // 对于少量点，正常绘制
-//             for point in segment.points.iter().skip(1) {
-//                 ctx.line_to(point.x, point.y);
-//             }
-//         }
-//
-//         ctx.stroke();
-//     }
-//
-//     // 优化的增量绘制函数
-//     fn draw_last_segment(&self, ctx: &CanvasRenderingContext2d, scale: f64) {
-//         if let Some(segment) = &self.current_segment {
-//             if segment.points.len() < 2 {
-//                 return;
-//             }
-//
-//             // 计算最后一个颜色
-//             let color = format!(
-//                 "#{:02x}{:02x}{:02x}",
-//                 hsv_to_rgb_r(segment.color),
-//                 hsv_to_rgb_g(segment.color),
-//                 hsv_to_rgb_b(segment.color)
-//             );
-//
-//             ctx.set_stroke_style(&color.as_str().into());
-//             ctx.set_line_width(2.0 / scale);
-//
-//             // 绘制最新的点与上一个点之间的线段
-//             let len = segment.points.len();
-//             if len >= 2 {
-//                 let last_index = len - 1;
-//                 let prev_index = len - 2;
-//
-//                 ctx.begin_path();
-//                 ctx.move_to(segment.points[prev_index].x, segment.points[prev_index].y);
-//                 ctx.line_to(segment.points[last_index].x, segment.points[last_index].y);
-//                 ctx.stroke();
-//             }
-//         }
-//
-//         // 重要：如果上一个线段存在且这是一个新段，绘制连接线
-//         if !self.segments.is_empty() && self.current_segment.is_some() {
-//             let last_segment = &self.segments[self.segments.len() - 1];
-//             let current_segment = self.current_segment.as_ref().unwrap();
-//
-//             // 我们只需确保这些点是连接的，无论颜色是否变化
-//             if let (Some(last_point_prev), Some(first_point_current)) =
-//                 (last_segment.points.last(), current_segment.points.first())
-//             {
-//                 // 重要：始终绘制连接线，确保视觉连续性
-//                 // 使用两种颜色各绘制一半，创造平滑过渡效果
-//
-//                 // 1. 先用旧颜色绘制一条短线
-//                 let connect_color1 = format!(
-//                     "#{:02x}{:02x}{:02x}",
-//                     hsv_to_rgb_r(last_segment.color),
-//                     hsv_to_rgb_g(last_segment.color),
-//                     hsv_to_rgb_b(last_segment.color)
-//                 );
-//
-//                 ctx.set_stroke_style(&connect_color1.as_str().into());
-//                 ctx.begin_path();
-//                 ctx.move_to(last_point_prev.x, last_point_prev.y);
-//                 // 绘制到中点
-//                 let mid_x = (last_point_prev.x + first_point_current.x) / 2.0;
-//                 let mid_y = (last_point_prev.y + first_point_current.y) / 2.0;
-//                 ctx.line_to(mid_x, mid_y);
-//                 ctx.stroke();
-//
-//                 // 2. 再用新颜色绘制另一半
-//                 let connect_color2 = format!(
-//                     "#{:02x}{:02x}{:02x}",
-//                     hsv_to_rgb_r(current_segment.color),
-//                     hsv_to_rgb_g(current_segment.color),
-//                     hsv_to_rgb_b(current_segment.color)
-//                 );
-//
-//                 ctx.set_stroke_style(&connect_color2.as_str().into());
-//                 ctx.begin_path();
-//                 ctx.move_to(mid_x, mid_y);
-//                 ctx.line_to(first_point_current.x, first_point_current.y);
-//                 ctx.stroke();
-//             }
-//         }
-//
-//         // 绘制当前点作为标记
-//         if let Some(segment) = &self.current_segment {
-//             if let Some(current_point) = segment.points.last() {
-//                 let color = format!(
-//                     "#{:02x}{:02x}{:02x}",
-//                     hsv_to_rgb_r(current_point.color),
-//                     hsv_to_rgb_g(current_point.color),
-//                     hsv_to_rgb_b(current_point.color)
-//                 );
-//
-//                 ctx.set_fill_style(&color.as_str().into());
-//                 ctx.begin_path();
-//                 ctx.arc(
-//                     current_point.x,
-//                     current_point.y,
-//                     3.0 / scale,
-//                     0.0,
-//                     2.0 * std::f64::consts::PI,
-//                 )
-//                 .unwrap();
-//                 ctx.fill();
-//             }
-//         }
-//     }
-// }
-//
-// // 改进的画布渲染函数 - 优化渲染性能
-// fn draw_canvas(
-//     ctx: &CanvasRenderingContext2d,
-//     scale: f64,
-//     offset_x: f64,
-//     offset_y: f64,
-//     canvas: &web_sys::HtmlCanvasElement,
-//     path_history: &PathHistory,
-//     redraw_mode: RedrawMode,
-// ) {
-//     // 计算当前视图的矩形区域（世界坐标）
-//     let view_rect = Rect {
-//         x_min: -offset_x / scale,
-//         y_min: -offset_y / scale,
-//         x_max: (canvas.width() as f64 - offset_x) / scale,
-//         y_max: (canvas.height() as f64 - offset_y) / scale,
-//     };
-//
-//     match redraw_mode {
-//         RedrawMode::Full => {
-//             // 完全重绘 - 清除画布并绘制所有内容
-//             ctx.save();
-//             ctx.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
-//             ctx.clear_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
-//             ctx.restore();
-//
-//             // 保存当前状态
-//             ctx.save();
-//
-//             // 应用变换
-//             ctx.translate(offset_x, offset_y).unwrap();
-//             ctx.scale(scale, scale).unwrap();
-//
-//             // 1. 绘制网格和参考线
-//             draw_grid(ctx, scale, &view_rect);
-//
-//             // 2. 绘制完整路径历史（传递可见区域信息以优化渲染）
-//             path_history.draw_complete_path(ctx, scale, &view_rect);
-//
-//             // 恢复状态
-//             ctx.restore();
-//         }
-//         RedrawMode::Incremental => {
-//             // 增量绘制 - 只绘制最新的线段
-//             ctx.save();
-//             ctx.translate(offset_x, offset_y).unwrap();
-//             ctx.scale(scale, scale).unwrap();
-//
-//             path_history.draw_last_segment(ctx, scale);
-//
-//             ctx.restore();
-//         }
-//         RedrawMode::Navigation => {
-//             // 导航绘制 - 清除并重绘
-//             ctx.save();
-//             ctx.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
-//             ctx.clear_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
-//             ctx.restore();
-//
-//             ctx.save();
-//             ctx.translate(offset_x, offset_y).unwrap();
-//             ctx.scale(scale, scale).unwrap();
-//
-//             // 1. 绘制网格和参考线
-//             draw_grid(ctx, scale, &view_rect);
-//
-//             // 2. 绘制完整路径历史（传递可见区域信息）
-//             path_history.draw_complete_path(ctx, scale, &view_rect);
-//
-//             ctx.restore();
-//         }
-//     }
-// }
-//
-// // 优化的网格绘制函数
-// fn draw_grid(ctx: &CanvasRenderingContext2d, scale: f64, view_rect: &Rect) {
-//     ctx.set_stroke_style(&"#d0d0d0".into());
-//     ctx.set_line_width(0.5 / scale);
-//
-//     let grid_size = 10.0;
-//
-//     // 只绘制可见区域的网格线，而不是固定数量
-//     let min_x = (view_rect.x_min / grid_size).floor() as i32;
-//     let max_x = (view_rect.x_max / grid_size).ceil() as i32;
-//     let min_y = (view_rect.y_min / grid_size).floor() as i32;
-//     let max_y = (view_rect.y_max / grid_size).ceil() as i32;
-//
-//     // 限制线条数量以避免性能问题
-//     let max_lines = 100;
-//     let x_step = if max_x - min_x > max_lines {
-//         (max_x - min_x) / max_lines
-//     } else {
-//         1
-//     };
-//     let y_step = if max_y - min_y > max_lines {
-//         (max_y - min_y) / max_lines
-//     } else {
-//         1
-//     };
-//
-//     // 绘制垂直线
-//     for i in (min_x..=max_x).step_by(x_step as usize) {
-//         ctx.begin_path();
-//         ctx.move_to(i as f64 * grid_size, view_rect.y_min);
-//         ctx.line_to(i as f64 * grid_size, view_rect.y_max);
-//         ctx.stroke();
-//     }
-//
-//     // 绘制水平线
-//     for i in (min_y..=max_y).step_by(y_step as usize) {
-//         ctx.begin_path();
-//         ctx.move_to(view_rect.x_min, i as f64 * grid_size);
-//         ctx.line_to(view_rect.x_max, i as f64 * grid_size);
-//         ctx.stroke();
-//     }
-// }
-//
-// // 绘制模式枚举
-// enum RedrawMode {
-//     Full,        // 完全重绘（清除并绘制所有内容）
-//     Incremental, // 增量绘制（只绘制新内容）
-//     Navigation,  // 导航绘制（用于缩放/平移）
-// }
-//
-// // 优化的存储函数，分块保存和加载
-// fn save_path_history(path_history: &PathHistory) {
-//     // 确保当前线段已添加到segments中
-//     let mut segments = path_history.segments.clone();
-//     if let Some(segment) = &path_history.current_segment {
-//         segments.push(segment.clone());
-//     }
-//
-//     // 对于大量数据，分片存储
-//     if segments.len() > 50 {
-//         // 每批最多存储50个线段
-//         let batch_count = (segments.len() + 49) / 50;
-//
-//         for batch in 0..batch_count {
-//             let start = batch * 50;
-//             let end = std::cmp::min(start + 50, segments.len());
-//             let batch_segments = &segments[start..end];
-//
-//             let (_, set_segments, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
-//                 leptos_use::storage::StorageType::Local,
-//                 format!("path_segments_batch_{}", batch).as_str(),
-//             );
-//             set_segments.set(batch_segments.to_vec());
-//         }
-//
-//         // 存储批次数量
-//         let (_, set_batch_count, _) = use_storage::<usize, JsonSerdeCodec>(
-//             leptos_use::storage::StorageType::Local,
-//             "path_segments_batch_count",
-//         );
-//         set_batch_count.set(batch_count);
-//     } else {
-//         // 数据量小，直接存储
-//         let (_, set_segments, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
-//             leptos_use::storage::StorageType::Local,
-//             "path_segments",
-//         );
-//         set_segments.set(segments);
-//
-//         // 清除批次计数
-//         let (_, set_batch_count, _) = use_storage::<usize, JsonSerdeCodec>(
-//             leptos_use::storage::StorageType::Local,
-//             "path_segments_batch_count",
-//         );
-//         set_batch_count.set(0);
-//     }
-// }
-//
-// // 从本地存储加载路径历史
-// fn load_path_history() -> PathHistory {
-//     let (batch_count_signal, _, _) = use_storage::<usize, JsonSerdeCodec>(
-//         leptos_use::storage::StorageType::Local,
-//         "path_segments_batch_count",
-//     );
-//     let batch_count = batch_count_signal.get_untracked();
-//
-//     let mut history = PathHistory::new();
-//
-//     if batch_count > 0 {
-//         // 分批加载
-//         for batch in 0..batch_count {
-//             let (segments_signal, _, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
-//                 leptos_use::storage::StorageType::Local,
-//                 format!("path_segments_batch_{}", batch).as_str(),
-//             );
-//             let segments = segments_signal.get_untracked();
-//             history.segments.extend(segments);
-//
-//             // 重建点列表
-//             for segment in &history.segments {
-//                 history.points.extend(segment.points.clone());
-//             }
-//         }
-//     } else {
-//         // 直接加载
-//         let (segments_signal, _, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
-//             leptos_use::storage::StorageType::Local,
-//             "path_segments",
-//         );
-//         history.segments = segments_signal.get_untracked();
-//
-//         // 重建点列表
-//         for segment in &history.segments {
-//             history.points.extend(segment.points.clone());
-//         }
-//     }
-//
-//     // 重建空间索引
-//     if !history.segments.is_empty() {
-//         history.rebuild_spatial_chunks();
-//     }
-//
-//     history
-// }
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq)]
+struct Point {
+    x: f64,
+    y: f64,
+    color: u8, //指定当前绘制的画笔的颜色
+    // G0快移走过的点
+    #[serde(default)]
+    rapid: bool,
+}
 
-// Decrepted for bad performance
-// #[component]
-// fn PointVisual() -> impl IntoView {
-//     let canvas_ref = NodeRef::<Canvas>::new();
-//     let context = Rc::new(RefCell::new(None));
-//
-//     // 使用RefCell保存路径历史，以便在不同闭包中修改
-//     let path_history = Rc::new(RefCell::new(PathHistory::new()));
-//
-//     // 存储最新的移动状态
-//     let current_status = RwSignal::new(MoveStatus::default());
-//
-//     // 视图变换状态
-//     let scale = RwSignal::new(1.0);
-//     let offset_x = RwSignal::new(200.0);
-//     let offset_y = RwSignal::new(200.0);
-//
-//     // 鼠标交互状态
-//     let dragging = RwSignal::new(false);
-//     let last_mouse_x = RwSignal::new(0);
-//     let last_mouse_y = RwSignal::new(0);
-//
-//     // 连接到WebSocket的移动状态信号
-//     let move_status =
-//         leptos_ws::ServerSignal::new("move_status".to_string(), MoveStatus::default()).unwrap();
-//
-//     // 初始化画布和加载历史
-//     let context_clone = context.clone();
-//     let path_history_clone = path_history.clone();
-//
-//     Effect::new(move || {
-//         if canvas_ref.get().is_some() && context_clone.borrow().is_none() {
-//             logging::log!("Initializing canvas...");
-//
-//             // 获取Canvas上下文
-//             let canvas = canvas_ref.get().unwrap();
-//             let ctx = canvas
-//                 .get_context("2d")
-//                 .unwrap()
-//                 .unwrap()
-//                 .dyn_into::<CanvasRenderingContext2d>()
-//                 .unwrap();
-//
-//             // 设置线条样式
-//             ctx.set_line_cap("round");
-//             ctx.set_line_join("round");
-//
-//             // 存储上下文
-//             context_clone.borrow_mut().replace(ctx);
-//
-//             // 加载历史路径
-//             *path_history_clone.borrow_mut() = load_path_history();
-//
-//             // 初始渲染
-//             if let Some(ctx) = context_clone.borrow().as_ref() {
-//                 draw_canvas(
-//                     ctx,
-//                     scale.get(),
-//                     offset_x.get(),
-//                     offset_y.get(),
-//                     &canvas,
-//                     &path_history_clone.borrow(),
-//                     RedrawMode::Full,
-//                 );
-//             }
-//         }
-//     });
-//
-//     // 处理移动状态更新
-//     let context_clone = context.clone();
-//     let path_history_clone = path_history.clone();
-//
-//     Effect::new(move || {
-//         let status = move_status.get();
-//         current_status.set(status.clone());
-//
-//         if let Some(canvas) = canvas_ref.get() {
-//             if let Some(ctx) = context_clone.borrow().as_ref() {
-//                 let mut path = path_history_clone.borrow_mut();
-//
-//                 // 只有当点不同时才添加新点
-//                 let is_new_point = path.points.is_empty()
-//                     || path
-//                         .points
-//                         .last()
-//                         .map(|point| {
-//                             point.x != status.x.pos as f64 || point.y != status.y.pos as f64
-//                         })
-//                         .unwrap_or(true);
-//
-//                 if is_new_point {
-//                     // 计算当前Z轴位置作为颜色
-//                     let current_color = (5.0 - status.z.pos).clamp(0.0, 5.0) as u8 * (255 / 5);
-//
-//                     // 添加新点到路径历史
-//                     path.add_point(status.x.pos as f64, status.y.pos as f64, current_color);
-//
-//                     // 每次添加点后，确保完整绘制当前线段
-//                     // 这样可以解决颜色过渡时的断线问题
-//                     let redraw_mode = if path.points.len() <= 2 {
-//                         // 第一个或第二个点时完全重绘
-//                         RedrawMode::Full
-//                     } else {
-//                         // 增量绘制包含连接线
-//                         RedrawMode::Incremental
-//                     };
-//
-//                     draw_canvas(
-//                         ctx,
-//                         scale.get(),
-//                         offset_x.get(),
-//                         offset_y.get(),
-//                         &canvas,
-//                         &path,
-//                         redraw_mode,
-//                     );
-//
-//                     // 定期保存历史
-//                     if path.points.len() % 10 == 0 {
-//                         save_path_history(&path);
-//                     }
-//                 }
-//             }
-//         }
-//     });
-//
-//     // 鼠标滚轮缩放
-//     let context_clone = context.clone();
-//     let path_history_clone = path_history.clone();
-//
-//     let handle_wheel = move |ev: web_sys::WheelEvent| {
-//         ev.prevent_default();
-//         let delta = if ev.delta_y() > 0.0 { 0.9 } else { 1.1 };
-//
-//         if let Some(canvas) = canvas_ref.get() {
-//             if let Some(ctx) = context_clone.borrow().as_ref() {
-//                 let rect = canvas.get_bounding_client_rect();
-//                 let mouse_x = ev.client_x() as f64 - rect.left();
-//                 let mouse_y = ev.client_y() as f64 - rect.top();
-//
-//                 // 计算缩放前鼠标在世界坐标中的位置
-//                 let world_x = (mouse_x - offset_x.get()) / scale.get();
-//                 let world_y = (mouse_y - offset_y.get()) / scale.get();
-//
-//                 // 更新缩放
-//                 scale.update(|s| *s *= delta);
-//
-//                 // 调整偏移量以保持鼠标位置不变
-//                 offset_x.set(mouse_x - world_x * scale.get());
-//                 offset_y.set(mouse_y - world_y * scale.get());
-//
-//                 // 重绘使用导航模式
-//                 draw_canvas(
-//                     ctx,
-//                     scale.get(),
-//                     offset_x.get(),
-//                     offset_y.get(),
-//                     &canvas,
-//                     &path_history_clone.borrow(),
-//                     RedrawMode::Navigation,
-//                 );
-//             }
-//         }
-//     };
-//
-//     // 鼠标拖拽平移
-//     let context_clone = context.clone();
-//     let path_history_clone = path_history.clone();
-//
-//     let handle_mouse_down = move |ev: web_sys::MouseEvent| {
-//         dragging.set(true);
-//         last_mouse_x.set(ev.client_x());
-//         last_mouse_y.set(ev.client_y());
-//     };
-//
-//     let handle_mouse_move = move |ev: web_sys::MouseEvent| {
-//         if dragging.get() {
-//             if let Some(canvas) = canvas_ref.get() {
-//                 if let Some(ctx) = context_clone.borrow().as_ref() {
-//                     let dx = ev.client_x() - last_mouse_x.get();
-//                     let dy = ev.client_y() - last_mouse_y.get();
-//
-//                     offset_x.update(|x| *x += dx as f64);
-//                     offset_y.update(|y| *y += dy as f64);
-//
-//                     last_mouse_x.set(ev.client_x());
-//                     last_mouse_y.set(ev.client_y());
-//
-//                     // 使用导航模式重绘
-//                     draw_canvas(
-//                         ctx,
-//                         scale.get(),
-//                         offset_x.get(),
-//                         offset_y.get(),
-//                         &canvas,
-//                         &path_history_clone.borrow(),
-//                         RedrawMode::Navigation,
-//                     );
-//                 }
-//             }
-//         }
-//     };
-//
-//     let handle_mouse_up = move |_| {
-//         dragging.set(false);
-//     };
-//
-//     // 重置视图
-//     let context_clone = context.clone();
-//     let path_history_clone = path_history.clone();
-//
-//     let reset_view = move |_| {
-//         if let Some(canvas) = canvas_ref.get() {
-//             if let Some(ctx) = context_clone.borrow().as_ref() {
-//                 scale.set(1.0);
-//                 offset_x.set(200.0);
-//                 offset_y.set(200.0);
-//
-//                 draw_canvas(
-//                     ctx,
-//                     scale.get(),
-//                     offset_x.get(),
-//                     offset_y.get(),
-//                     &canvas,
-//                     &path_history_clone.borrow(),
-//                     RedrawMode::Full,
-//                 );
-//             }
-//         }
-//     };
-//
-//     // 清除路径
-//     let context_clone = context.clone();
-//     let path_history_clone = path_history.clone();
-//
-//     let clear_path = move |_| {
-//         if let Some(canvas) = canvas_ref.get() {
-//             if let Some(ctx) = context_clone.borrow().as_ref() {
-//                 // 完全清空路径历史
-//                 let mut history = path_history_clone.borrow_mut();
-//                 history.points.clear();
-//                 history.segments.clear();
-//                 history.current_segment = None;
-//                 history.chunks.clear();
-//
-//                 // 重绘空画布
-//                 draw_canvas(
-//                     ctx,
-//                     scale.get(),
-//                     offset_x.get(),
-//                     offset_y.get(),
-//                     &canvas,
-//                     &history,
-//                     RedrawMode::Full,
-//                 );
-//
-//                 // 清除本地存储中所有相关数据
-//                 let (batch_count, set_batch_count, _) = use_storage::<usize, JsonSerdeCodec>(
-//                     leptos_use::storage::StorageType::Local,
-//                     "path_segments_batch_count",
-//                 );
-//                 let batch_count = batch_count.get_untracked();
-//
-//                 // 清除所有批次
-//                 for batch in 0..batch_count {
-//                     let (_, set_segments, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
-//                         leptos_use::storage::StorageType::Local,
-//                         format!("path_segments_batch_{}", batch).as_str(),
-//                     );
-//                     set_segments.set(Vec::new());
-//                 }
-//
-//                 // 清除主存储
-//                 let (_, set_segments, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
-//                     leptos_use::storage::StorageType::Local,
-//                     "path_segments",
-//                 );
-//                 set_segments.set(Vec::new());
-//
-//                 // 重置批次计数
-//                 set_batch_count.set(0);
-//             }
-//         }
-//     };
-//
-//     // 保存路径
-//     let path_history_clone = path_history.clone();
-//
-//     let save_path = move |_| {
-//         logging::log!("Manually saving path history");
-//         save_path_history(&path_history_clone.borrow());
-//     };
-//
-//     view! {
-//         <div class="canvas-controls">
-//             <input
-//                 type="range"
-//                 min="0.1"
-//                 max="5.0"
-//                 step="0.1"
-//                 value="1.0"
-//                 on:input=move |ev| {
-//                     if let Ok(value) = event_target_value(&ev).parse::<f64>() {
-//                         if let Some(canvas) = canvas_ref.get() {
-//                             if let Some(ctx) = context.borrow().as_ref() {
-//                                 scale.set(value);
-//                                 draw_canvas(
-//                                     ctx,
-//                                     scale.get(),
-//                                     offset_x.get(),
-//                                     offset_y.get(),
-//                                     &canvas,
-//                                     &path_history.borrow(),
-//                                     RedrawMode::Navigation,
-//                                 );
-//                             }
-//                         }
-//                     }
-//                 }
-//             />
-//             <span class="zoom-info">{move || format!("Zoom: {:.1}x", scale.get())}</span>
-//             <button on:click=save_path>"Save Path"</button>
-//             <button on:click=clear_path>"Clear Path"</button>
-//             <button on:click=reset_view>"Reset View"</button>
-//             <span class="position-info">
-//                 {move || {
-//                     format!(
-//                         "X: {:.2}, Y: {:.2}",
-//                         current_status.get().x.pos,
-//                         current_status.get().y.pos,
-//                     )
-//                 }}
-//             </span>
-//         </div>
-//         <canvas
-//             width="400"
-//             height="400"
-//             style="border: 1px solid black; cursor: move; background-color: #fafafa;"
-//             node_ref=canvas_ref
-//             on:mousedown=handle_mouse_down
-//             on:mousemove=handle_mouse_move
-//             on:mouseup=handle_mouse_up
-//             on:mouseleave=handle_mouse_up
-//             on:wheel=handle_wheel
-//         />
-//     }
-// }
+// 路径点导出为CSV，color为由Z换算的画笔颜色，rapid为1表示G0快移走过的点
+fn points_to_csv(points: &[Point]) -> String {
+    let mut csv = String::from("x,y,color,rapid\n");
+    for point in points {
+        let rapid = u8::from(point.rapid);
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            point.x, point.y, point.color, rapid
+        ));
+    }
+    csv
+}
 
-#[component]
-fn AxisVisual() -> impl IntoView {
-    let (global_state, set_global_state) =
-        use_cookie::<GlobalState, JsonSerdeCodec>("global_state_cookie");
-    // Ensure global state is initialized
-    if global_state.read_untracked().is_none() {
-        set_global_state.set(Some(GlobalState::default()));
+// 解析CSV路径点，跳过表头、空行和格式错误的行，返回点和跳过的行数。
+// 没有rapid列的旧文件按进给点处理
+fn points_from_csv(csv: &str) -> (Vec<Point>, usize) {
+    let mut points = Vec::new();
+    let mut skipped = 0;
+    for line in csv.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let parsed = match fields.as_slice() {
+            [x, y, color] => (x.parse(), y.parse(), color.parse(), Some(false)),
+            [x, y, color, rapid] => {
+                let rapid = match *rapid {
+                    "0" => Some(false),
+                    "1" => Some(true),
+                    _ => None,
+                };
+                (x.parse(), y.parse(), color.parse(), rapid)
+            }
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+        match parsed {
+            (Ok(x), Ok(y), Ok(color), Some(rapid)) => points.push(Point { x, y, color, rapid }),
+            // 表头不算作错误行
+            _ if line.starts_with("x,") => {}
+            _ => skipped += 1,
+        }
     }
+    (points, skipped)
+}
 
-    let connected = move || global_state.get().unwrap().connected;
+// HSV转RGB颜色转换
+fn hsv_to_rgb(h: f64, s: f64, v: f64) -> (u8, u8, u8) {
+    let c = v * s;
+    let h_prime = h / 60.0;
+    let x = c * (1.0 - ((h_prime % 2.0) - 1.0).abs());
+    let m = v - c;
 
-    let move_status =
-        leptos_ws::ServerSignal::new("move_status".to_string(), MoveStatus::default()).unwrap();
+    let (r1, g1, b1) = match h_prime as u8 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        5 => (c, 0.0, x),
+        _ => (0.0, 0.0, 0.0),
+    };
 
-    view! {
-        <Transition fallback=move || {
-            view! { <p>"Loading..."</p> }
-        }>
-            <div class="axis-status">
-                {move || {
-                    if !connected() {
-                        view! { <div class="error-message">"Not connected"</div> }
-                    } else {
-                        let status = move_status.get();
-                        view! {
-                            <div class="axis-status-container">
-                                <Table class="axis-status-table">
-                                    <TableHeader>
-                                        <TableRow>
-                                            <TableCell>"    "</TableCell>
-                                            <TableCell>
-                                                <h3>"X Axis"</h3>
-                                            </TableCell>
-                                            <TableCell>
-                                                <h3>"Y Axis"</h3>
-                                            </TableCell>
-                                            <TableCell>
-                                                <h3>"Z Axis"</h3>
-                                            </TableCell>
-                                        </TableRow>
-                                    </TableHeader>
-                                    <TableBody>
-                                        <TableRow>
-                                            <TableCell>"Idle"</TableCell>
-                                            <TableCell>
-                                                {if status.x.is_idle { "Yes" } else { "No" }}
-                                            </TableCell>
-                                            <TableCell>
-                                                {if status.y.is_idle { "Yes" } else { "No" }}
-                                            </TableCell>
-                                            <TableCell>
-                                                {if status.z.is_idle { "Yes" } else { "No" }}
-                                            </TableCell>
-                                        </TableRow>
-                                        <TableRow>
-                                            <TableCell>"Speed"</TableCell>
-                                            <TableCell>{format!("{:.2}", status.x.speed)}</TableCell>
-                                            <TableCell>{format!("{:.2}", status.y.speed)}</TableCell>
-                                            <TableCell>{format!("{:.2}", status.z.speed)}</TableCell>
-                                        </TableRow>
-                                        <TableRow>
-                                            <TableCell>"Position"</TableCell>
-                                            <TableCell>{format!("{:.3}", status.x.pos)}</TableCell>
-                                            <TableCell>{format!("{:.3}", status.y.pos)}</TableCell>
-                                            <TableCell>{format!("{:.3}", status.z.pos)}</TableCell>
-                                        </TableRow>
-                                    </TableBody>
-                                </Table>
-                            </div>
-                        }
-                    }
-                }}
-            </div>
-        </Transition>
+    let r = ((r1 + m) * 255.0) as u8;
+    let g = ((g1 + m) * 255.0) as u8;
+    let b = ((b1 + m) * 255.0) as u8;
+
+    (r, g, b)
+}
+
+// 使用lazy_static预计算HSV->RGB转换的查表数组
+lazy_static! {
+    static ref RGB_TABLE_R: [u8; 256] = {
+        let mut table = [0u8; 256];
+        for i in 0..256 {
+            let hue = (i as f64 / 255.0) * 360.0;
+            let (r, _, _) = hsv_to_rgb(hue, 1.0, 0.9);
+            table[i] = r;
+        }
+        table
+    };
+    static ref RGB_TABLE_G: [u8; 256] = {
+        let mut table = [0u8; 256];
+        for i in 0..256 {
+            let hue = (i as f64 / 255.0) * 360.0;
+            let (_, g, _) = hsv_to_rgb(hue, 1.0, 0.9);
+            table[i] = g;
+        }
+        table
+    };
+    static ref RGB_TABLE_B: [u8; 256] = {
+        let mut table = [0u8; 256];
+        for i in 0..256 {
+            let hue = (i as f64 / 255.0) * 360.0;
+            let (_, _, b) = hsv_to_rgb(hue, 1.0, 0.9);
+            table[i] = b;
+        }
+        table
+    };
+}
+// 使用查表法获取RGB值
+fn hsv_to_rgb_r(color_val: u8) -> u8 {
+    RGB_TABLE_R[color_val as usize]
+}
+
+fn hsv_to_rgb_g(color_val: u8) -> u8 {
+    RGB_TABLE_G[color_val as usize]
+}
+
+fn hsv_to_rgb_b(color_val: u8) -> u8 {
+    RGB_TABLE_B[color_val as usize]
+}
+
+impl Point {
+    fn get_color(&self) -> String {
+        // 将u8值映射到HSV色彩空间的色相(0-360度)，然后转为RGB
+        let hue = (self.color as f64 / 255.0) * 360.0;
+        // 固定饱和度和明度为高值，保证颜色鲜艳
+        let (r, g, b) = hsv_to_rgb(hue, 1.0, 0.9);
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
     }
 }
+// 快移虚线的线段和间隔长度(屏幕像素)及不透明度
+const RAPID_DASH_PX: f64 = 6.0;
+const RAPID_ALPHA: f64 = 0.45;
 
-#[component]
-pub fn VisualView() -> impl IntoView {
-    view! {
-        <div class="status">
-            <AxisVisual />
-            <PathVisualizer />
-        // <PointVisual />
-        </div>
+// 恢复实线和不透明
+fn clear_rapid_style(ctx: &CanvasRenderingContext2d) {
+    let _ = ctx.set_line_dash(&Array::new());
+    ctx.set_global_alpha(1.0);
+}
+
+// 驻留热力图的格子边长(世界坐标)，超过MAX_DWELL_GAP_MS的采样间隔视为断线，不计入驻留
+const DWELL_CELL_SIZE: f64 = 1.0;
+const MAX_DWELL_GAP_MS: f64 = 60_000.0;
+
+// 按格子累计机床停留的时间(ms)。位置不变时没有新的采样，
+// 所以两次采样之间的时间算在前一次采样所在的格子，主轴停转且各轴空闲时不计
+#[derive(Clone, Debug, Default)]
+struct DwellMap {
+    cells: HashMap<(i64, i64), f64>,
+    // 上一次计入驻留的采样所在格子和时间
+    last: Option<((i64, i64), f64)>,
+    max_ms: f64,
+}
+
+impl DwellMap {
+    fn cell(x: f64, y: f64) -> (i64, i64) {
+        (
+            (x / DWELL_CELL_SIZE).floor() as i64,
+            (y / DWELL_CELL_SIZE).floor() as i64,
+        )
+    }
+
+    fn record(&mut self, x: f64, y: f64, time_ms: f64, active: bool) {
+        if let Some((cell, since)) = self.last.take() {
+            let gap = time_ms - since;
+            if gap > 0.0 && gap <= MAX_DWELL_GAP_MS {
+                let dwell = self.cells.entry(cell).or_default();
+                *dwell += gap;
+                self.max_ms = self.max_ms.max(*dwell);
+            }
+        }
+        if active {
+            self.last = Some((Self::cell(x, y), time_ms));
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    // 停留越久颜色越接近红色，最短的为蓝色
+    fn draw(&self, ctx: &CanvasRenderingContext2d, view_rect: &Rect) {
+        if self.max_ms <= 0.0 {
+            return;
+        }
+        for (&(cell_x, cell_y), &dwell) in &self.cells {
+            let (x, y) = (
+                cell_x as f64 * DWELL_CELL_SIZE,
+                cell_y as f64 * DWELL_CELL_SIZE,
+            );
+            if x + DWELL_CELL_SIZE < view_rect.x_min
+                || x > view_rect.x_max
+                || y + DWELL_CELL_SIZE < view_rect.y_min
+                || y > view_rect.y_max
+            {
+                continue;
+            }
+            let (r, g, b) = hsv_to_rgb((1.0 - dwell / self.max_ms) * 240.0, 1.0, 0.9);
+            ctx.set_fill_style(&format!("rgb({}, {}, {})", r, g, b).into());
+            ctx.fill_rect(x, y, DWELL_CELL_SIZE, DWELL_CELL_SIZE);
+        }
+    }
+}
+
+// 使用矢量存储所有已绘制的点，而不是依赖图像
+#[derive(Clone, Debug)]
+struct PathHistory {
+    points: Vec<Point>,   // 存储已绘制的点
+    current_index: usize, // 当前绘制到的索引
+    // 用于减少存储和绘制的点数
+    simplification_tolerance: f64,
+    // 存储线段而非每个点以减少内存使用
+    segments: Vec<PathSegment>,
+    // 当前正在构建的线段
+    current_segment: Option<PathSegment>,
+    // 分块存储数据，用于快速渲染
+    chunks: Vec<PathChunk>,
+    // 快移线段画成淡色虚线，来自路径图样式
+    distinguish_rapids: bool,
+    // 各处的驻留时间，热力图模式下代替按Z着色的路径显示
+    dwell: DwellMap,
+    heat_mode: bool,
+}
+
+// 存储连续颜色相近的线段
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+struct PathSegment {
+    points: Vec<Point>,
+    color: u8,
+    // 快移线段，颜色或运动类型变化时开始新线段
+    #[serde(default)]
+    rapid: bool,
+}
+
+// 用于优化渲染的数据块
+#[derive(Clone, Debug)]
+struct PathChunk {
+    bounds: Rect,
+    segments: Vec<usize>, // 索引到主segments列表
+}
+
+// 简单的矩形结构，用于空间划分
+#[derive(Clone, Debug)]
+struct Rect {
+    x_min: f64,
+    y_min: f64,
+    x_max: f64,
+    y_max: f64,
+}
+
+impl PathHistory {
+    fn new() -> Self {
+        Self {
+            points: Vec::new(),
+            current_index: 0,
+            simplification_tolerance: 1.0, // 简化阈值，根据需要调整
+            segments: Vec::new(),
+            current_segment: None,
+            chunks: Vec::new(),
+            distinguish_rapids: false,
+            dwell: DwellMap::default(),
+            heat_mode: false,
+        }
+    }
+
+    fn add_point(&mut self, x: f64, y: f64, color: u8, rapid: bool) {
+        let new_point = Point { x, y, color, rapid };
+
+        // 仅当点与上一个点不在简化阈值内时才添加
+        let should_add = self.points.last().map_or(true, |last_point| {
+            let dx = last_point.x - x;
+            let dy = last_point.y - y;
+            let distance_squared = dx * dx + dy * dy;
+
+            // 距离超过阈值、颜色变化明显或运动类型变化时才添加点
+            let color_change = (last_point.color as i16 - color as i16).abs() > 10;
+
+            distance_squared > self.simplification_tolerance * self.simplification_tolerance
+                || color_change
+                || last_point.rapid != rapid
+        });
+
+        if should_add {
+            // 添加到主点列表
+            self.points.push(new_point.clone());
+
+            // 处理线段构建
+            let color_changed = self.update_segments(new_point);
+
+            // 颜色变化时强制创建桥接点，确保实时绘制中线条不会断开
+            if color_changed && self.points.len() >= 2 {
+                // 这里已在update_segments中处理
+            }
+
+            // 定期重构空间分块数据（每100个点）
+            if self.points.len() % 100 == 0 {
+                self.rebuild_spatial_chunks();
+            }
+        }
+    }
+
+    // 返回布尔值表示颜色是否变化
+    fn update_segments(&mut self, point: Point) -> bool {
+        let mut color_changed = false;
+
+        match &mut self.current_segment {
+            None => {
+                // 创建新线段
+                let mut new_segment = PathSegment {
+                    points: Vec::with_capacity(100),
+                    color: point.color,
+                    rapid: point.rapid,
+                };
+                new_segment.points.push(point);
+                self.current_segment = Some(new_segment);
+            }
+            Some(segment) => {
+                // 检查颜色是否有明显变化，快移和进给之间切换也算作变化
+                color_changed = (segment.color as i16 - point.color as i16).abs() > 10
+                    || segment.rapid != point.rapid;
+
+                if color_changed {
+                    // 颜色变化明显，创建桥接点并开始新线段
+
+                    // 重要：添加桥接点，确保线段连续
+                    let bridge_point = Point {
+                        x: point.x,
+                        y: point.y,
+                        color: segment.color, // 使用旧颜色
+                        rapid: segment.rapid,
+                    };
+                    segment.points.push(bridge_point.clone());
+
+                    // 创建新线段并添加当前点
+                    let mut new_segment = PathSegment {
+                        points: Vec::with_capacity(100),
+                        color: point.color,
+                        rapid: point.rapid,
+                    };
+                    // 先添加一个与桥接点坐标相同但颜色不同的点
+                    new_segment.points.push(point);
+
+                    // 完成当前线段，移到segments中
+                    if let Some(completed_segment) = self.current_segment.take() {
+                        self.segments.push(completed_segment);
+                    }
+                    self.current_segment = Some(new_segment);
+                } else {
+                    // 颜色相似，添加到当前线段
+                    segment.points.push(point);
+                }
+            }
+        }
+
+        color_changed
+    }
+
+    fn rebuild_spatial_chunks(&mut self) {
+        // 确保当前线段已添加到segments
+        if let Some(segment) = self.current_segment.take() {
+            self.segments.push(segment.clone());
+            self.current_segment = Some(segment);
+        }
+
+        // 清除现有chunks
+        self.chunks.clear();
+
+        // 如果segments太少，不需要空间分块
+        if self.segments.len() < 10 {
+            return;
+        }
+
+        // 简单的空间分块策略：划分为16个区域
+        let mut x_values: Vec<f64> = self.points.iter().map(|p| p.x).collect();
+        let mut y_values: Vec<f64> = self.points.iter().map(|p| p.y).collect();
+        x_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        y_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        // 找到整体边界
+        let x_min = *x_values.first().unwrap_or(&0.0);
+        let x_max = *x_values.last().unwrap_or(&0.0);
+        let y_min = *y_values.first().unwrap_or(&0.0);
+        let y_max = *y_values.last().unwrap_or(&0.0);
+
+        // 分区创建chunks
+        let div = 4; // 4x4 分块
+        let x_step = (x_max - x_min) / div as f64;
+        let y_step = (y_max - y_min) / div as f64;
+
+        for i in 0..div {
+            for j in 0..div {
+                let chunk_x_min = x_min + i as f64 * x_step;
+                let chunk_x_max = chunk_x_min + x_step;
+                let chunk_y_min = y_min + j as f64 * y_step;
+                let chunk_y_max = chunk_y_min + y_step;
+
+                let chunk_bounds = Rect {
+                    x_min: chunk_x_min,
+                    y_min: chunk_y_min,
+                    x_max: chunk_x_max,
+                    y_max: chunk_y_max,
+                };
+
+                // 找出与此chunk相交的线段
+                let mut segment_indices = Vec::new();
+                for (idx, segment) in self.segments.iter().enumerate() {
+                    if segment.points.iter().any(|p| {
+                        p.x >= chunk_x_min
+                            && p.x <= chunk_x_max
+                            && p.y >= chunk_y_min
+                            && p.y <= chunk_y_max
+                    }) {
+                        segment_indices.push(idx);
+                    }
+                }
+
+                if !segment_indices.is_empty() {
+                    self.chunks.push(PathChunk {
+                        bounds: chunk_bounds,
+                        segments: segment_indices,
+                    });
+                }
+            }
+        }
+    }
+
+    // 按当前模式绘制：按Z着色的路径或驻留热力图
+    fn draw_history(&self, ctx: &CanvasRenderingContext2d, scale: f64, view_rect: &Rect) {
+        if self.heat_mode {
+            self.dwell.draw(ctx, view_rect);
+        } else {
+            self.draw_complete_path(ctx, scale, view_rect);
+        }
+    }
+
+    fn draw_complete_path(&self, ctx: &CanvasRenderingContext2d, scale: f64, view_rect: &Rect) {
+        if self.segments.len() < 1 && self.current_segment.is_none() {
+            return;
+        }
+
+        ctx.set_line_width(2.0 / scale);
+
+        // 高效绘制: 仅绘制可见区域内的块
+        if !self.chunks.is_empty() {
+            // 筛选可见的块
+            let visible_chunks: Vec<&PathChunk> = self
+                .chunks
+                .iter()
+                .filter(|chunk| {
+                    // 检查chunk是否与视图矩形相交
+                    !(chunk.bounds.x_max < view_rect.x_min
+                        || chunk.bounds.x_min > view_rect.x_max
+                        || chunk.bounds.y_max < view_rect.y_min
+                        || chunk.bounds.y_min > view_rect.y_max)
+                })
+                .collect();
+
+            // 绘制可见块中的线段
+            for chunk in visible_chunks {
+                for &segment_idx in &chunk.segments {
+                    if segment_idx < self.segments.len() {
+                        let segment = &self.segments[segment_idx];
+                        self.draw_segment(ctx, segment, scale);
+                    }
+                }
+            }
+        } else {
+            // 如果没有块数据，直接绘制所有线段
+            for segment in &self.segments {
+                self.draw_segment(ctx, segment, scale);
+            }
+        }
+
+        // 绘制当前正在构建的线段
+        if let Some(segment) = &self.current_segment {
+            self.draw_segment(ctx, segment, scale);
+        }
+
+        // 绘制终点标记
+        if let Some(last_point) = self.points.last() {
+            ctx.set_fill_style(&last_point.get_color().as_str().into());
+            ctx.begin_path();
+            ctx.arc(
+                last_point.x,
+                last_point.y,
+                3.0 / scale,
+                0.0,
+                2.0 * std::f64::consts::PI,
+            )
+            .unwrap();
+            ctx.fill();
+        }
+    }
+
+    // 绘制单个线段
+    fn draw_segment(&self, ctx: &CanvasRenderingContext2d, segment: &PathSegment, scale: f64) {
+        if segment.points.len() < 2 {
+            return;
+        }
+
+        let color = format!(
+            "#{:02x}{:02x}{:02x}",
+            hsv_to_rgb_r(segment.color),
+            hsv_to_rgb_g(segment.color),
+            hsv_to_rgb_b(segment.color)
+        );
+        ctx.set_stroke_style(&color.as_str().into());
+        let dashed = self.set_rapid_style(ctx, segment.rapid, scale);
+
+        ctx.begin_path();
+        ctx.move_to(segment.points[0].x, segment.points[0].y);
+
+        // 使用更高效的路径绘制方法
+        if segment.points.len() > 100 {
+            // 对于大量点，使用更高效的绘制策略
+            let mut i = 0;
+            while i < segment.points.len() {
+                ctx.line_to(segment.points[i].x, segment.points[i].y);
+                // 在大量点的情况下跳过一些点
+                i += if segment.points.len() > 1000 { 5 } else { 2 };
+            }
+            // 确保最后一个点被绘制
+            if i > segment.points.len() && !segment.points.is_empty() {
+                let last = segment.points.last().unwrap();
+                ctx.line_to(last.x, last.y);
+            }
+        } else {
+            // 对于少量点，正常绘制
+            for point in segment.points.iter().skip(1) {
+                ctx.line_to(point.x, point.y);
+            }
+        }
+
+        ctx.stroke();
+        if dashed {
+            clear_rapid_style(ctx);
+        }
+    }
+
+    // 开启区分快移时快移线段用淡色虚线，返回是否设置了虚线，画完后需要clear_rapid_style
+    fn set_rapid_style(&self, ctx: &CanvasRenderingContext2d, rapid: bool, scale: f64) -> bool {
+        let motion = if rapid {
+            MotionKind::Rapid
+        } else {
+            MotionKind::Feed
+        };
+        if !motion.dashed(self.distinguish_rapids) {
+            return false;
+        }
+        let dash = RAPID_DASH_PX / scale;
+        let _ = ctx.set_line_dash(&Array::of2(&dash.into(), &dash.into()));
+        ctx.set_global_alpha(RAPID_ALPHA);
+        true
+    }
+
+    // 优化的增量绘制函数
+    fn draw_last_segment(&self, ctx: &CanvasRenderingContext2d, scale: f64) {
+        if let Some(segment) = &self.current_segment {
+            if segment.points.len() < 2 {
+                return;
+            }
+
+            // 计算最后一个颜色
+            let color = format!(
+                "#{:02x}{:02x}{:02x}",
+                hsv_to_rgb_r(segment.color),
+                hsv_to_rgb_g(segment.color),
+                hsv_to_rgb_b(segment.color)
+            );
+
+            ctx.set_stroke_style(&color.as_str().into());
+            ctx.set_line_width(2.0 / scale);
+            let dashed = self.set_rapid_style(ctx, segment.rapid, scale);
+
+            // 绘制最新的点与上一个点之间的线段
+            let len = segment.points.len();
+            if len >= 2 {
+                let last_index = len - 1;
+                let prev_index = len - 2;
+
+                ctx.begin_path();
+                ctx.move_to(segment.points[prev_index].x, segment.points[prev_index].y);
+                ctx.line_to(segment.points[last_index].x, segment.points[last_index].y);
+                ctx.stroke();
+            }
+            if dashed {
+                clear_rapid_style(ctx);
+            }
+        }
+
+        // 重要：如果上一个线段存在且这是一个新段，绘制连接线
+        if !self.segments.is_empty() && self.current_segment.is_some() {
+            let last_segment = &self.segments[self.segments.len() - 1];
+            let current_segment = self.current_segment.as_ref().unwrap();
+
+            // 我们只需确保这些点是连接的，无论颜色是否变化
+            if let (Some(last_point_prev), Some(first_point_current)) =
+                (last_segment.points.last(), current_segment.points.first())
+            {
+                // 重要：始终绘制连接线，确保视觉连续性
+                // 使用两种颜色各绘制一半，创造平滑过渡效果
+
+                // 1. 先用旧颜色绘制一条短线
+                let connect_color1 = format!(
+                    "#{:02x}{:02x}{:02x}",
+                    hsv_to_rgb_r(last_segment.color),
+                    hsv_to_rgb_g(last_segment.color),
+                    hsv_to_rgb_b(last_segment.color)
+                );
+
+                ctx.set_stroke_style(&connect_color1.as_str().into());
+                ctx.begin_path();
+                ctx.move_to(last_point_prev.x, last_point_prev.y);
+                // 绘制到中点
+                let mid_x = (last_point_prev.x + first_point_current.x) / 2.0;
+                let mid_y = (last_point_prev.y + first_point_current.y) / 2.0;
+                ctx.line_to(mid_x, mid_y);
+                ctx.stroke();
+
+                // 2. 再用新颜色绘制另一半
+                let connect_color2 = format!(
+                    "#{:02x}{:02x}{:02x}",
+                    hsv_to_rgb_r(current_segment.color),
+                    hsv_to_rgb_g(current_segment.color),
+                    hsv_to_rgb_b(current_segment.color)
+                );
+
+                ctx.set_stroke_style(&connect_color2.as_str().into());
+                ctx.begin_path();
+                ctx.move_to(mid_x, mid_y);
+                ctx.line_to(first_point_current.x, first_point_current.y);
+                ctx.stroke();
+            }
+        }
+
+        // 绘制当前点作为标记
+        if let Some(segment) = &self.current_segment {
+            if let Some(current_point) = segment.points.last() {
+                let color = format!(
+                    "#{:02x}{:02x}{:02x}",
+                    hsv_to_rgb_r(current_point.color),
+                    hsv_to_rgb_g(current_point.color),
+                    hsv_to_rgb_b(current_point.color)
+                );
+
+                ctx.set_fill_style(&color.as_str().into());
+                ctx.begin_path();
+                ctx.arc(
+                    current_point.x,
+                    current_point.y,
+                    3.0 / scale,
+                    0.0,
+                    2.0 * std::f64::consts::PI,
+                )
+                .unwrap();
+                ctx.fill();
+            }
+        }
+    }
+
+    // 撤销最近的一条线段并重建点列表和空间分块，没有线段时返回false
+    fn undo_last_segment(&mut self) -> bool {
+        if self.current_segment.take().is_none() && self.segments.pop().is_none() {
+            return false;
+        }
+        self.points = self
+            .segments
+            .iter()
+            .flat_map(|segment| segment.points.iter().cloned())
+            .collect();
+        self.rebuild_spatial_chunks();
+        true
+    }
+
+    // 按绘制顺序遍历所有线段（包括正在构建的线段）
+    fn ordered_segments(&self) -> impl Iterator<Item = &PathSegment> {
+        self.segments.iter().chain(self.current_segment.iter())
+    }
+
+    // 查找距离给定位置最近的路径点，超过max_distance时返回None
+    fn nearest_point(&self, x: f64, y: f64, max_distance: f64) -> Option<(f64, f64)> {
+        self.points
+            .iter()
+            .map(|p| ((p.x, p.y), distance((p.x, p.y), (x, y))))
+            .filter(|(_, d)| *d <= max_distance)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(p, _)| p)
+    }
+
+    // 回放时可前进的总点数
+    fn playback_len(&self) -> usize {
+        self.ordered_segments().map(|s| s.points.len()).sum()
+    }
+
+    // 回放绘制：只绘制第from到to个点之间的线段，复用draw_segment
+    fn draw_playback(&self, ctx: &CanvasRenderingContext2d, scale: f64, from: usize, to: usize) {
+        ctx.set_line_width(2.0 / scale);
+
+        // 从上一个点开始绘制，保证增量绘制的线段首尾相连
+        let from = from.saturating_sub(1);
+        let mut start = 0;
+        for segment in self.ordered_segments() {
+            let end = start + segment.points.len();
+            if end > from && start < to {
+                let slice_start = from.max(start) - start;
+                let slice_end = to.min(end) - start;
+                let partial = PathSegment {
+                    points: segment.points[slice_start..slice_end].to_vec(),
+                    color: segment.color,
+                    rapid: segment.rapid,
+                };
+                self.draw_segment(ctx, &partial, scale);
+            }
+            if end >= to {
+                break;
+            }
+            start = end;
+        }
+    }
+}
+
+// 路径回放的逐帧状态机
+#[derive(Clone, Debug, PartialEq)]
+struct Playback {
+    // 已回放的点数
+    index: usize,
+    // 可回放的总点数
+    total: usize,
+    // 每帧前进的点数
+    speed: usize,
+    playing: bool,
+}
+
+impl Playback {
+    fn new(speed: usize) -> Self {
+        Self {
+            index: 0,
+            total: 0,
+            speed: speed.max(1),
+            playing: false,
+        }
+    }
+
+    // 是否处于回放模式（正在播放或暂停在中途）
+    fn active(&self) -> bool {
+        self.playing || (self.index > 0 && self.index < self.total)
+    }
+
+    fn play(&mut self, total: usize) {
+        self.total = total;
+        // 已经播放到结尾时从头开始
+        if self.index >= self.total {
+            self.index = 0;
+        }
+        self.playing = self.total > 0;
+    }
+
+    fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    // 前进一帧，到达结尾时自动停止
+    fn step(&mut self) {
+        if !self.playing {
+            return;
+        }
+        self.index = (self.index + self.speed).min(self.total);
+        if self.index >= self.total {
+            self.playing = false;
+        }
+    }
+
+    fn seek(&mut self, index: usize) {
+        self.index = index.min(self.total);
+    }
+}
+
+// 绘制回放帧，from为0时清空画布并重绘网格
+fn draw_playback_frame(
+    ctx: &CanvasRenderingContext2d,
+    scale: f64,
+    offset_x: f64,
+    offset_y: f64,
+    canvas: &web_sys::HtmlCanvasElement,
+    path_history: &PathHistory,
+    from: usize,
+    to: usize,
+) {
+    if from == 0 {
+        let view_rect = Rect {
+            x_min: -offset_x / scale,
+            y_min: -offset_y / scale,
+            x_max: (canvas.width() as f64 - offset_x) / scale,
+            y_max: (canvas.height() as f64 - offset_y) / scale,
+        };
+        ctx.save();
+        ctx.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+        ctx.clear_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
+        ctx.restore();
+
+        ctx.save();
+        ctx.translate(offset_x, offset_y).unwrap();
+        ctx.scale(scale, scale).unwrap();
+        draw_grid(ctx, scale, &view_rect);
+        ctx.restore();
+    }
+
+    ctx.save();
+    ctx.translate(offset_x, offset_y).unwrap();
+    ctx.scale(scale, scale).unwrap();
+    path_history.draw_playback(ctx, scale, from, to);
+    ctx.restore();
+}
+
+// 屏幕坐标转换为世界坐标（draw_canvas中平移+缩放变换的逆变换）
+fn screen_to_world(
+    screen_x: f64,
+    screen_y: f64,
+    offset_x: f64,
+    offset_y: f64,
+    scale: f64,
+) -> (f64, f64) {
+    ((screen_x - offset_x) / scale, (screen_y - offset_y) / scale)
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+// 测量距离的显示格式
+fn format_distance(a: (f64, f64), b: (f64, f64), decimals: usize) -> String {
+    format_decimals(distance(a, b), decimals)
+}
+
+// 绘制测量端点和连线，并标注距离
+fn draw_measurement(
+    ctx: &CanvasRenderingContext2d,
+    scale: f64,
+    offset_x: f64,
+    offset_y: f64,
+    points: &[(f64, f64)],
+    decimals: usize,
+) {
+    ctx.save();
+    ctx.translate(offset_x, offset_y).unwrap();
+    ctx.scale(scale, scale).unwrap();
+    ctx.set_stroke_style(&"#e11d48".into());
+    ctx.set_fill_style(&"#e11d48".into());
+    ctx.set_line_width(1.0 / scale);
+
+    for (x, y) in points {
+        ctx.begin_path();
+        ctx.arc(*x, *y, 3.0 / scale, 0.0, 2.0 * std::f64::consts::PI)
+            .unwrap();
+        ctx.fill();
+    }
+
+    if let [a, b] = points {
+        ctx.begin_path();
+        ctx.move_to(a.0, a.1);
+        ctx.line_to(b.0, b.1);
+        ctx.stroke();
+
+        // 文字不随缩放变化大小
+        ctx.set_font(&format!("{}px sans-serif", 12.0 / scale));
+        ctx.fill_text(
+            &format_distance(*a, *b, decimals),
+            (a.0 + b.0) / 2.0 + 4.0 / scale,
+            (a.1 + b.1) / 2.0 - 4.0 / scale,
+        )
+        .unwrap();
+    }
+    ctx.restore();
+}
+
+// 改进的画布渲染函数 - 优化渲染性能
+fn draw_canvas(
+    ctx: &CanvasRenderingContext2d,
+    scale: f64,
+    offset_x: f64,
+    offset_y: f64,
+    canvas: &web_sys::HtmlCanvasElement,
+    path_history: &PathHistory,
+    redraw_mode: RedrawMode,
+) {
+    // 计算当前视图的矩形区域（世界坐标）
+    let view_rect = Rect {
+        x_min: -offset_x / scale,
+        y_min: -offset_y / scale,
+        x_max: (canvas.width() as f64 - offset_x) / scale,
+        y_max: (canvas.height() as f64 - offset_y) / scale,
+    };
+
+    match redraw_mode {
+        RedrawMode::Full => {
+            // 完全重绘 - 清除画布并绘制所有内容
+            ctx.save();
+            ctx.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+            ctx.clear_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
+            ctx.restore();
+
+            // 保存当前状态
+            ctx.save();
+
+            // 应用变换
+            ctx.translate(offset_x, offset_y).unwrap();
+            ctx.scale(scale, scale).unwrap();
+
+            // 1. 绘制网格和参考线
+            draw_grid(ctx, scale, &view_rect);
+
+            // 2. 绘制完整路径历史（传递可见区域信息以优化渲染）
+            path_history.draw_history(ctx, scale, &view_rect);
+
+            // 恢复状态
+            ctx.restore();
+        }
+        RedrawMode::Incremental => {
+            // 增量绘制 - 只绘制最新的线段
+            ctx.save();
+            ctx.translate(offset_x, offset_y).unwrap();
+            ctx.scale(scale, scale).unwrap();
+
+            if !path_history.heat_mode {
+                path_history.draw_last_segment(ctx, scale);
+            }
+
+            ctx.restore();
+        }
+        RedrawMode::Navigation => {
+            // 导航绘制 - 清除并重绘
+            ctx.save();
+            ctx.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+            ctx.clear_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
+            ctx.restore();
+
+            ctx.save();
+            ctx.translate(offset_x, offset_y).unwrap();
+            ctx.scale(scale, scale).unwrap();
+
+            // 1. 绘制网格和参考线
+            draw_grid(ctx, scale, &view_rect);
+
+            // 2. 绘制完整路径历史（传递可见区域信息）
+            path_history.draw_history(ctx, scale, &view_rect);
+
+            ctx.restore();
+        }
+    }
+}
+
+// 优化的网格绘制函数
+fn draw_grid(ctx: &CanvasRenderingContext2d, scale: f64, view_rect: &Rect) {
+    ctx.set_stroke_style(&"#d0d0d0".into());
+    ctx.set_line_width(0.5 / scale);
+
+    let grid_size = 10.0;
+
+    // 只绘制可见区域的网格线，而不是固定数量
+    let min_x = (view_rect.x_min / grid_size).floor() as i32;
+    let max_x = (view_rect.x_max / grid_size).ceil() as i32;
+    let min_y = (view_rect.y_min / grid_size).floor() as i32;
+    let max_y = (view_rect.y_max / grid_size).ceil() as i32;
+
+    // 限制线条数量以避免性能问题
+    let max_lines = 100;
+    let x_step = if max_x - min_x > max_lines {
+        (max_x - min_x) / max_lines
+    } else {
+        1
+    };
+    let y_step = if max_y - min_y > max_lines {
+        (max_y - min_y) / max_lines
+    } else {
+        1
+    };
+
+    // 绘制垂直线
+    for i in (min_x..=max_x).step_by(x_step as usize) {
+        ctx.begin_path();
+        ctx.move_to(i as f64 * grid_size, view_rect.y_min);
+        ctx.line_to(i as f64 * grid_size, view_rect.y_max);
+        ctx.stroke();
+    }
+
+    // 绘制水平线
+    for i in (min_y..=max_y).step_by(y_step as usize) {
+        ctx.begin_path();
+        ctx.move_to(view_rect.x_min, i as f64 * grid_size);
+        ctx.line_to(view_rect.x_max, i as f64 * grid_size);
+        ctx.stroke();
+    }
+
+    // 坐标轴刻度标签
+    ctx.set_fill_style(&"#888".into());
+    ctx.set_font(&format!("{}px sans-serif", 10.0 / scale));
+    for tick in axis_ticks(view_rect.x_min, view_rect.x_max, MAX_TICK_LABELS) {
+        ctx.fill_text(&format_tick(tick), tick, view_rect.y_max - 2.0 / scale)
+            .unwrap();
+    }
+    for tick in axis_ticks(view_rect.y_min, view_rect.y_max, MAX_TICK_LABELS) {
+        ctx.fill_text(&format_tick(tick), view_rect.x_min + 2.0 / scale, tick)
+            .unwrap();
+    }
+}
+
+// 绘制模式枚举
+enum RedrawMode {
+    Full,        // 完全重绘（清除并绘制所有内容）
+    Incremental, // 增量绘制（只绘制新内容）
+    Navigation,  // 导航绘制（用于缩放/平移）
+}
+
+// 优化的存储函数，分块保存和加载
+fn save_path_history(path_history: &PathHistory) {
+    // 确保当前线段已添加到segments中
+    let mut segments = path_history.segments.clone();
+    if let Some(segment) = &path_history.current_segment {
+        segments.push(segment.clone());
+    }
+
+    // 对于大量数据，分片存储
+    if segments.len() > 50 {
+        // 每批最多存储50个线段
+        let batch_count = (segments.len() + 49) / 50;
+
+        for batch in 0..batch_count {
+            let start = batch * 50;
+            let end = std::cmp::min(start + 50, segments.len());
+            let batch_segments = &segments[start..end];
+
+            let (_, set_segments, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
+                leptos_use::storage::StorageType::Local,
+                format!("path_segments_batch_{}", batch).as_str(),
+            );
+            set_segments.set(batch_segments.to_vec());
+        }
+
+        // 存储批次数量
+        let (_, set_batch_count, _) = use_storage::<usize, JsonSerdeCodec>(
+            leptos_use::storage::StorageType::Local,
+            "path_segments_batch_count",
+        );
+        set_batch_count.set(batch_count);
+    } else {
+        // 数据量小，直接存储
+        let (_, set_segments, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
+            leptos_use::storage::StorageType::Local,
+            "path_segments",
+        );
+        set_segments.set(segments);
+
+        // 清除批次计数
+        let (_, set_batch_count, _) = use_storage::<usize, JsonSerdeCodec>(
+            leptos_use::storage::StorageType::Local,
+            "path_segments_batch_count",
+        );
+        set_batch_count.set(0);
+    }
+}
+
+// 从本地存储加载路径历史
+fn load_path_history() -> PathHistory {
+    let (batch_count_signal, _, _) = use_storage::<usize, JsonSerdeCodec>(
+        leptos_use::storage::StorageType::Local,
+        "path_segments_batch_count",
+    );
+    let batch_count = batch_count_signal.get_untracked();
+
+    let mut history = PathHistory::new();
+
+    if batch_count > 0 {
+        // 分批加载
+        for batch in 0..batch_count {
+            let (segments_signal, _, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
+                leptos_use::storage::StorageType::Local,
+                format!("path_segments_batch_{}", batch).as_str(),
+            );
+            let segments = segments_signal.get_untracked();
+            history.segments.extend(segments);
+
+            // 重建点列表
+            for segment in &history.segments {
+                history.points.extend(segment.points.clone());
+            }
+        }
+    } else {
+        // 直接加载
+        let (segments_signal, _, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
+            leptos_use::storage::StorageType::Local,
+            "path_segments",
+        );
+        history.segments = segments_signal.get_untracked();
+
+        // 重建点列表
+        for segment in &history.segments {
+            history.points.extend(segment.points.clone());
+        }
+    }
+
+    // 重建空间索引
+    if !history.segments.is_empty() {
+        history.rebuild_spatial_chunks();
+    }
+
+    history
+}
+
+// Decrepted for bad performance
+#[component]
+fn PointVisual() -> impl IntoView {
+    let i18n = use_i18n();
+    let canvas_ref = NodeRef::<Canvas>::new();
+    let context = Rc::new(RefCell::new(None));
+
+    // 使用RefCell保存路径历史，以便在不同闭包中修改
+    let path_history = Rc::new(RefCell::new(PathHistory::new()));
+
+    // 存储最新的移动状态
+    let current_status = RwSignal::new(MoveStatus::default());
+
+    // 视图变换状态，恢复上次的缩放和平移
+    let (scale, offset_x, offset_y) = use_view_preferences("point_visual_view");
+
+    // 鼠标交互状态
+    let dragging = RwSignal::new(false);
+    let last_mouse_x = RwSignal::new(0);
+    let last_mouse_y = RwSignal::new(0);
+
+    // 路径回放状态
+    let playback = RwSignal::new(Playback::new(5));
+
+    // 点简化阈值(世界坐标)，只影响之后新增的点
+    let tolerance = RwSignal::new(PathHistory::new().simplification_tolerance);
+
+    // 测量模式：两次点击放置测量端点（世界坐标）
+    let measuring = RwSignal::new(false);
+    let (ui_preferences, _) = use_cookie::<UiPreferences, JsonSerdeCodec>("ui_preferences_cookie");
+    let measure_points = RwSignal::new(Vec::<(f64, f64)>::new());
+
+    // 连接到WebSocket的移动状态信号
+    let move_status =
+        leptos_ws::ServerSignal::new("move_status".to_string(), MoveStatus::default()).unwrap();
+    // 快移画法与路径图样式一致
+    let (path_image_style, _) =
+        use_cookie::<PathImageStyle, JsonSerdeCodec>("path_image_style_cookie");
+    let distinguish_rapids = Memo::new(move |_| {
+        path_image_style
+            .get()
+            .unwrap_or_default()
+            .distinguish_rapids
+    });
+    // 驻留热力图模式，以及当前最长的驻留时间(ms)用于图例
+    let heat_mode = RwSignal::new(false);
+    let max_dwell_ms = RwSignal::new(0.0);
+
+    // 初始化画布和加载历史
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+
+    Effect::new(move || {
+        if canvas_ref.get().is_some() && context_clone.borrow().is_none() {
+            logging::log!("Initializing canvas...");
+
+            // 获取Canvas上下文
+            let canvas = canvas_ref.get().unwrap();
+            let ctx = canvas
+                .get_context("2d")
+                .unwrap()
+                .unwrap()
+                .dyn_into::<CanvasRenderingContext2d>()
+                .unwrap();
+
+            // 设置线条样式
+            ctx.set_line_cap("round");
+            ctx.set_line_join("round");
+
+            // 存储上下文
+            context_clone.borrow_mut().replace(ctx);
+
+            // 加载历史路径
+            let mut history = load_path_history();
+            history.simplification_tolerance = tolerance.get_untracked();
+            history.distinguish_rapids = distinguish_rapids.get_untracked();
+            history.heat_mode = heat_mode.get_untracked();
+            *path_history_clone.borrow_mut() = history;
+
+            // 初始渲染
+            if let Some(ctx) = context_clone.borrow().as_ref() {
+                draw_canvas(
+                    ctx,
+                    scale.get(),
+                    offset_x.get(),
+                    offset_y.get(),
+                    &canvas,
+                    &path_history_clone.borrow(),
+                    RedrawMode::Full,
+                );
+            }
+        }
+    });
+
+    // 处理移动状态更新
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+
+    Effect::new(move || {
+        let status = move_status.get();
+        current_status.set(status.clone());
+
+        if let Some(canvas) = canvas_ref.get() {
+            if let Some(ctx) = context_clone.borrow().as_ref() {
+                let mut path = path_history_clone.borrow_mut();
+
+                // 主轴转动或有轴在运动时才计入驻留，停机时的等待不算
+                let active = status.spindle_rpm > 0.0
+                    || !(status.x.is_idle && status.y.is_idle && status.z.is_idle);
+                path.dwell.record(
+                    status.x.pos as f64,
+                    status.y.pos as f64,
+                    web_sys::js_sys::Date::now(),
+                    active,
+                );
+                max_dwell_ms.set(path.dwell.max_ms);
+
+                // 只有当点不同时才添加新点
+                let is_new_point = path.points.is_empty()
+                    || path
+                        .points
+                        .last()
+                        .map(|point| {
+                            point.x != status.x.pos as f64 || point.y != status.y.pos as f64
+                        })
+                        .unwrap_or(true);
+
+                if is_new_point {
+                    // 计算当前Z轴位置作为颜色
+                    let current_color = (5.0 - status.z.pos).clamp(0.0, 5.0) as u8 * (255 / 5);
+
+                    // 添加新点到路径历史
+                    let rapid = status.motion == MotionKind::Rapid;
+                    path.add_point(
+                        status.x.pos as f64,
+                        status.y.pos as f64,
+                        current_color,
+                        rapid,
+                    );
+
+                    // 每次添加点后，确保完整绘制当前线段
+                    // 这样可以解决颜色过渡时的断线问题
+                    let redraw_mode = if path.points.len() <= 2 || path.heat_mode {
+                        // 第一个或第二个点时完全重绘，热力图的颜色随最长驻留变化也要完全重绘
+                        RedrawMode::Full
+                    } else {
+                        // 增量绘制包含连接线
+                        RedrawMode::Incremental
+                    };
+
+                    // 回放期间不绘制实时路径，避免覆盖回放画面
+                    if !playback.with_untracked(Playback::active) {
+                        draw_canvas(
+                            ctx,
+                            scale.get(),
+                            offset_x.get(),
+                            offset_y.get(),
+                            &canvas,
+                            &path,
+                            redraw_mode,
+                        );
+                    }
+
+                    // 定期保存历史
+                    if path.points.len() % 10 == 0 {
+                        save_path_history(&path);
+                    }
+                }
+            }
+        }
+    });
+
+    // 切换快移画法后整体重绘
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+    Effect::watch(
+        move || distinguish_rapids.get(),
+        move |distinguish, _, _| {
+            path_history_clone.borrow_mut().distinguish_rapids = *distinguish;
+            let Some(canvas) = canvas_ref.get_untracked() else {
+                return;
+            };
+            if let Some(ctx) = context_clone.borrow().as_ref() {
+                draw_canvas(
+                    ctx,
+                    scale.get_untracked(),
+                    offset_x.get_untracked(),
+                    offset_y.get_untracked(),
+                    &canvas,
+                    &path_history_clone.borrow(),
+                    RedrawMode::Full,
+                );
+            }
+        },
+        false,
+    );
+
+    // 在按Z着色和驻留热力图之间切换后整体重绘
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+    Effect::watch(
+        move || heat_mode.get(),
+        move |heat_mode, _, _| {
+            path_history_clone.borrow_mut().heat_mode = *heat_mode;
+            let Some(canvas) = canvas_ref.get_untracked() else {
+                return;
+            };
+            if let Some(ctx) = context_clone.borrow().as_ref() {
+                draw_canvas(
+                    ctx,
+                    scale.get_untracked(),
+                    offset_x.get_untracked(),
+                    offset_y.get_untracked(),
+                    &canvas,
+                    &path_history_clone.borrow(),
+                    RedrawMode::Full,
+                );
+            }
+        },
+        false,
+    );
+
+    // 鼠标滚轮缩放
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+
+    let handle_wheel = move |ev: web_sys::WheelEvent| {
+        ev.prevent_default();
+        let delta = if ev.delta_y() > 0.0 { 0.9 } else { 1.1 };
+
+        if let Some(canvas) = canvas_ref.get() {
+            if let Some(ctx) = context_clone.borrow().as_ref() {
+                let rect = canvas.get_bounding_client_rect();
+                let mouse_x = ev.client_x() as f64 - rect.left();
+                let mouse_y = ev.client_y() as f64 - rect.top();
+
+                // 计算缩放前鼠标在世界坐标中的位置
+                let world_x = (mouse_x - offset_x.get()) / scale.get();
+                let world_y = (mouse_y - offset_y.get()) / scale.get();
+
+                // 更新缩放
+                scale.update(|s| *s *= delta);
+
+                // 调整偏移量以保持鼠标位置不变
+                offset_x.set(mouse_x - world_x * scale.get());
+                offset_y.set(mouse_y - world_y * scale.get());
+
+                // 重绘使用导航模式
+                draw_canvas(
+                    ctx,
+                    scale.get(),
+                    offset_x.get(),
+                    offset_y.get(),
+                    &canvas,
+                    &path_history_clone.borrow(),
+                    RedrawMode::Navigation,
+                );
+            }
+        }
+    };
+
+    // 鼠标拖拽平移
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+
+    let snap_history = path_history.clone();
+
+    let handle_mouse_down = move |ev: web_sys::MouseEvent| {
+        if measuring.get_untracked() {
+            if let Some(canvas) = canvas_ref.get() {
+                let rect = canvas.get_bounding_client_rect();
+                let (x, y) = screen_to_world(
+                    ev.client_x() as f64 - rect.left(),
+                    ev.client_y() as f64 - rect.top(),
+                    offset_x.get(),
+                    offset_y.get(),
+                    scale.get(),
+                );
+                // 吸附到附近的路径点
+                let point = snap_history
+                    .borrow()
+                    .nearest_point(x, y, 5.0 / scale.get())
+                    .unwrap_or((x, y));
+                measure_points.update(|points| {
+                    if points.len() >= 2 {
+                        points.clear();
+                    }
+                    points.push(point);
+                });
+            }
+            return;
+        }
+        dragging.set(true);
+        last_mouse_x.set(ev.client_x());
+        last_mouse_y.set(ev.client_y());
+    };
+
+    let handle_mouse_move = move |ev: web_sys::MouseEvent| {
+        if dragging.get() {
+            if let Some(canvas) = canvas_ref.get() {
+                if let Some(ctx) = context_clone.borrow().as_ref() {
+                    let dx = ev.client_x() - last_mouse_x.get();
+                    let dy = ev.client_y() - last_mouse_y.get();
+
+                    offset_x.update(|x| *x += dx as f64);
+                    offset_y.update(|y| *y += dy as f64);
+
+                    last_mouse_x.set(ev.client_x());
+                    last_mouse_y.set(ev.client_y());
+
+                    // 使用导航模式重绘
+                    draw_canvas(
+                        ctx,
+                        scale.get(),
+                        offset_x.get(),
+                        offset_y.get(),
+                        &canvas,
+                        &path_history_clone.borrow(),
+                        RedrawMode::Navigation,
+                    );
+                }
+            }
+        }
+    };
+
+    let handle_mouse_up = move |_| {
+        dragging.set(false);
+    };
+
+    // 重置视图
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+
+    let reset_view = move |_| {
+        if let Some(canvas) = canvas_ref.get() {
+            if let Some(ctx) = context_clone.borrow().as_ref() {
+                let default = ViewPreferences::default();
+                scale.set(default.zoom);
+                offset_x.set(default.offset_x);
+                offset_y.set(default.offset_y);
+
+                draw_canvas(
+                    ctx,
+                    scale.get(),
+                    offset_x.get(),
+                    offset_y.get(),
+                    &canvas,
+                    &path_history_clone.borrow(),
+                    RedrawMode::Full,
+                );
+            }
+        }
+    };
+
+    // 清除路径
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+
+    let clear_path = move |_| {
+        if let Some(canvas) = canvas_ref.get() {
+            if let Some(ctx) = context_clone.borrow().as_ref() {
+                // 完全清空路径历史
+                let mut history = path_history_clone.borrow_mut();
+                history.points.clear();
+                history.segments.clear();
+                history.current_segment = None;
+                history.chunks.clear();
+                history.dwell.clear();
+                max_dwell_ms.set(0.0);
+
+                // 重绘空画布
+                draw_canvas(
+                    ctx,
+                    scale.get(),
+                    offset_x.get(),
+                    offset_y.get(),
+                    &canvas,
+                    &history,
+                    RedrawMode::Full,
+                );
+
+                // 清除本地存储中所有相关数据
+                let (batch_count, set_batch_count, _) = use_storage::<usize, JsonSerdeCodec>(
+                    leptos_use::storage::StorageType::Local,
+                    "path_segments_batch_count",
+                );
+                let batch_count = batch_count.get_untracked();
+
+                // 清除所有批次
+                for batch in 0..batch_count {
+                    let (_, set_segments, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
+                        leptos_use::storage::StorageType::Local,
+                        format!("path_segments_batch_{}", batch).as_str(),
+                    );
+                    set_segments.set(Vec::new());
+                }
+
+                // 清除主存储
+                let (_, set_segments, _) = use_storage::<Vec<PathSegment>, JsonSerdeCodec>(
+                    leptos_use::storage::StorageType::Local,
+                    "path_segments",
+                );
+                set_segments.set(Vec::new());
+
+                // 重置批次计数
+                set_batch_count.set(0);
+            }
+        }
+    };
+
+    // 撤销最近的线段，重绘并同步本地存储
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+
+    let undo_segment = move |_| {
+        let mut history = path_history_clone.borrow_mut();
+        if !history.undo_last_segment() {
+            return;
+        }
+        save_path_history(&history);
+        if let Some(canvas) = canvas_ref.get() {
+            if let Some(ctx) = context_clone.borrow().as_ref() {
+                draw_canvas(
+                    ctx,
+                    scale.get(),
+                    offset_x.get(),
+                    offset_y.get(),
+                    &canvas,
+                    &history,
+                    RedrawMode::Full,
+                );
+            }
+        }
+    };
+
+    // 导出路径点为CSV
+    let csv_link = NodeRef::<leptos::html::A>::new();
+    let path_history_clone = path_history.clone();
+
+    let export_csv = move |_| {
+        if let Some(link) = csv_link.get_untracked() {
+            let csv = points_to_csv(&path_history_clone.borrow().points);
+            link.set_href(&data_url("text/csv", &csv));
+            link.click();
+        }
+    };
+
+    // 从CSV导入路径点，替换当前路径
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+
+    let import_csv = move |ev: web_sys::Event| {
+        let input = event_target::<web_sys::HtmlInputElement>(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        // 允许再次选择同一个文件
+        input.set_value("");
+        let reader = web_sys::FileReader::new().expect("Failed to create FileReader");
+        let context = context_clone.clone();
+        let path_history = path_history_clone.clone();
+        let loaded = Closure::once_into_js(move |event: web_sys::ProgressEvent| {
+            let target = event.target().expect("Event should have a target");
+            let reader: web_sys::FileReader =
+                target.dyn_into().expect("Target should be a FileReader");
+            let Some(text) = reader.result().ok().and_then(|result| result.as_string()) else {
+                logging::error!("Failed to read CSV file");
+                return;
+            };
+            let (points, skipped) = points_from_csv(&text);
+            if skipped > 0 {
+                logging::warn!("Skipped {} malformed CSV rows", skipped);
+            }
+            let mut history = path_history.borrow_mut();
+            *history = PathHistory::new();
+            history.simplification_tolerance = tolerance.get_untracked();
+            history.distinguish_rapids = distinguish_rapids.get_untracked();
+            history.heat_mode = heat_mode.get_untracked();
+            for point in points {
+                history.add_point(point.x, point.y, point.color, point.rapid);
+            }
+            history.rebuild_spatial_chunks();
+            save_path_history(&history);
+            if let Some(canvas) = canvas_ref.get_untracked() {
+                if let Some(ctx) = context.borrow().as_ref() {
+                    draw_canvas(
+                        ctx,
+                        scale.get_untracked(),
+                        offset_x.get_untracked(),
+                        offset_y.get_untracked(),
+                        &canvas,
+                        &history,
+                        RedrawMode::Full,
+                    );
+                }
+            }
+        });
+        reader.set_onload(Some(loaded.unchecked_ref()));
+        if let Err(e) = reader.read_as_text(&file) {
+            logging::error!("Error reading file: {:?}", e);
+        }
+    };
+
+    // 回放动画：播放时每帧前进一步
+    Effect::new(move || {
+        if playback.with(|p| p.playing) {
+            request_animation_frame(move || playback.update(Playback::step));
+        }
+    });
+
+    // 回放绘制：前进时增量绘制，后退或重新开始时完全重绘
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+
+    Effect::watch(
+        move || playback.with(|p| p.index),
+        move |index, prev_index, _| {
+            if let Some(canvas) = canvas_ref.get_untracked() {
+                if let Some(ctx) = context_clone.borrow().as_ref() {
+                    let from = match prev_index {
+                        Some(prev) if *prev <= *index => *prev,
+                        _ => 0,
+                    };
+                    draw_playback_frame(
+                        ctx,
+                        scale.get_untracked(),
+                        offset_x.get_untracked(),
+                        offset_y.get_untracked(),
+                        &canvas,
+                        &path_history_clone.borrow(),
+                        from,
+                        *index,
+                    );
+                }
+            }
+        },
+        false,
+    );
+
+    // 测量标注：端点或视图变化后重绘画布并叠加测量线
+    let context_clone = context.clone();
+    let path_history_clone = path_history.clone();
+
+    Effect::watch(
+        move || {
+            (
+                measure_points.get(),
+                scale.get(),
+                offset_x.get(),
+                offset_y.get(),
+            )
+        },
+        move |(points, scale, offset_x, offset_y), _, _| {
+            if points.is_empty() {
+                return;
+            }
+            if let Some(canvas) = canvas_ref.get_untracked() {
+                if let Some(ctx) = context_clone.borrow().as_ref() {
+                    draw_canvas(
+                        ctx,
+                        *scale,
+                        *offset_x,
+                        *offset_y,
+                        &canvas,
+                        &path_history_clone.borrow(),
+                        RedrawMode::Navigation,
+                    );
+                    let decimals = ui_preferences
+                        .get_untracked()
+                        .unwrap_or_default()
+                        .position_decimals;
+                    draw_measurement(ctx, *scale, *offset_x, *offset_y, points, decimals);
+                }
+            }
+        },
+        false,
+    );
+
+    let path_history_clone = path_history.clone();
+    let toggle_playback = move |_| {
+        let total = path_history_clone.borrow().playback_len();
+        playback.update(|p| {
+            if p.playing {
+                p.pause();
+            } else {
+                p.play(total);
+            }
+        });
+    };
+
+    // 保存路径
+    let path_history_clone = path_history.clone();
+
+    let save_path = move |_| {
+        logging::log!("Manually saving path history");
+        save_path_history(&path_history_clone.borrow());
+    };
+
+    let path_history_clone = path_history.clone();
+    let set_tolerance = move |ev| {
+        if let Ok(value) = event_target_value(&ev).parse::<f64>() {
+            tolerance.set(value);
+            path_history_clone.borrow_mut().simplification_tolerance = value;
+        }
+    };
+
+    view! {
+        <div class="canvas-controls">
+            <input
+                type="range"
+                min="0.1"
+                max="5.0"
+                step="0.1"
+                value="1.0"
+                on:input=move |ev| {
+                    if let Ok(value) = event_target_value(&ev).parse::<f64>() {
+                        if let Some(canvas) = canvas_ref.get() {
+                            if let Some(ctx) = context.borrow().as_ref() {
+                                scale.set(value);
+                                draw_canvas(
+                                    ctx,
+                                    scale.get(),
+                                    offset_x.get(),
+                                    offset_y.get(),
+                                    &canvas,
+                                    &path_history.borrow(),
+                                    RedrawMode::Navigation,
+                                );
+                            }
+                        }
+                    }
+                }
+            />
+            <span class="zoom-info">{move || format!("Zoom: {:.1}x", scale.get())}</span>
+            // 阈值越小细节越多，阈值越大点越少、占用内存越少
+            <label title=move || i18n.t("simplification_tolerance_hint")>
+                {move || i18n.t("simplification_tolerance")}
+                " "
+                <input
+                    type="range"
+                    min="0.1"
+                    max="5.0"
+                    step="0.1"
+                    prop:value=move || tolerance.get().to_string()
+                    on:input=set_tolerance
+                />
+                {move || format!(" {:.1}", tolerance.get())}
+            </label>
+            <button on:click=save_path>{move || i18n.t("save_path")}</button>
+            <button on:click=undo_segment>{move || i18n.t("undo_segment")}</button>
+            <button on:click=clear_path>{move || i18n.t("clear_path")}</button>
+            <button on:click=export_csv>{move || i18n.t("export_csv")}</button>
+            <a node_ref=csv_link download="path_points.csv" style="display: none;"></a>
+            <label class="import-csv">
+                {move || i18n.t("import_csv")}
+                <input type="file" accept=".csv,text/csv" on:change=import_csv />
+            </label>
+            <button on:click=reset_view>{move || i18n.t("reset_view")}</button>
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || heat_mode.get()
+                    on:change=move |ev| heat_mode.set(event_target_checked(&ev))
+                />
+                {move || i18n.t("dwell_heat")}
+            </label>
+            <Show when=move || heat_mode.get()>
+                <span class="zoom-info">
+                    {move || {
+                        format!("{}: {:.1}s", i18n.t("max_dwell"), max_dwell_ms.get() / 1000.0)
+                    }}
+                </span>
+            </Show>
+            <span class="position-info">
+                {move || {
+                    let prefs = ui_preferences.get().unwrap_or_default();
+                    format!(
+                        "X: {}, Y: {}",
+                        prefs.format_position(current_status.get().x.pos as f64),
+                        prefs.format_position(current_status.get().y.pos as f64),
+                    )
+                }}
+            </span>
+        </div>
+        <div class="canvas-controls playback-controls">
+            <button on:click=toggle_playback>
+                {move || {
+                    if playback.with(|p| p.playing) { i18n.t("pause") } else { i18n.t("play") }
+                }}
+            </button>
+            <input
+                type="range"
+                min="0"
+                max=move || playback.with(|p| p.total).to_string()
+                prop:value=move || playback.with(|p| p.index).to_string()
+                on:input=move |ev| {
+                    if let Ok(index) = event_target_value(&ev).parse::<usize>() {
+                        playback.update(|p| {
+                            p.pause();
+                            p.seek(index);
+                        });
+                    }
+                }
+            />
+            <span class="zoom-info">
+                {move || playback.with(|p| format!("{}/{}", p.index, p.total))}
+            </span>
+            <button on:click=move |_| {
+                measuring.update(|m| *m = !*m);
+                measure_points.update(|points| points.clear());
+            }>
+                {move || {
+                    if measuring.get() { i18n.t("exit_measure") } else { i18n.t("measure") }
+                }}
+            </button>
+            <span class="position-info">
+                {move || {
+                    measure_points
+                        .with(|points| match points.as_slice() {
+                            [a, b] => {
+                                let decimals =
+                                    ui_preferences.get().unwrap_or_default().position_decimals;
+                                format!("Distance: {}", format_distance(*a, *b, decimals))
+                            }
+                            _ => String::new(),
+                        })
+                }}
+            </span>
+            <label>{move || i18n.t("speed")}</label>
+            <input
+                type="range"
+                min="1"
+                max="50"
+                step="1"
+                value="5"
+                on:input=move |ev| {
+                    if let Ok(speed) = event_target_value(&ev).parse::<usize>() {
+                        playback.update(|p| p.speed = speed.max(1));
+                    }
+                }
+            />
+        </div>
+        <canvas
+            width="400"
+            height="400"
+            style="border: 1px solid black; cursor: move; background-color: #fafafa;"
+            node_ref=canvas_ref
+            on:mousedown=handle_mouse_down
+            on:mousemove=handle_mouse_move
+            on:mouseup=handle_mouse_up
+            on:mouseleave=handle_mouse_up
+            on:wheel=handle_wheel
+        />
+    }
+}
+
+// 当前速度占配置最大速度的比例(0~1)，用于速度条宽度，最大速度未配置(<=0)时为0
+fn speed_ratio(speed: f64, max_speed: f64) -> f64 {
+    if max_speed <= 0.0 || !max_speed.is_finite() || !speed.is_finite() {
+        0.0
+    } else {
+        (speed.abs() / max_speed).clamp(0.0, 1.0)
+    }
+}
+
+// 状态表一行的内容，由语言、显示精度和轴状态生成
+type AxisRowFormat = fn(I18n, UiPreferences, &AxisMoveStatus) -> String;
+
+// 状态表的表头，每列一个轴名，axes为(轴名, 列的class)
+#[component]
+fn AxisStatusHeader(axes: Vec<(&'static str, &'static str)>) -> impl IntoView {
+    let i18n = use_i18n();
+    let header_cells = axes
+        .into_iter()
+        .map(|(name, class)| {
+            view! {
+                <TableCell class=class>
+                    <h3>{move || format!("{name}{}", i18n.t("axis_suffix"))}</h3>
+                </TableCell>
+            }
+        })
+        .collect_view();
+    view! {
+        <TableHeader>
+            <TableRow>
+                <TableCell>"    "</TableCell>
+                {header_cells}
+            </TableRow>
+        </TableHeader>
+    }
+}
+
+#[component]
+fn AxisVisual() -> impl IntoView {
+    let (global_state, set_global_state) =
+        use_cookie::<GlobalState, JsonSerdeCodec>("global_state_cookie");
+    // Ensure global state is initialized
+    if global_state.read_untracked().is_none() {
+        set_global_state.set(Some(GlobalState::default()));
+    }
+
+    let connected = move || global_state.get().unwrap().connected;
+
+    let move_status =
+        leptos_ws::ServerSignal::new("move_status".to_string(), MoveStatus::default()).unwrap();
+
+    let i18n = use_i18n();
+
+    // Disabled axes are greyed out
+    let (parameters, _) = use_cookie::<Parameters, JsonSerdeCodec>("parameters_cookie");
+    let (ui_preferences, _) = use_cookie::<UiPreferences, JsonSerdeCodec>("ui_preferences_cookie");
+
+    view! {
+        <Transition fallback=move || {
+            view! { <p>{move || i18n.t("loading")}</p> }
+        }>
+            <div class="axis-status">
+                {move || {
+                    if !connected() {
+                        view! { <div class="error-message">"Not connected"</div> }
+                    } else {
+                        let params = parameters.get().unwrap_or_default();
+                        let axis_nums = [params.x.axis_num, params.y.axis_num, params.z.axis_num];
+                        // 每列对应一个轴，表头和各行都由数据生成
+                        let axes: Vec<_> = move_status
+                            .get()
+                            .axes()
+                            .into_iter()
+                            .zip(params.enabled_axes())
+                            .zip(axis_nums)
+                            .map(|(((name, status), enabled), axis_num)| {
+                                let class = if enabled { "" } else { "axis-disabled" };
+                                (name, status, class, axis_num)
+                            })
+                            .collect();
+                        let prefs = ui_preferences.get().unwrap_or_default();
+                        let rows: [(&'static str, AxisRowFormat); 4] = [
+                            ("idle", |i18n, _, s| {
+                                i18n.t(if s.is_idle { "yes" } else { "no" }).into()
+                            }),
+                            ("speed", |_, prefs, s| prefs.format_speed(s.speed as f64)),
+                            ("position", |_, prefs, s| prefs.format_position(s.pos as f64)),
+                            ("alarm", |i18n, _, s| {
+                                i18n.t(if s.in_alarm { "yes" } else { "no" }).into()
+                            }),
+                        ];
+                        // 报警的轴显示醒目提示和复位按钮，复位前服务端拒绝运动指令
+                        let alarm_banner = axes
+                            .iter()
+                            .filter(|(_, status, _, _)| status.in_alarm)
+                            .map(|(name, _, _, axis_num)| {
+                                let (name, axis_num) = (*name, *axis_num);
+                                let reset = move |_| {
+                                    spawn_local(async move {
+                                        if let Err(e) = zmc_reset_alarm(axis_num).await {
+                                            logging::error!("Failed to reset alarm: {}", e);
+                                        }
+                                    });
+                                };
+                                view! {
+                                    <div class="alarm-banner">
+                                        {move || {
+                                            format!(
+                                                "{name}{} {}",
+                                                i18n.t("axis_suffix"),
+                                                i18n.t("axis_alarm"),
+                                            )
+                                        }}
+                                        " "
+                                        <Button on_click=reset>
+                                            {move || i18n.t("reset_alarm")}
+                                        </Button>
+                                    </div>
+                                }
+                            })
+                            .collect_view();
+                        let header_axes =
+                            axes.iter().map(|(name, _, class, _)| (*name, *class)).collect();
+                        let body_rows = rows
+                            .into_iter()
+                            .map(|(key, value)| {
+                                let cells = axes
+                                    .iter()
+                                    .map(|(_, status, class, _)| {
+                                        let text = value(i18n, prefs, status);
+                                        view! { <TableCell class=*class>{text}</TableCell> }
+                                    })
+                                    .collect_view();
+                                view! {
+                                    <TableRow>
+                                        <TableCell>{move || i18n.t(key)}</TableCell>
+                                        {cells}
+                                    </TableRow>
+                                }
+                            })
+                            .collect_view();
+                        // 跟随误差超过参数中的限值时标红
+                        let following_error_cells = axes
+                            .iter()
+                            .map(|(_, status, class, _)| {
+                                let exceeded = params.following_error_exceeded(
+                                    status.following_error,
+                                );
+                                let text = prefs.format_position(status.following_error as f64);
+                                view! {
+                                    <TableCell class=*class>
+                                        <span class:following-error-exceeded=exceeded>
+                                            {text}
+                                        </span>
+                                    </TableCell>
+                                }
+                            })
+                            .collect_view();
+                        // 速度条：当前速度相对于速度参数中的最大速度
+                        let max_speed = params.speed.max_speed as f64;
+                        let speed_cells = axes
+                            .iter()
+                            .map(|(_, status, class, _)| {
+                                let percent = speed_ratio(status.speed as f64, max_speed) * 100.0;
+                                view! {
+                                    <TableCell class=*class>
+                                        <div class="speed-bar">
+                                            <div
+                                                class="speed-bar-fill"
+                                                style=format!("width: {percent:.0}%")
+                                            ></div>
+                                        </div>
+                                        <span class="speed-bar-percent">
+                                            {format!("{percent:.0}%")}
+                                        </span>
+                                    </TableCell>
+                                }
+                            })
+                            .collect_view();
+                        view! {
+                            <div class="axis-status-container">
+                                {alarm_banner}
+                                <Table class="axis-status-table">
+                                    <AxisStatusHeader axes=header_axes />
+                                    <TableBody>
+                                        {body_rows}
+                                        <TableRow>
+                                            <TableCell>
+                                                {move || i18n.t("following_error")}
+                                            </TableCell>
+                                            {following_error_cells}
+                                        </TableRow>
+                                        <TableRow>
+                                            <TableCell>{move || i18n.t("speed_ratio")}</TableCell>
+                                            {speed_cells}
+                                        </TableRow>
+                                    </TableBody>
+                                </Table>
+                            </div>
+                        }
+                    }
+                }}
+            </div>
+        </Transition>
+    }
+}
+
+// 计算刷新率时保留的最近到达次数
+const RATE_WINDOW: usize = 20;
+// 可选的服务端状态推送间隔(ms)
+const POLLING_INTERVAL_OPTIONS: [u64; 5] = [50, 100, 200, 500, 1000];
+
+// 由最近的到达时间戳(ms)计算滚动平均刷新率(Hz)，不足两次时返回None
+fn update_rate(arrivals: &VecDeque<f64>) -> Option<f64> {
+    let span = arrivals.back()? - arrivals.front()?;
+    (arrivals.len() > 1 && span > 0.0).then(|| (arrivals.len() - 1) as f64 * 1000.0 / span)
+}
+
+#[component]
+fn UpdateRate() -> impl IntoView {
+    let i18n = use_i18n();
+    let move_status = ServerSignal::new("move_status".to_string(), MoveStatus::default())
+        .expect("Failed to create client signal");
+
+    // 记录每次move_status到达的时间
+    let arrivals = RwSignal::new(VecDeque::with_capacity(RATE_WINDOW));
+    Effect::new(move |_| {
+        // 只关心到达时间，不需要状态内容
+        let _ = move_status.get();
+        let now = web_sys::js_sys::Date::now();
+        arrivals.update(|arrivals| {
+            if arrivals.len() == RATE_WINDOW {
+                arrivals.pop_front();
+            }
+            arrivals.push_back(now);
+        });
+    });
+    let rate_text = move || match arrivals.with(update_rate) {
+        Some(rate) => format!("{:.1} Hz", rate),
+        None => "-".to_string(),
+    };
+
+    // 请求的推送间隔保存在cookie中，变化时通知服务端
+    let (polling_interval, set_polling_interval) =
+        use_cookie::<u64, JsonSerdeCodec>("polling_interval_cookie");
+    if polling_interval.read_untracked().is_none() {
+        set_polling_interval.set(Some(100));
+    }
+    Effect::watch(
+        move || polling_interval.get(),
+        move |interval, _, _| {
+            if let Some(interval) = *interval {
+                spawn_local(async move {
+                    zmc_set_polling_interval(interval)
+                        .await
+                        .expect("Failed to set polling interval");
+                });
+            }
+        },
+        true,
+    );
+
+    view! {
+        <div class="update-rate">
+            <label>
+                {move || i18n.t("update_interval")}
+                " "
+                <select on:change=move |ev| {
+                    if let Ok(interval) = event_target_value(&ev).parse() {
+                        set_polling_interval.set(Some(interval));
+                    }
+                }>
+                    {POLLING_INTERVAL_OPTIONS
+                        .into_iter()
+                        .map(|interval| {
+                            view! {
+                                <option
+                                    value=interval.to_string()
+                                    selected=move || polling_interval.get() == Some(interval)
+                                >
+                                    {format!("{} ms", interval)}
+                                </option>
+                            }
+                        })
+                        .collect_view()}
+                </select>
+            </label>
+            <span class="update-rate-info">
+                {move || i18n.t("update_rate")}
+                ": "
+                {rate_text}
+            </span>
+        </div>
+    }
+}
+
+// 位置和速度的显示小数位数，保存在界面偏好cookie中
+#[component]
+fn DisplayPrecision() -> impl IntoView {
+    let i18n = use_i18n();
+    let (ui_preferences, set_ui_preferences) =
+        use_cookie::<UiPreferences, JsonSerdeCodec>("ui_preferences_cookie");
+    let prefs = move || ui_preferences.get().unwrap_or_default();
+    let update = move |f: fn(&mut UiPreferences, usize), ev: leptos::ev::Event| {
+        if let Ok(decimals) = event_target_value(&ev).parse() {
+            let mut prefs = ui_preferences.get_untracked().unwrap_or_default();
+            f(&mut prefs, decimals);
+            set_ui_preferences.set(Some(prefs));
+        }
+    };
+    let options = move |selected: fn(&UiPreferences) -> usize| {
+        (0..=UiPreferences::MAX_DECIMALS)
+            .map(|decimals| {
+                view! {
+                    <option
+                        value=decimals.to_string()
+                        selected=move || selected(&prefs()) == decimals
+                    >
+                        {decimals}
+                    </option>
+                }
+            })
+            .collect_view()
+    };
+
+    view! {
+        <div class="display-precision">
+            <label>
+                {move || i18n.t("position_decimals")}
+                " "
+                <select on:change=move |ev| update(|p, d| p.position_decimals = d, ev)>
+                    {options(|p| p.position_decimals)}
+                </select>
+            </label>
+            <label>
+                {move || i18n.t("speed_decimals")}
+                " "
+                <select on:change=move |ev| update(|p, d| p.speed_decimals = d, ev)>
+                    {options(|p| p.speed_decimals)}
+                </select>
+            </label>
+        </div>
+    }
+}
+
+#[component]
+pub fn VisualView() -> impl IntoView {
+    // 画布视图性能较差，默认关闭，需要回放等功能时再打开，上次的选择保存在localStorage中
+    let canvas_view = RwSignal::new(false);
+    let (stored_canvas_view, set_stored_canvas_view, _) =
+        use_storage::<bool, JsonSerdeCodec>(StorageType::Local, "canvas_view_enabled");
+    Effect::new(move |_| canvas_view.set(stored_canvas_view.get_untracked()));
+    Effect::watch(
+        move || canvas_view.get(),
+        move |enabled, _, _| set_stored_canvas_view.set(*enabled),
+        false,
+    );
+    let i18n = use_i18n();
+
+    view! {
+        <div class="status">
+            <AxisVisual />
+            <UpdateRate />
+            <DisplayPrecision />
+            <PathVisualizer />
+            <SpeedChart />
+            <Switch checked=canvas_view label=i18n.tr("canvas_view") />
+            <Show when=move || canvas_view.get()>
+                <PointVisual />
+            </Show>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::provide_i18n;
+
+    #[test]
+    fn playback_advances_monotonically_and_stops_at_the_end() {
+        let mut playback = Playback::new(3);
+        playback.play(7);
+        let mut previous = playback.index;
+        while playback.playing {
+            playback.step();
+            assert!(playback.index > previous);
+            previous = playback.index;
+        }
+        assert_eq!(playback.index, 7);
+        assert!(!playback.active());
+        // 停下后继续step不再前进
+        playback.step();
+        assert_eq!(playback.index, 7);
+    }
+
+    #[test]
+    fn playback_does_not_step_while_paused() {
+        let mut playback = Playback::new(2);
+        playback.play(10);
+        playback.step();
+        playback.pause();
+        playback.step();
+        assert_eq!(playback.index, 2);
+        assert!(playback.active());
+    }
+
+    #[test]
+    fn playback_restarts_from_the_beginning_after_reaching_the_end() {
+        let mut playback = Playback::new(5);
+        playback.play(5);
+        playback.step();
+        assert_eq!(playback.index, 5);
+        playback.play(5);
+        assert_eq!(playback.index, 0);
+        assert!(playback.playing);
+    }
+
+    #[test]
+    fn playback_seek_is_clamped_to_the_total() {
+        let mut playback = Playback::new(1);
+        playback.play(4);
+        playback.seek(2);
+        assert_eq!(playback.index, 2);
+        playback.seek(10);
+        assert_eq!(playback.index, 4);
+    }
+
+    #[test]
+    fn playback_speed_is_at_least_one_point_per_frame() {
+        let mut playback = Playback::new(0);
+        playback.play(3);
+        playback.step();
+        assert_eq!(playback.index, 1);
+    }
+
+    #[test]
+    fn playback_of_an_empty_path_does_not_start() {
+        let mut playback = Playback::new(4);
+        playback.play(0);
+        assert!(!playback.playing);
+        assert!(!playback.active());
+    }
+
+    #[test]
+    fn each_status_table_header_names_its_own_axis() {
+        Owner::new().with(|| {
+            provide_i18n();
+            let axes = MoveStatus::default()
+                .axes()
+                .into_iter()
+                .map(|(name, _)| (name, ""))
+                .collect();
+            let html = view! { <AxisStatusHeader axes=axes /> }.to_html();
+            let headers: Vec<&str> = html
+                .split("<h3>")
+                .skip(1)
+                .map(|rest| rest.split("</h3>").next().unwrap())
+                .collect();
+            assert_eq!(headers.len(), 3);
+            for (header, axis) in headers.iter().zip(["X", "Y", "Z"]) {
+                assert!(header.starts_with(axis), "{}", html);
+            }
+        });
+    }
+
+    // 按刻度标签比较，避免浮点误差
+    fn tick_labels(min: f64, max: f64, max_ticks: usize) -> Vec<String> {
+        axis_ticks(min, max, max_ticks)
+            .into_iter()
+            .map(format_tick)
+            .collect()
+    }
+
+    #[test]
+    fn steps_round_up_to_one_two_or_five_times_a_power_of_ten() {
+        for (raw, step) in [
+            (1.0, "1"),
+            (1.2, "2"),
+            (2.0, "2"),
+            (3.0, "5"),
+            (7.0, "10"),
+            (25.0, "50"),
+            (0.03, "0.05"),
+            (0.2, "0.2"),
+        ] {
+            assert_eq!(format_tick(nice_step(raw)), step, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn invalid_raw_steps_fall_back_to_one() {
+        for raw in [0.0, -5.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(nice_step(raw), 1.0, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn ticks_align_to_the_step_inside_the_range() {
+        assert_eq!(
+            tick_labels(0.0, 100.0, 10),
+            ["0", "10", "20", "30", "40", "50", "60", "70", "80", "90", "100"]
+        );
+        assert_eq!(tick_labels(-7.0, 13.0, 4), ["-5", "0", "5", "10"]);
+        assert_eq!(
+            tick_labels(0.1, 0.5, 4),
+            ["0.1", "0.2", "0.3", "0.4", "0.5"]
+        );
+    }
+
+    #[test]
+    fn ticks_stay_near_the_requested_count() {
+        for (min, max) in [(0.0, 1.0), (-250.0, 250.0), (3.0, 1234.5), (0.001, 0.009)] {
+            let ticks = axis_ticks(min, max, MAX_TICK_LABELS);
+            // 步长向上取整，刻度数最多比上限多出两端对齐的一个
+            assert!(ticks.len() <= MAX_TICK_LABELS + 1, "{:?}", ticks);
+            assert!(ticks.len() >= 2, "{:?}", ticks);
+            // 刻度由整数倍步长算出，允许末位的浮点误差
+            let slack = (max - min) * 1e-9;
+            let inside = |t: &f64| *t >= min - slack && *t <= max + slack;
+            assert!(ticks.iter().all(inside), "{:?}", ticks);
+        }
+    }
+
+    #[test]
+    fn empty_or_reversed_ranges_have_no_ticks() {
+        assert!(axis_ticks(5.0, 5.0, 10).is_empty());
+        assert!(axis_ticks(10.0, 0.0, 10).is_empty());
+        assert!(axis_ticks(0.0, 10.0, 0).is_empty());
+    }
+
+    #[test]
+    fn measured_distances_are_euclidean() {
+        assert_eq!(distance((0.0, 0.0), (3.0, 4.0)), 5.0);
+        assert_eq!(distance((3.0, 4.0), (0.0, 0.0)), 5.0);
+        assert_eq!(distance((-1.0, -1.0), (-1.0, -1.0)), 0.0);
+        assert_eq!(format_distance((0.0, 0.0), (3.0, 4.0), 2), "5.00");
+        assert_eq!(format_distance((0.0, 0.0), (1.0, 1.0), 3), "1.414");
+    }
+
+    #[test]
+    fn screen_points_map_back_through_the_view_transform() {
+        assert_eq!(screen_to_world(10.0, 20.0, 10.0, 20.0, 4.0), (0.0, 0.0));
+        assert_eq!(screen_to_world(50.0, 0.0, 10.0, 20.0, 4.0), (10.0, -5.0));
+        assert_eq!(screen_to_world(5.0, 5.0, 0.0, 0.0, 0.5), (10.0, 10.0));
+    }
+
+    #[test]
+    fn world_points_are_scaled_and_flipped_onto_the_screen() {
+        let upright = PathOrientation::default();
+        assert_eq!(
+            world_to_screen(0.0, 0.0, upright, 2.0, 30.0, 40.0),
+            (30.0, 40.0)
+        );
+        // 每毫米BITMAP_PX_PER_MM像素，屏幕Y轴向下
+        assert_eq!(
+            world_to_screen(1.0, 2.0, upright, 2.0, 30.0, 40.0),
+            (38.0, 24.0)
+        );
+        let turned = PathOrientation {
+            rotation: 90,
+            ..Default::default()
+        };
+        assert_eq!(
+            world_to_screen(1.0, 0.0, turned, 1.0, 0.0, 0.0),
+            (0.0, -4.0)
+        );
+    }
+
+    #[test]
+    fn hex_colors_parse_to_rgb() {
+        assert_eq!(hex_to_rgb("#ff8000"), Some([255, 128, 0]));
+        assert_eq!(hex_to_rgb("#00AaFf"), Some([0, 170, 255]));
+    }
+
+    #[test]
+    fn malformed_hex_colors_are_rejected() {
+        for hex in [
+            "ff8000", "#ff800", "#ff80000", "#gg0000", "#+1+2+3", "#aé123", "",
+        ] {
+            assert_eq!(hex_to_rgb(hex), None, "{}", hex);
+        }
+    }
+
+    #[test]
+    fn dwell_time_is_charged_to_the_cell_of_the_earlier_sample() {
+        let mut dwell = DwellMap::default();
+        dwell.record(0.5, 0.5, 0.0, true);
+        dwell.record(0.7, 0.2, 100.0, true);
+        dwell.record(1.5, -0.5, 400.0, true);
+        dwell.record(1.6, -0.4, 450.0, true);
+        assert_eq!(dwell.cells[&(0, 0)], 400.0);
+        assert_eq!(dwell.cells[&(1, -1)], 50.0);
+        assert_eq!(dwell.cells.len(), 2);
+        assert_eq!(dwell.max_ms, 400.0);
+    }
+
+    #[test]
+    fn idle_samples_and_long_gaps_add_no_dwell() {
+        let mut dwell = DwellMap::default();
+        // 空闲的采样只结束上一段
+        dwell.record(0.5, 0.5, 0.0, true);
+        dwell.record(0.5, 0.5, 100.0, false);
+        dwell.record(0.5, 0.5, 5000.0, true);
+        assert_eq!(dwell.cells[&(0, 0)], 100.0);
+        // 两次采样间隔太久(例如页面在后台)不计
+        dwell.record(3.5, 0.5, 5000.0 + MAX_DWELL_GAP_MS + 1.0, true);
+        assert_eq!(dwell.cells[&(0, 0)], 100.0);
+        // 时间倒退的采样不计
+        dwell.record(3.5, 0.5, 0.0, true);
+        assert!(!dwell.cells.contains_key(&(3, 0)));
+        dwell.clear();
+        assert!(dwell.cells.is_empty());
+        assert_eq!(dwell.max_ms, 0.0);
+    }
+
+    fn point(x: f64, y: f64, color: u8, rapid: bool) -> Point {
+        Point { x, y, color, rapid }
+    }
+
+    #[test]
+    fn exported_csv_points_round_trip() {
+        let points = vec![
+            point(0.0, 0.0, 0, true),
+            point(12.5, -3.25, 17, false),
+            point(-0.001, 1e6, 255, true),
+        ];
+        let csv = points_to_csv(&points);
+        assert!(csv.starts_with("x,y,color,rapid\n"));
+        assert_eq!(points_from_csv(&csv), (points, 0));
+    }
+
+    #[test]
+    fn csv_without_a_rapid_column_loads_feed_points() {
+        let csv = "x,y,color\n1,2,3\n\n 4 , 5 , 6 \n";
+        let expected = vec![point(1.0, 2.0, 3, false), point(4.0, 5.0, 6, false)];
+        assert_eq!(points_from_csv(csv), (expected, 0));
+    }
+
+    #[test]
+    fn malformed_csv_rows_are_skipped_and_counted() {
+        let csv = "x,y,color,rapid\n1,2,3,1\n1,2\n1,2,300,0\na,2,3,0\n1,2,3,yes\n1,2,3,0,9\n";
+        assert_eq!(points_from_csv(csv), (vec![point(1.0, 2.0, 3, true)], 5));
+    }
+
+    #[test]
+    fn speed_ratios_are_the_share_of_the_max_speed() {
+        assert_eq!(speed_ratio(0.0, 100.0), 0.0);
+        assert_eq!(speed_ratio(25.0, 100.0), 0.25);
+        assert_eq!(speed_ratio(100.0, 100.0), 1.0);
+        // 反向运动的速度为负
+        assert_eq!(speed_ratio(-50.0, 100.0), 0.5);
+    }
+
+    #[test]
+    fn speed_ratios_above_the_max_speed_are_clamped() {
+        assert_eq!(speed_ratio(150.0, 100.0), 1.0);
+        assert_eq!(speed_ratio(-150.0, 100.0), 1.0);
+    }
+
+    #[test]
+    fn speed_ratios_without_a_max_speed_are_zero() {
+        for max_speed in [0.0, -10.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(speed_ratio(50.0, max_speed), 0.0, "{}", max_speed);
+        }
+        for speed in [f64::NAN, f64::INFINITY] {
+            assert_eq!(speed_ratio(speed, 100.0), 0.0, "{}", speed);
+        }
+    }
+
+    #[test]
+    fn update_rate_averages_over_the_window() {
+        let arrivals = VecDeque::from([0.0, 100.0, 200.0, 300.0, 400.0]);
+        assert_eq!(update_rate(&arrivals), Some(10.0));
+        // 间隔不均匀时取整个窗口的平均值
+        let arrivals = VecDeque::from([1000.0, 1050.0, 1500.0]);
+        assert_eq!(update_rate(&arrivals), Some(4.0));
+    }
+
+    #[test]
+    fn update_rate_needs_two_distinct_arrivals() {
+        assert_eq!(update_rate(&VecDeque::new()), None);
+        assert_eq!(update_rate(&VecDeque::from([5.0])), None);
+        assert_eq!(update_rate(&VecDeque::from([5.0, 5.0])), None);
+    }
+
+    #[test]
+    fn moved_views_are_stored_and_the_default_view_is_cleared() {
+        assert_eq!(ViewPreferences::default().to_store(), None);
+        let view = ViewPreferences {
+            zoom: 2.5,
+            ..Default::default()
+        };
+        assert_eq!(view.to_store(), Some(view));
+        let view = ViewPreferences {
+            offset_y: -40.0,
+            ..Default::default()
+        };
+        assert_eq!(view.to_store(), Some(view));
+    }
+
+    #[test]
+    fn stored_views_restore_unchanged() {
+        use leptos::server::codee::{Decoder, Encoder};
+        let view = ViewPreferences {
+            zoom: 0.8,
+            offset_x: 12.5,
+            offset_y: -300.0,
+        };
+        let encoded = JsonSerdeCodec::encode(&view).unwrap();
+        let restored: ViewPreferences = JsonSerdeCodec::decode(&encoded).unwrap();
+        assert_eq!(restored, view);
+    }
+
+    #[test]
+    fn malformed_stored_views_are_not_restored() {
+        use leptos::server::codee::Decoder;
+        for stored in [
+            "",
+            "null",
+            r#"{"zoom":2.0}"#,
+            r#"{"zoom":"x","offset_x":0,"offset_y":0}"#,
+        ] {
+            let restored: Result<ViewPreferences, _> = JsonSerdeCodec::decode(stored);
+            assert!(restored.is_err(), "{}", stored);
+        }
+    }
+
+    // 三段颜色明显不同的线段，最后一段还在构建中
+    fn three_segment_history() -> PathHistory {
+        let mut history = PathHistory::new();
+        for (x, color) in [(0.0, 0), (5.0, 0), (10.0, 100), (15.0, 100), (20.0, 200)] {
+            history.add_point(x, 0.0, color, false);
+        }
+        history
+    }
+
+    #[test]
+    fn undo_removes_only_the_last_segment() {
+        let mut history = three_segment_history();
+        let before: Vec<PathSegment> = history.ordered_segments().cloned().collect();
+        assert_eq!(before.len(), 3);
+        assert!(history.undo_last_segment());
+        let after: Vec<PathSegment> = history.ordered_segments().cloned().collect();
+        assert_eq!(after, before[..2]);
+        assert!(history.points.iter().all(|point| point.color != 200));
+    }
+
+    #[test]
+    fn undo_without_segments_does_nothing() {
+        let mut history = three_segment_history();
+        while history.undo_last_segment() {}
+        assert_eq!(history.ordered_segments().count(), 0);
+        assert!(history.points.is_empty());
+        assert!(!history.undo_last_segment());
+    }
+
+    #[test]
+    fn a_larger_simplification_tolerance_keeps_fewer_points() {
+        let kept = |tolerance: f64| {
+            let mut history = PathHistory::new();
+            history.simplification_tolerance = tolerance;
+            for i in 0..30 {
+                history.add_point(i as f64 * 0.5, 0.0, 0, false);
+            }
+            history.points.len()
+        };
+        assert_eq!(kept(0.1), 30);
+        assert!(kept(1.0) < kept(0.1));
+        assert!(kept(3.0) < kept(1.0));
     }
 }
@@ -0,0 +1,601 @@
+use leptos::{prelude::*, server::codee::string::JsonSerdeCodec};
+use leptos_use::use_cookie;
+
+// 界面语言
+#[derive(Default, Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq)]
+pub enum Lang {
+    #[default]
+    Zh,
+    En,
+}
+
+impl Lang {
+    pub fn toggle(self) -> Self {
+        match self {
+            Lang::Zh => Lang::En,
+            Lang::En => Lang::Zh,
+        }
+    }
+}
+
+const EN: &[(&str, &str)] = &[
+    // Navigation
+    ("parameters", "Parameters"),
+    ("manual_control", "Manual Control"),
+    ("auto_mode", "Auto mode"),
+    ("command_log", "Command Log"),
+    ("about", "About"),
+    ("connected", "Connected"),
+    ("connected_simulated", "Connected (SIM)"),
+    ("controller_model", "Controller model"),
+    ("controller_firmware", "Firmware version"),
+    ("controller_axis_count", "Axis count"),
+    ("disconnected", "Disconnected"),
+    ("switch_language", "中文"),
+    ("loading", "Loading..."),
+    // Parameters
+    ("enabled", "Enabled"),
+    ("axis_num", "Axis No."),
+    ("pulse_equivalent", "Pulse equivalent"),
+    ("positive_limit_io", "Positive limit IO"),
+    ("negative_limit_io", "Negative limit IO"),
+    ("zero_point_io", "Zero point IO"),
+    ("software_positive_limit", "Soft positive limit"),
+    ("software_negative_limit", "Soft negative limit"),
+    ("backlash", "Backlash"),
+    ("emergency_stop_io", "E-stop IO"),
+    ("door_switch_io", "Door switch IO"),
+    ("processing_speed", "Processing speed"),
+    ("max_speed", "Max speed"),
+    ("acceleration", "Acceleration"),
+    ("deceleration", "Deceleration"),
+    ("transition_time", "Transition time"),
+    ("crawling_speed", "Crawling speed"),
+    ("sramp_time", "S-curve time"),
+    ("emergency_stop_inverted", "Invert E-stop"),
+    ("door_switch_inverted", "Invert door switch"),
+    ("limit_io_inverted", "Invert limit IO"),
+    ("spindle_poles", "Spindle motor poles"),
+    ("spindle_ratio", "Spindle ratio"),
+    ("safe_z_height", "Safe Z height"),
+    ("probe_io", "Probe IO"),
+    ("probe_max_distance", "Probe max distance"),
+    ("save", "Save"),
+    ("connect", "Connect"),
+    ("disconnect", "Disconnect"),
+    ("confirm_disconnect", "Disconnect while a job is running?"),
+    (
+        "confirm_disconnect_body",
+        "The running job will lose its connection and the machine may be left mid-move.",
+    ),
+    ("connection", "Connection"),
+    ("connecting_failed", "Connecting failed"),
+    ("disconnection", "Disconnection"),
+    ("disconnecting_failed", "Disconnecting failed"),
+    // Limit status
+    ("emergency_stop", "E-stop"),
+    ("door_switch", "Door"),
+    ("alarm", "Alarm"),
+    ("waiting_for_connection", "Waiting for connection"),
+    ("idle_disconnect_in", "Idle, disconnecting in"),
+    // Manual control
+    ("set_zero", "Zero coordinates"),
+    ("goto_zero", "Return to zero"),
+    ("setup_sequence", "Home all and zero"),
+    ("setup_sequence_done", "Homed and zeroed"),
+    ("setup_sequence_failed", "Setup failed"),
+    ("jog_ramp", "Accelerate while held"),
+    ("probe_z", "Probe Z"),
+    ("probe_failed", "Probe failed"),
+    ("jog_initial_speed", "Jog initial speed"),
+    ("jog_ramp_rate", "Jog ramp rate (/s)"),
+    ("step_jog", "Step jog"),
+    ("step_distance", "Step distance"),
+    ("step_preview", "Preview before moving"),
+    ("step_target", "Step target"),
+    ("step_out_of_limits", "outside software limits"),
+    ("step_jog_failed", "Step jog failed"),
+    ("confirm", "Confirm"),
+    ("macro_name", "Macro name"),
+    ("record_macro", "Record macro"),
+    ("stop_recording", "Stop and save"),
+    ("recorded_steps", "Recorded steps:"),
+    ("saved_macros", "Saved macros"),
+    ("run_macro", "Run macro"),
+    ("delete_macro", "Delete macro"),
+    ("macro_failed", "Macro failed"),
+    ("watchdog_timeout", "Watchdog timeout (ms)"),
+    ("idle_timeout", "Idle disconnect (min, 0 = off)"),
+    ("frequency", "Frequency"),
+    ("reverse", "Reverse"),
+    ("start", "Start"),
+    ("stop", "Stop"),
+    ("commanded_rpm", "Commanded RPM"),
+    ("actual_rpm", "Actual RPM"),
+    // Auto mode
+    ("generate", "Generate"),
+    ("upload", "Upload"),
+    ("invalid_gcode_file", "Not a G-code file"),
+    ("recent_files", "Recent files"),
+    ("resume", "Resume"),
+    ("path_exceeds_limits", "Path exceeds soft limits"),
+    ("cancel", "Cancel"),
+    ("start_anyway", "Start anyway"),
+    ("dry_run", "Dry run"),
+    ("block_delete", "Block delete (/)"),
+    ("speed_per_minute", "Speed in units/min"),
+    ("min_line_interval", "Min time per line"),
+    (
+        "min_line_interval_hint",
+        "Debug throttle: each line takes at least this long, 0 disables it",
+    ),
+    ("safe_rapids", "Retract to safe Z before rapids"),
+    ("gcode_content", "G-code Content:"),
+    ("job_completed", "Job completed"),
+    ("job_failed", "Job failed"),
+    ("controller_error", "Controller read failed"),
+    ("job_failed_body", "G-code execution stopped with an error"),
+    // Visualization
+    ("machine_path", "Machine Path Visualization"),
+    ("zoom_in", "Zoom In"),
+    ("zoom_out", "Zoom Out"),
+    ("reset_view", "Reset View"),
+    ("clear_view", "Clear View"),
+    ("export_dxf", "Export DXF"),
+    ("background", "Background"),
+    ("opacity", "Opacity"),
+    ("line_width", "Line width"),
+    ("mirror_x", "Mirror X"),
+    ("mirror_y", "Mirror Y"),
+    ("rotation", "Rotation"),
+    ("path_origin", "Origin"),
+    ("origin_center", "Center"),
+    ("origin_bottom_left", "Bottom left"),
+    ("origin_top_left", "Top left"),
+    ("origin_bottom_right", "Bottom right"),
+    ("origin_top_right", "Top right"),
+    ("overlay_preview", "Overlay preview"),
+    ("fit_work_area", "Fit work area"),
+    ("path_image_format", "Image format"),
+    ("jpeg_quality", "JPEG quality"),
+    ("position_decimals", "Position decimals"),
+    ("door_interlock", "Door interlock"),
+    ("jog_speed", "Jog speed"),
+    ("ignored_codes", "Ignored:"),
+    ("at_lines", "at lines"),
+    ("spin_up_secs", "Spindle spin-up (s)"),
+    ("spin_down_secs", "Spindle spin-down (s)"),
+    ("mdi_placeholder", "MDI: e.g. G1 X10 F500"),
+    ("mdi_run", "Run line"),
+    ("mdi_done", "Line executed"),
+    ("mdi_failed", "MDI failed"),
+    ("developer_mode", "Developer mode"),
+    ("raw_command", "ZMC BASIC command"),
+    ("send_command", "Send"),
+    ("clear_console", "Clear"),
+    ("command_error", "Error"),
+    ("toggle_breakpoint", "Click to toggle a breakpoint"),
+    ("breakpoint_reached", "Paused at breakpoint"),
+    ("breakpoint_line", "Line"),
+    ("read_attempts", "Status read attempts"),
+    ("parameters_rejected", "Parameters not applied"),
+    ("speed_chart", "Axis speed"),
+    ("pause_chart", "Pause"),
+    ("resume_chart", "Resume"),
+    ("clear_chart", "Clear"),
+    ("end_retract", "Retract to safe Z at program end"),
+    ("end_return_xy", "Return to X0/Y0 at program end"),
+    ("distinguish_rapids", "Dashed rapids (G0)"),
+    ("following_error", "Following error"),
+    ("following_error_limit", "Following error limit"),
+    ("stall_cycles", "Stall detection cycles (0 = off)"),
+    ("possible_stall", "Possible stall, axis not moving"),
+    ("coordinate_decimals", "Round coordinates to decimals"),
+    ("no_rounding", "Off"),
+    ("export_basic", "Export as BASIC"),
+    ("io_test", "IO test"),
+    ("input_io", "Input"),
+    ("output_io", "Output"),
+    ("io_on", "ON"),
+    ("io_off", "OFF"),
+    ("io_invert", "Invert"),
+    ("io_uninvert", "Clear invert"),
+    ("io_test_failed", "IO access failed"),
+    (
+        "outputs_need_developer_mode",
+        "Outputs can only be set in developer mode",
+    ),
+    ("gcode_control_rejected", "G-code request rejected"),
+    ("clamped_feeds", "Feedrate limited to max speed:"),
+    ("dwell_heat", "Dwell heat map"),
+    ("max_dwell", "Max dwell"),
+    ("stop_before_homing", "Stop spindle before homing/zeroing"),
+    ("run_history", "Run History"),
+    ("run_started", "Started"),
+    ("run_ended", "Ended"),
+    ("file_name", "File"),
+    ("run_result", "Result"),
+    ("line_count", "Lines"),
+    ("run_completed", "Completed"),
+    ("run_failed", "Failed"),
+    ("run_stopped", "Stopped"),
+    ("run_aborted", "Aborted"),
+    ("speed_decimals", "Speed decimals"),
+    ("depth_current", "Z now"),
+    ("depth_min", "min"),
+    ("depth_max", "max"),
+    ("waiting_for_data", "Waiting for machine data..."),
+    ("save_path", "Save Path"),
+    ("undo_segment", "Undo Last Segment"),
+    ("simplification_tolerance", "Point spacing"),
+    (
+        "simplification_tolerance_hint",
+        "Smaller keeps finer detail, larger skips more points and saves memory",
+    ),
+    ("clear_path", "Clear Path"),
+    ("export_csv", "Export CSV"),
+    ("import_csv", "Import CSV"),
+    ("play", "Play"),
+    ("pause", "Pause"),
+    ("reset", "Reset"),
+    ("emergency_abort", "Emergency abort"),
+    ("measure", "Measure"),
+    ("exit_measure", "Exit Measure"),
+    ("speed", "Speed"),
+    ("speed_ratio", "Speed / max"),
+    ("axis_suffix", " Axis"),
+    ("idle", "Idle"),
+    ("yes", "Yes"),
+    ("no", "No"),
+    ("position", "Position"),
+    ("canvas_view", "Canvas view"),
+    ("update_interval", "Update interval"),
+    ("update_rate", "Update rate"),
+    ("axis_alarm", "alarm, moves are blocked until it is reset"),
+    ("reset_alarm", "Reset alarm"),
+    // Command log
+    ("refresh", "Refresh"),
+    ("download_csv", "Download CSV"),
+    ("download_json", "Download JSON"),
+    ("time", "Time"),
+    ("command", "Command"),
+    ("detail", "Detail"),
+];
+
+const ZH: &[(&str, &str)] = &[
+    // 导航
+    ("parameters", "参数设置"),
+    ("manual_control", "手动控制"),
+    ("auto_mode", "自动模式"),
+    ("command_log", "指令记录"),
+    ("about", "关于"),
+    ("connected", "已连接"),
+    ("connected_simulated", "已连接(模拟)"),
+    ("controller_model", "控制器型号"),
+    ("controller_firmware", "固件版本"),
+    ("controller_axis_count", "轴数"),
+    ("disconnected", "未连接"),
+    ("switch_language", "English"),
+    ("loading", "加载中..."),
+    // 参数
+    ("enabled", "启用"),
+    ("axis_num", "轴号"),
+    ("pulse_equivalent", "脉冲当量"),
+    ("positive_limit_io", "正限位IO"),
+    ("negative_limit_io", "负限位IO"),
+    ("zero_point_io", "零点IO"),
+    ("software_positive_limit", "软件正限位"),
+    ("software_negative_limit", "软件负限位"),
+    ("backlash", "反向间隙"),
+    ("emergency_stop_io", "急停IO"),
+    ("door_switch_io", "门限位IO"),
+    ("processing_speed", "加工速度"),
+    ("max_speed", "最大速度"),
+    ("acceleration", "加速度"),
+    ("deceleration", "减速度"),
+    ("transition_time", "过渡时间"),
+    ("crawling_speed", "爬行速度"),
+    ("sramp_time", "S曲线时间"),
+    ("emergency_stop_inverted", "急停反向"),
+    ("door_switch_inverted", "门限位反向"),
+    ("limit_io_inverted", "限位IO反向"),
+    ("spindle_poles", "主轴电机极数"),
+    ("spindle_ratio", "主轴传动比"),
+    ("safe_z_height", "Z轴安全高度"),
+    ("probe_io", "对刀探针IO"),
+    ("probe_max_distance", "探测最大行程"),
+    ("save", "保存"),
+    ("connect", "连接"),
+    ("disconnect", "断开"),
+    ("confirm_disconnect", "任务正在运行，确定断开连接？"),
+    (
+        "confirm_disconnect_body",
+        "断开后正在运行的任务将失去连接，机床可能停在运动途中。",
+    ),
+    ("connection", "连接"),
+    ("connecting_failed", "连接失败"),
+    ("disconnection", "断开连接"),
+    ("disconnecting_failed", "断开失败"),
+    // 限位状态
+    ("emergency_stop", "急停"),
+    ("door_switch", "门限位"),
+    ("alarm", "报警"),
+    ("waiting_for_connection", "等待连接"),
+    ("idle_disconnect_in", "长时间无操作，即将断开"),
+    // 手动控制
+    ("set_zero", "坐标置零"),
+    ("goto_zero", "回到零点"),
+    ("setup_sequence", "全部回零并置零"),
+    ("setup_sequence_done", "已回零并置零"),
+    ("setup_sequence_failed", "回零置零失败"),
+    ("jog_ramp", "按住加速"),
+    ("probe_z", "Z轴对刀"),
+    ("probe_failed", "对刀失败"),
+    ("jog_initial_speed", "点动起始速度"),
+    ("jog_ramp_rate", "点动加速度(/s)"),
+    ("step_jog", "单步点动"),
+    ("step_distance", "单步距离"),
+    ("step_preview", "移动前预览"),
+    ("step_target", "单步目标"),
+    ("step_out_of_limits", "超出软件限位"),
+    ("step_jog_failed", "单步点动失败"),
+    ("confirm", "确认"),
+    ("macro_name", "宏名称"),
+    ("record_macro", "录制宏"),
+    ("stop_recording", "停止并保存"),
+    ("recorded_steps", "已录制步数："),
+    ("saved_macros", "已保存的宏"),
+    ("run_macro", "运行宏"),
+    ("delete_macro", "删除宏"),
+    ("macro_failed", "宏执行失败"),
+    ("watchdog_timeout", "看门狗超时(ms)"),
+    ("idle_timeout", "空闲断开(分钟，0为关闭)"),
+    ("frequency", "输入频率"),
+    ("reverse", "反转"),
+    ("start", "启动"),
+    ("stop", "停止"),
+    ("commanded_rpm", "设定转速"),
+    ("actual_rpm", "实际转速"),
+    // 自动模式
+    ("generate", "生成预览"),
+    ("upload", "上传"),
+    ("invalid_gcode_file", "不是G代码文件"),
+    ("recent_files", "最近文件"),
+    ("resume", "继续"),
+    ("path_exceeds_limits", "路径超出软件限位"),
+    ("cancel", "取消"),
+    ("start_anyway", "仍然启动"),
+    ("dry_run", "空运行"),
+    ("block_delete", "跳段 (/)"),
+    ("speed_per_minute", "速度单位为units/min"),
+    ("min_line_interval", "每行最短时间"),
+    (
+        "min_line_interval_hint",
+        "调试限速：每行至少执行这么长时间，0为不限速",
+    ),
+    ("safe_rapids", "快移前抬刀到安全高度"),
+    ("gcode_content", "G代码内容:"),
+    ("job_completed", "任务完成"),
+    ("job_failed", "任务失败"),
+    ("controller_error", "控制器读取失败"),
+    ("job_failed_body", "G代码执行出错已停止"),
+    // 可视化
+    ("machine_path", "机床路径"),
+    ("zoom_in", "放大"),
+    ("zoom_out", "缩小"),
+    ("reset_view", "重置视图"),
+    ("clear_view", "清除视图"),
+    ("export_dxf", "导出DXF"),
+    ("background", "背景"),
+    ("opacity", "不透明度"),
+    ("line_width", "线宽"),
+    ("mirror_x", "X镜像"),
+    ("mirror_y", "Y镜像"),
+    ("rotation", "旋转"),
+    ("path_origin", "零点位置"),
+    ("origin_center", "中心"),
+    ("origin_bottom_left", "左下角"),
+    ("origin_top_left", "左上角"),
+    ("origin_bottom_right", "右下角"),
+    ("origin_top_right", "右上角"),
+    ("overlay_preview", "叠加预览"),
+    ("fit_work_area", "适配加工区域"),
+    ("path_image_format", "图片格式"),
+    ("jpeg_quality", "JPEG质量"),
+    ("position_decimals", "位置小数位数"),
+    ("door_interlock", "门禁联锁"),
+    ("jog_speed", "点动速度"),
+    ("ignored_codes", "已忽略："),
+    ("at_lines", "所在行"),
+    ("spin_up_secs", "主轴启动等待(秒)"),
+    ("spin_down_secs", "主轴停转等待(秒)"),
+    ("mdi_placeholder", "MDI：如 G1 X10 F500"),
+    ("mdi_run", "执行"),
+    ("mdi_done", "已执行"),
+    ("mdi_failed", "MDI执行失败"),
+    ("developer_mode", "开发者模式"),
+    ("raw_command", "ZMC BASIC指令"),
+    ("send_command", "发送"),
+    ("clear_console", "清空"),
+    ("command_error", "错误"),
+    ("toggle_breakpoint", "点击设置或取消断点"),
+    ("breakpoint_reached", "已在断点处暂停"),
+    ("breakpoint_line", "行"),
+    ("read_attempts", "状态读取尝试次数"),
+    ("parameters_rejected", "参数未生效"),
+    ("speed_chart", "轴速度"),
+    ("pause_chart", "暂停"),
+    ("resume_chart", "继续"),
+    ("clear_chart", "清空"),
+    ("end_retract", "程序结束后抬刀到安全高度"),
+    ("end_return_xy", "程序结束后回到X0/Y0"),
+    ("distinguish_rapids", "快移(G0)显示为虚线"),
+    ("following_error", "跟随误差"),
+    ("following_error_limit", "跟随误差限值"),
+    ("stall_cycles", "失速检测周期数(0为关闭)"),
+    ("possible_stall", "可能失速，轴没有移动"),
+    ("coordinate_decimals", "坐标保留小数位数"),
+    ("no_rounding", "不取整"),
+    ("export_basic", "导出为BASIC程序"),
+    ("io_test", "IO测试"),
+    ("input_io", "输入"),
+    ("output_io", "输出"),
+    ("io_on", "开"),
+    ("io_off", "关"),
+    ("io_invert", "反转"),
+    ("io_uninvert", "取消反转"),
+    ("io_test_failed", "IO读写失败"),
+    (
+        "outputs_need_developer_mode",
+        "只有开发者模式下才能设置输出",
+    ),
+    ("gcode_control_rejected", "G代码操作被拒绝"),
+    ("clamped_feeds", "进给速度已限制为最大速度："),
+    ("dwell_heat", "驻留热力图"),
+    ("max_dwell", "最长驻留"),
+    ("stop_before_homing", "回零/设零前自动停止主轴"),
+    ("run_history", "运行记录"),
+    ("run_started", "开始时间"),
+    ("run_ended", "结束时间"),
+    ("file_name", "文件"),
+    ("run_result", "结果"),
+    ("line_count", "行数"),
+    ("run_completed", "完成"),
+    ("run_failed", "失败"),
+    ("run_stopped", "已停止"),
+    ("run_aborted", "已中止"),
+    ("speed_decimals", "速度小数位数"),
+    ("depth_current", "当前Z"),
+    ("depth_min", "最低"),
+    ("depth_max", "最高"),
+    ("waiting_for_data", "等待机床数据..."),
+    ("save_path", "保存路径"),
+    ("undo_segment", "撤销上一段"),
+    ("simplification_tolerance", "点间距"),
+    (
+        "simplification_tolerance_hint",
+        "越小细节越多，越大跳过的点越多、占用内存越少",
+    ),
+    ("clear_path", "清除路径"),
+    ("export_csv", "导出CSV"),
+    ("import_csv", "导入CSV"),
+    ("play", "播放"),
+    ("pause", "暂停"),
+    ("reset", "复位"),
+    ("emergency_abort", "紧急中止"),
+    ("measure", "测量"),
+    ("exit_measure", "退出测量"),
+    ("speed", "速度"),
+    ("speed_ratio", "速度占比"),
+    ("axis_suffix", "轴"),
+    ("idle", "空闲"),
+    ("yes", "是"),
+    ("no", "否"),
+    ("position", "位置"),
+    ("canvas_view", "画布视图"),
+    ("update_interval", "刷新间隔"),
+    ("update_rate", "实际刷新率"),
+    ("axis_alarm", "报警，复位前禁止运动"),
+    ("reset_alarm", "复位报警"),
+    // 指令记录
+    ("refresh", "刷新"),
+    ("download_csv", "下载CSV"),
+    ("download_json", "下载JSON"),
+    ("time", "时间"),
+    ("command", "指令"),
+    ("detail", "详情"),
+];
+
+// 查找key对应的文本，找不到时返回key本身
+pub fn translate(lang: Lang, key: &'static str) -> &'static str {
+    let table = match lang {
+        Lang::En => EN,
+        Lang::Zh => ZH,
+    };
+    table
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, text)| *text)
+        .unwrap_or(key)
+}
+
+// 通过context共享的当前语言，保存在cookie中
+#[derive(Clone, Copy)]
+pub struct I18n {
+    lang: Signal<Option<Lang>>,
+    set_lang: WriteSignal<Option<Lang>>,
+}
+
+impl I18n {
+    pub fn lang(&self) -> Lang {
+        self.lang.get().unwrap_or_default()
+    }
+
+    pub fn toggle(&self) {
+        self.set_lang.set(Some(self.lang().toggle()));
+    }
+
+    pub fn t(&self, key: &'static str) -> &'static str {
+        translate(self.lang(), key)
+    }
+
+    // 用于组件属性的响应式文本
+    pub fn tr(&self, key: &'static str) -> Signal<String> {
+        let i18n = *self;
+        Signal::derive(move || i18n.t(key).to_string())
+    }
+}
+
+pub fn provide_i18n() {
+    let (lang, set_lang) = use_cookie::<Lang, JsonSerdeCodec>("lang_cookie");
+    provide_context(I18n { lang, set_lang });
+}
+
+pub fn use_i18n() -> I18n {
+    expect_context::<I18n>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn keys(table: &[(&'static str, &str)]) -> BTreeSet<&'static str> {
+        table.iter().map(|(key, _)| *key).collect()
+    }
+
+    #[test]
+    fn keys_are_translated_per_language() {
+        assert_eq!(translate(Lang::En, "parameters"), "Parameters");
+        assert_eq!(translate(Lang::Zh, "reset_alarm"), "复位报警");
+        assert_eq!(translate(Lang::En, "switch_language"), "中文");
+    }
+
+    #[test]
+    fn missing_keys_fall_back_to_the_key() {
+        assert_eq!(translate(Lang::En, "no_such_key"), "no_such_key");
+        assert_eq!(translate(Lang::Zh, "no_such_key"), "no_such_key");
+    }
+
+    #[test]
+    fn both_languages_have_the_same_keys() {
+        let (en, zh) = (keys(EN), keys(ZH));
+        let only_en: Vec<_> = en.difference(&zh).collect();
+        let only_zh: Vec<_> = zh.difference(&en).collect();
+        assert!(only_en.is_empty(), "missing in ZH: {:?}", only_en);
+        assert!(only_zh.is_empty(), "missing in EN: {:?}", only_zh);
+    }
+
+    #[test]
+    fn keys_are_not_repeated() {
+        // 重复的key只有第一个生效
+        assert_eq!(keys(EN).len(), EN.len());
+        assert_eq!(keys(ZH).len(), ZH.len());
+    }
+
+    #[test]
+    fn toggling_switches_between_the_two_languages() {
+        assert_eq!(Lang::Zh.toggle(), Lang::En);
+        assert_eq!(Lang::En.toggle(), Lang::Zh);
+    }
+}
@@ -1,5 +1,5 @@
 #[cfg(feature = "ssr")]
-use axum::routing::get;
+use axum::routing::{get, post};
 #[cfg(feature = "ssr")]
 use leptos_ws::server_signals::ServerSignals;
 
@@ -10,6 +10,7 @@ async fn main() {
     use leptos::logging::log;
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
+    use leptos_ssr_startup::api::{job_handler, park_machine, status_handler};
     use leptos_ssr_startup::app::*;
 
     let conf = get_configuration(None).unwrap();
@@ -25,6 +26,8 @@ async fn main() {
             "/ws",
             get(leptos_ws::axum::websocket(server_signals.clone())),
         )
+        .route("/api/status", get(status_handler))
+        .route("/api/job", post(job_handler))
         .leptos_routes_with_context(
             &leptos_options,
             routes,
@@ -43,9 +46,39 @@ async fn main() {
     // `axum::Server` is a re-export of `hyper::Server`
     log!("listening on http://{}", &addr);
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app.into_make_service())
-        .await
-        .unwrap();
+    // websocket连接不会自行结束，收到退出信号后不等待连接关闭，停好机床后直接退出
+    tokio::select! {
+        result = axum::serve(listener, app.into_make_service()) => result.unwrap(),
+        _ = shutdown_signal() => {
+            log!("Shutting down, parking the machine");
+            if let Err(e) = park_machine().await {
+                log!("Failed to park the machine: {}", e);
+            }
+        }
+    }
+}
+
+// 等待Ctrl-C或SIGTERM
+#[cfg(feature = "ssr")]
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl-C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 }
 
 #[cfg(not(feature = "ssr"))]
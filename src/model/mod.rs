@@ -1,5 +1,5 @@
 // From client to post to the server
-#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
 pub struct ManualControl {
     pub converter_frequency: u16,
     pub converter_inverted: bool,
@@ -7,6 +7,163 @@ pub struct ManualControl {
     // 对刀恢复坐标存储
     pub pos_store_x: f32,
     pub pos_store_y: f32,
+    // 点动看门狗超时(ms)，超时未收到心跳时停止点动
+    #[serde(default = "default_watchdog_timeout_ms")]
+    pub watchdog_timeout_ms: u64,
+    // 按住点动按钮时逐渐加速：起始速度和每秒增加的速度
+    #[serde(default)]
+    pub jog_ramp: bool,
+    #[serde(default = "default_jog_initial_speed")]
+    pub jog_initial_speed: f32,
+    #[serde(default = "default_jog_ramp_rate")]
+    pub jog_ramp_rate: f32,
+    // 无操作多少分钟后自动断开控制器，0表示不断开
+    #[serde(default)]
+    pub idle_timeout_min: u64,
+    // 单步点动：每按一次移动固定距离，可先预览目标位置再确认执行
+    #[serde(default)]
+    pub step_jog: bool,
+    #[serde(default = "default_step_distance")]
+    pub step_distance: f32,
+    #[serde(default)]
+    pub step_preview: bool,
+    // 点动速度，与加工速度分开设置，对刀等需要慢速移动时不影响程序的进给
+    #[serde(default = "default_jog_speed")]
+    pub jog_speed: f32,
+}
+
+fn default_step_distance() -> f32 {
+    1.0
+}
+
+fn default_jog_speed() -> f32 {
+    10.0
+}
+
+fn default_watchdog_timeout_ms() -> u64 {
+    1500
+}
+
+fn default_jog_initial_speed() -> f32 {
+    5.0
+}
+
+fn default_jog_ramp_rate() -> f32 {
+    10.0
+}
+
+impl ManualControl {
+    // 按住held_secs秒后的点动速度，不超过max_speed（max_speed未设置时不限制）
+    pub fn jog_speed(&self, held_secs: f32, max_speed: f32) -> f32 {
+        let speed = self.jog_initial_speed + self.jog_ramp_rate * held_secs.max(0.0);
+        if max_speed > 0.0 {
+            speed.min(max_speed)
+        } else {
+            speed
+        }
+    }
+}
+
+// 单步点动的目标位置：当前位置沿方向移动distance
+pub fn step_target(current: f32, direction: i8, distance: f32) -> f32 {
+    current + direction.signum() as f32 * distance.abs()
+}
+
+// 手动操作宏中的一步，按录制顺序重放
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub enum MacroCommand {
+    // 单步点动：各轴以jog_speed沿方向移动distance
+    JogStep {
+        axis_list: Vec<u8>,
+        directions: Vec<i8>,
+        distance: f32,
+        #[serde(default = "default_jog_speed")]
+        jog_speed: f32,
+    },
+    GotoZero,
+    SetZero {
+        axis_list: Vec<u8>,
+    },
+}
+
+// 录制的手动操作宏，保存在浏览器本地存储中
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct JogMacro {
+    pub name: String,
+    pub commands: Vec<MacroCommand>,
+}
+
+// 界面偏好设置，保存在cookie中
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct UiPreferences {
+    // 位置和速度显示的小数位数，脉冲当量很小的机床需要更多位数
+    #[serde(default = "default_position_decimals")]
+    pub position_decimals: usize,
+    #[serde(default = "default_speed_decimals")]
+    pub speed_decimals: usize,
+}
+
+fn default_position_decimals() -> usize {
+    3
+}
+
+fn default_speed_decimals() -> usize {
+    2
+}
+
+impl Default for UiPreferences {
+    fn default() -> Self {
+        Self {
+            position_decimals: default_position_decimals(),
+            speed_decimals: default_speed_decimals(),
+        }
+    }
+}
+
+impl UiPreferences {
+    pub const MAX_DECIMALS: usize = 6;
+
+    pub fn format_position(&self, value: f64) -> String {
+        format_decimals(value, self.position_decimals)
+    }
+
+    pub fn format_speed(&self, value: f64) -> String {
+        format_decimals(value, self.speed_decimals)
+    }
+}
+
+// 按指定小数位数格式化，位数超过UiPreferences::MAX_DECIMALS时按最大值处理
+pub fn format_decimals(value: f64, decimals: usize) -> String {
+    format!("{:.*}", decimals.min(UiPreferences::MAX_DECIMALS), value)
+}
+
+// 单步点动预览：确认前在画布上显示的目标位置
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct JogPreview {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Default for ManualControl {
+    fn default() -> Self {
+        Self {
+            converter_frequency: 0,
+            converter_inverted: false,
+            converter_enabled: false,
+            pos_store_x: 0.0,
+            pos_store_y: 0.0,
+            watchdog_timeout_ms: default_watchdog_timeout_ms(),
+            jog_ramp: false,
+            jog_initial_speed: default_jog_initial_speed(),
+            jog_ramp_rate: default_jog_ramp_rate(),
+            idle_timeout_min: 0,
+            step_jog: false,
+            step_distance: default_step_distance(),
+            step_preview: false,
+            jog_speed: default_jog_speed(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq)]
@@ -16,8 +173,11 @@ pub struct InvertedStatus {
     pub limit_io_level_inverted: bool,
 }
 
-#[derive(Default, Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
 pub struct AxisParameters {
+    // 是否启用该轴，停用的轴不设置参数、不回零、忽略G代码中的坐标
+    #[serde(default = "default_axis_enabled")]
+    pub enabled: bool,
     // 轴号
     pub axis_num: u8,
     // 脉冲当量
@@ -33,6 +193,29 @@ pub struct AxisParameters {
     pub negative_limit_io: u16,
     // 零点IO
     pub zero_point_io: u16,
+    // 反向间隙补偿量，0表示不补偿
+    #[serde(default)]
+    pub backlash: f32,
+}
+
+fn default_axis_enabled() -> bool {
+    true
+}
+
+impl Default for AxisParameters {
+    fn default() -> Self {
+        Self {
+            enabled: default_axis_enabled(),
+            axis_num: 0,
+            pulse_equivalent: 0.0,
+            software_positive_limit: 0.0,
+            software_negative_limit: 0.0,
+            positive_limit_io: 0,
+            negative_limit_io: 0,
+            zero_point_io: 0,
+            backlash: 0.0,
+        }
+    }
 }
 
 #[derive(Default, Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
@@ -41,7 +224,7 @@ pub struct PidParameters {
     pub i: f32,
     pub d: f32,
 }
-#[derive(Default, Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
 pub struct SpeedParameters {
     // 加工速度
     pub processing_speed: f32,
@@ -53,8 +236,80 @@ pub struct SpeedParameters {
     pub transition_time: f32,
     // 爬行速度
     pub crawling_speed: f32,
+    // S曲线加减速时间(ms)
+    #[serde(default = "default_sramp_time")]
+    pub sramp_time: f32,
 }
-#[derive(Default, Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+
+fn default_sramp_time() -> f32 {
+    20.0
+}
+
+impl SpeedParameters {
+    // G代码F值换算出的速度不超过max_speed(未设置时不限制)，返回实际速度和是否被限制
+    pub fn clamp_feed_speed(&self, speed: f32) -> (f32, bool) {
+        if self.max_speed > 0.0 && speed > self.max_speed {
+            (self.max_speed, true)
+        } else {
+            (speed, false)
+        }
+    }
+}
+
+impl Default for SpeedParameters {
+    fn default() -> Self {
+        Self {
+            processing_speed: 0.0,
+            max_speed: 0.0,
+            acceleration: 0.0,
+            deceleration: 0.0,
+            transition_time: 0.0,
+            crawling_speed: 0.0,
+            sramp_time: default_sramp_time(),
+        }
+    }
+}
+// 主轴电机参数，用于由变频器输出频率换算转速
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+pub struct SpindleParameters {
+    // 电机极数
+    pub poles: u32,
+    // 电机到主轴的传动比
+    pub ratio: f32,
+    // M3/M4启动后等待主轴达到转速的时间(秒)
+    #[serde(default)]
+    pub spin_up_secs: f32,
+    // M5停止后等待主轴停转的时间(秒)
+    #[serde(default)]
+    pub spin_down_secs: f32,
+    // 回零、设零时变频器仍在运行：为true时先停止变频器并等待停转，否则拒绝执行
+    #[serde(default)]
+    pub stop_before_homing: bool,
+}
+
+impl Default for SpindleParameters {
+    fn default() -> Self {
+        Self {
+            poles: 2,
+            ratio: 1.0,
+            spin_up_secs: 0.0,
+            spin_down_secs: 0.0,
+            stop_before_homing: false,
+        }
+    }
+}
+
+impl SpindleParameters {
+    // 同步转速 n = 120 * f / p，再乘以传动比
+    pub fn rpm(&self, frequency: f32) -> f32 {
+        if self.poles == 0 {
+            return 0.0;
+        }
+        120.0 * frequency / self.poles as f32 * self.ratio
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
 pub struct Parameters {
     pub pid: PidParameters,
     pub x: AxisParameters,
@@ -66,6 +321,167 @@ pub struct Parameters {
     // 门限位IO
     pub door_switch_io: u16,
     pub inverted_status: InvertedStatus,
+    #[serde(default)]
+    pub spindle: SpindleParameters,
+    // 回零点前Z轴抬起的安全高度
+    #[serde(default = "default_safe_z_height")]
+    pub safe_z_height: f32,
+    // 对刀探针IO和探测的最大行程
+    #[serde(default)]
+    pub probe_io: u16,
+    #[serde(default = "default_probe_max_distance")]
+    pub probe_max_distance: f32,
+    // 门禁联锁：门打开时拒绝开始G代码并暂停正在进行的运动，没有门的机床可以关闭
+    #[serde(default)]
+    pub door_interlock: bool,
+    // 状态读取失败时最多读取的次数(含第一次)，1表示不重试
+    #[serde(default = "default_read_attempts")]
+    pub read_attempts: u32,
+    // 跟随误差超过该值(mm)时在轴状态中标红，0表示不检查
+    #[serde(default = "default_following_error_limit")]
+    pub following_error_limit: f32,
+    // 轴在运动但位置连续这么多个状态读取周期不变时提示可能失速，0表示不检测
+    #[serde(default = "default_stall_cycles")]
+    pub stall_cycles: u32,
+}
+
+fn default_stall_cycles() -> u32 {
+    40
+}
+
+fn default_read_attempts() -> u32 {
+    3
+}
+
+fn default_following_error_limit() -> f32 {
+    0.5
+}
+
+fn default_safe_z_height() -> f32 {
+    5.0
+}
+
+fn default_probe_max_distance() -> f32 {
+    10.0
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Self {
+            pid: PidParameters::default(),
+            x: AxisParameters::default(),
+            y: AxisParameters::default(),
+            z: AxisParameters::default(),
+            emergency_stop_io: 0,
+            speed: SpeedParameters::default(),
+            door_switch_io: 0,
+            inverted_status: InvertedStatus::default(),
+            spindle: SpindleParameters::default(),
+            safe_z_height: default_safe_z_height(),
+            probe_io: 0,
+            probe_max_distance: default_probe_max_distance(),
+            door_interlock: false,
+            read_attempts: default_read_attempts(),
+            following_error_limit: default_following_error_limit(),
+            stall_cycles: default_stall_cycles(),
+        }
+    }
+}
+
+// 控制器没有报告轴数时假定的轴数
+pub const CONTROLLER_AXIS_COUNT: u8 = 4;
+
+impl Parameters {
+    // 检查X/Y/Z轴号互不相同且小于控制器的轴数
+    pub fn validate_axis_nums(&self, axis_count: u8) -> Result<(), String> {
+        let axes = [
+            ("X", self.x.axis_num),
+            ("Y", self.y.axis_num),
+            ("Z", self.z.axis_num),
+        ];
+        for (i, (name, axis_num)) in axes.iter().enumerate() {
+            if *axis_num >= axis_count {
+                return Err(format!(
+                    "{} axis number {} is out of range (0-{})",
+                    name,
+                    axis_num,
+                    axis_count.saturating_sub(1)
+                ));
+            }
+            if let Some((other, _)) = axes[..i].iter().find(|(_, num)| num == axis_num) {
+                return Err(format!(
+                    "{} and {} axes both use axis number {}",
+                    other, name, axis_num
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // X/Y软件限位围成的加工区域
+    pub fn work_area(&self) -> PathBounds {
+        PathBounds {
+            min_x: self.x.software_negative_limit,
+            max_x: self.x.software_positive_limit,
+            min_y: self.y.software_negative_limit,
+            max_y: self.y.software_positive_limit,
+        }
+    }
+
+    // X、Y、Z轴是否启用
+    pub fn enabled_axes(&self) -> [bool; 3] {
+        [self.x.enabled, self.y.enabled, self.z.enabled]
+    }
+
+    // 回工件零点的运动顺序：先抬Z到安全高度，再移动X/Y到0，停用的轴不动
+    pub fn goto_zero_moves(&self) -> Vec<(Vec<u8>, Vec<f32>)> {
+        let mut moves = Vec::new();
+        if self.z.enabled {
+            moves.push((vec![self.z.axis_num], vec![self.safe_z_height]));
+        }
+        let xy: Vec<u8> = [&self.x, &self.y]
+            .into_iter()
+            .filter(|axis| axis.enabled)
+            .map(|axis| axis.axis_num)
+            .collect();
+        if !xy.is_empty() {
+            moves.push((xy.clone(), vec![0.0; xy.len()]));
+        }
+        moves
+    }
+
+    // 回机械零点的顺序：先Z离开工件，再X、Y，停用的轴不回零
+    pub fn homing_order(&self) -> Vec<u8> {
+        [&self.z, &self.x, &self.y]
+            .into_iter()
+            .filter(|axis| axis.enabled)
+            .map(|axis| axis.axis_num)
+            .collect()
+    }
+
+    // 根据轴号查找对应轴的参数
+    pub fn axis_by_num(&self, axis_num: u8) -> Option<&AxisParameters> {
+        [&self.x, &self.y, &self.z]
+            .into_iter()
+            .find(|axis| axis.axis_num == axis_num)
+    }
+
+    // 跟随误差是否超限，说明PID参数需要调整或机械有卡滞
+    pub fn following_error_exceeded(&self, following_error: f32) -> bool {
+        self.following_error_limit > 0.0 && following_error.abs() > self.following_error_limit
+    }
+
+    // 门禁联锁是否生效：开启联锁且门开关处于触发(门打开)状态，与LimitStatus::triggered一样考虑电平反转
+    pub fn door_interlock_engaged(&self, status: &LimitStatus) -> bool {
+        self.door_interlock && status.door_switch != self.inverted_status.door_switch_level_inverted
+    }
+
+    // 目标位置是否在该轴的软件限位以内，未知轴号不限制
+    pub fn within_soft_limits(&self, axis_num: u8, pos: f32) -> bool {
+        self.axis_by_num(axis_num).is_none_or(|axis| {
+            pos >= axis.software_negative_limit && pos <= axis.software_positive_limit
+        })
+    }
 }
 
 // From server to send to client by websocket
@@ -102,6 +518,30 @@ impl LimitStatus {
             z_minus,
         }
     }
+
+    // 当前处于触发状态的输入名称（已考虑电平反转）
+    pub fn triggered(&self, inverted: &InvertedStatus) -> Vec<&'static str> {
+        let limit = inverted.limit_io_level_inverted;
+        [
+            (
+                "emergency_stop",
+                self.emergency_stop != inverted.emergency_stop_level_inverted,
+            ),
+            (
+                "door_switch",
+                self.door_switch != inverted.door_switch_level_inverted,
+            ),
+            ("X+", self.x_plus != limit),
+            ("X-", self.x_minus != limit),
+            ("Y+", self.y_plus != limit),
+            ("Y-", self.y_minus != limit),
+            ("Z+", self.z_plus != limit),
+            ("Z-", self.z_minus != limit),
+        ]
+        .into_iter()
+        .filter_map(|(name, active)| active.then_some(name))
+        .collect()
+    }
 }
 
 #[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -109,6 +549,12 @@ pub struct AxisMoveStatus {
     pub is_idle: bool,
     pub speed: f32,
     pub pos: f32,
+    // 轴处于报警状态（驱动器报警、通讯错误或跟随误差超限）
+    #[serde(default)]
+    pub in_alarm: bool,
+    // 跟随误差：指令位置(d_pos)减实际位置(m_pos)
+    #[serde(default)]
+    pub following_error: f32,
 }
 
 #[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -116,4 +562,1235 @@ pub struct MoveStatus {
     pub x: AxisMoveStatus,
     pub y: AxisMoveStatus,
     pub z: AxisMoveStatus,
+    // 由变频器输出频率换算的主轴实际转速
+    #[serde(default)]
+    pub spindle_rpm: f32,
+    // G代码正在执行的运动类型，手动移动算作进给
+    #[serde(default)]
+    pub motion: MotionKind,
+}
+
+// 路径的运动类型：G0快移或G1~G3进给
+#[derive(Default, Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq)]
+pub enum MotionKind {
+    Rapid,
+    #[default]
+    Feed,
+}
+
+impl MotionKind {
+    // G0为快移，G1~G3为进给，其他G代码不是运动
+    pub fn from_g_number(number: i32) -> Option<Self> {
+        match number {
+            0 => Some(MotionKind::Rapid),
+            1..=3 => Some(MotionKind::Feed),
+            _ => None,
+        }
+    }
+
+    // 开启区分快移时，快移画成淡色虚线，进给画成实线
+    pub fn dashed(self, distinguish_rapids: bool) -> bool {
+        distinguish_rapids && self == MotionKind::Rapid
+    }
+}
+
+impl MoveStatus {
+    // 按轴名排列的状态，界面按此生成表格列
+    pub fn axes(self) -> [(&'static str, AxisMoveStatus); 3] {
+        [("X", self.x), ("Y", self.y), ("Z", self.z)]
+    }
+
+    pub fn any_alarm(&self) -> bool {
+        self.x.in_alarm || self.y.in_alarm || self.z.in_alarm
+    }
+}
+
+// 位置变化小于该值时认为没有移动
+const STALL_POSITION_EPSILON: f32 = 1e-4;
+
+// 失速检测：轴不空闲(正在被指令运动)但位置连续多个周期没有变化，可能是机械卡滞或丢步
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct StallDetector {
+    last_pos: Option<f32>,
+    frozen_cycles: u32,
+}
+
+impl StallDetector {
+    // 每个状态读取周期调用一次，位置连续cycles个周期不变时返回true，cycles为0时不检测
+    pub fn update(&mut self, is_idle: bool, pos: f32, cycles: u32) -> bool {
+        let frozen = self
+            .last_pos
+            .is_some_and(|last| (pos - last).abs() < STALL_POSITION_EPSILON);
+        self.last_pos = Some(pos);
+        if is_idle || !frozen {
+            self.frozen_cycles = 0;
+            return false;
+        }
+        self.frozen_cycles = self.frozen_cycles.saturating_add(1);
+        cycles > 0 && self.frozen_cycles >= cycles
+    }
+}
+
+// G代码执行状态
+#[derive(Default, Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq)]
+pub enum RunState {
+    #[default]
+    Idle,
+    Running,
+    Paused,
+    Completed,
+    Error,
+}
+
+// 界面按钮根据执行状态决定是否可用
+impl RunState {
+    pub fn can_start(self) -> bool {
+        matches!(self, RunState::Idle | RunState::Completed | RunState::Error)
+    }
+
+    pub fn can_pause(self) -> bool {
+        self == RunState::Running
+    }
+
+    pub fn can_stop(self) -> bool {
+        matches!(self, RunState::Running | RunState::Paused)
+    }
+
+    pub fn can_resume(self) -> bool {
+        matches!(self, RunState::Paused | RunState::Error)
+    }
+
+    pub fn can_reset(self) -> bool {
+        self != RunState::Running
+    }
+}
+
+// 一次G代码运行的统计，运行结束时保存到磁盘
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct JobStats {
+    // 执行的行数
+    pub lines_executed: usize,
+    // 运行时间(秒)
+    pub runtime_secs: f64,
+    // 总移动距离(mm)，圆弧按弦长计
+    pub distance: f32,
+    // 结束时的状态：Completed或Error
+    pub status: RunState,
+    // 运行中遇到但未实现、被跳过的G/M代码
+    #[serde(default)]
+    pub ignored_codes: Vec<IgnoredCode>,
+}
+
+// 每种忽略的代码最多记录的行号数量
+pub const MAX_IGNORED_LINES: usize = 10;
+
+// 一次G代码运行的生产记录，运行结束时追加保存到磁盘，跨会话保留
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct RunRecord {
+    // 程序文件名，直接加载的程序为空
+    pub file_name: String,
+    // 开始和结束时间(毫秒时间戳)，暂停后继续的运行从第一次开始算起
+    pub started_ms: i64,
+    pub ended_ms: i64,
+    // 运行的结果
+    pub status: RunOutcome,
+    // 程序的总行数
+    pub lines: usize,
+}
+
+// 一次运行的结果：正常完成、出错、安全停止或紧急中止
+#[derive(Default, Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq)]
+pub enum RunOutcome {
+    #[default]
+    Completed,
+    Error,
+    // 旧的记录中途停止保存为Idle
+    #[serde(alias = "Idle")]
+    Stopped,
+    Aborted,
+}
+
+// 运行时跳过的一种G/M代码及其所在行
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct IgnoredCode {
+    // 如"G43"、"M98"
+    pub code: String,
+    // 出现的行号(从1开始)，只保留前MAX_IGNORED_LINES个
+    pub lines: Vec<usize>,
+    // 出现的行数超过MAX_IGNORED_LINES，部分行号没有记录
+    pub truncated: bool,
+}
+
+impl IgnoredCode {
+    // 记录一次出现，同一行重复出现只记一次行号；返回记录是否有变化
+    pub fn record(codes: &mut Vec<IgnoredCode>, code: String, line: usize) -> bool {
+        let index = match codes.iter().position(|c| c.code == code) {
+            Some(index) => index,
+            None => {
+                codes.push(IgnoredCode {
+                    code,
+                    ..Default::default()
+                });
+                codes.len() - 1
+            }
+        };
+        let entry = &mut codes[index];
+        if entry.lines.contains(&line) {
+            return false;
+        }
+        if entry.lines.len() < MAX_IGNORED_LINES {
+            entry.lines.push(line);
+            true
+        } else {
+            !std::mem::replace(&mut entry.truncated, true)
+        }
+    }
+}
+
+// 如"G43, M98 at lines 3, 12, 40…"，没有忽略的代码时返回None
+pub fn format_ignored_codes(codes: &[IgnoredCode], at_lines: &str) -> Option<String> {
+    if codes.is_empty() {
+        return None;
+    }
+    let names: Vec<&str> = codes.iter().map(|c| c.code.as_str()).collect();
+    let mut lines: Vec<usize> = codes.iter().flat_map(|c| c.lines.iter().copied()).collect();
+    lines.sort_unstable();
+    lines.dedup();
+    let truncated = codes.iter().any(|c| c.truncated);
+    let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+    Some(format!(
+        "{} {} {}{}",
+        names.join(", "),
+        at_lines,
+        lines.join(", "),
+        if truncated { "\u{2026}" } else { "" }
+    ))
+}
+
+// 发送给控制器的一条指令记录
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct LogEntry {
+    // Unix时间戳(ms)
+    pub timestamp_ms: i64,
+    pub command: String,
+    pub detail: String,
+}
+
+// GET /api/status返回的机床状态
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct MachineStatus {
+    pub move_status: MoveStatus,
+    pub limit_status: LimitStatus,
+}
+
+// 服务端推送的控制器错误，seq每次递增，客户端据此区分重复发生的相同错误
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct ErrorEvent {
+    pub seq: u64,
+    pub message: String,
+}
+
+// 实时路径图中发生变化的一块区域，data_url只包含该区域的图像
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct PathTile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub data_url: String,
+}
+
+// 服务端推送的路径图增量，seq每次递增，客户端发现缺失时重新获取完整图像
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct PathImageDelta {
+    pub seq: u64,
+    // 整幅图像的大小，大小改变时客户端重新获取完整图像
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    pub tiles: Vec<PathTile>,
+}
+
+// 完整的实时路径图，包含seq及之前所有增量的内容
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct PathImageSnapshot {
+    pub seq: u64,
+    pub width: u32,
+    pub height: u32,
+    pub data_url: String,
+}
+
+// 当前连接的控制器类型，模拟控制器不会驱动真实机床
+#[derive(Default, Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+pub enum ControllerKind {
+    #[default]
+    None,
+    Real,
+    Fake,
+}
+
+// 连接时从控制器读取的型号、固件版本和轴数
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct ControllerInfo {
+    pub model: String,
+    pub firmware: String,
+    pub axis_count: u8,
+}
+
+// 服务端当前的连接状态，页面刷新后用于同步客户端cookie
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct ConnectionSnapshot {
+    pub connected: bool,
+    #[serde(default)]
+    pub kind: ControllerKind,
+    // 以太网连接的控制器IP，模拟控制器为None
+    pub ip: Option<String>,
+    pub parameters: Parameters,
+}
+
+// 从start行开始的一段G代码及程序总行数
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct GCodeWindow {
+    pub start: usize,
+    pub total: usize,
+    pub lines: Vec<String>,
+}
+
+// 最近上传的G代码文件，服务端保存了一份副本可以直接重新加载
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct RecentGCodeFile {
+    pub name: String,
+    pub lines: usize,
+    // 上传时间(毫秒时间戳)
+    pub uploaded_ms: i64,
+}
+
+// POST /api/job提交成功后返回的任务编号
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct JobSubmission {
+    pub job_id: u64,
+}
+
+// 走过路径的Z深度范围，清除路径时重新开始统计
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct DepthRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl DepthRange {
+    pub fn from_point(z: f32) -> Self {
+        Self { min: z, max: z }
+    }
+
+    pub fn include(&mut self, z: f32) {
+        self.min = self.min.min(z);
+        self.max = self.max.max(z);
+    }
+}
+
+// 路径在X/Y平面上的包围盒
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct PathBounds {
+    pub min_x: f32,
+    pub max_x: f32,
+    pub min_y: f32,
+    pub max_y: f32,
+}
+
+impl PathBounds {
+    pub fn from_point(x: f32, y: f32) -> Self {
+        Self {
+            min_x: x,
+            max_x: x,
+            min_y: y,
+            max_y: y,
+        }
+    }
+
+    pub fn include(&mut self, x: f32, y: f32) {
+        self.min_x = self.min_x.min(x);
+        self.max_x = self.max_x.max(x);
+        self.min_y = self.min_y.min(y);
+        self.max_y = self.max_y.max(y);
+    }
+
+    // 是否完全在limits以内，刚好落在边界上也算在内
+    pub fn within(&self, limits: &PathBounds) -> bool {
+        self.min_x >= limits.min_x
+            && self.max_x <= limits.max_x
+            && self.min_y >= limits.min_y
+            && self.max_y <= limits.max_y
+    }
+
+    // 列出超出limits的轴和方向，如"X+ 120.000 > 100.000"
+    pub fn violations(&self, limits: &PathBounds) -> Vec<String> {
+        let mut violations = Vec::new();
+        if self.max_x > limits.max_x {
+            violations.push(format!("X+ {:.3} > {:.3}", self.max_x, limits.max_x));
+        }
+        if self.min_x < limits.min_x {
+            violations.push(format!("X- {:.3} < {:.3}", self.min_x, limits.min_x));
+        }
+        if self.max_y > limits.max_y {
+            violations.push(format!("Y+ {:.3} > {:.3}", self.max_y, limits.max_y));
+        }
+        if self.min_y < limits.min_y {
+            violations.push(format!("Y- {:.3} < {:.3}", self.min_y, limits.min_y));
+        }
+        violations
+    }
+
+    // 经过显示方向变换后的包围盒，旋转90/270度时X/Y范围互换
+    pub fn oriented(&self, orientation: PathOrientation) -> PathBounds {
+        let corners = [
+            (self.min_x, self.min_y),
+            (self.max_x, self.min_y),
+            (self.min_x, self.max_y),
+            (self.max_x, self.max_y),
+        ]
+        .map(|(x, y)| orientation.apply(x as f64, y as f64));
+        let (x, y) = corners[0];
+        let mut bounds = PathBounds::from_point(x as f32, y as f32);
+        for (x, y) in &corners[1..] {
+            bounds.include(*x as f32, *y as f32);
+        }
+        bounds
+    }
+}
+
+// 路径图适配加工区域后的比例(像素/毫米)和零点位置(同PathOrigin::fraction)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BitmapFit {
+    pub scale: f32,
+    pub origin: (f32, f32),
+}
+
+// 把x_range、y_range(毫米)完整放进width×height像素的图像，四周留边并居中。
+// 范围总会扩展到包含零点，保证零点在图像内；范围为空时返回None
+pub fn fit_to_range(
+    width: usize,
+    height: usize,
+    x_range: (f32, f32),
+    y_range: (f32, f32),
+) -> Option<BitmapFit> {
+    let (min_x, max_x) = (x_range.0.min(0.0), x_range.1.max(0.0));
+    let (min_y, max_y) = (y_range.0.min(0.0), y_range.1.max(0.0));
+    let usable = 1.0 - 2.0 * PATH_ORIGIN_MARGIN;
+    let scale_x = width as f32 * usable / (max_x - min_x);
+    let scale_y = height as f32 * usable / (max_y - min_y);
+    // 只有一个方向有范围时按该方向适配
+    let scale = match (scale_x.is_finite(), scale_y.is_finite()) {
+        (true, true) => scale_x.min(scale_y),
+        (true, false) => scale_x,
+        (false, true) => scale_y,
+        (false, false) => return None,
+    };
+    if scale <= 0.0 {
+        return None;
+    }
+    // 范围中心对准图像中心，图像y轴向下
+    let center_x = (min_x + max_x) / 2.0;
+    let center_y = (min_y + max_y) / 2.0;
+    Some(BitmapFit {
+        scale,
+        origin: (
+            0.5 - center_x * scale / width as f32,
+            0.5 + center_y * scale / height as f32,
+        ),
+    })
+}
+
+// 适配加工区域时路径图的目标精度(像素/毫米)和每边像素数的范围
+pub const PATH_FIT_PX_PER_MM: f32 = 4.0;
+pub const PATH_FIT_MIN_SIZE: usize = 200;
+pub const PATH_FIT_MAX_SIZE: usize = 2000;
+
+// 适配加工区域时按范围选择路径图大小，使比例接近PATH_FIT_PX_PER_MM；
+// 范围太大时降低比例使每边不超过PATH_FIT_MAX_SIZE，结果再交给fit_to_range计算比例和零点
+pub fn fitted_bitmap_size(x_range: (f32, f32), y_range: (f32, f32)) -> (usize, usize) {
+    let usable = 1.0 - 2.0 * PATH_ORIGIN_MARGIN;
+    let span_x = x_range.1.max(0.0) - x_range.0.min(0.0);
+    let span_y = y_range.1.max(0.0) - y_range.0.min(0.0);
+    let max_scale = |span: f32| {
+        if span > 0.0 {
+            PATH_FIT_MAX_SIZE as f32 * usable / span
+        } else {
+            f32::INFINITY
+        }
+    };
+    let scale = PATH_FIT_PX_PER_MM
+        .min(max_scale(span_x))
+        .min(max_scale(span_y));
+    let side = |span: f32| {
+        ((span * scale / usable).ceil() as usize).clamp(PATH_FIT_MIN_SIZE, PATH_FIT_MAX_SIZE)
+    };
+    (side(span_x), side(span_y))
+}
+
+// 路径图片样式
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct PathImageStyle {
+    // 背景色RGBA
+    pub background: [u8; 4],
+    // 路径线宽(像素)
+    #[serde(default = "default_line_width")]
+    pub line_width: usize,
+    // 路径显示方向，使预览与操作者看到的机床方向一致
+    #[serde(default)]
+    pub orientation: PathOrientation,
+    // 实时路径下方淡色叠加G代码预览，对比指令路径和实际路径
+    #[serde(default)]
+    pub overlay_preview: bool,
+    // 机床零点在路径图中的位置
+    #[serde(default)]
+    pub origin: PathOrigin,
+    // 按X/Y软件限位自动计算比例，使整个加工区域显示在路径图内，开启时忽略origin
+    #[serde(default)]
+    pub fit_work_area: bool,
+    // 路径图片的编码格式，带宽有限时可以改用JPEG或WebP
+    #[serde(default)]
+    pub format: PathImageFormat,
+    // JPEG质量(1-100)
+    #[serde(default = "default_jpeg_quality")]
+    pub jpeg_quality: u8,
+    // G0快移画成淡色虚线，与进给区分
+    #[serde(default)]
+    pub distinguish_rapids: bool,
+}
+
+#[derive(Default, Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq)]
+pub enum PathImageFormat {
+    #[default]
+    Png,
+    // 有损压缩，线条边缘会有杂色
+    Jpeg,
+    // 无损压缩，通常比PNG小
+    WebP,
+}
+
+impl PathImageFormat {
+    pub const ALL: [PathImageFormat; 3] = [
+        PathImageFormat::Png,
+        PathImageFormat::Jpeg,
+        PathImageFormat::WebP,
+    ];
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            PathImageFormat::Png => "image/png",
+            PathImageFormat::Jpeg => "image/jpeg",
+            PathImageFormat::WebP => "image/webp",
+        }
+    }
+
+    pub fn data_url_prefix(self) -> String {
+        format!("data:{};base64,", self.mime())
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PathImageFormat::Png => "PNG",
+            PathImageFormat::Jpeg => "JPEG",
+            PathImageFormat::WebP => "WebP",
+        }
+    }
+}
+
+// 是否为base64编码的图片data URL，格式可以是PathImageFormat中的任意一种
+pub fn is_image_data_url(url: &str) -> bool {
+    url.strip_prefix("data:image/")
+        .and_then(|rest| rest.split_once(";base64,"))
+        .is_some_and(|(subtype, data)| !subtype.is_empty() && !data.is_empty())
+}
+
+// 路径图零点的位置，零点在角上的机床放在中心时只用得到四分之一的图像
+#[derive(Default, Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq)]
+pub enum PathOrigin {
+    #[default]
+    Center,
+    BottomLeft,
+    TopLeft,
+    BottomRight,
+    TopRight,
+}
+
+// 零点放在角上时离图像边缘留出的比例，避免线宽被裁掉
+const PATH_ORIGIN_MARGIN: f32 = 0.05;
+
+impl PathOrigin {
+    pub const ALL: [PathOrigin; 5] = [
+        PathOrigin::Center,
+        PathOrigin::BottomLeft,
+        PathOrigin::TopLeft,
+        PathOrigin::BottomRight,
+        PathOrigin::TopRight,
+    ];
+
+    // 零点在图像中的位置，取宽高的比例，y从图像顶部算起
+    pub fn fraction(self) -> (f32, f32) {
+        let (near, far) = (PATH_ORIGIN_MARGIN, 1.0 - PATH_ORIGIN_MARGIN);
+        match self {
+            PathOrigin::Center => (0.5, 0.5),
+            PathOrigin::BottomLeft => (near, far),
+            PathOrigin::TopLeft => (near, near),
+            PathOrigin::BottomRight => (far, far),
+            PathOrigin::TopRight => (far, near),
+        }
+    }
+
+    pub fn i18n_key(self) -> &'static str {
+        match self {
+            PathOrigin::Center => "origin_center",
+            PathOrigin::BottomLeft => "origin_bottom_left",
+            PathOrigin::TopLeft => "origin_top_left",
+            PathOrigin::BottomRight => "origin_bottom_right",
+            PathOrigin::TopRight => "origin_top_right",
+        }
+    }
+}
+
+// 路径显示方向：先镜像再逆时针旋转(0/90/180/270度)，只变换显示，不改变坐标
+#[derive(Default, Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct PathOrientation {
+    pub mirror_x: bool,
+    pub mirror_y: bool,
+    pub rotation: u16,
+}
+
+impl PathOrientation {
+    pub const ROTATIONS: [u16; 4] = [0, 90, 180, 270];
+
+    // 机床坐标变换为显示坐标
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let x = if self.mirror_x { -x } else { x };
+        let y = if self.mirror_y { -y } else { y };
+        match self.rotation % 360 {
+            90 => (-y, x),
+            180 => (-x, -y),
+            270 => (y, -x),
+            _ => (x, y),
+        }
+    }
+}
+
+fn default_line_width() -> usize {
+    1
+}
+
+fn default_jpeg_quality() -> u8 {
+    80
+}
+
+impl Default for PathImageStyle {
+    fn default() -> Self {
+        // 透明白色背景，1像素线宽
+        Self {
+            background: [255, 255, 255, 0],
+            line_width: default_line_width(),
+            orientation: PathOrientation::default(),
+            overlay_preview: false,
+            origin: PathOrigin::default(),
+            fit_work_area: false,
+            format: PathImageFormat::default(),
+            jpeg_quality: default_jpeg_quality(),
+            distinguish_rapids: false,
+        }
+    }
+}
+
+// G代码执行选项
+#[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct ExecutionOptions {
+    // 跳段：跳过以/开头的可选程序段
+    pub block_delete: bool,
+    // 控制器速度单位为units/min时F值不再除以60
+    #[serde(default)]
+    pub speed_per_minute: bool,
+    // 安全快移：改变XY的G0先抬到安全高度，快移后再下降
+    #[serde(default)]
+    pub safe_rapids: bool,
+    // 程序结束(或安全停止)后抬Z到安全高度
+    #[serde(default)]
+    pub end_retract: bool,
+    // 程序结束后抬Z并回到X0/Y0
+    #[serde(default)]
+    pub end_return_xy: bool,
+    // 调试用：每行至少执行多长时间(ms)，0表示不限制
+    #[serde(default)]
+    pub min_line_interval_ms: u64,
+    // 发给控制器的目标坐标保留的小数位数，None表示不取整
+    #[serde(default)]
+    pub coordinate_decimals: Option<u8>,
+}
+
+impl ExecutionOptions {
+    // 可选的坐标小数位数
+    pub const COORDINATE_DECIMALS: [u8; 7] = [0, 1, 2, 3, 4, 5, 6];
+}
+
+// 按小数位数四舍五入坐标，去掉f64转f32带来的尾数噪声，decimals为None时原样返回
+pub fn round_coordinate(value: f32, decimals: Option<u8>) -> f32 {
+    match decimals {
+        Some(decimals) => {
+            let factor = 10f64.powi(decimals as i32);
+            ((value as f64 * factor).round() / factor) as f32
+        }
+        None => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jog_speed_ramps_from_the_initial_speed() {
+        let control = ManualControl {
+            jog_initial_speed: 5.0,
+            jog_ramp_rate: 10.0,
+            ..Default::default()
+        };
+        assert_eq!(control.jog_speed(0.0, 100.0), 5.0);
+        assert_eq!(control.jog_speed(1.5, 100.0), 20.0);
+    }
+
+    #[test]
+    fn jog_speed_is_capped_at_the_max_speed() {
+        let control = ManualControl::default();
+        assert_eq!(control.jog_speed(60.0, 50.0), 50.0);
+    }
+
+    #[test]
+    fn jog_speed_is_not_capped_without_a_max_speed() {
+        let control = ManualControl::default();
+        assert_eq!(control.jog_speed(60.0, 0.0), 605.0);
+    }
+
+    #[test]
+    fn jog_speed_ignores_a_negative_hold_time() {
+        let control = ManualControl::default();
+        assert_eq!(control.jog_speed(-2.0, 100.0), control.jog_initial_speed);
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn fit_to_range_fills_the_limiting_axis_up_to_the_margin() {
+        let fit = fit_to_range(400, 400, (0.0, 100.0), (0.0, 50.0)).unwrap();
+        let usable = 1.0 - 2.0 * PATH_ORIGIN_MARGIN;
+        assert_close(fit.scale, 400.0 * usable / 100.0);
+        // X的两端正好落在左右留边处
+        let left = fit.origin.0 * 400.0;
+        let right = left + 100.0 * fit.scale;
+        assert_close(left, 400.0 * PATH_ORIGIN_MARGIN);
+        assert_close(right, 400.0 * (1.0 - PATH_ORIGIN_MARGIN));
+    }
+
+    #[test]
+    fn fit_to_range_always_keeps_the_origin_in_the_image() {
+        let fit = fit_to_range(400, 400, (50.0, 100.0), (20.0, 40.0)).unwrap();
+        assert_eq!(
+            fit,
+            fit_to_range(400, 400, (0.0, 100.0), (0.0, 40.0)).unwrap()
+        );
+        assert!((0.0..=1.0).contains(&fit.origin.0));
+        assert!((0.0..=1.0).contains(&fit.origin.1));
+    }
+
+    #[test]
+    fn fit_to_range_uses_the_only_axis_with_a_range() {
+        let fit = fit_to_range(300, 200, (0.0, 0.0), (-10.0, 10.0)).unwrap();
+        assert_close(fit.scale, 200.0 * (1.0 - 2.0 * PATH_ORIGIN_MARGIN) / 20.0);
+        assert_close(fit.origin.1, 0.5);
+    }
+
+    #[test]
+    fn fit_to_range_of_empty_ranges_is_none() {
+        assert_eq!(fit_to_range(400, 400, (0.0, 0.0), (0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn step_target_moves_the_distance_along_the_direction() {
+        assert_eq!(step_target(10.0, 1, 2.5), 12.5);
+        assert_eq!(step_target(10.0, -1, 2.5), 7.5);
+    }
+
+    #[test]
+    fn step_target_uses_only_the_sign_of_the_direction_and_distance() {
+        assert_eq!(step_target(0.0, 5, -1.0), 1.0);
+        assert_eq!(step_target(0.0, -3, -1.0), -1.0);
+        assert_eq!(step_target(4.0, 0, 1.0), 4.0);
+    }
+
+    #[test]
+    fn ignored_codes_record_each_line_once() {
+        let mut codes = Vec::new();
+        assert!(IgnoredCode::record(&mut codes, "G43".to_string(), 3));
+        assert!(!IgnoredCode::record(&mut codes, "G43".to_string(), 3));
+        assert!(IgnoredCode::record(&mut codes, "G43".to_string(), 7));
+        assert!(IgnoredCode::record(&mut codes, "M98".to_string(), 7));
+        assert_eq!(codes.len(), 2);
+        assert_eq!(codes[0].lines, vec![3, 7]);
+        assert_eq!(codes[1].lines, vec![7]);
+    }
+
+    #[test]
+    fn ignored_codes_stop_recording_lines_at_the_limit() {
+        let mut codes = Vec::new();
+        for line in 1..=MAX_IGNORED_LINES {
+            IgnoredCode::record(&mut codes, "T1".to_string(), line);
+        }
+        // 第一次超出上限时标记截断，算作变化，之后不再变化
+        assert!(IgnoredCode::record(&mut codes, "T1".to_string(), 100));
+        assert!(!IgnoredCode::record(&mut codes, "T1".to_string(), 101));
+        assert_eq!(codes[0].lines.len(), MAX_IGNORED_LINES);
+        assert!(codes[0].truncated);
+    }
+
+    #[test]
+    fn a_moving_axis_whose_position_is_frozen_is_reported_after_the_cycles() {
+        let mut detector = StallDetector::default();
+        assert!(!detector.update(false, 1.0, 3));
+        assert!(!detector.update(false, 1.0, 3));
+        assert!(!detector.update(false, 1.0, 3));
+        assert!(detector.update(false, 1.0, 3));
+    }
+
+    #[test]
+    fn a_changing_position_or_an_idle_axis_resets_the_stall_count() {
+        let mut detector = StallDetector::default();
+        detector.update(false, 1.0, 2);
+        detector.update(false, 1.0, 2);
+        assert!(!detector.update(false, 1.5, 2));
+        assert!(!detector.update(false, 1.5, 2));
+        assert!(!detector.update(true, 1.5, 2));
+        assert!(!detector.update(false, 1.5, 2));
+        assert!(detector.update(false, 1.5, 2));
+    }
+
+    #[test]
+    fn stall_detection_is_off_with_zero_cycles() {
+        let mut detector = StallDetector::default();
+        for _ in 0..10 {
+            assert!(!detector.update(false, 1.0, 0));
+        }
+    }
+
+    #[test]
+    fn coordinates_are_rounded_to_the_chosen_decimals() {
+        assert_close(round_coordinate(1.23456, Some(3)), 1.235);
+        assert_close(round_coordinate(-1.23456, Some(2)), -1.23);
+        assert_close(round_coordinate(2.5, Some(0)), 3.0);
+    }
+
+    #[test]
+    fn coordinates_are_issued_unchanged_without_rounding() {
+        assert_eq!(round_coordinate(1.23456, None), 1.23456);
+    }
+
+    const RUN_STATES: [RunState; 5] = [
+        RunState::Idle,
+        RunState::Running,
+        RunState::Paused,
+        RunState::Completed,
+        RunState::Error,
+    ];
+
+    fn allowed(can: fn(RunState) -> bool) -> Vec<RunState> {
+        RUN_STATES.into_iter().filter(|state| can(*state)).collect()
+    }
+
+    #[test]
+    fn a_program_can_start_only_when_no_run_is_active() {
+        assert_eq!(
+            allowed(RunState::can_start),
+            vec![RunState::Idle, RunState::Completed, RunState::Error]
+        );
+    }
+
+    #[test]
+    fn only_a_running_program_can_pause() {
+        assert_eq!(allowed(RunState::can_pause), vec![RunState::Running]);
+    }
+
+    #[test]
+    fn running_and_paused_programs_can_stop() {
+        assert_eq!(
+            allowed(RunState::can_stop),
+            vec![RunState::Running, RunState::Paused]
+        );
+    }
+
+    #[test]
+    fn paused_and_failed_programs_can_resume() {
+        assert_eq!(
+            allowed(RunState::can_resume),
+            vec![RunState::Paused, RunState::Error]
+        );
+    }
+
+    #[test]
+    fn every_state_but_running_can_reset() {
+        assert_eq!(
+            allowed(RunState::can_reset),
+            vec![
+                RunState::Idle,
+                RunState::Paused,
+                RunState::Completed,
+                RunState::Error
+            ]
+        );
+    }
+
+    #[test]
+    fn following_errors_beyond_the_limit_are_flagged_in_either_direction() {
+        let parameters = Parameters {
+            following_error_limit: 0.5,
+            ..Default::default()
+        };
+        assert!(!parameters.following_error_exceeded(0.0));
+        assert!(!parameters.following_error_exceeded(0.5));
+        assert!(!parameters.following_error_exceeded(-0.5));
+        assert!(parameters.following_error_exceeded(0.51));
+        assert!(parameters.following_error_exceeded(-0.51));
+    }
+
+    #[test]
+    fn a_zero_following_error_limit_never_flags() {
+        let parameters = Parameters {
+            following_error_limit: 0.0,
+            ..Default::default()
+        };
+        assert!(!parameters.following_error_exceeded(100.0));
+        assert!(!parameters.following_error_exceeded(-100.0));
+    }
+
+    // X/Y/Z依次使用给定轴号的参数
+    fn with_axis_nums(x: u8, y: u8, z: u8) -> Parameters {
+        let mut parameters = Parameters::default();
+        parameters.x.axis_num = x;
+        parameters.y.axis_num = y;
+        parameters.z.axis_num = z;
+        parameters
+    }
+
+    #[test]
+    fn distinct_axis_numbers_within_the_controller_are_valid() {
+        assert_eq!(with_axis_nums(0, 1, 2).validate_axis_nums(3), Ok(()));
+        assert_eq!(with_axis_nums(3, 0, 2).validate_axis_nums(4), Ok(()));
+    }
+
+    #[test]
+    fn a_shared_axis_number_is_rejected() {
+        assert_eq!(
+            with_axis_nums(0, 0, 2).validate_axis_nums(4),
+            Err("X and Y axes both use axis number 0".to_string())
+        );
+        assert_eq!(
+            with_axis_nums(0, 1, 1).validate_axis_nums(4),
+            Err("Y and Z axes both use axis number 1".to_string())
+        );
+        assert_eq!(
+            with_axis_nums(2, 1, 2).validate_axis_nums(4),
+            Err("X and Z axes both use axis number 2".to_string())
+        );
+    }
+
+    #[test]
+    fn an_axis_number_beyond_the_controller_is_rejected() {
+        assert_eq!(
+            with_axis_nums(0, 1, 4).validate_axis_nums(4),
+            Err("Z axis number 4 is out of range (0-3)".to_string())
+        );
+    }
+
+    fn with_max_speed(max_speed: f32) -> SpeedParameters {
+        SpeedParameters {
+            max_speed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn feed_speeds_above_the_max_speed_are_clamped() {
+        assert_eq!(with_max_speed(50.0).clamp_feed_speed(80.0), (50.0, true));
+    }
+
+    #[test]
+    fn feed_speeds_up_to_the_max_speed_are_unchanged() {
+        assert_eq!(with_max_speed(50.0).clamp_feed_speed(20.0), (20.0, false));
+        assert_eq!(with_max_speed(50.0).clamp_feed_speed(50.0), (50.0, false));
+    }
+
+    #[test]
+    fn feed_speeds_are_not_clamped_without_a_max_speed() {
+        assert_eq!(with_max_speed(0.0).clamp_feed_speed(1e6), (1e6, false));
+        assert_eq!(with_max_speed(-1.0).clamp_feed_speed(80.0), (80.0, false));
+    }
+
+    #[test]
+    fn spindle_rpm_follows_the_frequency_poles_and_ratio() {
+        // 2极电机50Hz同步转速3000rpm
+        assert_close(SpindleParameters::default().rpm(50.0), 3000.0);
+        let spindle = SpindleParameters {
+            poles: 4,
+            ratio: 1.5,
+            ..Default::default()
+        };
+        assert_close(spindle.rpm(400.0), 18000.0);
+        assert_close(spindle.rpm(0.0), 0.0);
+    }
+
+    #[test]
+    fn spindle_rpm_without_poles_is_zero() {
+        let spindle = SpindleParameters {
+            poles: 0,
+            ..Default::default()
+        };
+        assert_eq!(spindle.rpm(50.0), 0.0);
+    }
+
+    #[test]
+    fn going_to_zero_raises_z_before_moving_x_and_y() {
+        let parameters = Parameters {
+            safe_z_height: 12.0,
+            ..with_axis_nums(0, 1, 2)
+        };
+        assert_eq!(
+            parameters.goto_zero_moves(),
+            vec![(vec![2], vec![12.0]), (vec![0, 1], vec![0.0, 0.0])]
+        );
+    }
+
+    #[test]
+    fn disabled_axes_do_not_move_to_zero() {
+        let mut parameters = with_axis_nums(0, 1, 2);
+        parameters.x.enabled = false;
+        assert_eq!(
+            parameters.goto_zero_moves(),
+            vec![(vec![2], vec![5.0]), (vec![1], vec![0.0])]
+        );
+        parameters.z.enabled = false;
+        assert_eq!(parameters.goto_zero_moves(), vec![(vec![1], vec![0.0])]);
+        parameters.y.enabled = false;
+        assert!(parameters.goto_zero_moves().is_empty());
+    }
+
+    #[test]
+    fn enabled_axes_are_listed_in_x_y_z_order() {
+        let mut parameters = Parameters::default();
+        assert_eq!(parameters.enabled_axes(), [true, true, true]);
+        parameters.y.enabled = false;
+        assert_eq!(parameters.enabled_axes(), [true, false, true]);
+    }
+
+    #[test]
+    fn homing_starts_with_z_and_skips_disabled_axes() {
+        let mut parameters = with_axis_nums(3, 0, 1);
+        assert_eq!(parameters.homing_order(), vec![1, 3, 0]);
+        parameters.x.enabled = false;
+        assert_eq!(parameters.homing_order(), vec![1, 0]);
+        parameters.z.enabled = false;
+        parameters.y.enabled = false;
+        assert!(parameters.homing_order().is_empty());
+    }
+
+    fn bounds(min_x: f32, max_x: f32, min_y: f32, max_y: f32) -> PathBounds {
+        PathBounds {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+        }
+    }
+
+    #[test]
+    fn paths_inside_or_on_the_limits_are_within() {
+        let limits = bounds(-100.0, 100.0, 0.0, 50.0);
+        assert!(bounds(-10.0, 10.0, 5.0, 20.0).within(&limits));
+        assert!(limits.within(&limits));
+        assert!(PathBounds::from_point(100.0, 0.0).within(&limits));
+    }
+
+    #[test]
+    fn paths_past_any_limit_are_not_within() {
+        let limits = bounds(-100.0, 100.0, 0.0, 50.0);
+        for path in [
+            bounds(-100.1, 0.0, 0.0, 10.0),
+            bounds(0.0, 100.1, 0.0, 10.0),
+            bounds(0.0, 10.0, -0.1, 10.0),
+            bounds(0.0, 10.0, 0.0, 50.1),
+        ] {
+            assert!(!path.within(&limits), "{:?}", path);
+            assert_eq!(path.violations(&limits).len(), 1, "{:?}", path);
+        }
+    }
+
+    fn orientation(mirror_x: bool, mirror_y: bool, rotation: u16) -> PathOrientation {
+        PathOrientation {
+            mirror_x,
+            mirror_y,
+            rotation,
+        }
+    }
+
+    #[test]
+    fn orientations_rotate_counterclockwise() {
+        assert_eq!(orientation(false, false, 0).apply(2.0, 1.0), (2.0, 1.0));
+        assert_eq!(orientation(false, false, 90).apply(2.0, 1.0), (-1.0, 2.0));
+        assert_eq!(orientation(false, false, 180).apply(2.0, 1.0), (-2.0, -1.0));
+        assert_eq!(orientation(false, false, 270).apply(2.0, 1.0), (1.0, -2.0));
+        assert_eq!(orientation(false, false, 450).apply(2.0, 1.0), (-1.0, 2.0));
+    }
+
+    #[test]
+    fn orientations_mirror_before_rotating() {
+        assert_eq!(orientation(true, false, 0).apply(2.0, 1.0), (-2.0, 1.0));
+        assert_eq!(orientation(false, true, 0).apply(2.0, 1.0), (2.0, -1.0));
+        // 先镜像X得到(-2, 1)，再旋转90度
+        assert_eq!(orientation(true, false, 90).apply(2.0, 1.0), (-1.0, -2.0));
+    }
+
+    #[test]
+    fn oriented_bounds_swap_the_axes_for_quarter_turns() {
+        let path = bounds(0.0, 100.0, -10.0, 20.0);
+        assert_eq!(path.oriented(PathOrientation::default()), path);
+        assert_eq!(
+            path.oriented(orientation(false, false, 90)),
+            bounds(-20.0, 10.0, 0.0, 100.0)
+        );
+        assert_eq!(
+            path.oriented(orientation(false, false, 180)),
+            bounds(-100.0, 0.0, -20.0, 10.0)
+        );
+        assert_eq!(
+            path.oriented(orientation(true, true, 0)),
+            bounds(-100.0, 0.0, -20.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn fitted_bitmaps_keep_the_target_scale_for_medium_areas() {
+        let usable = 1.0 - 2.0 * PATH_ORIGIN_MARGIN;
+        let expected = |span: f32| (span * PATH_FIT_PX_PER_MM / usable).ceil() as usize;
+        assert_eq!(
+            fitted_bitmap_size((0.0, 300.0), (0.0, 150.0)),
+            (expected(300.0), expected(150.0))
+        );
+        // 范围总会扩展到包含零点
+        assert_eq!(
+            fitted_bitmap_size((-150.0, 150.0), (50.0, 150.0)),
+            fitted_bitmap_size((0.0, 300.0), (0.0, 150.0))
+        );
+    }
+
+    #[test]
+    fn fitted_bitmaps_are_at_least_the_min_size() {
+        assert_eq!(
+            fitted_bitmap_size((0.0, 10.0), (0.0, 0.0)),
+            (PATH_FIT_MIN_SIZE, PATH_FIT_MIN_SIZE)
+        );
+    }
+
+    #[test]
+    fn large_areas_lower_the_scale_to_stay_within_the_max_size() {
+        let (width, height) = fitted_bitmap_size((0.0, 2000.0), (0.0, 1000.0));
+        assert_eq!(width, PATH_FIT_MAX_SIZE);
+        // 两个方向使用同一个降低后的比例
+        assert!(height.abs_diff(PATH_FIT_MAX_SIZE / 2) <= 1, "{}", height);
+        let fit = fit_to_range(width, height, (0.0, 2000.0), (0.0, 1000.0)).unwrap();
+        assert!(fit.scale < PATH_FIT_PX_PER_MM);
+    }
+
+    #[test]
+    fn values_are_formatted_with_the_chosen_decimals() {
+        assert_eq!(format_decimals(1.23456, 0), "1");
+        assert_eq!(format_decimals(1.23456, 2), "1.23");
+        assert_eq!(format_decimals(-0.5, 3), "-0.500");
+    }
+
+    #[test]
+    fn decimals_beyond_the_max_are_capped() {
+        assert_eq!(
+            format_decimals(1.0, UiPreferences::MAX_DECIMALS + 4),
+            format_decimals(1.0, UiPreferences::MAX_DECIMALS)
+        );
+        assert_eq!(format_decimals(1.0, usize::MAX), "1.000000");
+    }
+
+    #[test]
+    fn positions_and_speeds_use_their_own_decimals() {
+        let preferences = UiPreferences {
+            position_decimals: 3,
+            speed_decimals: 1,
+        };
+        assert_eq!(preferences.format_position(12.34567), "12.346");
+        assert_eq!(preferences.format_speed(12.34567), "12.3");
+    }
+
+    #[test]
+    fn missing_ui_preferences_use_the_defaults() {
+        let preferences: UiPreferences = serde_json::from_str("{}").unwrap();
+        assert_eq!(preferences, UiPreferences::default());
+    }
+
+    fn door(open: bool) -> LimitStatus {
+        LimitStatus {
+            door_switch: open,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn the_door_interlock_engages_when_the_door_switch_triggers() {
+        let mut parameters = Parameters {
+            door_interlock: true,
+            ..Default::default()
+        };
+        assert!(parameters.door_interlock_engaged(&door(true)));
+        assert!(!parameters.door_interlock_engaged(&door(false)));
+        // 电平反转时输入为低表示门打开
+        parameters.inverted_status.door_switch_level_inverted = true;
+        assert!(!parameters.door_interlock_engaged(&door(true)));
+        assert!(parameters.door_interlock_engaged(&door(false)));
+    }
+
+    #[test]
+    fn the_door_interlock_never_engages_when_disabled() {
+        let mut parameters = Parameters::default();
+        assert!(!parameters.door_interlock_engaged(&door(true)));
+        parameters.inverted_status.door_switch_level_inverted = true;
+        assert!(!parameters.door_interlock_engaged(&door(false)));
+    }
+
+    #[test]
+    fn depth_ranges_grow_to_include_each_depth() {
+        let mut range = DepthRange::from_point(-1.0);
+        assert_eq!(
+            range,
+            DepthRange {
+                min: -1.0,
+                max: -1.0
+            }
+        );
+        range.include(-3.5);
+        range.include(2.0);
+        range.include(0.0);
+        assert_eq!(
+            range,
+            DepthRange {
+                min: -3.5,
+                max: 2.0
+            }
+        );
+    }
 }
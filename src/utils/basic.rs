@@ -0,0 +1,149 @@
+// 将参数导出为ZMC BASIC初始化程序，设置项与zmc_set_parameters下发给控制器的一致
+use std::fmt::Write;
+
+use crate::model::Parameters;
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
+
+pub fn parameters_to_basic(params: &Parameters) -> String {
+    let mut basic = String::new();
+    // 写入String不会失败
+    let _ = writeln!(basic, "' Axis parameters exported from the upper computer");
+    // 输入IO的电平反转
+    let inverted = &params.inverted_status;
+    let _ = writeln!(
+        basic,
+        "INVERT_IN({}, {})",
+        params.emergency_stop_io,
+        on_off(inverted.emergency_stop_level_inverted)
+    );
+    let _ = writeln!(
+        basic,
+        "INVERT_IN({}, {})",
+        params.door_switch_io,
+        on_off(inverted.door_switch_level_inverted)
+    );
+    for axis in [&params.x, &params.y, &params.z] {
+        for io in [axis.positive_limit_io, axis.negative_limit_io] {
+            let _ = writeln!(
+                basic,
+                "INVERT_IN({}, {})",
+                io,
+                on_off(inverted.limit_io_level_inverted)
+            );
+        }
+    }
+
+    for (name, axis) in [("X", &params.x), ("Y", &params.y), ("Z", &params.z)] {
+        let _ = writeln!(basic);
+        // 停用的轴不做设置
+        if !axis.enabled {
+            let _ = writeln!(basic, "' {} axis ({}) is disabled", name, axis.axis_num);
+            continue;
+        }
+        let _ = writeln!(basic, "' {} axis", name);
+        let _ = writeln!(basic, "BASE({})", axis.axis_num);
+        let _ = writeln!(basic, "ATYPE = 65");
+        let _ = writeln!(basic, "SPEED = {}", params.speed.processing_speed);
+        let _ = writeln!(basic, "LSPEED = 0");
+        let _ = writeln!(basic, "ACCEL = {}", params.speed.acceleration);
+        let _ = writeln!(basic, "DECEL = {}", params.speed.deceleration);
+        let _ = writeln!(basic, "SRAMP = {}", params.speed.sramp_time);
+        let _ = writeln!(basic, "UNITS = {}", axis.pulse_equivalent);
+        let _ = writeln!(basic, "FS_LIMIT = {}", axis.software_positive_limit);
+        let _ = writeln!(basic, "RS_LIMIT = {}", axis.software_negative_limit);
+        let _ = writeln!(basic, "FWD_IN = {}", axis.positive_limit_io);
+        let _ = writeln!(basic, "REV_IN = {}", axis.negative_limit_io);
+        // 0表示未配置回零开关
+        if axis.zero_point_io != 0 {
+            let _ = writeln!(basic, "DATUM_IN = {}", axis.zero_point_io);
+        }
+        let _ = writeln!(basic, "ALM_IN = {}", params.emergency_stop_io);
+    }
+    basic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters() -> Parameters {
+        let mut params = Parameters::default();
+        (params.x.axis_num, params.y.axis_num, params.z.axis_num) = (0, 1, 2);
+        params.speed.processing_speed = 25.0;
+        params.speed.acceleration = 300.0;
+        params.speed.deceleration = 400.0;
+        params.x.pulse_equivalent = 1000.0;
+        params.x.software_positive_limit = 450.0;
+        params.y.pulse_equivalent = 800.0;
+        params.y.software_positive_limit = 300.0;
+        params
+    }
+
+    // BASE(axis_num)之后到空行之间的设置
+    fn section(basic: &str, axis_num: u8) -> Vec<&str> {
+        basic
+            .lines()
+            .skip_while(|line| *line != format!("BASE({})", axis_num))
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn each_enabled_axis_gets_its_own_motion_settings() {
+        let basic = parameters_to_basic(&parameters());
+        let x = section(&basic, 0);
+        for line in [
+            "ATYPE = 65",
+            "SPEED = 25",
+            "ACCEL = 300",
+            "DECEL = 400",
+            "UNITS = 1000",
+            "FS_LIMIT = 450",
+        ] {
+            assert!(x.contains(&line), "{} missing from {:?}", line, x);
+        }
+        let y = section(&basic, 1);
+        assert!(y.contains(&"UNITS = 800"));
+        assert!(y.contains(&"FS_LIMIT = 300"));
+        assert!(y.contains(&"SPEED = 25"));
+    }
+
+    #[test]
+    fn disabled_axes_are_left_out() {
+        let mut params = parameters();
+        params.z.enabled = false;
+        let basic = parameters_to_basic(&params);
+        assert!(section(&basic, 2).is_empty());
+        assert!(basic.contains("' Z axis (2) is disabled"));
+        assert_eq!(basic.matches("ATYPE = 65").count(), 2);
+    }
+
+    #[test]
+    fn the_configured_s_curve_ramp_is_set_on_every_enabled_axis() {
+        let mut params = parameters();
+        params.speed.sramp_time = 120.0;
+        let basic = parameters_to_basic(&params);
+        for axis_num in 0..3 {
+            assert!(section(&basic, axis_num).contains(&"SRAMP = 120"));
+        }
+    }
+
+    #[test]
+    fn only_a_configured_zero_point_io_is_set_as_the_datum_input() {
+        let mut params = parameters();
+        params.x.zero_point_io = 12;
+        let basic = parameters_to_basic(&params);
+        assert!(section(&basic, 0).contains(&"DATUM_IN = 12"));
+        assert!(!section(&basic, 1)
+            .iter()
+            .any(|line| line.starts_with("DATUM_IN")));
+    }
+}
@@ -1,53 +1,235 @@
 use base64::{engine::general_purpose, Engine as _};
 use std::io::Cursor;
 
+use crate::model::{fit_to_range, MotionKind, PathImageFormat, PathOrientation};
+
+// 默认背景：透明白色
+pub const TRANSPARENT_WHITE: [u8; 4] = [255, 255, 255, 0];
+
+// JPEG quality used until set_format is called
+const DEFAULT_JPEG_QUALITY: u8 = 80;
+
+// Edge length in pixels of the tiles used to track changed regions
+pub const TILE_SIZE: usize = 50;
+
+// Rapids are drawn as dashes of this many points followed by a gap of the same length
+const RAPID_DASH_POINTS: usize = 4;
+// Alpha of rapid points, faint compared to the opaque feed moves
+const RAPID_ALPHA: u8 = 110;
+
+// Whether the n-th point of a rapid move falls on a dash rather than a gap
+fn rapid_dash_visible(n: usize) -> bool {
+    n % (2 * RAPID_DASH_POINTS) < RAPID_DASH_POINTS
+}
+
 // A simple bitmap representation
+#[derive(Clone)]
 pub struct Bitmap {
     // Width and height of the bitmap
     width: usize,
     height: usize,
     // Image data in RGBA format (4 bytes per pixel)
     data: Vec<u8>,
-    // Scaling factor to map machine coordinates to pixels
-    scale: f32,
+    // Scaling factors to map machine coordinates to pixels
+    scale_x: f32,
+    scale_y: f32,
     // Origin point in the bitmap (center by default)
     origin_x: usize,
     origin_y: usize,
+    // Where the origin sits as a fraction of width and height, y counted from the top
+    origin_fraction: (f32, f32),
+    // Background color in RGBA, used by the initial fill and clear()
+    background: [u8; 4],
+    // Width in pixels of the drawn path, each point is drawn as a square of this size
+    line_width: usize,
+    // View transform applied to points before they are drawn
+    orientation: PathOrientation,
+    // Number of points that fell outside the bitmap and were skipped
+    out_of_bounds: usize,
+    // Draw rapids faint and dashed, rapid_run counts the points of the current rapid move
+    distinguish_rapids: bool,
+    rapid_run: usize,
+    // Encoding used by to_data_url, JPEG quality is 1-100
+    format: PathImageFormat,
+    jpeg_quality: u8,
+    // One flag per TILE_SIZE x TILE_SIZE tile, row by row, set when the tile changed since
+    // the last take_dirty_regions
+    dirty_tiles: Vec<bool>,
 }
 
 impl Bitmap {
+    // Same scale for both axes
     pub fn new(width: usize, height: usize, scale: f32) -> Self {
-        // Initialize with transparent white background
-        let mut data = vec![255, 255, 255, 0]; // RGBA: transparent white
-        data.resize(width * height * 4, 0);
+        Self::with_scales(width, height, scale, scale)
+    }
 
+    pub fn with_scales(width: usize, height: usize, scale_x: f32, scale_y: f32) -> Self {
+        // Initialize with transparent white background
         Bitmap {
             width,
             height,
-            data,
-            scale,
+            data: TRANSPARENT_WHITE.repeat(width * height),
+            scale_x,
+            scale_y,
             origin_x: width / 2,
             origin_y: height / 2,
+            origin_fraction: (0.5, 0.5),
+            background: TRANSPARENT_WHITE,
+            line_width: 1,
+            orientation: PathOrientation::default(),
+            out_of_bounds: 0,
+            distinguish_rapids: false,
+            rapid_run: 0,
+            format: PathImageFormat::default(),
+            jpeg_quality: DEFAULT_JPEG_QUALITY,
+            // Nothing has been sent yet
+            dirty_tiles: vec![true; width.div_ceil(TILE_SIZE) * height.div_ceil(TILE_SIZE)],
         }
     }
 
+    // Scale and origin chosen so that the given machine ranges (mm) fill the bitmap with a margin,
+    // None when both ranges are empty
+    pub fn fit_to_range(
+        width: usize,
+        height: usize,
+        x_range: (f32, f32),
+        y_range: (f32, f32),
+    ) -> Option<Self> {
+        let fit = fit_to_range(width, height, x_range, y_range)?;
+        let mut bitmap = Self::new(width, height, fit.scale);
+        bitmap.set_origin(fit.origin.0, fit.origin.1);
+        Some(bitmap)
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn with_background(width: usize, height: usize, scale: f32, background: [u8; 4]) -> Self {
+        let mut bitmap = Self::new(width, height, scale);
+        bitmap.set_background(background);
+        bitmap
+    }
+
+    pub fn line_width(&self) -> usize {
+        self.line_width
+    }
+
+    pub fn set_line_width(&mut self, line_width: usize) {
+        self.line_width = line_width.max(1);
+    }
+
+    pub fn orientation(&self) -> PathOrientation {
+        self.orientation
+    }
+
+    // Only affects points drawn afterwards, callers redraw the path when it changes
+    pub fn set_orientation(&mut self, orientation: PathOrientation) {
+        self.orientation = orientation;
+    }
+
+    pub fn distinguish_rapids(&self) -> bool {
+        self.distinguish_rapids
+    }
+
+    // Only affects points drawn afterwards, callers redraw the path when it changes
+    pub fn set_distinguish_rapids(&mut self, distinguish_rapids: bool) {
+        self.distinguish_rapids = distinguish_rapids;
+    }
+
+    // How many points were skipped for being outside the bitmap
+    pub fn out_of_bounds_count(&self) -> usize {
+        self.out_of_bounds
+    }
+
+    // Format and JPEG quality, as passed to set_format
+    pub fn encoding(&self) -> (PathImageFormat, u8) {
+        (self.format, self.jpeg_quality)
+    }
+
+    // Encoding used by to_data_url, the quality only applies to JPEG
+    pub fn set_format(&mut self, format: PathImageFormat, jpeg_quality: u8) {
+        self.format = format;
+        self.jpeg_quality = jpeg_quality.clamp(1, 100);
+    }
+
+    pub fn origin_fraction(&self) -> (f32, f32) {
+        self.origin_fraction
+    }
+
+    // Move the origin, e.g. to a corner for machines whose work zero is there.
+    // Like the orientation it only affects points drawn afterwards
+    pub fn set_origin(&mut self, fraction_x: f32, fraction_y: f32) {
+        self.origin_fraction = (fraction_x.clamp(0.0, 1.0), fraction_y.clamp(0.0, 1.0));
+        self.update_pos(0.0, 0.0);
+    }
+
+    pub fn scale(&self) -> (f32, f32) {
+        (self.scale_x, self.scale_y)
+    }
+
+    pub fn background(&self) -> [u8; 4] {
+        self.background
+    }
+
+    // Change the background color, pixels still showing the old background are repainted
+    pub fn set_background(&mut self, background: [u8; 4]) {
+        let old = self.background;
+        for pixel in self.data.chunks_exact_mut(4) {
+            if pixel == old {
+                pixel.copy_from_slice(&background);
+            }
+        }
+        if old != background {
+            self.mark_all_dirty();
+        }
+        self.background = background;
+    }
+
     pub fn update_pos(&mut self, x: f32, y: f32) {
         // Update the origin point based on the new position
-        self.origin_x = (self.width as f32 / 2.0 + x * self.scale) as usize;
-        self.origin_y = (self.height as f32 / 2.0 + y * self.scale) as usize; // Changed from - to +
+        let (fraction_x, fraction_y) = self.origin_fraction;
+        self.origin_x = (self.width as f32 * fraction_x + x * self.scale_x) as usize;
+        // Changed from - to +
+        self.origin_y = (self.height as f32 * fraction_y + y * self.scale_y) as usize;
+    }
+
+    // Set a pixel for a point of a move of the given kind. With distinguish_rapids on, rapid
+    // points are faint and every other run of RAPID_DASH_POINTS points is left out
+    pub fn set_motion_pixel(&mut self, x: f32, y: f32, z: f32, motion: MotionKind) {
+        if !motion.dashed(self.distinguish_rapids) {
+            self.set_pixel(x, y, z);
+            return;
+        }
+        let visible = rapid_dash_visible(self.rapid_run);
+        self.rapid_run += 1;
+        if visible {
+            self.paint(x, y, z, RAPID_ALPHA);
+        }
     }
 
     // Set a pixel at machine coordinates (will be translated to bitmap coordinates)
     pub fn set_pixel(&mut self, x: f32, y: f32, z: f32) {
-        // Convert machine coordinates to bitmap pixel coordinates
-        let px = (self.origin_x as f32 + x * self.scale) as usize;
-        let py = (self.origin_y as f32 - y * self.scale) as usize; // Changed from + to -
+        // A feed point ends the current rapid, the next one starts with a dash
+        self.rapid_run = 0;
+        self.paint(x, y, z, 255);
+    }
+
+    // Paint the point with the color for z and the given alpha
+    fn paint(&mut self, x: f32, y: f32, z: f32, alpha: u8) {
+        let (x, y) = self.orientation.apply(x as f64, y as f64);
+        let (x, y) = (x as f32, y as f32);
+        // Convert machine coordinates to bitmap pixel coordinates, floor keeps points just left
+        // of or above the bitmap negative instead of truncating them onto the first column/row
+        let px = (self.origin_x as f32 + x * self.scale_x).floor();
+        let py = (self.origin_y as f32 - y * self.scale_y).floor(); // Changed from + to -
 
-        // Check bounds
-        if px >= self.width || py >= self.height {
-            println!("Pixel out of bounds: ({}, {})", px, py);
+        // Check bounds, everything below relies on 0 <= px < width and 0 <= py < height
+        if !(px >= 0.0 && py >= 0.0 && px < self.width as f32 && py < self.height as f32) {
+            self.out_of_bounds += 1;
             return;
         }
+        let (px, py) = (px as usize, py as usize);
 
         let (r, g, b, a) = {
             // Map z from range 0.0 to -4.0 to hue angle 0° to 360°
@@ -82,28 +264,108 @@ impl Bitmap {
                 (c, 0.0, x)
             };
 
-            // Convert to 0-255 range with full opacity
+            // Convert to 0-255 range
             (
                 ((r + m) * 255.0) as u8,
                 ((g + m) * 255.0) as u8,
                 ((b + m) * 255.0) as u8,
-                255,
+                alpha,
             )
         };
 
-        // Calculate pixel index in the data array
-        let idx = (py * self.width + px) * 4;
+        // Paint a line_width x line_width square centered on the point
+        let half = (self.line_width as isize - 1) / 2;
+        for dy in -half..self.line_width as isize - half {
+            for dx in -half..self.line_width as isize - half {
+                let qx = px as isize + dx;
+                let qy = py as isize + dy;
+                if qx < 0 || qy < 0 || qx as usize >= self.width || qy as usize >= self.height {
+                    continue;
+                }
+
+                // Calculate pixel index in the data array, in range thanks to the check above
+                let idx = (qy as usize * self.width + qx as usize) * 4;
+                self.data[idx..idx + 4].copy_from_slice(&[r, g, b, a]);
+            }
+        }
+        let x0 = (px as isize - half).max(0) as usize;
+        let y0 = (py as isize - half).max(0) as usize;
+        self.mark_dirty(x0, y0, x0 + self.line_width, y0 + self.line_width);
+    }
+
+    // Flag the tiles overlapping the pixel rectangle [x0, x1) x [y0, y1)
+    fn mark_dirty(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        let (x1, y1) = (x1.min(self.width), y1.min(self.height));
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        let tiles_x = self.width.div_ceil(TILE_SIZE);
+        let (tx1, ty1) = ((x1 - 1) / TILE_SIZE, (y1 - 1) / TILE_SIZE);
+        for ty in y0 / TILE_SIZE..=ty1 {
+            for tx in x0 / TILE_SIZE..=tx1 {
+                self.dirty_tiles[ty * tiles_x + tx] = true;
+            }
+        }
+    }
+
+    // Make the next take_dirty_regions return the whole bitmap
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty_tiles.fill(true);
+    }
+
+    /// Pixel rectangles (x, y, width, height) of the tiles changed since the last call
+    ///
+    /// The flags are reset, so each change is reported once. Tiles on the right and
+    /// bottom edges are cut to the bitmap size
+    pub fn take_dirty_regions(&mut self) -> Vec<(usize, usize, usize, usize)> {
+        let tiles_x = self.width.div_ceil(TILE_SIZE);
+        let mut regions = Vec::new();
+        for (i, dirty) in self.dirty_tiles.iter_mut().enumerate() {
+            if !std::mem::take(dirty) {
+                continue;
+            }
+            let (x, y) = ((i % tiles_x) * TILE_SIZE, (i / tiles_x) * TILE_SIZE);
+            regions.push((
+                x,
+                y,
+                TILE_SIZE.min(self.width - x),
+                TILE_SIZE.min(self.height - y),
+            ));
+        }
+        regions
+    }
 
-        // Set the color
-        if idx + 3 < self.data.len() {
-            self.data[idx] = r;
-            self.data[idx + 1] = g;
-            self.data[idx + 2] = b;
-            self.data[idx + 3] = a;
+    // Copy of a rectangle of this bitmap with the same scale and encoding, e.g. to send a tile
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Bitmap {
+        let width = width.min(self.width.saturating_sub(x));
+        let height = height.min(self.height.saturating_sub(y));
+        let mut cropped = Bitmap::with_scales(width, height, self.scale_x, self.scale_y);
+        for row in 0..height {
+            let src = ((y + row) * self.width + x) * 4;
+            let dst = row * width * 4;
+            cropped.data[dst..dst + width * 4].copy_from_slice(&self.data[src..src + width * 4]);
         }
+        cropped.background = self.background;
+        cropped.format = self.format;
+        cropped.jpeg_quality = self.jpeg_quality;
+        cropped
     }
 
     pub fn to_data_url(&self) -> String {
+        let image_data = match self.format {
+            PathImageFormat::Png => self.encode_png(),
+            PathImageFormat::Jpeg => self.encode_jpeg(),
+            PathImageFormat::WebP => self.encode_webp(),
+        };
+
+        // Convert the image to base64
+        let base64_data = general_purpose::STANDARD.encode(&image_data);
+
+        // Return as data URL
+        format!("{}{}", self.format.data_url_prefix(), base64_data)
+    }
+
+    fn encode_png(&self) -> Vec<u8> {
         // Create a new PNG encoder
         let mut png_data = Vec::new();
         {
@@ -118,26 +380,90 @@ impl Bitmap {
             let mut writer = encoder.write_header().unwrap();
             writer.write_image_data(&self.data).unwrap();
         }
+        png_data
+    }
 
-        // Convert PNG to base64
-        let base64_data = general_purpose::STANDARD.encode(&png_data);
+    // JPEG has no alpha channel, transparent pixels are composited onto white
+    // to match the light background of the path view
+    fn encode_jpeg(&self) -> Vec<u8> {
+        let rgb: Vec<u8> = self
+            .data
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                let alpha = pixel[3] as u16;
+                [0, 1, 2].map(|c| ((pixel[c] as u16 * alpha + 255 * (255 - alpha)) / 255) as u8)
+            })
+            .collect();
+        let mut jpeg_data = Vec::new();
+        jpeg_encoder::Encoder::new(&mut jpeg_data, self.jpeg_quality)
+            .encode(
+                &rgb,
+                self.width as u16,
+                self.height as u16,
+                jpeg_encoder::ColorType::Rgb,
+            )
+            .unwrap();
+        jpeg_data
+    }
 
-        // Return as data URL
-        format!("data:image/png;base64,{}", base64_data)
+    // Lossless WebP keeps the path lines crisp and is usually smaller than PNG
+    fn encode_webp(&self) -> Vec<u8> {
+        let mut webp_data = Vec::new();
+        image_webp::WebPEncoder::new(Cursor::new(&mut webp_data))
+            .encode(
+                &self.data,
+                self.width as u32,
+                self.height as u32,
+                image_webp::ColorType::Rgba8,
+            )
+            .unwrap();
+        webp_data
     }
 
-    // Clear the bitmap (set all pixels to transparent)
+    // Clear the bitmap (set all pixels to the background color)
     pub fn clear(&mut self) {
-        for i in 0..self.data.len() / 4 {
-            let idx = i * 4;
-            self.data[idx] = 255;
-            self.data[idx + 1] = 255;
-            self.data[idx + 2] = 255;
-            self.data[idx + 3] = 0;
+        for pixel in self.data.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&self.background);
         }
+        self.mark_all_dirty();
+    }
+
+    /// Draws the path of another bitmap faintly beneath the path of this one
+    ///
+    /// Only pixels still showing this bitmap's background are painted, the other
+    /// bitmap's path color is blended over the background with `opacity` (0-1).
+    /// The bitmaps are aligned on their origins so their sizes may differ
+    pub fn underlay(&mut self, other: &Bitmap, opacity: f32) {
+        let opacity = opacity.clamp(0.0, 1.0);
+        let dx = self.origin_x as isize - other.origin_x as isize;
+        let dy = self.origin_y as isize - other.origin_y as isize;
+        for (i, pixel) in other.data.chunks_exact(4).enumerate() {
+            if pixel[3] == 0 || pixel == other.background {
+                continue;
+            }
+            let x = (i % other.width) as isize + dx;
+            let y = (i / other.width) as isize + dy;
+            if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+                continue;
+            }
+            let idx = (y as usize * self.width + x as usize) * 4;
+            if self.data[idx..idx + 4] != self.background {
+                continue;
+            }
+            for c in 0..3 {
+                let background = self.background[c] as f32;
+                self.data[idx + c] =
+                    (background + (pixel[c] as f32 - background) * opacity).round() as u8;
+            }
+            self.data[idx + 3] = self.background[3].max((pixel[3] as f32 * opacity) as u8);
+        }
+        self.mark_all_dirty();
     }
 
     /// Merges another bitmap into this one by copying non-transparent pixels
+    ///
+    /// The source should use a transparent background, otherwise its background
+    /// pixels overwrite everything in this bitmap
     pub fn merge(&mut self, other: &Bitmap) {
         // Check if bitmaps have compatible dimensions
         if self.width != other.width || self.height != other.height {
@@ -158,5 +484,408 @@ impl Bitmap {
                 self.data[idx + 3] = other.data[idx + 3]; // A
             }
         }
+        self.mark_all_dirty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(bitmap: &Bitmap, x: usize, y: usize) -> [u8; 4] {
+        let idx = (y * bitmap.width + x) * 4;
+        bitmap.data[idx..idx + 4].try_into().unwrap()
+    }
+
+    // Pixels no longer showing the background, row by row
+    fn painted(bitmap: &Bitmap) -> Vec<(usize, usize)> {
+        (0..bitmap.width * bitmap.height)
+            .map(|i| (i % bitmap.width, i / bitmap.width))
+            .filter(|&(x, y)| pixel(bitmap, x, y) != bitmap.background)
+            .collect()
+    }
+
+    #[test]
+    fn points_on_the_edges_paint_the_edge_pixels() {
+        // 10x10 with the origin at pixel (5, 5)
+        let mut bitmap = Bitmap::new(10, 10, 1.0);
+        bitmap.set_pixel(-5.0, 5.0, 0.0);
+        bitmap.set_pixel(4.0, -4.0, 0.0);
+        bitmap.set_pixel(4.9, 4.5, 0.0);
+        assert_eq!(painted(&bitmap), [(0, 0), (9, 0), (9, 9)]);
+        assert_eq!(bitmap.out_of_bounds_count(), 0);
+    }
+
+    #[test]
+    fn points_outside_the_bitmap_are_counted_and_skipped() {
+        let mut bitmap = Bitmap::new(10, 10, 1.0);
+        bitmap.set_pixel(5.0, 0.0, 0.0);
+        bitmap.set_pixel(0.0, -5.0, 0.0);
+        bitmap.set_pixel(-5.01, 0.0, 0.0);
+        bitmap.set_pixel(0.0, 5.01, 0.0);
+        bitmap.set_pixel(1.0e9, -1.0e9, 0.0);
+        bitmap.set_pixel(f32::NAN, 0.0, 0.0);
+        assert!(painted(&bitmap).is_empty());
+        assert_eq!(bitmap.out_of_bounds_count(), 6);
+    }
+
+    #[test]
+    fn x_and_y_scales_are_applied_independently() {
+        // Origin at pixel (10, 10), 2 pixels per mm along X and half a pixel along Y
+        let mut bitmap = Bitmap::with_scales(20, 20, 2.0, 0.5);
+        assert_eq!(bitmap.scale(), (2.0, 0.5));
+        bitmap.set_pixel(2.0, 4.0, 0.0);
+        bitmap.set_pixel(-3.0, -6.0, 0.0);
+        assert_eq!(painted(&bitmap), [(14, 8), (4, 13)]);
+    }
+
+    // Pixels of the square from (x0, y0) to (x1, y1) inclusive, row by row
+    fn square(x0: usize, y0: usize, x1: usize, y1: usize) -> Vec<(usize, usize)> {
+        (y0..=y1)
+            .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+            .collect()
+    }
+
+    #[test]
+    fn wide_lines_paint_a_square_around_the_point() {
+        let mut bitmap = Bitmap::new(10, 10, 1.0);
+        bitmap.set_line_width(3);
+        bitmap.set_pixel(0.0, 0.0, 0.0);
+        assert_eq!(painted(&bitmap), square(4, 4, 6, 6));
+
+        // Even widths extend one pixel further right and down
+        let mut bitmap = Bitmap::new(10, 10, 1.0);
+        bitmap.set_line_width(4);
+        bitmap.set_pixel(0.0, 0.0, 0.0);
+        assert_eq!(painted(&bitmap), square(4, 4, 7, 7));
+    }
+
+    #[test]
+    fn wide_lines_are_clipped_at_the_edges() {
+        let mut bitmap = Bitmap::new(10, 10, 1.0);
+        bitmap.set_line_width(3);
+        bitmap.set_pixel(-5.0, 5.0, 0.0);
+        assert_eq!(painted(&bitmap), square(0, 0, 1, 1));
+
+        let mut bitmap = Bitmap::new(10, 10, 1.0);
+        bitmap.set_line_width(3);
+        bitmap.set_pixel(4.0, -4.0, 0.0);
+        assert_eq!(painted(&bitmap), square(8, 8, 9, 9));
+        assert_eq!(bitmap.out_of_bounds_count(), 0);
+    }
+
+    #[test]
+    fn a_zero_line_width_paints_single_pixels() {
+        let mut bitmap = Bitmap::new(10, 10, 1.0);
+        bitmap.set_line_width(0);
+        assert_eq!(bitmap.line_width(), 1);
+        bitmap.set_pixel(0.0, 0.0, 0.0);
+        assert_eq!(painted(&bitmap), [(5, 5)]);
+    }
+
+    #[test]
+    fn a_new_background_repaints_only_background_pixels() {
+        let mut bitmap = Bitmap::new(4, 4, 1.0);
+        bitmap.set_pixel(0.0, 0.0, 0.0);
+        let path = pixel(&bitmap, 2, 2);
+        bitmap.take_dirty_regions();
+        bitmap.set_background([0, 0, 0, 255]);
+        assert_eq!(bitmap.background(), [0, 0, 0, 255]);
+        assert_eq!(pixel(&bitmap, 0, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(&bitmap, 2, 2), path);
+        assert_eq!(painted(&bitmap), [(2, 2)]);
+        assert_eq!(bitmap.take_dirty_regions(), [(0, 0, 4, 4)]);
+        // Setting the same color again changes nothing
+        bitmap.set_background([0, 0, 0, 255]);
+        assert!(bitmap.take_dirty_regions().is_empty());
+    }
+
+    #[test]
+    fn clearing_fills_everything_with_the_background() {
+        let mut bitmap = Bitmap::with_background(4, 4, 1.0, [10, 20, 30, 255]);
+        bitmap.set_pixel(0.0, 0.0, 0.0);
+        bitmap.set_pixel(1.0, 1.0, -2.0);
+        bitmap.take_dirty_regions();
+        bitmap.clear();
+        assert!(painted(&bitmap).is_empty());
+        assert_eq!(pixel(&bitmap, 3, 3), [10, 20, 30, 255]);
+        assert_eq!(bitmap.take_dirty_regions(), [(0, 0, 4, 4)]);
+    }
+
+    #[test]
+    fn a_corner_origin_puts_the_zero_point_on_the_margin_pixel() {
+        use crate::model::PathOrigin;
+        for (origin, expected) in [
+            (PathOrigin::Center, (100, 50)),
+            (PathOrigin::BottomLeft, (10, 95)),
+            (PathOrigin::TopLeft, (10, 5)),
+            (PathOrigin::BottomRight, (190, 95)),
+            (PathOrigin::TopRight, (190, 5)),
+        ] {
+            let mut bitmap = Bitmap::new(200, 100, 1.0);
+            let (fraction_x, fraction_y) = origin.fraction();
+            bitmap.set_origin(fraction_x, fraction_y);
+            bitmap.set_pixel(0.0, 0.0, 0.0);
+            assert_eq!(painted(&bitmap), [expected], "{:?}", origin);
+        }
+    }
+
+    #[test]
+    fn origin_fractions_are_clamped_to_the_bitmap() {
+        let mut bitmap = Bitmap::new(10, 10, 1.0);
+        bitmap.set_origin(-1.0, 2.0);
+        assert_eq!(bitmap.origin_fraction(), (0.0, 1.0));
+        bitmap.set_pixel(0.0, 1.0, 0.0);
+        assert_eq!(painted(&bitmap), [(0, 9)]);
+    }
+
+    #[test]
+    fn update_pos_shifts_the_origin_by_scaled_machine_units() {
+        let mut bitmap = Bitmap::new(20, 20, 2.0);
+        bitmap.update_pos(1.5, 2.0);
+        bitmap.set_pixel(0.0, 0.0, 0.0);
+        assert_eq!(painted(&bitmap), [(13, 14)]);
+        // The next set_origin starts again from the fraction alone
+        bitmap.set_origin(0.5, 0.5);
+        bitmap.clear();
+        bitmap.set_pixel(0.0, 0.0, 0.0);
+        assert_eq!(painted(&bitmap), [(10, 10)]);
+    }
+
+    // Bitmap with a few path points, encoded in the given format and split into the data URL
+    // prefix and the decoded image bytes
+    fn encoded(format: PathImageFormat) -> (Bitmap, String, Vec<u8>) {
+        let mut bitmap = Bitmap::new(30, 20, 1.0);
+        bitmap.set_format(format, 90);
+        bitmap.set_pixel(0.0, 0.0, 0.0);
+        bitmap.set_pixel(-3.0, 2.0, -2.0);
+        let url = bitmap.to_data_url();
+        let (prefix, data) = url.split_at(url.find(',').unwrap() + 1);
+        let bytes = general_purpose::STANDARD.decode(data).unwrap();
+        (bitmap, prefix.to_string(), bytes)
+    }
+
+    #[test]
+    fn webp_data_urls_decode_to_the_same_pixels() {
+        let (bitmap, prefix, bytes) = encoded(PathImageFormat::WebP);
+        assert_eq!(prefix, "data:image/webp;base64,");
+        let mut decoder = image_webp::WebPDecoder::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(decoder.dimensions(), (30, 20));
+        assert!(decoder.has_alpha());
+        let mut decoded = vec![0; decoder.output_buffer_size().unwrap()];
+        decoder.read_image(&mut decoded).unwrap();
+        assert_eq!(decoded, bitmap.data);
+    }
+
+    #[test]
+    fn jpeg_data_urls_hold_a_complete_image_of_the_bitmap_size() {
+        let (_, prefix, bytes) = encoded(PathImageFormat::Jpeg);
+        assert_eq!(prefix, "data:image/jpeg;base64,");
+        // Start and end of image markers
+        assert_eq!(bytes[..2], [0xFF, 0xD8]);
+        assert_eq!(bytes[bytes.len() - 2..], [0xFF, 0xD9]);
+        // Baseline frame header: marker, length, precision, height, width
+        let sof = bytes.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+        let size = |i: usize| u16::from_be_bytes([bytes[sof + i], bytes[sof + i + 1]]);
+        assert_eq!((size(7), size(5)), (30, 20));
+    }
+
+    #[test]
+    fn png_stays_the_default_encoding() {
+        let (bitmap, prefix, bytes) = encoded(PathImageFormat::default());
+        assert_eq!(prefix, "data:image/png;base64,");
+        let mut reader = png::Decoder::new(Cursor::new(bytes)).read_info().unwrap();
+        let mut decoded = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut decoded).unwrap();
+        assert_eq!((info.width, info.height), (30, 20));
+        assert_eq!(decoded, bitmap.data);
+    }
+
+    #[test]
+    fn each_change_is_reported_once_as_its_tile() {
+        let mut bitmap = Bitmap::new(120, 80, 1.0);
+        // A new bitmap is dirty everywhere, edge tiles cut to the bitmap size
+        assert_eq!(
+            bitmap.take_dirty_regions(),
+            [
+                (0, 0, 50, 50),
+                (50, 0, 50, 50),
+                (100, 0, 20, 50),
+                (0, 50, 50, 30),
+                (50, 50, 50, 30),
+                (100, 50, 20, 30),
+            ]
+        );
+        assert!(bitmap.take_dirty_regions().is_empty());
+        // Origin at pixel (60, 40)
+        bitmap.set_pixel(0.0, 0.0, 0.0);
+        bitmap.set_pixel(45.0, -30.0, 0.0);
+        assert_eq!(
+            bitmap.take_dirty_regions(),
+            [(50, 0, 50, 50), (100, 50, 20, 30)]
+        );
+        assert!(bitmap.take_dirty_regions().is_empty());
+    }
+
+    #[test]
+    fn a_wide_point_on_a_tile_corner_dirties_every_tile_it_touches() {
+        let mut bitmap = Bitmap::new(100, 100, 1.0);
+        bitmap.take_dirty_regions();
+        bitmap.set_line_width(3);
+        // Origin at pixel (50, 50), the square spans 49..=51 in both directions
+        bitmap.set_pixel(0.0, 0.0, 0.0);
+        assert_eq!(
+            bitmap.take_dirty_regions(),
+            [
+                (0, 0, 50, 50),
+                (50, 0, 50, 50),
+                (0, 50, 50, 50),
+                (50, 50, 50, 50)
+            ]
+        );
+    }
+
+    #[test]
+    fn crops_copy_the_pixels_and_are_clipped_at_the_edges() {
+        let mut bitmap = Bitmap::with_background(120, 80, 1.0, [1, 2, 3, 255]);
+        bitmap.set_format(PathImageFormat::Jpeg, 40);
+        bitmap.set_pixel(45.0, -30.0, -1.0);
+        let tile = bitmap.crop(100, 50, TILE_SIZE, TILE_SIZE);
+        assert_eq!(tile.size(), (20, 30));
+        assert_eq!(painted(&tile), [(5, 20)]);
+        assert_eq!(pixel(&tile, 5, 20), pixel(&bitmap, 105, 70));
+        assert_eq!(pixel(&tile, 0, 0), [1, 2, 3, 255]);
+        assert_eq!(tile.encoding(), (PathImageFormat::Jpeg, 40));
+        assert_eq!(bitmap.crop(130, 0, 10, 10).size(), (0, 10));
+    }
+
+    #[test]
+    fn rapid_dashes_alternate_runs_of_points() {
+        let visible: Vec<bool> = (0..12).map(rapid_dash_visible).collect();
+        let dash = [true, true, true, true, false, false, false, false];
+        assert_eq!(visible, [&dash[..], &dash[..4]].concat());
+    }
+
+    // Columns of the painted pixels, for tests drawing along a single row
+    fn dashed_xs(bitmap: &Bitmap) -> Vec<usize> {
+        painted(bitmap).into_iter().map(|(x, _)| x).collect()
+    }
+
+    #[test]
+    fn rapids_are_faint_dashes_only_when_distinguished() {
+        let mut bitmap = Bitmap::new(30, 3, 1.0);
+        bitmap.set_distinguish_rapids(true);
+        for x in 0..10 {
+            bitmap.set_motion_pixel(x as f32 - 10.0, 0.0, 0.0, MotionKind::Rapid);
+        }
+        assert_eq!(dashed_xs(&bitmap), [5, 6, 7, 8, 13, 14]);
+        assert_eq!(pixel(&bitmap, 5, 1)[3], RAPID_ALPHA);
+
+        let mut bitmap = Bitmap::new(30, 3, 1.0);
+        for x in 0..10 {
+            bitmap.set_motion_pixel(x as f32 - 10.0, 0.0, 0.0, MotionKind::Rapid);
+        }
+        assert_eq!(dashed_xs(&bitmap), (5..15).collect::<Vec<_>>());
+        assert_eq!(pixel(&bitmap, 5, 1)[3], 255);
+    }
+
+    #[test]
+    fn a_feed_point_restarts_the_dash_pattern() {
+        let mut bitmap = Bitmap::new(30, 3, 1.0);
+        bitmap.set_distinguish_rapids(true);
+        let kinds = [MotionKind::Rapid; 6]
+            .into_iter()
+            .chain([MotionKind::Feed])
+            .chain([MotionKind::Rapid; 5]);
+        for (x, kind) in kinds.enumerate() {
+            bitmap.set_motion_pixel(x as f32 - 10.0, 0.0, 0.0, kind);
+        }
+        // Points 0-3 dashed, 4-5 in the gap, 6 the feed, 7-10 a new dash, 11 a gap
+        assert_eq!(dashed_xs(&bitmap), [5, 6, 7, 8, 11, 12, 13, 14, 15]);
+        assert_eq!(pixel(&bitmap, 11, 1)[3], 255);
+    }
+
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    // Larger transparent bitmap with path points at (1, 1), (2, 2) and far outside the
+    // 10x10 bitmaps below
+    fn previous_path() -> Bitmap {
+        let mut other = Bitmap::new(40, 40, 1.0);
+        for (x, y) in [(1.0, 1.0), (2.0, 2.0), (15.0, 0.0)] {
+            other.set_pixel(x, y, 0.0);
+        }
+        other
+    }
+
+    #[test]
+    fn underlays_are_aligned_on_the_origins() {
+        let mut bitmap = Bitmap::with_background(10, 10, 1.0, WHITE);
+        bitmap.underlay(&previous_path(), 1.0);
+        // Same pixels as drawing the points here, the one outside is dropped
+        let mut drawn = Bitmap::with_background(10, 10, 1.0, WHITE);
+        drawn.set_pixel(1.0, 1.0, 0.0);
+        drawn.set_pixel(2.0, 2.0, 0.0);
+        assert_eq!(painted(&bitmap), [(7, 3), (6, 4)]);
+        assert_eq!(bitmap.data, drawn.data);
+    }
+
+    #[test]
+    fn underlays_only_paint_background_pixels() {
+        let mut bitmap = Bitmap::with_background(10, 10, 1.0, WHITE);
+        bitmap.set_pixel(1.0, 1.0, -2.0);
+        let own = pixel(&bitmap, 6, 4);
+        bitmap.underlay(&previous_path(), 1.0);
+        assert_eq!(pixel(&bitmap, 6, 4), own);
+        assert_ne!(pixel(&bitmap, 7, 3), WHITE);
+    }
+
+    #[test]
+    fn underlays_blend_the_path_color_over_the_background() {
+        let other = previous_path();
+        assert_eq!(pixel(&other, 21, 19), [216, 38, 38, 255]);
+        let mut bitmap = Bitmap::with_background(10, 10, 1.0, WHITE);
+        bitmap.underlay(&other, 0.5);
+        assert_eq!(pixel(&bitmap, 6, 4), [236, 147, 147, 255]);
+
+        // On a transparent background the alpha follows the opacity
+        let mut bitmap = Bitmap::new(10, 10, 1.0);
+        bitmap.underlay(&other, 0.5);
+        assert_eq!(pixel(&bitmap, 6, 4), [236, 147, 147, 127]);
+
+        let mut bitmap = Bitmap::with_background(10, 10, 1.0, WHITE);
+        bitmap.underlay(&other, 0.0);
+        assert!(painted(&bitmap).is_empty());
+    }
+
+    #[test]
+    fn a_fitted_bitmap_shows_every_corner_of_the_work_area() {
+        for (x_range, y_range) in [
+            ((0.0, 400.0), (0.0, 300.0)),
+            ((-50.0, 150.0), (-20.0, 80.0)),
+            ((-600.0, -100.0), (10.0, 30.0)),
+        ] {
+            let mut bitmap = Bitmap::fit_to_range(200, 100, x_range, y_range).unwrap();
+            for x in [x_range.0, x_range.1] {
+                for y in [y_range.0, y_range.1] {
+                    bitmap.set_pixel(x, y, 0.0);
+                }
+            }
+            assert_eq!(
+                bitmap.out_of_bounds_count(),
+                0,
+                "{:?} {:?}",
+                x_range,
+                y_range
+            );
+            assert_eq!(painted(&bitmap).len(), 4);
+            // The zero point is always inside as well
+            bitmap.set_pixel(0.0, 0.0, 0.0);
+            assert_eq!(bitmap.out_of_bounds_count(), 0);
+        }
+    }
+
+    #[test]
+    fn empty_work_areas_cannot_be_fitted() {
+        assert!(Bitmap::fit_to_range(200, 100, (0.0, 0.0), (0.0, 0.0)).is_none());
     }
 }
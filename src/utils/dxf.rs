@@ -0,0 +1,109 @@
+// 将路径点导出为最简DXF（R12格式），每段路径一个LINE实体，按Z深度分层
+
+// 按Z深度分层的层名，band为每层的深度范围
+fn layer_name(z: f32, band: f32) -> String {
+    let index = if band > 0.0 {
+        (z / band).floor() as i64
+    } else {
+        0
+    };
+    format!("Z_{}", index)
+}
+
+fn push_pair(dxf: &mut String, code: u16, value: impl std::fmt::Display) {
+    dxf.push_str(&format!("{}\n{}\n", code, value));
+}
+
+pub fn path_to_dxf(points: &[[f32; 3]], band: f32) -> String {
+    let mut dxf = String::new();
+    // HEADER只写版本号，保证大多数CAD都能导入
+    push_pair(&mut dxf, 0, "SECTION");
+    push_pair(&mut dxf, 2, "HEADER");
+    push_pair(&mut dxf, 9, "$ACADVER");
+    push_pair(&mut dxf, 1, "AC1009");
+    push_pair(&mut dxf, 0, "ENDSEC");
+
+    push_pair(&mut dxf, 0, "SECTION");
+    push_pair(&mut dxf, 2, "ENTITIES");
+    for segment in points.windows(2) {
+        let [from, to] = [segment[0], segment[1]];
+        push_pair(&mut dxf, 0, "LINE");
+        // 取线段较深的一端决定所在层
+        push_pair(&mut dxf, 8, layer_name(from[2].min(to[2]), band));
+        push_pair(&mut dxf, 10, from[0]);
+        push_pair(&mut dxf, 20, from[1]);
+        push_pair(&mut dxf, 30, from[2]);
+        push_pair(&mut dxf, 11, to[0]);
+        push_pair(&mut dxf, 21, to[1]);
+        push_pair(&mut dxf, 31, to[2]);
+    }
+    push_pair(&mut dxf, 0, "ENDSEC");
+    push_pair(&mut dxf, 0, "EOF");
+    dxf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 把DXF拆成(组码, 值)对
+    fn pairs(dxf: &str) -> Vec<(&str, &str)> {
+        let lines: Vec<&str> = dxf.lines().collect();
+        lines.chunks(2).map(|pair| (pair[0], pair[1])).collect()
+    }
+
+    #[test]
+    fn layers_are_named_after_their_depth_band() {
+        assert_eq!(layer_name(0.0, 1.0), "Z_0");
+        assert_eq!(layer_name(0.5, 1.0), "Z_0");
+        assert_eq!(layer_name(-0.5, 1.0), "Z_-1");
+        assert_eq!(layer_name(-1.0, 1.0), "Z_-1");
+        assert_eq!(layer_name(-1.01, 1.0), "Z_-2");
+        assert_eq!(layer_name(-3.0, 0.5), "Z_-6");
+        // 没有分层时都在同一层
+        assert_eq!(layer_name(-3.0, 0.0), "Z_0");
+    }
+
+    #[test]
+    fn each_segment_is_one_line_on_its_deeper_layer() {
+        let dxf = path_to_dxf(
+            &[[0.0, 0.0, 0.0], [10.0, 5.0, -0.5], [10.0, 5.0, -1.5]],
+            1.0,
+        );
+        let pairs = pairs(&dxf);
+        let lines: Vec<_> = pairs.split(|pair| *pair == ("0", "LINE")).skip(1).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            [
+                ("8", "Z_-1"),
+                ("10", "0"),
+                ("20", "0"),
+                ("30", "0"),
+                ("11", "10"),
+                ("21", "5"),
+                ("31", "-0.5"),
+            ]
+        );
+        assert_eq!(lines[1][0], ("8", "Z_-2"));
+        assert_eq!(lines[1][1..4], [("10", "10"), ("20", "5"), ("30", "-0.5")]);
+        assert_eq!(lines[1][7..], [("0", "ENDSEC"), ("0", "EOF")]);
+    }
+
+    #[test]
+    fn a_path_without_segments_is_an_empty_drawing() {
+        for points in [&[][..], &[[1.0, 2.0, 3.0]][..]] {
+            let dxf = path_to_dxf(points, 1.0);
+            assert!(!dxf.contains("LINE"));
+            assert_eq!(
+                pairs(&dxf)[5..],
+                [
+                    ("0", "SECTION"),
+                    ("2", "ENTITIES"),
+                    ("0", "ENDSEC"),
+                    ("0", "EOF")
+                ]
+            );
+        }
+    }
+}
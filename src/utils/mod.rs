@@ -1,5 +1,13 @@
 #[cfg(feature = "ssr")]
+mod basic;
+#[cfg(feature = "ssr")]
 mod bitmap;
+#[cfg(feature = "ssr")]
+mod dxf;
 
+#[cfg(feature = "ssr")]
+pub use basic::*;
 #[cfg(feature = "ssr")]
 pub use bitmap::*;
+#[cfg(feature = "ssr")]
+pub use dxf::*;